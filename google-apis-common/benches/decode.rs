@@ -0,0 +1,142 @@
+//! Measures how fast large list responses are read and decoded, as they are by the `doit()`
+//! method of call builders. Run it with `cargo bench` in the directory of this crate.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use futures::executor::block_on;
+use google_apis_common::{decode_json, decode_json_slice, get_body_as_string, read_body};
+use hyper::body::Bytes;
+use serde_derive::Deserialize;
+
+/// The amount of occurrences in the decoded list response.
+const OCCURRENCES: usize = 10_000;
+/// The size of the chunks the body of the response arrives in.
+const CHUNK_SIZE: usize = 16 * 1024;
+const SEVERITIES: [&str; 4] = ["LOW", "MEDIUM", "HIGH", "CRITICAL"];
+
+// A subset of the fields of a container analysis occurrence, shaped like the generated structs.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct PackageIssue {
+    affected_package: Option<String>,
+    affected_version: Option<Version>,
+    fixed_version: Option<Version>,
+    fix_available: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct Version {
+    name: Option<String>,
+    kind: Option<String>,
+    epoch: Option<i32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct Vulnerability {
+    severity: Option<String>,
+    cvss_score: Option<f32>,
+    short_description: Option<String>,
+    package_issue: Option<Vec<PackageIssue>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct Occurrence {
+    name: Option<String>,
+    resource_uri: Option<String>,
+    note_name: Option<String>,
+    kind: Option<String>,
+    create_time: Option<String>,
+    vulnerability: Option<Vulnerability>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ListOccurrencesResponse {
+    occurrences: Option<Vec<Occurrence>>,
+    next_page_token: Option<String>,
+}
+
+fn list_response() -> String {
+    let occurrences: Vec<_> = (0..OCCURRENCES)
+        .map(|i| {
+            serde_json::json!({
+                "name": format!("projects/p/occurrences/{:08x}", i),
+                "resourceUri": format!("https://gcr.io/p/image@sha256:{:064x}", i),
+                "noteName": format!("projects/goog-vulnz/notes/CVE-2023-{}", i % 977),
+                "kind": "VULNERABILITY",
+                "createTime": "2023-05-04T12:34:56.789Z",
+                "vulnerability": {
+                    "severity": SEVERITIES[i % 4],
+                    "cvssScore": (i % 100) as f32 / 10.0,
+                    "shortDescription": "A \"quoted\" description with an escaped \\u00e9 character",
+                    "packageIssue": [{
+                        "affectedPackage": "openssl",
+                        "affectedVersion": {"name": "1.1.1", "kind": "NORMAL", "epoch": 0},
+                        "fixedVersion": {"name": "1.1.1t", "kind": "NORMAL", "epoch": 0},
+                        "fixAvailable": i % 3 == 0,
+                    }],
+                },
+            })
+        })
+        .collect();
+    serde_json::json!({"occurrences": occurrences, "nextPageToken": "next"}).to_string()
+}
+
+fn chunked_body(chunks: &[Bytes]) -> hyper::Body {
+    let chunks: Vec<_> = chunks
+        .iter()
+        .cloned()
+        .map(Ok::<_, std::io::Error>)
+        .collect();
+    hyper::Body::wrap_stream(futures::stream::iter(chunks))
+}
+
+fn decode(c: &mut Criterion) {
+    let json = list_response();
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("decode_json", |b| {
+        b.iter(|| decode_json::<ListOccurrencesResponse>(&json).unwrap())
+    });
+    group.bench_function("decode_json_slice", |b| {
+        b.iter(|| decode_json_slice::<ListOccurrencesResponse>(json.as_bytes()).unwrap())
+    });
+    group.finish();
+}
+
+fn read_and_decode(c: &mut Criterion) {
+    let json = Bytes::from(list_response());
+    let chunks: Vec<_> = (0..json.len())
+        .step_by(CHUNK_SIZE)
+        .map(|start| json.slice(start..json.len().min(start + CHUNK_SIZE)))
+        .collect();
+    let mut group = c.benchmark_group("read_and_decode");
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("get_body_as_string", |b| {
+        b.iter(|| {
+            let json = block_on(get_body_as_string(&mut chunked_body(&chunks)));
+            decode_json::<ListOccurrencesResponse>(&json).unwrap()
+        })
+    });
+    group.bench_function("read_body", |b| {
+        b.iter(|| {
+            let json = block_on(read_body(&mut chunked_body(&chunks))).unwrap();
+            decode_json_slice::<ListOccurrencesResponse>(&json).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = decode, read_and_decode
+}
+criterion_main!(benches);
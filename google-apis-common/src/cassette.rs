@@ -0,0 +1,336 @@
+//! Recording of the exchanges of a hub with the server into a cassette file, and their replay
+//! without credentials or network, to iterate on what is done with responses, or to reproduce
+//! bugs with the cassette attached to their report.
+//!
+//! Both are local servers, whose address is used as the root and base url of the hub. The
+//! `Recorder` forwards all requests to the actual server, and saves each exchange to the cassette.
+//! Request headers aren't saved, which keeps the credentials out of it. The `Player` responds to
+//! each request with the first response recorded for the same method and uri, which it didn't
+//! respond with yet, or with the last one once all were used.
+//!
+//! Resumable uploads continue at the url the server responds with, so only their first request is
+//! recorded.
+
+pub use axum;
+
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use hyper::header::{
+    HeaderValue, ACCEPT_ENCODING, CONTENT_LENGTH, HOST, SET_COOKIE, TRANSFER_ENCODING,
+};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json as json;
+
+use crate::{ConnectionOptions, Connector};
+
+/// A request and the response the server sent to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interaction {
+    /// The http method, like `GET`.
+    pub method: String,
+    /// The path and query of the request, like `/v1/projects/p/topics?alt=json`.
+    pub uri: String,
+    pub request_body: Vec<u8>,
+    pub status: u16,
+    /// The headers of the response, except for cookies.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Interaction {
+    /// Returns the interaction as JSON object, whose bodies are strings if they are UTF-8, or
+    /// base64 encoded strings in `requestBodyBase64` and `bodyBase64` otherwise.
+    pub fn to_json(&self) -> json::Value {
+        let mut value = json::json!({
+            "method": self.method,
+            "uri": self.uri,
+            "status": self.status,
+            "headers": self.headers.iter().map(|(k, v)| json::json!([k, v])).collect::<Vec<_>>(),
+        });
+        set_body(&mut value, "requestBody", &self.request_body);
+        set_body(&mut value, "body", &self.body);
+        value
+    }
+
+    /// Decodes an interaction from its JSON, as written by `to_json()`. Returns `None` if it has
+    /// no method or uri, or a body isn't valid base64.
+    pub fn from_json(value: &json::Value) -> Option<Interaction> {
+        let headers = value["headers"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|h| Some((h[0].as_str()?.to_string(), h[1].as_str()?.to_string())))
+            .collect();
+        Some(Interaction {
+            method: value["method"].as_str()?.to_string(),
+            uri: value["uri"].as_str()?.to_string(),
+            request_body: body(value, "requestBody")?,
+            status: value["status"].as_u64().unwrap_or(200) as u16,
+            headers,
+            body: body(value, "body")?,
+        })
+    }
+
+    /// Returns the recorded response.
+    pub fn to_response(&self) -> Response<Body> {
+        let mut response = Response::builder()
+            .status(StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+        for (name, value) in &self.headers {
+            response = response.header(name.as_str(), value.as_str());
+        }
+        response
+            .body(Body::from(self.body.clone()))
+            .unwrap_or_else(|err| error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+    }
+}
+
+fn set_body(value: &mut json::Value, key: &str, body: &[u8]) {
+    match std::str::from_utf8(body) {
+        Ok(text) => value[key] = json::Value::String(text.to_string()),
+        Err(_) => value[format!("{}Base64", key)] = json::Value::String(base64::encode(body)),
+    }
+}
+
+fn body(value: &json::Value, key: &str) -> Option<Vec<u8>> {
+    match value[format!("{}Base64", key)].as_str() {
+        Some(encoded) => base64::decode(encoded).ok(),
+        None => Some(value[key].as_str().unwrap_or_default().as_bytes().to_vec()),
+    }
+}
+
+/// The interactions of a hub with the server, in the order they happened.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Returns the cassette as JSON object, like `{"interactions": [...]}`.
+    pub fn to_json(&self) -> json::Value {
+        json::json!({
+            "interactions": self.interactions.iter().map(Interaction::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Decodes a cassette from its JSON, as written by `to_json()`. Returns `None` if it, or one of
+    /// its interactions, is invalid.
+    pub fn from_json(value: &json::Value) -> Option<Cassette> {
+        Some(Cassette {
+            interactions: value["interactions"]
+                .as_array()?
+                .iter()
+                .map(Interaction::from_json)
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// Reads the cassette from the file at `path`.
+    pub fn load(path: &Path) -> io::Result<Cassette> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "The cassette is invalid");
+        let value: json::Value = json::from_slice(&fs::read(path)?).map_err(|_| invalid())?;
+        Cassette::from_json(&value).ok_or_else(invalid)
+    }
+
+    /// Writes the cassette to the file at `path`, replacing it.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, json::to_vec_pretty(&self.to_json())?)
+    }
+}
+
+/// Returns a response with the given status, and an error like the ones of the server.
+fn error(status: StatusCode, message: String) -> Response<Body> {
+    let body = json::json!({"error": {"code": status.as_u16(), "message": message}});
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Serves the `router` on a free port of the local host, in a task of the current tokio runtime.
+fn spawn(router: axum::Router) -> SocketAddr {
+    let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .serve(router.into_make_service());
+    let address = server.local_addr();
+    tokio::spawn(server);
+    address
+}
+
+/// Responds to requests with the responses of a cassette.
+#[derive(Clone)]
+pub struct Player {
+    // the interactions, and whether they were responded with
+    interactions: Arc<Mutex<Vec<(Interaction, bool)>>>,
+}
+
+impl Player {
+    /// Creates a player of the interactions of `cassette`.
+    pub fn new(cassette: Cassette) -> Player {
+        Player {
+            interactions: Arc::new(Mutex::new(
+                cassette
+                    .interactions
+                    .into_iter()
+                    .map(|i| (i, false))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Returns the interaction to respond to a request with the given method and uri with, which is
+    /// the first one recorded for them that wasn't used yet, or the last one once all were used.
+    pub fn handle(&self, method: &Method, uri: &str) -> Option<Interaction> {
+        let mut interactions = self.interactions.lock().unwrap();
+        let mut matching = interactions
+            .iter_mut()
+            .filter(|(i, _)| i.method == method.as_str() && i.uri == uri)
+            .peekable();
+        let mut last = None;
+        while let Some((interaction, used)) = matching.next() {
+            if !*used || matching.peek().is_none() {
+                *used = true;
+                last = Some(interaction.clone());
+                break;
+            }
+        }
+        last
+    }
+
+    /// Returns an `axum::Router` handling all requests with this player.
+    pub fn router(&self) -> axum::Router {
+        let player = self.clone();
+        axum::Router::new().fallback(move |method: Method, uri: hyper::Uri| async move {
+            let uri = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+            match player.handle(&method, uri) {
+                Some(interaction) => interaction.to_response(),
+                None => error(
+                    StatusCode::NOT_FOUND,
+                    format!("The cassette has no response for {} {}", method, uri),
+                ),
+            }
+        })
+    }
+
+    /// Serves this player on a free port of the local host, in a task of the current tokio
+    /// runtime. Returns the address to use in the root url of the hub, like `http://{address}/`.
+    pub fn spawn(&self) -> SocketAddr {
+        spawn(self.router())
+    }
+}
+
+/// Forwards requests to the server, and saves the exchanges to a cassette file.
+#[derive(Clone)]
+pub struct Recorder {
+    root_url: String,
+    path: PathBuf,
+    client: hyper::Client<Connector, Body>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl Recorder {
+    /// Creates a recorder forwarding requests to the server at `root_url`, like
+    /// `https://pubsub.googleapis.com/`, which replaces the cassette at `path` once it recorded
+    /// the first interaction.
+    pub fn new(root_url: &str, path: &Path) -> Recorder {
+        Recorder {
+            root_url: root_url.trim_end_matches('/').to_string(),
+            path: path.to_path_buf(),
+            client: ConnectionOptions::default().client(),
+            cassette: Default::default(),
+        }
+    }
+
+    /// Returns the interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// Forwards the request to the server, and saves the exchange to the cassette. Responds with a
+    /// `502` error if the server couldn't be reached, and a `500` one if the cassette couldn't be
+    /// saved.
+    pub async fn forward(&self, request: Request<Body>) -> Response<Body> {
+        let (parts, body) = request.into_parts();
+        let uri = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/")
+            .to_string();
+        let request_body = match hyper::body::to_bytes(body).await {
+            Ok(bytes) => bytes,
+            Err(err) => return error(StatusCode::BAD_REQUEST, err.to_string()),
+        };
+        let mut forwarded = Request::builder()
+            .method(parts.method.clone())
+            .uri(format!("{}{}", self.root_url, uri));
+        // Compressed responses would only be recorded as such.
+        for (name, value) in parts
+            .headers
+            .iter()
+            .filter(|(name, _)| **name != HOST && **name != ACCEPT_ENCODING)
+        {
+            forwarded = forwarded.header(name, value);
+        }
+        let forwarded = match forwarded.body(Body::from(request_body.clone())) {
+            Ok(forwarded) => forwarded,
+            Err(err) => return error(StatusCode::BAD_REQUEST, err.to_string()),
+        };
+        let (parts_of_response, body) = match self.client.request(forwarded).await {
+            Ok(response) => response.into_parts(),
+            Err(err) => return error(StatusCode::BAD_GATEWAY, err.to_string()),
+        };
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(bytes) => bytes,
+            Err(err) => return error(StatusCode::BAD_GATEWAY, err.to_string()),
+        };
+        let interaction = Interaction {
+            method: parts.method.to_string(),
+            uri,
+            request_body: request_body.to_vec(),
+            status: parts_of_response.status.as_u16(),
+            headers: parts_of_response
+                .headers
+                .iter()
+                .filter(|(name, _)| ![SET_COOKIE, CONTENT_LENGTH, TRANSFER_ENCODING].contains(name))
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: body.to_vec(),
+        };
+        let response = interaction.to_response();
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(interaction);
+        if let Err(err) = cassette.save(&self.path) {
+            return error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "Failed to save the cassette '{}': {}",
+                    self.path.display(),
+                    err
+                ),
+            );
+        }
+        response
+    }
+
+    /// Returns an `axum::Router` handling all requests with this recorder.
+    pub fn router(&self) -> axum::Router {
+        let recorder = self.clone();
+        axum::Router::new()
+            .fallback(move |request: Request<Body>| async move { recorder.forward(request).await })
+    }
+
+    /// Serves this recorder on a free port of the local host, in a task of the current tokio
+    /// runtime. Returns the address to use in the root url of the hub, like `http://{address}/`.
+    pub fn spawn(&self) -> SocketAddr {
+        spawn(self.router())
+    }
+}
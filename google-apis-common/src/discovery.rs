@@ -0,0 +1,396 @@
+//! Fetches the live discovery documents of APIs, and compares the methods called on a hub to the
+//! ones its crate was generated from, to warn if the crate is outdated for them.
+//!
+//! Documents are cached on disk by `DiscoveryCache`, and revalidated with the server once they are
+//! older than its maximum age, so the check costs a request about once a day rather than once per
+//! process. Hubs only run the check if a `DiscoveryCheck` was set with their `discovery_check()`
+//! method, and report each outdated method once, on the first call to it.
+//!
+//! Methods are compared by their signature, made of their HTTP method, their path, their
+//! parameters and the types of their request and response, as changes to their documentation don't
+//! affect how they are called.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use hyper::header::{ETAG, IF_NONE_MATCH, USER_AGENT};
+use hyper::StatusCode;
+use serde_json as json;
+
+use crate::{read_body, Connector, Error, Result};
+
+/// The root of the URLs of the discovery documents of all APIs, which are followed by the name
+/// and version of the API, like `pubsub/v1/rest`.
+pub const DISCOVERY_ROOT: &str = "https://www.googleapis.com/discovery/v1/apis/";
+
+/// The environment variable overriding the directory the documents are cached in by default.
+pub const CACHE_DIR_ENV: &str = "GOOGLE_APIS_DISCOVERY_CACHE_DIR";
+
+/// The time a cached document is used for without asking the server whether it changed.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+const USER_AGENT_VALUE: &str = "google-apis-rs/discovery-check";
+
+/// Returns the directory documents are cached in by default, which is the one set in
+/// `GOOGLE_APIS_DISCOVERY_CACHE_DIR`, or `google-apis-rs/discovery` in the cache directory of the
+/// user, like `~/.cache`, and the directory for temporary files if there is none.
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os(CACHE_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(env::temp_dir);
+    cache.join("google-apis-rs").join("discovery")
+}
+
+/// A cache of discovery documents in a directory, which persists them across processes.
+#[derive(Debug, Clone)]
+pub struct DiscoveryCache {
+    dir: PathBuf,
+    root: String,
+    max_age: Duration,
+}
+
+impl Default for DiscoveryCache {
+    fn default() -> DiscoveryCache {
+        DiscoveryCache::new(default_cache_dir())
+    }
+}
+
+impl DiscoveryCache {
+    /// Returns a cache of the documents in `dir`, which is created once the first one is stored.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> DiscoveryCache {
+        DiscoveryCache {
+            dir: dir.into(),
+            root: DISCOVERY_ROOT.to_string(),
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+
+    /// Sets the time a cached document is used for without asking the server whether it changed.
+    /// It defaults to `DEFAULT_MAX_AGE`.
+    pub fn max_age(mut self, max_age: Duration) -> DiscoveryCache {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Sets the root of the URLs the documents are fetched from, like a mirror of the discovery
+    /// service. It defaults to `DISCOVERY_ROOT`.
+    pub fn root(mut self, root: &str) -> DiscoveryCache {
+        self.root = root.to_string();
+        if !self.root.ends_with('/') {
+            self.root.push('/');
+        }
+        self
+    }
+
+    /// The directory the documents are cached in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the URL of the document of `api`, like `pubsub:v1`.
+    pub fn url(&self, api: &str) -> String {
+        let (name, version) = api.split_once(':').unwrap_or((api, ""));
+        format!("{}{}/{}/rest", self.root, name, version)
+    }
+
+    fn path(&self, api: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", api.replace(':', ".")))
+    }
+
+    /// Returns the live discovery document of `api`, like `pubsub:v1`.
+    ///
+    /// The cached document is returned as long as it is younger than the maximum age. Older ones
+    /// are revalidated with the server by their etag, and returned as well if it can't be reached.
+    pub async fn document(
+        &self,
+        client: &hyper::Client<Connector, hyper::body::Body>,
+        api: &str,
+    ) -> Result<json::Value> {
+        let path = self.path(api);
+        let cached = fs::read(&path)
+            .ok()
+            .and_then(|bytes| json::from_slice::<json::Value>(&bytes).ok());
+        let age = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if let (Some(cached), Some(age)) = (&cached, age) {
+            if age < self.max_age {
+                return Ok(cached["document"].clone());
+            }
+        }
+
+        let mut req = hyper::Request::get(self.url(api)).header(USER_AGENT, USER_AGENT_VALUE);
+        if let Some(etag) = cached.as_ref().and_then(|c| c["etag"].as_str()) {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        let req = req
+            .body(hyper::body::Body::empty())
+            .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidInput, err)))?;
+        let res = match client.request(req).await {
+            Ok(res) => res,
+            Err(err) => {
+                return cached
+                    .map(|cached| cached["document"].clone())
+                    .ok_or(Error::HttpError(err))
+            }
+        };
+
+        let entry = match (res.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => cached,
+            (StatusCode::OK, _) => {
+                let etag = res
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string);
+                let body = read_body(&mut res.into_body()).await?;
+                let document: json::Value = json::from_slice(&body).map_err(|err| {
+                    let body = String::from_utf8_lossy(&body).into_owned();
+                    Error::JsonDecodeError(body, String::new(), err)
+                })?;
+                json::json!({"etag": etag, "document": document})
+            }
+            (_, Some(cached)) => return Ok(cached["document"].clone()),
+            (_, None) => return Err(Error::Failure(res)),
+        };
+        // Storing it again also renews its age, if it didn't change
+        self.store(&path, &entry).map_err(Error::Io)?;
+        Ok(entry["document"].clone())
+    }
+
+    fn store(&self, path: &Path, entry: &json::Value) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Writes are atomic, as other processes may read the document at the same time
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, entry.to_string())?;
+        fs::rename(&tmp, path)
+    }
+}
+
+/// Returns the signature of the given `method` of a discovery document, like
+/// `GET v1/{+topic} topic:path:string! - Topic`, which changes if the way it is called does.
+///
+/// It consists of the HTTP method, the path, the parameters sorted by name, with their location
+/// and type, followed by `[]` if they are repeated and `!` if they are required, and the types of
+/// the request and the response, which are `-` if there is none.
+pub fn method_signature(method: &json::Value) -> String {
+    let params: BTreeMap<&str, &json::Value> = method["parameters"]
+        .as_object()
+        .map(|params| params.iter().map(|(k, v)| (k.as_str(), v)).collect())
+        .unwrap_or_default();
+    let params: Vec<String> = params
+        .into_iter()
+        .map(|(name, param)| {
+            let mut signature = format!(
+                "{}:{}:{}",
+                name,
+                param["location"].as_str().unwrap_or(""),
+                param["type"].as_str().unwrap_or("")
+            );
+            if param["repeated"].as_bool() == Some(true) {
+                signature.push_str("[]");
+            }
+            if param["required"].as_bool() == Some(true) {
+                signature.push('!');
+            }
+            signature
+        })
+        .collect();
+    format!(
+        "{} {} {} {} {}",
+        method["httpMethod"].as_str().unwrap_or(""),
+        method["path"].as_str().unwrap_or(""),
+        params.join(","),
+        method["request"]["$ref"].as_str().unwrap_or("-"),
+        method["response"]["$ref"].as_str().unwrap_or("-")
+    )
+}
+
+/// Returns the signatures of all methods of a discovery `document`, by their id.
+pub fn method_signatures(document: &json::Value) -> HashMap<String, String> {
+    fn collect(resource: &json::Value, signatures: &mut HashMap<String, String>) {
+        if let Some(methods) = resource["methods"].as_object() {
+            for method in methods.values() {
+                if let Some(id) = method["id"].as_str() {
+                    signatures.insert(id.to_string(), method_signature(method));
+                }
+            }
+        }
+        if let Some(resources) = resource["resources"].as_object() {
+            for resource in resources.values() {
+                collect(resource, signatures);
+            }
+        }
+    }
+
+    let mut signatures = HashMap::new();
+    collect(document, &mut signatures);
+    signatures
+}
+
+/// A method as it was defined in the discovery document a crate was generated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratedMethod {
+    /// The id of the API, like `pubsub:v1`.
+    pub api: &'static str,
+    /// The revision of the document, like `20230101`.
+    pub revision: &'static str,
+    /// The id of the method, like `pubsub.projects.topics.get`.
+    pub id: &'static str,
+    /// The signature of the method, see `method_signature()`.
+    pub signature: &'static str,
+}
+
+/// A method which changed, or was removed, since the crate calling it was generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutdatedMethod {
+    /// The method as it was generated.
+    pub generated: GeneratedMethod,
+    /// The revision of the live document.
+    pub revision: String,
+    /// The signature of the method in the live document, which is `None` if it was removed.
+    pub signature: Option<String>,
+}
+
+impl fmt::Display for OutdatedMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let generated = &self.generated;
+        match self.signature {
+            Some(ref signature) => write!(
+                f,
+                "The method '{}' changed since revision {} of '{}' this crate was generated from, \
+                 it is '{}' instead of '{}' in revision {}, consider updating the crate",
+                generated.id,
+                generated.revision,
+                generated.api,
+                signature,
+                generated.signature,
+                self.revision
+            ),
+            None => write!(
+                f,
+                "The method '{}' was removed since revision {} of '{}' this crate was generated \
+                 from, and is missing in revision {}",
+                generated.id, generated.revision, generated.api, self.revision
+            ),
+        }
+    }
+}
+
+struct LiveApi {
+    revision: String,
+    signatures: HashMap<String, String>,
+}
+
+// `None` for APIs whose document couldn't be fetched, which are tried once per process
+type LiveApis = HashMap<&'static str, Option<Arc<LiveApi>>>;
+
+/// Compares the methods called on hubs to their live definition, and reports the outdated ones
+/// once. Clones share the documents and the reported methods, so a check can be set on many hubs.
+///
+/// The check never fails a call. If the document can't be fetched, the methods aren't compared.
+#[derive(Clone)]
+pub struct DiscoveryCheck {
+    cache: DiscoveryCache,
+    apis: Arc<tokio::sync::Mutex<LiveApis>>,
+    reported: Arc<Mutex<HashSet<&'static str>>>,
+    on_outdated: Arc<dyn Fn(&OutdatedMethod) + Send + Sync>,
+}
+
+impl fmt::Debug for DiscoveryCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DiscoveryCheck")
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+impl Default for DiscoveryCheck {
+    fn default() -> DiscoveryCheck {
+        DiscoveryCheck::new(DiscoveryCache::default())
+    }
+}
+
+impl DiscoveryCheck {
+    /// Returns a check of the documents in `cache`, which prints outdated methods to stderr.
+    pub fn new(cache: DiscoveryCache) -> DiscoveryCheck {
+        DiscoveryCheck {
+            cache,
+            apis: Default::default(),
+            reported: Default::default(),
+            on_outdated: Arc::new(|outdated| eprintln!("warning: {}", outdated)),
+        }
+    }
+
+    /// Sets the function outdated methods are reported to, like one logging them, instead of
+    /// printing them to stderr.
+    pub fn on_outdated<F>(mut self, on_outdated: F) -> DiscoveryCheck
+    where
+        F: Fn(&OutdatedMethod) + Send + Sync + 'static,
+    {
+        self.on_outdated = Arc::new(on_outdated);
+        self
+    }
+
+    /// Compares the `generated` method to its live definition, returning and reporting it if it
+    /// is outdated and wasn't reported before.
+    pub async fn check(
+        &self,
+        client: &hyper::Client<Connector, hyper::body::Body>,
+        generated: &GeneratedMethod,
+    ) -> Option<OutdatedMethod> {
+        if self.reported.lock().unwrap().contains(generated.id) {
+            return None;
+        }
+        let live = {
+            let mut apis = self.apis.lock().await;
+            match apis.get(generated.api) {
+                Some(live) => live.clone(),
+                None => {
+                    let live = self
+                        .cache
+                        .document(client, generated.api)
+                        .await
+                        .ok()
+                        .map(|doc| {
+                            Arc::new(LiveApi {
+                                revision: doc["revision"].as_str().unwrap_or("").to_string(),
+                                signatures: method_signatures(&doc),
+                            })
+                        });
+                    apis.insert(generated.api, live.clone());
+                    live
+                }
+            }
+        }?;
+
+        let signature = live.signatures.get(generated.id);
+        if live.revision == generated.revision
+            || signature.map(String::as_str) == Some(generated.signature)
+        {
+            return None;
+        }
+        if !self.reported.lock().unwrap().insert(generated.id) {
+            return None;
+        }
+        let outdated = OutdatedMethod {
+            generated: *generated,
+            revision: live.revision.clone(),
+            signature: signature.cloned(),
+        };
+        (self.on_outdated)(&outdated);
+        Some(outdated)
+    }
+}
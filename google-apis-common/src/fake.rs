@@ -0,0 +1,293 @@
+//! A minimal fake of an API's server, keeping its resources in memory, to run integration tests
+//! against hermetically.
+//!
+//! Each API crate provides the routes of its methods as `api::fake::ROUTES`, derived from its
+//! discovery document. Resources are stored under the path they were created at, which is the one
+//! used to get, update, patch or delete them afterwards. Listing a collection returns the resources
+//! stored directly below its path. All other methods respond with an empty object.
+//!
+//! As the fake doesn't check the tokens of calls, `authenticator()` provides one which has a token
+//! for any scope, without asking anyone for consent.
+
+pub use axum;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use hyper::{Method, StatusCode};
+use serde_json as json;
+
+use crate::{oauth2, ConnectionOptions, Connector};
+
+/// What a method does with the resources of the fake.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Get,
+    List,
+    Create,
+    Update,
+    Patch,
+    Delete,
+    /// Any other method, which responds with an empty object.
+    Other,
+}
+
+/// The route of a method of the API.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Route {
+    /// The http method, like `GET`.
+    pub method: &'static str,
+    /// The path template relative to the root url of the API, like `v1/{+parent}/topics`.
+    pub path: &'static str,
+    pub action: Action,
+    /// The field of the list response holding the resources, for `Action::List` routes.
+    pub list_field: Option<&'static str>,
+}
+
+impl Route {
+    /// Returns true if the route handles the `path` of a request, without leading slash.
+    pub fn matches(&self, path: &str) -> bool {
+        let (template, path) = match self.path.rfind('}') {
+            Some(pos) if self.path[pos..].contains(':') => {
+                let verb = &self.path[pos + 1..];
+                match path.strip_suffix(verb) {
+                    Some(path) => (&self.path[..pos + 1], path),
+                    None => return false,
+                }
+            }
+            _ => (self.path, path),
+        };
+        let template: Vec<_> = template.split('/').collect();
+        let path: Vec<_> = path.split('/').collect();
+        segments_match(&template, &path)
+    }
+}
+
+fn segments_match(template: &[&str], path: &[&str]) -> bool {
+    match (template.first(), path.first()) {
+        (None, None) => true,
+        (Some(t), _) if t.starts_with("{+") => (1..=path.len())
+            .any(|n| !path[..n].contains(&"") && segments_match(&template[1..], &path[n..])),
+        (Some(t), Some(p)) if t.starts_with('{') => {
+            !p.is_empty() && segments_match(&template[1..], &path[1..])
+        }
+        (Some(t), Some(p)) => t == p && segments_match(&template[1..], &path[1..]),
+        _ => false,
+    }
+}
+
+fn error(status: StatusCode, message: String) -> (StatusCode, json::Value) {
+    (
+        status,
+        json::json!({"error": {"code": status.as_u16(), "message": message}}),
+    )
+}
+
+/// A fake of an API's server, whose clones share the same resources.
+#[derive(Clone)]
+pub struct FakeServer {
+    routes: &'static [Route],
+    resources: Arc<Mutex<BTreeMap<String, json::Value>>>,
+    next_id: Arc<Mutex<u64>>,
+}
+
+impl FakeServer {
+    /// Creates a fake without resources, serving the given routes.
+    pub fn new(routes: &'static [Route]) -> FakeServer {
+        FakeServer {
+            routes,
+            resources: Default::default(),
+            next_id: Default::default(),
+        }
+    }
+
+    /// Stores the resource at the given path, without leading slash, replacing the one stored
+    /// there before.
+    pub fn insert(&self, path: &str, resource: json::Value) {
+        self.resources
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), resource);
+    }
+
+    /// Returns the resource stored at the given path, without leading slash.
+    pub fn get(&self, path: &str) -> Option<json::Value> {
+        self.resources.lock().unwrap().get(path).cloned()
+    }
+
+    /// Handles a request to the given path, without leading slash, with the given query string
+    /// and body. Returns the status and the body of the response.
+    pub fn handle(
+        &self,
+        method: &Method,
+        path: &str,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> (StatusCode, json::Value) {
+        // `v1/{+parent}/topics` is preferred over `v1/{+name}`, as it is more specific.
+        let route = match self
+            .routes
+            .iter()
+            .filter(|r| r.method == method.as_str() && r.matches(path))
+            .max_by_key(|r| r.path.split('/').filter(|s| !s.starts_with('{')).count())
+        {
+            Some(route) => route,
+            None => {
+                return error(
+                    StatusCode::NOT_FOUND,
+                    format!("No route for {} /{}", method, path),
+                )
+            }
+        };
+        let request_value = if body.is_empty() {
+            json::Value::Object(Default::default())
+        } else {
+            match json::from_slice(body) {
+                Ok(value) => value,
+                Err(err) => return error(StatusCode::BAD_REQUEST, err.to_string()),
+            }
+        };
+
+        let mut resources = self.resources.lock().unwrap();
+        let not_found = || error(StatusCode::NOT_FOUND, format!("/{} was not found", path));
+        match route.action {
+            Action::Get => match resources.get(path) {
+                Some(resource) => (StatusCode::OK, resource.clone()),
+                None => not_found(),
+            },
+            Action::List => {
+                let prefix = format!("{}/", path);
+                let items: Vec<_> = resources
+                    .iter()
+                    .filter(|(p, _)| p.strip_prefix(&prefix).is_some_and(|id| !id.contains('/')))
+                    .map(|(_, resource)| resource.clone())
+                    .collect();
+                let mut response = json::Map::new();
+                if let (Some(field), false) = (route.list_field, items.is_empty()) {
+                    response.insert(field.to_string(), json::Value::Array(items));
+                }
+                (StatusCode::OK, json::Value::Object(response))
+            }
+            Action::Create => {
+                // Resources are put at their own path, but posted to the one of their collection.
+                let path = if method == Method::PUT {
+                    path.to_string()
+                } else {
+                    let id = self.resource_id(&request_value, query);
+                    format!("{}/{}", path, id)
+                };
+                if resources.contains_key(&path) {
+                    return error(StatusCode::CONFLICT, format!("/{} exists already", path));
+                }
+                resources.insert(path, request_value.clone());
+                (StatusCode::OK, request_value)
+            }
+            Action::Update => match resources.get_mut(path) {
+                Some(resource) => {
+                    *resource = request_value.clone();
+                    (StatusCode::OK, request_value)
+                }
+                None => not_found(),
+            },
+            Action::Patch => match resources.get_mut(path) {
+                Some(resource) => {
+                    if let (Some(resource), json::Value::Object(fields)) =
+                        (resource.as_object_mut(), request_value)
+                    {
+                        resource.extend(fields);
+                    }
+                    (StatusCode::OK, resource.clone())
+                }
+                None => not_found(),
+            },
+            Action::Delete => match resources.remove(path) {
+                Some(_) => (StatusCode::OK, json::json!({})),
+                None => not_found(),
+            },
+            Action::Other => (StatusCode::OK, json::json!({})),
+        }
+    }
+
+    /// Returns the id of a resource to create, which is the last segment of its `name`, its `id`,
+    /// the value of an `...Id` query parameter or, if there is none, a new number.
+    fn resource_id(&self, resource: &json::Value, query: Option<&str>) -> String {
+        let from_query = || {
+            query?
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.ends_with("Id"))
+                .map(|(_, value)| value.to_string())
+        };
+        resource["name"]
+            .as_str()
+            .and_then(|name| name.rsplit('/').next())
+            .or_else(|| resource["id"].as_str())
+            .map(str::to_string)
+            .or_else(from_query)
+            .unwrap_or_else(|| {
+                let mut next_id = self.next_id.lock().unwrap();
+                *next_id += 1;
+                next_id.to_string()
+            })
+    }
+
+    /// Returns an `axum::Router` handling all requests with this fake.
+    pub fn router(&self) -> axum::Router {
+        let fake = self.clone();
+        axum::Router::new().fallback(
+            move |method: Method, uri: hyper::Uri, body: hyper::body::Bytes| async move {
+                let path = uri.path().trim_start_matches('/');
+                let (status, value) = fake.handle(&method, path, uri.query(), &body);
+                (status, axum::Json(value))
+            },
+        )
+    }
+
+    /// Serves this fake on a free port of the local host, in a task of the current tokio runtime.
+    /// Returns the address to use as root url of the hub, like `http://{address}/`.
+    pub fn spawn(&self) -> SocketAddr {
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(self.router().into_make_service());
+        let address = server.local_addr();
+        tokio::spawn(server);
+        address
+    }
+}
+
+/// Returns an authenticator with a token for the given `scopes`, which never expires, to make calls
+/// to a fake with.
+pub async fn authenticator<T: AsRef<str>>(
+    scopes: &[T],
+) -> io::Result<oauth2::authenticator::Authenticator<Connector>> {
+    static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+    // The token can only be handed to the authenticator by the file it is persisted to, which is
+    // read once it was built.
+    let path = env::temp_dir().join(format!(
+        "google-apis-fake-tokens-{}-{}.json",
+        process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    let scopes: Vec<_> = scopes.iter().map(|s| s.as_ref()).collect();
+    let tokens = json::json!([{
+        "scopes": scopes,
+        "token": {"access_token": "fake", "refresh_token": null, "expires_at": null},
+    }]);
+    fs::write(&path, tokens.to_string())?;
+    let auth = oauth2::InstalledFlowAuthenticator::builder(
+        oauth2::ApplicationSecret::default(),
+        oauth2::InstalledFlowReturnMethod::HTTPRedirect,
+    )
+    .hyper_client(ConnectionOptions::default().client())
+    .persist_tokens_to_disk(&path)
+    .build()
+    .await;
+    fs::remove_file(&path).ok();
+    auth
+}
@@ -0,0 +1,141 @@
+//! Access to the JSON Schema of the schemas of an API, which each API crate provides as its
+//! `api::JSON_SCHEMA`, to validate payloads in other languages or to generate forms consistent with
+//! the generated types.
+//!
+//! The document follows draft 7 of JSON Schema, and keeps each schema under its name in
+//! `definitions`, where the other schemas refer to it. Values are described as they are sent, so
+//! 64 bit integers and durations are strings, with their `format`, just like in the discovery
+//! document of the API.
+
+use std::sync::OnceLock;
+
+use serde_json as json;
+
+/// The dialect of JSON Schema the documents of API crates follow.
+pub const DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+const DEFINITIONS_REF: &str = "#/definitions/";
+const COMPONENTS_REF: &str = "#/components/schemas/";
+
+fn parse(document: &str) -> json::Value {
+    json::from_str(document).expect("the JSON Schema of API crates to be valid JSON")
+}
+
+/// A JSON Schema `document` which is parsed once it is first used, like the `api::JSON_SCHEMA` of an
+/// API crate, which strict decoding checks responses with.
+#[derive(Debug)]
+pub struct Document {
+    source: &'static str,
+    parsed: OnceLock<json::Value>,
+}
+
+impl Document {
+    pub const fn new(source: &'static str) -> Document {
+        Document {
+            source,
+            parsed: OnceLock::new(),
+        }
+    }
+
+    /// Returns the parsed document.
+    pub fn get(&self) -> &json::Value {
+        self.parsed.get_or_init(|| parse(self.source))
+    }
+
+    /// Returns the path and the value of the first string in `value`, a value of the schema `name`,
+    /// which is none of the values of the enumeration of its field, like `("notes[3].kind", "NEW")`.
+    /// Fields missing in the schema are skipped, as the server may know more of them than the
+    /// document does.
+    pub fn unknown_enum_value(&self, name: &str, value: &json::Value) -> Option<(String, String)> {
+        let definitions = &self.get()["definitions"];
+        let mut path = String::new();
+        unknown_enum_value(definitions, definitions.get(name)?, value, &mut path)
+    }
+}
+
+fn unknown_enum_value(
+    definitions: &json::Value,
+    schema: &json::Value,
+    value: &json::Value,
+    path: &mut String,
+) -> Option<(String, String)> {
+    let reference = schema.get("$ref").and_then(json::Value::as_str);
+    let schema = match reference.and_then(|r| r.strip_prefix(DEFINITIONS_REF)) {
+        Some(name) => definitions.get(name)?,
+        None => schema,
+    };
+    let len = path.len();
+    let found = match value {
+        json::Value::String(s) => {
+            let values = schema.get("enum")?.as_array()?;
+            if values.iter().any(|v| v == s) {
+                None
+            } else if path.is_empty() {
+                Some((".".to_string(), s.clone()))
+            } else {
+                Some((path.clone(), s.clone()))
+            }
+        }
+        json::Value::Array(items) => {
+            let schema = schema.get("items")?;
+            items.iter().enumerate().find_map(|(i, item)| {
+                path.truncate(len);
+                path.push_str(&format!("[{}]", i));
+                unknown_enum_value(definitions, schema, item, path)
+            })
+        }
+        json::Value::Object(fields) => fields.iter().find_map(|(name, field)| {
+            let schema = schema
+                .get("properties")
+                .and_then(|properties| properties.get(name))
+                .or_else(|| schema.get("additionalProperties"))?;
+            path.truncate(len);
+            if len > 0 {
+                path.push('.');
+            }
+            path.push_str(name);
+            unknown_enum_value(definitions, schema, field, path)
+        }),
+        _ => None,
+    };
+    path.truncate(len);
+    found
+}
+
+/// Returns the given JSON Schema `document` with the schema `name`, like `Topic`, at its root,
+/// which validates a single value of it. It is `None` if there is no such schema.
+pub fn definition(document: &str, name: &str) -> Option<json::Value> {
+    let mut document = parse(document);
+    document["definitions"].get(name)?;
+    document["$ref"] = json::Value::String(format!("{}{}", DEFINITIONS_REF, name));
+    Some(document)
+}
+
+/// Returns the schemas of the given JSON Schema `document` as the `components` of an OpenAPI 3.1
+/// document, whose references point to `#/components/schemas/` instead.
+pub fn openapi_components(document: &str) -> json::Value {
+    let mut schemas = parse(document)["definitions"].take();
+    rewrite_refs(&mut schemas);
+    json::json!({ "schemas": schemas })
+}
+
+fn rewrite_refs(value: &mut json::Value) {
+    match value {
+        json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let name = match value.as_str() {
+                    Some(reference) if key == "$ref" => reference.strip_prefix(DEFINITIONS_REF),
+                    _ => None,
+                };
+                match name {
+                    Some(name) => {
+                        *value = json::Value::String(format!("{}{}", COMPONENTS_REF, name))
+                    }
+                    None => rewrite_refs(value),
+                }
+            }
+        }
+        json::Value::Array(values) => values.iter_mut().for_each(rewrite_refs),
+        _ => {}
+    }
+}
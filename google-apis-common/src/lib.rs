@@ -0,0 +1,4257 @@
+//! Types and functionality shared by all generated API crates, like the `Delegate`, the `Error`
+//! and the helpers performing uploads.
+//!
+//! Each API crate re-exports this crate as its `client` module, which makes these types the same
+//! across all hubs.
+
+// Re-export the yup_oauth2 crate, whose types are part of the `Delegate` and the `Error`.
+pub use yup_oauth2 as oauth2;
+
+// Re-export the token cancelling calls, which is passed to hubs and call builders.
+pub use tokio_util::sync::CancellationToken;
+
+// Re-export the trait of the streams returned by calls, like the items of all pages of a list.
+pub use futures::stream::Stream;
+
+#[cfg(feature = "cassette")]
+pub mod cassette;
+pub mod discovery;
+#[cfg(feature = "fake")]
+pub mod fake;
+pub mod json_schema;
+pub mod patch;
+pub mod runtime;
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::env;
+use std::error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::Path;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures::future::{self, Either, Future};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use itertools::Itertools;
+
+use hyper::body::Bytes;
+use hyper::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH,
+    CONTENT_TYPE, USER_AGENT,
+};
+use hyper::Method;
+use hyper::StatusCode;
+
+use mime::{Attr, Mime, SubLevel, TopLevel, Value};
+
+use serde_json as json;
+
+use tokio::io::AsyncRead;
+
+const LINE_ENDING: &str = "\r\n";
+
+/// The amount of bytes read at once from an `AsyncRead` by `MediaStream::from_async_read()`.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+pub enum Retry {
+    /// Signal you don't want to retry
+    Abort,
+    /// Signals you want to retry after the given duration
+    After(Duration),
+}
+
+#[cfg(not(any(
+    feature = "rustls",
+    feature = "rustls-native-roots",
+    feature = "native-tls"
+)))]
+compile_error!(
+    "one of the features `rustls`, `rustls-native-roots` or `native-tls` must be enabled"
+);
+
+/// The connector all hubs use to talk to the server, whose TLS implementation is chosen by the
+/// `rustls` (default), `rustls-native-roots` and `native-tls` features.
+#[cfg(not(feature = "native-tls"))]
+pub type Connector = hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>;
+/// The connector all hubs use to talk to the server, whose TLS implementation is chosen by the
+/// `rustls` (default), `rustls-native-roots` and `native-tls` features.
+#[cfg(feature = "native-tls")]
+pub type Connector = hyper_tls::HttpsConnector<hyper::client::connect::HttpConnector>;
+
+/// Returns a new connector, using the TLS implementation and the root certificates of the enabled
+/// features:
+///
+/// * `native-tls` uses the TLS library of the OS, like OpenSSL, and its trust store. It takes
+///   precedence over the others, and doesn't negotiate HTTP/2.
+/// * `rustls-native-roots` uses rustls, which trusts the root certificates of the OS.
+/// * `rustls`, the default, uses rustls, which trusts the bundled Mozilla root certificates.
+pub fn https_connector() -> Connector {
+    #[cfg(feature = "native-tls")]
+    return hyper_tls::HttpsConnector::new();
+    #[cfg(all(not(feature = "native-tls"), feature = "rustls-native-roots"))]
+    return hyper_rustls::HttpsConnector::with_native_roots();
+    #[cfg(all(
+        not(feature = "native-tls"),
+        not(feature = "rustls-native-roots"),
+        feature = "rustls"
+    ))]
+    return hyper_rustls::HttpsConnector::with_webpki_roots();
+}
+
+/// Identifies the Hub. There is only one per library, this trait is supposed
+/// to make intended use more explicit.
+/// The hub allows to access all resource methods more easily.
+/// It can also be created by a `GoogleClient`, which shares its parts with other hubs.
+pub trait Hub {
+    /// Creates a hub using the given client and authenticator.
+    fn from_parts(
+        client: hyper::Client<Connector, hyper::body::Body>,
+        auth: oauth2::authenticator::Authenticator<Connector>,
+    ) -> Self
+    where
+        Self: Sized;
+}
+
+/// Fails to compile unless `T` may be shared between threads, which every hub asserts for itself
+/// so it can be put into an `Arc` by multi-threaded services.
+pub const fn assert_send_sync<T: Send + Sync>() {}
+
+/// Fails to compile unless `T` may be sent to another thread, like the call builders of hubs.
+pub const fn assert_send<T: Send>() {}
+
+/// Fails to compile unless the futures returned by `call` may be sent to another thread, to be
+/// spawned onto multi-threaded runtimes. `call` is never invoked.
+pub fn assert_send_future<B, F, C>(_call: C)
+where
+    C: FnOnce(B) -> F,
+    F: Future + Send,
+{
+}
+
+/// Owns a hyper client and an authenticator to hand out hubs of any library sharing them,
+/// and thus their connection pool and token cache.
+///
+/// ```ignore
+/// let google = GoogleClient::new(auth);
+/// let analysis = google.hub::<ContainerAnalysis>();
+/// let storage = google.hub::<Storage>();
+/// ```
+#[derive(Clone)]
+pub struct GoogleClient {
+    client: hyper::Client<Connector, hyper::body::Body>,
+    auth: oauth2::authenticator::Authenticator<Connector>,
+}
+
+impl GoogleClient {
+    /// Creates a client using the given authenticator, along with a new hyper client using the
+    /// `https_connector()`, which is configured with the default `ConnectionOptions`.
+    pub fn new(auth: oauth2::authenticator::Authenticator<Connector>) -> GoogleClient {
+        GoogleClient::with_options(auth, &ConnectionOptions::default())
+    }
+
+    /// Creates a client using the given authenticator, along with a new hyper client using the
+    /// `https_connector()`, which is configured with the given `options`.
+    pub fn with_options(
+        auth: oauth2::authenticator::Authenticator<Connector>,
+        options: &ConnectionOptions,
+    ) -> GoogleClient {
+        GoogleClient::from_parts(options.client(), auth)
+    }
+
+    /// Creates a client using the given hyper client and authenticator.
+    pub fn from_parts(
+        client: hyper::Client<Connector, hyper::body::Body>,
+        auth: oauth2::authenticator::Authenticator<Connector>,
+    ) -> GoogleClient {
+        GoogleClient { client, auth }
+    }
+
+    /// Returns a new hub of the given type, sharing the hyper client and authenticator with all
+    /// other hubs created by this instance.
+    pub fn hub<H: Hub>(&self) -> H {
+        H::from_parts(self.client.clone(), self.auth.clone())
+    }
+
+    /// Returns the hyper client shared by all hubs.
+    pub fn client(&self) -> &hyper::Client<Connector, hyper::body::Body> {
+        &self.client
+    }
+
+    /// Returns the authenticator shared by all hubs.
+    pub fn auth(&self) -> &oauth2::authenticator::Authenticator<Connector> {
+        &self.auth
+    }
+}
+
+/// Configures the connection pool of the hyper clients used by hubs, and their HTTP/2 connections.
+///
+/// Connections negotiate HTTP/2 using ALPN, and fall back to HTTP/1.1 if the server doesn't
+/// support it. HTTP/2 multiplexes all requests to a host over a single connection, which
+/// improves the throughput of workloads making many requests at once.
+///
+/// ```ignore
+/// let options = ConnectionOptions {
+///     pool_max_idle_per_host: Some(16),
+///     http2_keep_alive_interval: Some(Duration::from_secs(30)),
+///     ..Default::default()
+/// };
+/// let hub = Storage::new(options.client(), auth);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionOptions {
+    /// The maximum amount of idle connections kept per host, which is unlimited if `None`.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// The duration after which idle connections are closed, which is 90 seconds if `None`.
+    pub pool_idle_timeout: Option<Duration>,
+    /// The interval at which HTTP/2 pings are sent to keep connections alive, which
+    /// disables them if `None`.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Whether the HTTP/2 flow control windows adapt to the measured bandwidth and latency,
+    /// instead of being fixed. It is enabled by default.
+    pub http2_adaptive_window: bool,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> ConnectionOptions {
+        ConnectionOptions {
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            http2_keep_alive_interval: None,
+            http2_adaptive_window: true,
+        }
+    }
+}
+
+impl ConnectionOptions {
+    /// Returns a new hyper client configured with these options, using the `https_connector()`.
+    /// Pass it to the `hyper_client()` of authenticator builders to authenticate with the same
+    /// TLS implementation, whose connector is then the `Connector` of hubs.
+    pub fn client(&self) -> hyper::Client<Connector, hyper::body::Body> {
+        let mut builder = hyper::Client::builder();
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        builder
+            .http2_keep_alive_interval(self.http2_keep_alive_interval)
+            .http2_adaptive_window(self.http2_adaptive_window)
+            .executor(runtime::Executor::default())
+            .build(https_connector())
+    }
+}
+
+/// How the connection of a request failed, which tells whether retrying it may succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConnectionFailure {
+    /// The name of the server couldn't be resolved.
+    Dns,
+    /// The server couldn't be reached, or refused the connection.
+    Connect,
+    /// The TLS handshake failed, like if the certificate of the server isn't trusted.
+    Tls,
+    /// The connection was reset, or closed before the response was received.
+    Reset,
+    /// Connecting or waiting for the response timed out.
+    TimedOut,
+}
+
+impl ConnectionFailure {
+    /// Returns how the connection of the request failed with `err`, or `None` if it failed for
+    /// another reason, like a body which couldn't be read.
+    pub fn classify(err: &hyper::Error) -> Option<ConnectionFailure> {
+        let mut source: Option<&(dyn error::Error + 'static)> = Some(err);
+        while let Some(err) = source {
+            if err.is::<rustls::TLSError>() {
+                return Some(ConnectionFailure::Tls);
+            }
+            #[cfg(feature = "native-tls")]
+            if err.is::<hyper_tls::native_tls::Error>() {
+                return Some(ConnectionFailure::Tls);
+            }
+            // the error of the connector of hyper is private, but says what failed
+            if err.to_string().starts_with("dns error") {
+                return Some(ConnectionFailure::Dns);
+            }
+            let io_err = err.downcast_ref::<io::Error>();
+            match io_err.map(io::Error::kind) {
+                Some(io::ErrorKind::ConnectionRefused) | Some(io::ErrorKind::AddrNotAvailable) => {
+                    return Some(ConnectionFailure::Connect)
+                }
+                Some(io::ErrorKind::ConnectionReset)
+                | Some(io::ErrorKind::ConnectionAborted)
+                | Some(io::ErrorKind::BrokenPipe)
+                | Some(io::ErrorKind::UnexpectedEof) => return Some(ConnectionFailure::Reset),
+                Some(io::ErrorKind::TimedOut) => return Some(ConnectionFailure::TimedOut),
+                _ => {}
+            }
+            // the source of an `io::Error` is the one of the error it wraps, like the one of the
+            // TLS implementation, which would be skipped
+            source = match io_err {
+                Some(io_err) => io_err.get_ref().map(|err| err as _),
+                None => err.source(),
+            };
+        }
+        if err.is_timeout() {
+            Some(ConnectionFailure::TimedOut)
+        } else if err.is_connect() {
+            Some(ConnectionFailure::Connect)
+        } else if err.is_incomplete_message() || err.is_canceled() || err.is_closed() {
+            Some(ConnectionFailure::Reset)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if retrying the request may succeed, which is the case unless the TLS
+    /// handshake failed, as the certificate of the server won't change.
+    pub fn is_retryable(self) -> bool {
+        self != ConnectionFailure::Tls
+    }
+}
+
+/// Replaces the hyper client of a hub by a new one after its connections failed repeatedly, which
+/// otherwise leaves long-running processes with a client whose requests keep failing, like after
+/// the network changed. The new client has a new pool of connections, and resolves the names of
+/// servers again.
+///
+/// Clones share the new client, and the count of the failures.
+#[derive(Debug, Clone)]
+pub struct ConnectionRebuild {
+    options: ConnectionOptions,
+    max_failures: u32,
+    failures: Arc<std::sync::atomic::AtomicU32>,
+    client: Arc<Mutex<Option<hyper::Client<Connector, hyper::body::Body>>>>,
+}
+
+impl ConnectionRebuild {
+    /// The amount of consecutive connection failures after which hubs rebuild their client by
+    /// default.
+    pub const DEFAULT_MAX_FAILURES: u32 = 5;
+
+    /// Rebuilds the client with the given `options` after `max_failures` consecutive requests
+    /// failed to connect, or lost their connection.
+    pub fn new(options: ConnectionOptions, max_failures: u32) -> ConnectionRebuild {
+        ConnectionRebuild {
+            options,
+            max_failures: max_failures.max(1),
+            failures: Default::default(),
+            client: Default::default(),
+        }
+    }
+
+    /// Returns the client to send the next request with, which is `client` until it was rebuilt.
+    pub fn client(
+        &self,
+        client: &hyper::Client<Connector, hyper::body::Body>,
+    ) -> hyper::Client<Connector, hyper::body::Body> {
+        match *self.client.lock().unwrap() {
+            Some(ref rebuilt) => rebuilt.clone(),
+            None => client.clone(),
+        }
+    }
+
+    /// Records the outcome of sending a request, which failed with `err` if it is given, and
+    /// rebuilds the client once there were too many consecutive connection failures. Failures
+    /// which aren't retryable, like failed TLS handshakes, are skipped, as a new client wouldn't
+    /// avoid them. Returns true if it was rebuilt.
+    pub fn record(&self, err: Option<&hyper::Error>) -> bool {
+        use std::sync::atomic::Ordering;
+
+        match err.and_then(ConnectionFailure::classify) {
+            None => {
+                self.failures.store(0, Ordering::Relaxed);
+                return false;
+            }
+            Some(failure) if !failure.is_retryable() => return false,
+            Some(_) => {}
+        }
+        if self.failures.fetch_add(1, Ordering::Relaxed) + 1 < self.max_failures {
+            return false;
+        }
+        self.failures.store(0, Ordering::Relaxed);
+        *self.client.lock().unwrap() = Some(self.options.client());
+        true
+    }
+}
+
+/// Identifies types for building methods of a particular resource type
+pub trait MethodsBuilder {}
+
+/// Identifies types which represent builders for a particular resource method
+pub trait CallBuilder {}
+
+/// Identifies types which can be inserted and deleted.
+/// Types with this trait are most commonly used by clients of this API.
+pub trait Resource {}
+
+/// Identifies types which are used in API responses.
+pub trait ResponseResult {}
+
+/// Identifies types which are used in API requests.
+pub trait RequestValue {}
+
+/// Identifies types which are not actually used by the API
+/// This might be a bug within the google API schema.
+pub trait UnusedType {}
+
+/// Identifies types which are only used as part of other types, which
+/// usually are carrying the `Resource` trait.
+pub trait Part {}
+
+/// Identifies types which are only used by other types internally.
+/// They have no special meaning, this trait just marks them for completeness.
+pub trait NestedType {}
+
+/// A utility to specify reader types which provide seeking capabilities too
+pub trait ReadSeek: Seek + Read + Send {}
+impl<T: Seek + Read + Send> ReadSeek for T {}
+
+/// The amount of bytes at the start of a file which are inspected to detect its MIME type.
+pub const MIME_SNIFF_SIZE: usize = 8192;
+
+/// Returns the MIME type of the file at `path`, guessed from its extension, or from `head`, the
+/// bytes it starts with, if the extension is unknown. Falls back to `application/octet-stream`.
+pub fn guess_mime_type(path: &Path, head: &[u8]) -> Mime {
+    mime_guess::from_path(path)
+        .first_raw()
+        .or_else(|| infer::get(head).map(|kind| kind.mime_type()))
+        .and_then(|mime| mime.parse().ok())
+        .unwrap_or(Mime(TopLevel::Application, SubLevel::OctetStream, vec![]))
+}
+
+/// Like `guess_mime_type()`, but reads the first bytes of the file at `path` from `reader`, which
+/// is rewound afterwards.
+pub fn sniff_mime_type<R: Read + Seek>(path: &Path, reader: &mut R) -> io::Result<Mime> {
+    let mut head = Vec::with_capacity(MIME_SNIFF_SIZE);
+    reader
+        .by_ref()
+        .take(MIME_SNIFF_SIZE as u64)
+        .read_to_end(&mut head)?;
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(guess_mime_type(path, &head))
+}
+
+/// Opens the file at `path` for uploading it, along with its MIME type as detected by
+/// `sniff_mime_type()`.
+#[allow(clippy::result_large_err)]
+pub fn open_upload_file(path: &Path) -> Result<(fs::File, Mime)> {
+    let mut file = fs::File::open(path)?;
+    let mime_type = sniff_mime_type(path, &mut file)?;
+    Ok((file, mime_type))
+}
+
+/// A trait for all types that can convert themselves into a *parts* string
+pub trait ToParts {
+    fn to_parts(&self) -> String;
+}
+
+/// A trait specifying functionality to help controlling any request performed by the API.
+/// The trait has a conservative default implementation.
+///
+/// It contains methods to deal with all common issues, as well with the ones related to
+/// uploading media
+pub trait Delegate: Send {
+    /// Called at the beginning of any API request. The delegate should store the method
+    /// information if he is interesting in knowing more context when further calls to it
+    /// are made.
+    /// The matching `finished()` call will always be made, no matter whether or not the API
+    /// request was successful. That way, the delegate may easily maintain a clean state
+    /// between various API calls.
+    fn begin(&mut self, _info: MethodInfo) {}
+
+    /// Called whenever there is an [HttpError](hyper::Error), usually if there are network problems.
+    ///
+    /// If you choose to retry after a duration, the duration should be chosen using the
+    /// [exponential backoff algorithm](http://en.wikipedia.org/wiki/Exponential_backoff).
+    ///
+    /// Return retry information.
+    fn http_error(&mut self, _err: &hyper::Error) -> Retry {
+        Retry::Abort
+    }
+
+    /// Called whenever there is the need for your applications API key after
+    /// the official authenticator implementation didn't provide one, for some reason.
+    /// If this method returns None as well, the underlying operation will fail
+    fn api_key(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Called whenever the Authenticator didn't yield a token. The delegate
+    /// may attempt to provide one, or just take it as a general information about the
+    /// impending failure.
+    /// The given Error provides information about why the token couldn't be acquired in the
+    /// first place
+    fn token(&mut self, err: &oauth2::Error) -> Option<oauth2::AccessToken> {
+        let _ = err;
+        None
+    }
+
+    /// Called during resumable uploads to provide a URL for the impending upload.
+    /// It was saved after a previous call to `store_upload_url(...)`, and if not None,
+    /// will be used instead of asking the server for a new upload URL.
+    /// This is useful in case a previous resumable upload was aborted/canceled, but should now
+    /// be resumed.
+    /// The returned URL will be used exactly once - if it fails again and the delegate allows
+    /// to retry, we will ask the server for a new upload URL.
+    fn upload_url(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Called after we have retrieved a new upload URL for a resumable upload to store it
+    /// in case we fail or cancel. That way, we can attempt to resume the upload later,
+    /// see `upload_url()`.
+    /// It will also be called with None after a successful upload, which allows the delegate
+    /// to forget the URL. That way, we will not attempt to resume an upload that has already
+    /// finished.
+    fn store_upload_url(&mut self, url: Option<&str>) {
+        let _ = url;
+    }
+
+    /// Called whenever a server response could not be decoded from json.
+    /// It's for informational purposes only, the caller will return with an error
+    /// accordingly.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_encoded_value` - The json-encoded value which failed to decode.
+    /// * `json_decode_error`  - The decoder error
+    fn response_json_decode_error(
+        &mut self,
+        json_encoded_value: &str,
+        json_decode_error: &json::Error,
+    ) {
+        let _ = json_encoded_value;
+        let _ = json_decode_error;
+    }
+
+    /// Called whenever the http request returns with a non-success status code.
+    /// This can involve authentication issues, or anything else that very much
+    /// depends on the used API method.
+    /// The delegate should check the status, header and decoded json error to decide
+    /// whether to retry or not. In the latter case, the underlying call will fail.
+    ///
+    /// If you choose to retry after a duration, the duration should be chosen using the
+    /// [exponential backoff algorithm](http://en.wikipedia.org/wiki/Exponential_backoff).
+    /// If the server asked for a longer delay, that one is used instead, see `server_retry_delay()`.
+    ///
+    /// The default implementation, and thus the `DefaultDelegate`, never retries, so the delay
+    /// asked for by the server only applies to delegates returning `Retry::After`.
+    fn http_failure(
+        &mut self,
+        _: &hyper::Response<hyper::body::Body>,
+        _err: Option<serde_json::Value>,
+    ) -> Retry {
+        Retry::Abort
+    }
+
+    /// Called right before waiting for the given `delay` to retry a failed request, after
+    /// `http_failure()` asked for it. The delay is the one the server asked for, if it was longer.
+    fn retrying(&mut self, delay: Duration) {
+        let _ = delay;
+    }
+
+    /// Called prior to sending the main request of the given method. It can be used to time
+    /// the call or to print progress information.
+    /// It's also useful as you can be sure that a request will definitely be made.
+    fn pre_request(&mut self) {}
+
+    /// Return the size of each chunk of a resumable upload.
+    /// Must be a power of two, with 1<<18 being the smallest allowed chunk size.
+    /// Will be called once before starting any resumable upload.
+    fn chunk_size(&mut self) -> u64 {
+        1 << 23
+    }
+
+    /// Called before the given chunk is uploaded to the server.
+    /// If true is returned, the upload will be interrupted.
+    /// However, it may be resumable if you stored the upload URL in a previous call
+    /// to `store_upload_url()`
+    fn cancel_chunk_upload(&mut self, chunk: &ContentRange) -> bool {
+        let _ = chunk;
+        false
+    }
+
+    /// Called before the API request method returns, in every case. It can be used to clean up
+    /// internal state between calls to the API.
+    /// This call always has a matching call to `begin(...)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_success` - a true value indicates the operation was successful. If false, you should
+    ///   discard all values stored during `store_upload_url`.
+    fn finished(&mut self, is_success: bool) {
+        let _ = is_success;
+    }
+}
+
+/// A delegate with a conservative default implementation, which is used if no other delegate is
+/// set.
+#[derive(Default)]
+pub struct DefaultDelegate;
+
+impl Delegate for DefaultDelegate {}
+
+/// The error of a call. Variants may be added in new versions, so matches need a catch-all arm.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+#[non_exhaustive]
+pub enum Error {
+    /// The http connection failed
+    HttpError(hyper::Error),
+
+    /// An attempt was made to upload a resource with size stored in field `.0`
+    /// even though the maximum upload size is what is stored in field `.1`.
+    UploadSizeLimitExceeded(u64, u64),
+
+    /// Represents information about a request that was not understood by the server.
+    /// Details are included.
+    BadRequest(serde_json::Value),
+
+    /// We needed an API key for authentication, but didn't obtain one.
+    /// Neither through the authenticator, nor through the Delegate.
+    MissingAPIKey,
+
+    /// We required a Token, but didn't get one from the Authenticator
+    MissingToken(oauth2::Error),
+
+    /// The delgate instructed to cancel the operation, or a cancellation token was cancelled
+    Cancelled,
+
+    /// The operation didn't complete before the timeout of its call builder or hub, which is
+    /// stored in field `.0`
+    Timeout(Duration),
+
+    /// An additional, free form field clashed with one of the built-in optional ones
+    FieldClash(&'static str),
+
+    /// Shows that we failed to decode the server response.
+    /// This can happen if the protocol changes in conjunction with strict json decoding.
+    /// The fields are the body of the response, the path of the value that couldn't be decoded,
+    /// like `notes[3].kind`, and the error, which names the type that was expected.
+    JsonDecodeError(String, String, json::Error),
+
+    /// A header set on the CallBuilder is set by the call itself, or isn't a valid header.
+    /// The header is stored in field `.0`.
+    InvalidHeader(String),
+
+    /// Indicates an HTTP repsonse with a non-success status code
+    Failure(hyper::Response<hyper::body::Body>),
+
+    /// An IO error occurred while reading a stream into memory
+    Io(std::io::Error),
+
+    /// The checksum of downloaded media differs from the one the server sent, as it was corrupted
+    /// while it was received.
+    ChecksumMismatch(ChecksumMismatch),
+
+    /// A packed message, like the response of a long running operation, is of another type than
+    /// the one it was decoded as.
+    AnyTypeMismatch(AnyTypeMismatch),
+
+    /// The body of a response is larger than the maximum size of the hub, which is stored in field
+    /// `.0`. It is detected before the body is read, if it announces its size, or as it arrives.
+    ResponseTooLarge(u64),
+
+    /// A response holds a value which is none of the values of the enumeration of its field, as
+    /// hubs decoding strictly detect. The fields are the path of the value, like `notes[3].kind`,
+    /// and the value, which a crate generated from a newer discovery document may know.
+    UnknownEnumValue(String, String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => err.fmt(f),
+            Error::HttpError(ref err) => err.fmt(f),
+            Error::UploadSizeLimitExceeded(ref resource_size, ref max_size) => writeln!(
+                f,
+                "The media size {} exceeds the maximum allowed upload size of {}",
+                resource_size, max_size
+            ),
+            Error::MissingAPIKey => {
+                (writeln!(
+                    f,
+                    "The application's API key was not found in the configuration"
+                ))
+                .ok();
+                writeln!(
+                    f,
+                    "It is used as there are no Scopes defined for this method."
+                )
+            }
+            Error::BadRequest(ref message) => {
+                writeln!(f, "Bad Request: {}", message)?;
+                Ok(())
+            }
+            Error::MissingToken(ref err) => {
+                writeln!(f, "Token retrieval failed with error: {}", err)
+            }
+            Error::Cancelled => writeln!(f, "Operation cancelled"),
+            Error::Timeout(ref timeout) => {
+                writeln!(f, "Operation timed out after {:?}", timeout)
+            }
+            Error::FieldClash(field) => writeln!(
+                f,
+                "The custom parameter '{}' is already provided natively by the CallBuilder.",
+                field
+            ),
+            Error::InvalidHeader(ref name) => writeln!(
+                f,
+                "The header '{}' is set by the CallBuilder itself, or isn't a valid header.",
+                name
+            ),
+            Error::JsonDecodeError(_, ref path, ref err) => {
+                writeln!(f, "Failed to decode the response at '{}': {}", path, err)
+            }
+            Error::Failure(ref response) => {
+                writeln!(f, "Http status indicates failure: {:?}", response)
+            }
+            Error::ChecksumMismatch(ref mismatch) => writeln!(f, "{}", mismatch),
+            Error::AnyTypeMismatch(ref mismatch) => writeln!(f, "{}", mismatch),
+            Error::ResponseTooLarge(max_size) => writeln!(
+                f,
+                "The response exceeds the maximum allowed response size of {} bytes",
+                max_size
+            ),
+            Error::UnknownEnumValue(ref path, ref value) => writeln!(
+                f,
+                "The value '{}' at '{}' of the response is unknown to this version of the crate",
+                value, path
+            ),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the error the server responded with, if it sent one, whose reasons can be matched
+    /// on, like `api_err.has_reason(ErrorReason::RateLimitExceeded)`.
+    pub fn api_error(&self) -> Option<ApiError> {
+        match *self {
+            Error::BadRequest(ref value) => ApiError::from_json(value),
+            _ => None,
+        }
+    }
+
+    /// Returns how the connection of the request failed, if it did, which tells whether retrying
+    /// it may succeed.
+    pub fn connection_failure(&self) -> Option<ConnectionFailure> {
+        match *self {
+            Error::HttpError(ref err) => ConnectionFailure::classify(err),
+            _ => None,
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::HttpError(ref err) => err.source(),
+            Error::JsonDecodeError(_, _, ref err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<hyper::Error> for Error {
+    /// Converts errors reading the body of a response, which are `ChecksumMismatch` errors if the
+    /// checksum of the downloaded media didn't match.
+    fn from(err: hyper::Error) -> Self {
+        match checksum_mismatch(&err) {
+            Some(mismatch) => Error::ChecksumMismatch(mismatch.clone()),
+            None => Error::HttpError(err),
+        }
+    }
+}
+
+/// A universal result type used as return for all calls.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Limits the amount of requests a hub has in flight at the same time, across all of its clones,
+/// which keeps applications spawning many calls from opening an unbounded amount of connections.
+#[derive(Clone, Debug)]
+pub struct InFlightLimit {
+    max: usize,
+    semaphore: Arc<tokio::sync::Semaphore>,
+}
+
+impl InFlightLimit {
+    /// Allows `max` requests in flight at the same time, but at least one.
+    pub fn new(max: usize) -> InFlightLimit {
+        let max = max.max(1);
+        InFlightLimit {
+            max,
+            semaphore: Arc::new(tokio::sync::Semaphore::new(max)),
+        }
+    }
+
+    /// Returns the amount of requests allowed in flight at the same time.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Returns the amount of requests which may be sent right now, without waiting.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+/// Runs `future`, which sends a request, once `limit` allows another request in flight. Without a
+/// limit it runs right away. The request stays in flight until the body of its response was read,
+/// or dropped, as it still uses the connection until then.
+pub async fn in_flight<F>(
+    limit: Option<&InFlightLimit>,
+    future: F,
+) -> hyper::Result<hyper::Response<hyper::Body>>
+where
+    F: Future<Output = hyper::Result<hyper::Response<hyper::Body>>>,
+{
+    use hyper::body::HttpBody;
+
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return future.await,
+    };
+    let permit = limit
+        .semaphore
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("the semaphore is never closed");
+    let res = future.await?;
+    if res.body().is_end_stream() {
+        return Ok(res);
+    }
+    Ok(res.map(|body| {
+        hyper::Body::wrap_stream(InFlightBody {
+            body,
+            permit: Some(permit),
+        })
+    }))
+}
+
+// The body of a response, which holds the permit of its request until it was read
+struct InFlightBody {
+    body: hyper::Body,
+    permit: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+impl Stream for InFlightBody {
+    type Item = hyper::Result<Bytes>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<hyper::Result<Bytes>>> {
+        let next = Pin::new(&mut self.body).poll_next(cx);
+        if let std::task::Poll::Ready(None) = next {
+            self.permit = None;
+        }
+        next
+    }
+}
+
+/// The time a call has to complete, which is the shorter of the timeout of its hub and the one
+/// of its call builder, counted from now. The timeout is returned along with the deadline to
+/// report it if it passes.
+pub fn deadline(
+    hub_timeout: Option<Duration>,
+    call_timeout: Option<Duration>,
+) -> Option<(Instant, Duration)> {
+    let timeout = match (hub_timeout, call_timeout) {
+        (Some(hub), Some(call)) => hub.min(call),
+        (timeout, None) | (None, timeout) => timeout?,
+    };
+    Some((Instant::now() + timeout, timeout))
+}
+
+/// The header telling the server the time a call has left, in seconds, after which the client
+/// doesn't wait for its response anymore, so the server can stop working on it.
+pub const SERVER_TIMEOUT_HEADER: &str = "x-server-timeout";
+
+/// Returns the time left until the `deadline` of a call, as value of the `SERVER_TIMEOUT_HEADER`,
+/// like `9.5`. It is at least a millisecond, even once the deadline passed.
+pub fn server_timeout(deadline: (Instant, Duration)) -> String {
+    let left = deadline.0.saturating_duration_since(Instant::now());
+    let millis = left.as_millis().max(1);
+    format!("{}", millis as f64 / 1000.0)
+}
+
+/// The header naming the interface version of the API a call uses, like `v1_20240101`, for APIs
+/// whose methods changed incompatibly between such versions.
+pub const API_VERSION_HEADER: &str = "x-goog-api-version";
+
+/// The header naming the visibility labels of a call, which opt into the preview features of the
+/// APIs gating them, if the project of the call has access to them.
+pub const VISIBILITIES_HEADER: &str = "x-goog-visibilities";
+
+/// Returns the value of the `VISIBILITIES_HEADER` naming the given `labels`, like `PREVIEW,TRUSTED_TESTER`,
+/// or `None` if there are none. Empty labels are left out, and each label is named once.
+pub fn visibilities(labels: &[String]) -> Option<String> {
+    let mut named: Vec<&str> = Vec::new();
+    for label in labels.iter().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        if !named.contains(&label) {
+            named.push(label);
+        }
+    }
+    Some(named.join(",")).filter(|v| !v.is_empty())
+}
+
+/// Connects `client` to the server at `url`, while `fetch_token` obtains an access token, which
+/// are the costs the first call of a hub would otherwise pay at once.
+pub async fn warm_up<F: Future<Output = Result<()>>>(
+    client: &hyper::Client<Connector, hyper::body::Body>,
+    url: &str,
+    user_agent: &str,
+    fetch_token: F,
+) -> Result<()> {
+    let (connected, token) =
+        future::join(warm_up_connection(client, url, user_agent), fetch_token).await;
+    connected.and(token)
+}
+
+/// Connects `client` to the server at `url` by sending a `HEAD` request to it, which resolves the
+/// name of the server and negotiates TLS. The connection is kept by the pool of the client, and
+/// reused by the requests sent afterwards. The status of the response doesn't matter.
+pub async fn warm_up_connection(
+    client: &hyper::Client<Connector, hyper::body::Body>,
+    url: &str,
+    user_agent: &str,
+) -> Result<()> {
+    let req = hyper::Request::builder()
+        .method(Method::HEAD)
+        .uri(url)
+        .header(USER_AGENT, user_agent)
+        .body(hyper::body::Body::empty())
+        .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidInput, err)))?;
+    let res = client.request(req).await.map_err(Error::HttpError)?;
+    // The connection only returns to the pool once the response was read
+    read_body(&mut res.into_body()).await?;
+    Ok(())
+}
+
+/// Runs `future` to completion, unless the token of the hub or the one of the call is cancelled,
+/// or the deadline passes before. In these cases the future is dropped, which aborts the request
+/// it is making, and `Error::Cancelled` or `Error::Timeout` is returned. The deadline is waited for
+/// with the given `runtime`.
+pub async fn cancellable<F: Future>(
+    future: F,
+    hub_token: &CancellationToken,
+    call_token: Option<&CancellationToken>,
+    deadline: Option<(Instant, Duration)>,
+    runtime: &dyn runtime::Runtime,
+) -> Result<F::Output> {
+    let cancelled = async {
+        match call_token {
+            Some(call_token) => {
+                future::select(
+                    Box::pin(hub_token.cancelled()),
+                    Box::pin(call_token.cancelled()),
+                )
+                .await;
+            }
+            None => hub_token.cancelled().await,
+        }
+        Error::Cancelled
+    };
+    let timed_out = async {
+        match deadline {
+            Some((deadline, timeout)) => {
+                runtime
+                    .sleep(deadline.saturating_duration_since(Instant::now()))
+                    .await;
+                Error::Timeout(timeout)
+            }
+            None => future::pending().await,
+        }
+    };
+    match future::select(
+        Box::pin(future),
+        future::select(Box::pin(cancelled), Box::pin(timed_out)),
+    )
+    .await
+    {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right((Either::Left((err, _)) | Either::Right((err, _)), _)) => Err(err),
+    }
+}
+
+/// The factor by which `poll_until()` increases the delay between polls.
+pub const POLL_BACKOFF_FACTOR: f64 = 1.5;
+
+/// The delay between polls grows up to this many times the initial interval.
+pub const POLL_MAX_BACKOFF: u32 = 8;
+
+/// The delays between polls vary randomly by up to this fraction, so clients started at the same
+/// time don't poll in lockstep.
+pub const POLL_JITTER: f64 = 0.2;
+
+/// Returns the delay to wait for before the poll following the one numbered `attempt`, starting at
+/// zero, of a resource polled every `interval` at first. `jitter` is between -1 and 1, and scales
+/// the delay by up to `POLL_JITTER` in either direction.
+pub fn poll_delay(interval: Duration, attempt: u32, jitter: f64) -> Duration {
+    let backoff = POLL_BACKOFF_FACTOR
+        .powi(attempt.min(64) as i32)
+        .min(POLL_MAX_BACKOFF as f64);
+    interval.mul_f64(backoff * (1.0 + POLL_JITTER * jitter.clamp(-1.0, 1.0)))
+}
+
+/// Calls `poll` until the value it returns is `done`, and returns that value. The delay between
+/// calls starts at `interval` and grows with each of them, see `poll_delay()`, which is waited for
+/// with the given `runtime`. The first error of `poll` is returned as is.
+pub async fn poll_until<T, F, Fut, D>(
+    interval: Duration,
+    runtime: &dyn runtime::Runtime,
+    mut poll: F,
+    mut done: D,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    D: FnMut(&T) -> bool,
+{
+    let mut attempt = 0;
+    loop {
+        let value = poll().await?;
+        if done(&value) {
+            return Ok(value);
+        }
+        // the low bits of a random UUID, which are all random, mapped to [-1, 1)
+        let random = uuid::Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1);
+        let jitter = random as f64 / (1u64 << 52) as f64 - 1.0;
+        runtime.sleep(poll_delay(interval, attempt, jitter)).await;
+        attempt = attempt.saturating_add(1);
+    }
+}
+
+/// The error of converting a struct of one version of an API into the one of another version,
+/// which fails if a field is set that the other version doesn't have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    /// The name of the struct that couldn't be converted.
+    pub schema: &'static str,
+    /// The name of the field the other version lacks, as it appears in JSON.
+    pub field: &'static str,
+}
+
+impl ConversionError {
+    pub fn new(schema: &'static str, field: &'static str) -> ConversionError {
+        ConversionError { schema, field }
+    }
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The field '{}' of '{}' is set, but doesn't exist in the other version",
+            self.field, self.schema
+        )
+    }
+}
+
+impl error::Error for ConversionError {}
+
+/// The error of parsing one of the enumerations of the values of a field from a string which is
+/// none of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownEnumValue {
+    /// The string that was parsed.
+    pub value: String,
+    /// All values of the enumeration.
+    pub expected: &'static [&'static str],
+}
+
+impl UnknownEnumValue {
+    pub fn new(value: &str, expected: &'static [&'static str]) -> UnknownEnumValue {
+        UnknownEnumValue {
+            value: value.to_string(),
+            expected,
+        }
+    }
+}
+
+impl Display for UnknownEnumValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is not one of {}",
+            self.value,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl error::Error for UnknownEnumValue {}
+
+// Defines `ErrorReason` with a variant for each of the given values, along with the same items
+// the enumerations of the values of fields have.
+macro_rules! error_reasons {
+    ($($(#[$doc:meta])* $variant:ident => $value:literal,)*) => {
+        /// The reasons of errors the servers of Google APIs respond with, which explain the status of
+        /// the response. They are given by the `reason` of the `errors` of an error response, which
+        /// is camel case, like `notFound`, and by its `google.rpc.ErrorInfo` details, which is upper
+        /// snake case, like `RATE_LIMIT_EXCEEDED`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum ErrorReason {
+            $($(#[$doc])* $variant,)*
+        }
+
+        impl ErrorReason {
+            /// All values of the reasons.
+            pub const VALUES: &'static [&'static str] = &[$($value,)*];
+            /// All variants, in the order of `VALUES`.
+            pub const VARIANTS: &'static [ErrorReason] = &[$(ErrorReason::$variant,)*];
+
+            /// Returns the value of the reason, as sent by the server.
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $(ErrorReason::$variant => $value,)*
+                }
+            }
+        }
+
+        impl FromStr for ErrorReason {
+            type Err = UnknownEnumValue;
+
+            fn from_str(value: &str) -> std::result::Result<ErrorReason, UnknownEnumValue> {
+                match value {
+                    $($value => Ok(ErrorReason::$variant),)*
+                    _ => Err(UnknownEnumValue::new(value, ErrorReason::VALUES)),
+                }
+            }
+        }
+    };
+}
+
+error_reasons! {
+    /// The request is invalid.
+    BadRequest => "badRequest",
+    /// A value of the request is invalid.
+    Invalid => "invalid",
+    /// A parameter of the request is invalid.
+    InvalidParameter => "invalidParameter",
+    /// A value the request must have is missing.
+    Required => "required",
+    /// The resource doesn't exist.
+    NotFound => "notFound",
+    /// The request conflicts with the state of the resource.
+    Conflict => "conflict",
+    /// The resource exists already.
+    Duplicate => "duplicate",
+    /// A precondition of the request, like an etag, isn't met.
+    ConditionNotMet => "conditionNotMet",
+    /// The credentials are invalid.
+    AuthError => "authError",
+    /// The caller may not perform the request.
+    Forbidden => "forbidden",
+    /// The credentials lack the permissions the request needs.
+    InsufficientPermissions => "insufficientPermissions",
+    /// The API key is invalid.
+    KeyInvalid => "keyInvalid",
+    /// The API isn't enabled for the project.
+    AccessNotConfigured => "accessNotConfigured",
+    /// The project sent too many requests.
+    ProjectRateLimitExceeded => "rateLimitExceeded",
+    /// The user sent too many requests.
+    UserRateLimitExceeded => "userRateLimitExceeded",
+    /// The quota of the project is used up.
+    QuotaExceeded => "quotaExceeded",
+    /// The daily quota of the project is used up.
+    DailyLimitExceeded => "dailyLimitExceeded",
+    /// A limit of the resource, like its size, was exceeded.
+    LimitExceeded => "limitExceeded",
+    /// The server failed, and the request may be retried.
+    BackendError => "backendError",
+    /// The server failed.
+    InternalError => "internalError",
+    /// The API isn't enabled for the project.
+    ServiceDisabled => "SERVICE_DISABLED",
+    /// Billing isn't enabled for the project.
+    BillingDisabled => "BILLING_DISABLED",
+    /// The API key is invalid.
+    ApiKeyInvalid => "API_KEY_INVALID",
+    /// The API key may not call the API.
+    ApiKeyServiceBlocked => "API_KEY_SERVICE_BLOCKED",
+    /// The rate limit of the API was exceeded.
+    RateLimitExceeded => "RATE_LIMIT_EXCEEDED",
+    /// The quota of a resource of the API was exceeded.
+    ResourceQuotaExceeded => "RESOURCE_QUOTA_EXCEEDED",
+    /// The caller may not use the project to bill the request to.
+    UserProjectDenied => "USER_PROJECT_DENIED",
+    /// The project is suspended.
+    ConsumerSuspended => "CONSUMER_SUSPENDED",
+    /// The project is invalid, or was deleted.
+    ConsumerInvalid => "CONSUMER_INVALID",
+    /// The request violates a security policy.
+    SecurityPolicyViolated => "SECURITY_POLICY_VIOLATED",
+    /// The access token expired.
+    AccessTokenExpired => "ACCESS_TOKEN_EXPIRED",
+    /// The access token lacks the scopes the request needs.
+    AccessTokenScopeInsufficient => "ACCESS_TOKEN_SCOPE_INSUFFICIENT",
+    /// The account of the credentials is disabled or deleted.
+    AccountStateInvalid => "ACCOUNT_STATE_INVALID",
+    /// The request has no credentials.
+    CredentialsMissing => "CREDENTIALS_MISSING",
+    /// The project of the resource is invalid.
+    ResourceProjectInvalid => "RESOURCE_PROJECT_INVALID",
+    /// The request violates a restriction of the usage of the resource.
+    ResourceUsageRestrictionViolated => "RESOURCE_USAGE_RESTRICTION_VIOLATED",
+    /// The request violates a location policy of the organization.
+    LocationPolicyViolated => "LOCATION_POLICY_VIOLATED",
+}
+
+impl Display for ErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+const ERROR_INFO_TYPE: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+const BAD_REQUEST_TYPE: &str = "type.googleapis.com/google.rpc.BadRequest";
+const QUOTA_FAILURE_TYPE: &str = "type.googleapis.com/google.rpc.QuotaFailure";
+const HELP_TYPE: &str = "type.googleapis.com/google.rpc.Help";
+const REQUEST_INFO_TYPE: &str = "type.googleapis.com/google.rpc.RequestInfo";
+
+/// The name of the parameter selecting the format of error responses. All calls ask for version
+/// `2`, whose `details` `ApiError` decodes, unless another one is set with `param()`.
+pub const ERROR_FORMAT_PARAM: &str = "$.xgafv";
+/// The version of the format of error responses calls ask for by default.
+pub const DEFAULT_ERROR_FORMAT: &str = "2";
+
+/// A field of the request which has an invalid value, as described by a `google.rpc.BadRequest`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FieldViolation {
+    /// The path of the field, like `instance.machineType`.
+    pub field: String,
+    pub description: String,
+}
+
+/// A quota which was exceeded, as described by a `google.rpc.QuotaFailure`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QuotaViolation {
+    /// What the quota is counted for, like `project:my-project`.
+    pub subject: String,
+    pub description: String,
+}
+
+/// A link to documentation explaining the error, as given by a `google.rpc.Help`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HelpLink {
+    pub description: String,
+    pub url: String,
+}
+
+/// The error a server responded with, as decoded from the JSON of an `Error::BadRequest`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ApiError {
+    /// The HTTP status code.
+    pub code: Option<u16>,
+    pub message: String,
+    /// The canonical status, like `NOT_FOUND`.
+    pub status: Option<String>,
+    /// The known reasons of the error, of its `errors` and its `ErrorInfo`, in that order.
+    pub reasons: Vec<ErrorReason>,
+    /// All reasons of the error, including unknown ones, as sent by the server.
+    pub reason_values: Vec<String>,
+    /// The domain of the `ErrorInfo`, like `googleapis.com`.
+    pub domain: Option<String>,
+    /// The metadata of the `ErrorInfo`, like the `service` which isn't enabled.
+    pub metadata: BTreeMap<String, String>,
+    /// The fields of the request which have invalid values.
+    pub field_violations: Vec<FieldViolation>,
+    /// The quotas which were exceeded.
+    pub quota_violations: Vec<QuotaViolation>,
+    /// Links to documentation about the error.
+    pub help_links: Vec<HelpLink>,
+    /// The delay the server asked for before retrying the request, by its `RetryInfo`.
+    pub retry_delay: Option<Duration>,
+    /// The id of the request, by its `RequestInfo`, which support needs to look it up.
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// Decodes an error response like `{"error": {"code": 429, "message": ..., "errors": [...]}}`,
+    /// along with the `details` of version 2 of the format. Returns `None` if it has no `error`
+    /// object.
+    pub fn from_json(value: &json::Value) -> Option<ApiError> {
+        let error = value.get("error").filter(|e| e.is_object())?;
+        let details = error["details"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+        let detail = |t: &str| details.iter().find(|d| d["@type"] == t);
+        let error_info = detail(ERROR_INFO_TYPE);
+        let mut reason_values: Vec<String> = Vec::new();
+        let legacy_errors = error["errors"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+        for reason in legacy_errors
+            .iter()
+            .chain(error_info)
+            .filter_map(|e| e["reason"].as_str())
+        {
+            if !reason_values.iter().any(|r| r == reason) {
+                reason_values.push(reason.to_string());
+            }
+        }
+        // the entries of the array `field` of the detail of type `t`
+        let entries = |t: &str, field: &str| {
+            detail(t)
+                .and_then(|d| d[field].as_array())
+                .map(Vec::as_slice)
+                .unwrap_or(&[])
+        };
+        let string =
+            |v: &json::Value, field: &str| v[field].as_str().unwrap_or_default().to_string();
+        Some(ApiError {
+            code: error["code"].as_u64().and_then(|c| u16::try_from(c).ok()),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+            status: error["status"].as_str().map(str::to_string),
+            reasons: reason_values
+                .iter()
+                .filter_map(|r| r.parse().ok())
+                .collect(),
+            reason_values,
+            domain: error_info
+                .and_then(|i| i["domain"].as_str())
+                .map(str::to_string),
+            metadata: error_info
+                .and_then(|i| i["metadata"].as_object())
+                .map(|m| {
+                    m.iter()
+                        .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            field_violations: entries(BAD_REQUEST_TYPE, "fieldViolations")
+                .iter()
+                .map(|v| FieldViolation {
+                    field: string(v, "field"),
+                    description: string(v, "description"),
+                })
+                .collect(),
+            quota_violations: entries(QUOTA_FAILURE_TYPE, "violations")
+                .iter()
+                .map(|v| QuotaViolation {
+                    subject: string(v, "subject"),
+                    description: string(v, "description"),
+                })
+                .collect(),
+            help_links: entries(HELP_TYPE, "links")
+                .iter()
+                .map(|l| HelpLink {
+                    description: string(l, "description"),
+                    url: string(l, "url"),
+                })
+                .collect(),
+            retry_delay: detail(RETRY_INFO_TYPE)
+                .and_then(|d| d["retryDelay"].as_str())
+                .and_then(serde_duration::from_str),
+            request_id: detail(REQUEST_INFO_TYPE)
+                .and_then(|d| d["requestId"].as_str())
+                .map(str::to_string),
+        })
+    }
+
+    /// Returns true if `reason` is one of the reasons of the error.
+    pub fn has_reason(&self, reason: ErrorReason) -> bool {
+        self.reasons.contains(&reason)
+    }
+}
+
+/// Contains information about an API request.
+pub struct MethodInfo {
+    pub id: &'static str,
+    pub http_method: Method,
+}
+
+const BOUNDARY: &str = "MDuXWGyeE33QFXGchb2VFWc4Z7945d";
+
+/// Provides a `Read` interface that converts multiple parts into the protocol
+/// identified by [RFC2387](https://tools.ietf.org/html/rfc2387).
+/// **Note**: This implementation is just as rich as it needs to be to perform uploads
+/// to google APIs, and might not be a fully-featured implementation.
+#[derive(Default)]
+pub struct MultiPartReader<'a> {
+    raw_parts: Vec<(HeaderMap, &'a mut (dyn Read + Send))>,
+    current_part: Option<(Cursor<Vec<u8>>, &'a mut (dyn Read + Send))>,
+    last_part_boundary: Option<Cursor<Vec<u8>>>,
+}
+
+impl<'a> MultiPartReader<'a> {
+    /// Reserve memory for exactly the given amount of parts
+    pub fn reserve_exact(&mut self, cap: usize) {
+        self.raw_parts.reserve_exact(cap);
+    }
+
+    /// Add a new part to the queue of parts to be read on the first `read` call.
+    ///
+    /// # Arguments
+    ///
+    /// `headers` - identifying the body of the part. It's similar to the header
+    ///             in an ordinary single-part call, and should thus contain the
+    ///             same information.
+    /// `reader`  - a reader providing the part's body
+    /// `size`    - the amount of bytes provided by the reader. It will be put onto the header as
+    ///             content-size.
+    /// `mime`    - It will be put onto the content type
+    pub fn add_part(
+        &mut self,
+        reader: &'a mut (dyn Read + Send),
+        size: u64,
+        mime_type: Mime,
+    ) -> &mut MultiPartReader<'a> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            hyper::header::HeaderValue::from_str(&format!("{}", mime_type)).unwrap(),
+        );
+        headers.insert(CONTENT_LENGTH, size.into());
+        self.raw_parts.push((headers, reader));
+        self
+    }
+
+    /// Returns the mime-type representing our multi-part message.
+    /// Use it with the ContentType header.
+    pub fn mime_type(&self) -> Mime {
+        Mime(
+            TopLevel::Multipart,
+            SubLevel::Ext("related".to_string()),
+            vec![(
+                Attr::Ext("boundary".to_string()),
+                Value::Ext(BOUNDARY.to_string()),
+            )],
+        )
+    }
+
+    /// Returns true if we are totally used
+    fn is_depleted(&self) -> bool {
+        self.raw_parts.is_empty()
+            && self.current_part.is_none()
+            && self.last_part_boundary.is_none()
+    }
+
+    /// Returns true if we are handling our last part
+    fn is_last_part(&self) -> bool {
+        self.raw_parts.is_empty() && self.current_part.is_some()
+    }
+}
+
+impl<'a> Read for MultiPartReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (
+            self.raw_parts.len(),
+            self.current_part.is_none(),
+            self.last_part_boundary.is_none(),
+        ) {
+            (_, _, false) => {
+                let br = self
+                    .last_part_boundary
+                    .as_mut()
+                    .unwrap()
+                    .read(buf)
+                    .unwrap_or(0);
+                if br < buf.len() {
+                    self.last_part_boundary = None;
+                }
+                return Ok(br);
+            }
+            (0, true, true) => return Ok(0),
+            (n, true, _) if n > 0 => {
+                let (headers, reader) = self.raw_parts.remove(0);
+                let mut c = Cursor::new(Vec::<u8>::new());
+                // TODO: The first line ending should be omitted for the first part,
+                // fortunately Google's API serves don't seem to mind.
+                (write!(
+                    &mut c,
+                    "{}--{}{}{}{}{}",
+                    LINE_ENDING,
+                    BOUNDARY,
+                    LINE_ENDING,
+                    headers
+                        .iter()
+                        .map(|(k, v)| format!("{}: {}", k, v.to_str().unwrap()))
+                        .join(LINE_ENDING),
+                    LINE_ENDING,
+                    LINE_ENDING,
+                ))
+                .unwrap();
+                c.seek(SeekFrom::Start(0)).unwrap();
+                self.current_part = Some((c, reader));
+            }
+            _ => {}
+        }
+
+        // read headers as long as possible
+        let (hb, rr) = {
+            let &mut (ref mut c, ref mut reader) = self.current_part.as_mut().unwrap();
+            let b = c.read(buf).unwrap_or(0);
+            (b, reader.read(&mut buf[b..]))
+        };
+
+        match rr {
+            Ok(bytes_read) => {
+                if hb < buf.len() && bytes_read == 0 {
+                    if self.is_last_part() {
+                        // before clearing the last part, we will add the boundary that
+                        // will be written last
+                        self.last_part_boundary = Some(Cursor::new(
+                            format!("{}--{}--{}", LINE_ENDING, BOUNDARY, LINE_ENDING).into_bytes(),
+                        ))
+                    }
+                    // We are depleted - this can trigger the next part to come in
+                    self.current_part = None;
+                }
+                let mut total_bytes_read = hb + bytes_read;
+                while total_bytes_read < buf.len() && !self.is_depleted() {
+                    match self.read(&mut buf[total_bytes_read..]) {
+                        Ok(br) => total_bytes_read += br,
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(total_bytes_read)
+            }
+            Err(err) => {
+                // fail permanently
+                self.current_part = None;
+                self.last_part_boundary = None;
+                self.raw_parts.clear();
+                Err(err)
+            }
+        }
+    }
+}
+
+/// A stream of byte chunks, as sent in the body of a request.
+pub type BodyStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// Media to upload from an asynchronous source, like a `tokio::io::AsyncRead` or a stream of
+/// bytes received from the network.
+/// Chunks are sent as they are produced, neither blocking the executor nor holding all of the
+/// media in memory. As a stream can only be sent once, failed uploads can't be retried.
+pub struct MediaStream {
+    stream: Option<BodyStream>,
+    size: Option<u64>,
+}
+
+impl MediaStream {
+    /// Creates media from the given stream.
+    /// `size` is the total amount of bytes it produces, if known. It will be sent as content length
+    /// and allows to enforce upload size limits before sending anything.
+    pub fn new<S>(stream: S, size: Option<u64>) -> MediaStream
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        MediaStream {
+            stream: Some(Box::pin(stream)),
+            size,
+        }
+    }
+
+    /// Creates media read from the given reader in chunks of up to `STREAM_CHUNK_SIZE` bytes,
+    /// until it is depleted.
+    pub fn from_async_read<R>(reader: R, size: Option<u64>) -> MediaStream
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        use tokio::io::AsyncReadExt;
+
+        let chunks = stream::try_unfold(reader, |mut reader| async move {
+            let mut buf = vec![0; STREAM_CHUNK_SIZE];
+            let bytes_read = reader.read(&mut buf).await?;
+            if bytes_read == 0 {
+                return Ok(None);
+            }
+            buf.truncate(bytes_read);
+            Ok(Some((Bytes::from(buf), reader)))
+        });
+        MediaStream::new(chunks, size)
+    }
+
+    /// Returns the total amount of bytes of the media, if known.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    fn take(&mut self) -> io::Result<BodyStream> {
+        self.stream
+            .take()
+            .ok_or_else(|| io::Error::other("the media stream was sent already and can't be retried"))
+    }
+
+    /// Takes the stream to send it as is, with the given mime-type.
+    /// Fails if it was taken before.
+    pub fn take_body(&mut self, mime_type: &Mime) -> io::Result<StreamedBody> {
+        Ok(StreamedBody {
+            stream: self.take()?,
+            mime_type: mime_type.clone(),
+            size: self.size,
+        })
+    }
+
+    /// Takes the stream to send it as second part of a multi-part message, after the given json
+    /// request value. The message is formatted like the one of the `MultiPartReader`.
+    /// Fails if it was taken before.
+    pub fn take_multi_part_body(
+        &mut self,
+        json: &[u8],
+        json_mime_type: &Mime,
+        mime_type: &Mime,
+    ) -> io::Result<StreamedBody> {
+        let part_header = |mime_type: &Mime, size: Option<u64>| {
+            let mut header = format!(
+                "{}--{}{}{}: {}",
+                LINE_ENDING, BOUNDARY, LINE_ENDING, CONTENT_TYPE, mime_type
+            );
+            if let Some(size) = size {
+                header += &format!("{}{}: {}", LINE_ENDING, CONTENT_LENGTH, size);
+            }
+            header + LINE_ENDING + LINE_ENDING
+        };
+        let mut head = part_header(json_mime_type, Some(json.len() as u64)).into_bytes();
+        head.extend_from_slice(json);
+        head.extend(part_header(mime_type, self.size).into_bytes());
+        let tail = format!("{}--{}--{}", LINE_ENDING, BOUNDARY, LINE_ENDING).into_bytes();
+
+        let size = self.size.map(|size| (head.len() + tail.len()) as u64 + size);
+        let stream = stream::iter(vec![Ok(Bytes::from(head))])
+            .chain(self.take()?)
+            .chain(stream::iter(vec![Ok(Bytes::from(tail))]));
+        Ok(StreamedBody {
+            stream: Box::pin(stream),
+            mime_type: MultiPartReader::default().mime_type(),
+            size,
+        })
+    }
+}
+
+/// The body of a request, as taken from a `MediaStream`.
+pub struct StreamedBody {
+    stream: BodyStream,
+    mime_type: Mime,
+    size: Option<u64>,
+}
+
+impl StreamedBody {
+    /// Finishes the given request with this body, along with its content type and, if known, its length.
+    pub fn into_request(
+        self,
+        req_builder: hyper::http::request::Builder,
+    ) -> hyper::http::Result<hyper::Request<hyper::Body>> {
+        let req_builder = req_builder.header(CONTENT_TYPE, self.mime_type.to_string());
+        match self.size {
+            Some(size) => req_builder.header(CONTENT_LENGTH, size),
+            None => req_builder,
+        }
+        .body(hyper::Body::wrap_stream(self.stream))
+    }
+}
+
+/// The `X-Upload-Content-Type` header.
+///
+/// Generated via rustc --pretty expanded -Z unstable-options, and manually
+/// processed to be more readable.
+#[derive(PartialEq, Debug, Clone)]
+pub struct XUploadContentType(pub Mime);
+
+impl ::std::ops::Deref for XUploadContentType {
+    type Target = Mime;
+    fn deref(&self) -> &Mime {
+        &self.0
+    }
+}
+impl ::std::ops::DerefMut for XUploadContentType {
+    fn deref_mut(&mut self) -> &mut Mime {
+        &mut self.0
+    }
+}
+impl Display for XUploadContentType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Chunk {
+    pub first: u64,
+    pub last: u64,
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        (write!(fmt, "{}-{}", self.first, self.last)).ok();
+        Ok(())
+    }
+}
+
+impl FromStr for Chunk {
+    type Err = &'static str;
+
+    /// NOTE: only implements `%i-%i`, not `*`
+    fn from_str(s: &str) -> std::result::Result<Chunk, &'static str> {
+        let parts: Vec<&str> = s.split('-').collect();
+        if parts.len() != 2 {
+            return Err("Expected two parts: %i-%i");
+        }
+        Ok(Chunk {
+            first: match FromStr::from_str(parts[0]) {
+                Ok(d) => d,
+                _ => return Err("Couldn't parse 'first' as digit"),
+            },
+            last: match FromStr::from_str(parts[1]) {
+                Ok(d) => d,
+                _ => return Err("Couldn't parse 'last' as digit"),
+            },
+        })
+    }
+}
+
+/// Implements the Content-Range header, for serialization only
+#[derive(Clone, PartialEq, Debug)]
+pub struct ContentRange {
+    pub range: Option<Chunk>,
+    pub total_length: u64,
+}
+
+impl ContentRange {
+    pub fn header_value(&self) -> String {
+        format!(
+            "bytes {}/{}",
+            match self.range {
+                Some(ref c) => format!("{}", c),
+                None => "*".to_string(),
+            },
+            self.total_length
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RangeResponseHeader(pub Chunk);
+
+impl RangeResponseHeader {
+    fn from_bytes(raw: &[u8]) -> Self {
+        if !raw.is_empty() {
+            if let Ok(s) = std::str::from_utf8(raw) {
+                const PREFIX: &str = "bytes ";
+                if let Some(stripped) = s.strip_prefix(PREFIX) {
+                    if let Ok(c) = <Chunk as FromStr>::from_str(stripped) {
+                        return RangeResponseHeader(c);
+                    }
+                }
+            }
+        }
+
+        panic!("Unable to parse Range header {:?}", raw)
+    }
+}
+
+/// The smallest amount of bytes sent to a resumable upload session at once. All chunks but the
+/// last one must be a multiple of it.
+const MIN_UPLOAD_CHUNK_SIZE: u64 = 1 << 18;
+
+/// A utility type to perform a resumable upload from start to end.
+pub struct ResumableUploadHelper<'a, A: 'a> {
+    pub client: &'a hyper::client::Client<Connector, hyper::body::Body>,
+    pub delegate: &'a mut dyn Delegate,
+    pub start_at: Option<u64>,
+    pub auth: &'a A,
+    pub user_agent: &'a str,
+    pub auth_header: String,
+    pub url: &'a str,
+    pub reader: &'a mut dyn ReadSeek,
+    pub media_type: Mime,
+    pub content_length: u64,
+    pub in_flight: Option<&'a InFlightLimit>,
+    pub runtime: &'a dyn runtime::Runtime,
+}
+
+impl<'a, A> ResumableUploadHelper<'a, A> {
+    async fn query_transfer_status(
+        &mut self,
+    ) -> std::result::Result<u64, hyper::Result<hyper::Response<hyper::body::Body>>> {
+        loop {
+            let request = self.client.request(
+                hyper::Request::builder()
+                    .method(hyper::Method::POST)
+                    .uri(self.url)
+                    .header(USER_AGENT, self.user_agent.to_string())
+                    .header(
+                        "Content-Range",
+                        ContentRange {
+                            range: None,
+                            total_length: self.content_length,
+                        }
+                        .header_value(),
+                    )
+                    .header(AUTHORIZATION, self.auth_header.clone())
+                    .body(hyper::body::Body::empty())
+                    .unwrap(),
+            );
+            match in_flight(self.in_flight, request).await {
+                Ok(r) => {
+                    // 308 = resume-incomplete == PermanentRedirect
+                    let headers = r.headers().clone();
+                    let h: RangeResponseHeader = match headers.get("Range") {
+                        Some(hh) if r.status() == StatusCode::PERMANENT_REDIRECT => {
+                            RangeResponseHeader::from_bytes(hh.as_bytes())
+                        }
+                        None | Some(_) => {
+                            if let Retry::After(d) = self.delegate.http_failure(&r, None) {
+                                let d = retry_delay(&r, None, d);
+                                self.delegate.retrying(d);
+                                self.runtime.sleep(d).await;
+                                continue;
+                            }
+                            return Err(Ok(r));
+                        }
+                    };
+                    return Ok(h.0.last);
+                }
+                Err(err) => {
+                    if let Retry::After(d) = self.delegate.http_error(&err) {
+                        self.runtime.sleep(d).await;
+                        continue;
+                    }
+                    return Err(Err(err));
+                }
+            }
+        }
+    }
+
+    /// returns None if operation was cancelled by delegate, or the HttpResult.
+    /// It can be that we return the result just because we didn't understand the status code -
+    /// caller should check for status himself before assuming it's OK to use
+    pub async fn upload(&mut self) -> Option<hyper::Result<hyper::Response<hyper::body::Body>>> {
+        let mut start = match self.start_at {
+            Some(s) => s,
+            None => match self.query_transfer_status().await {
+                Ok(s) => s,
+                Err(result) => return Some(result),
+            },
+        };
+
+        let chunk_size = match self.delegate.chunk_size() {
+            cs if cs > MIN_UPLOAD_CHUNK_SIZE => cs,
+            _ => MIN_UPLOAD_CHUNK_SIZE,
+        };
+
+        loop {
+            self.reader.seek(SeekFrom::Start(start)).unwrap();
+
+            let request_size = match self.content_length - start {
+                rs if rs > chunk_size => chunk_size,
+                rs => rs,
+            };
+
+            let mut section_reader = self.reader.take(request_size);
+            let mut req_bytes = vec![];
+            section_reader.read_to_end(&mut req_bytes).unwrap();
+            let range_header = ContentRange {
+                range: Some(Chunk {
+                    first: start,
+                    last: start + request_size - 1,
+                }),
+                total_length: self.content_length,
+            };
+            if self.delegate.cancel_chunk_upload(&range_header) {
+                return None;
+            }
+            let request = self.client.request(
+                hyper::Request::builder()
+                    .uri(self.url)
+                    .method(hyper::Method::POST)
+                    .header("Content-Range", range_header.header_value())
+                    .header(CONTENT_TYPE, format!("{}", self.media_type))
+                    .header(USER_AGENT, self.user_agent.to_string())
+                    .body(hyper::body::Body::from(req_bytes))
+                    .unwrap(),
+            );
+            let res = in_flight(self.in_flight, request).await;
+            match res {
+                Ok(res) => {
+                    start += request_size;
+
+                    if res.status() == StatusCode::PERMANENT_REDIRECT {
+                        continue;
+                    }
+
+                    let (reconstructed_result, res_body) = match buffer_response(res).await {
+                        Ok(buffered) => buffered,
+                        Err(err) => return Some(Err(err)),
+                    };
+
+                    if !reconstructed_result.status().is_success() {
+                        let server_response: Option<json::Value> = json::from_slice(&res_body).ok();
+                        if let Retry::After(d) = self
+                            .delegate
+                            .http_failure(&reconstructed_result, server_response.clone())
+                        {
+                            let d = retry_delay(&reconstructed_result, server_response.as_ref(), d);
+                            self.delegate.retrying(d);
+                            self.runtime.sleep(d).await;
+                            continue;
+                        }
+                    }
+                    return Some(Ok(reconstructed_result));
+                }
+                Err(err) => {
+                    if let Retry::After(d) = self.delegate.http_error(&err) {
+                        self.runtime.sleep(d).await;
+                        continue;
+                    }
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// A chunk of media to send to a resumable upload session, as cut by `upload_chunks()`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct UploadChunk {
+    /// The position of the bytes within the media, or `None` if the chunk is empty.
+    pub range: Option<Chunk>,
+    /// The size of the media, known once its last chunk was read.
+    pub total_length: Option<u64>,
+    pub bytes: Bytes,
+}
+
+impl UploadChunk {
+    fn new(first: u64, total_length: Option<u64>, bytes: Bytes) -> UploadChunk {
+        UploadChunk {
+            range: match bytes.len() as u64 {
+                0 => None,
+                len => Some(Chunk {
+                    first,
+                    last: first + len - 1,
+                }),
+            },
+            total_length,
+            bytes,
+        }
+    }
+
+    /// Returns the value of the `Content-Range` header to send this chunk with.
+    pub fn header_value(&self) -> String {
+        format!(
+            "bytes {}/{}",
+            match self.range {
+                Some(ref c) => c.to_string(),
+                None => "*".to_string(),
+            },
+            match self.total_length {
+                Some(total_length) => total_length.to_string(),
+                None => "*".to_string(),
+            }
+        )
+    }
+
+    /// Returns the part of this chunk the server didn't persist yet, if it persisted the first
+    /// `persisted` bytes of the media.
+    pub fn remainder(&self, persisted: u64) -> Option<UploadChunk> {
+        let range = self.range.as_ref()?;
+        if persisted > range.last {
+            return None;
+        }
+        let offset = persisted.saturating_sub(range.first);
+        Some(UploadChunk::new(
+            range.first + offset,
+            self.total_length,
+            self.bytes.slice(offset as usize..),
+        ))
+    }
+}
+
+/// Cuts the media produced by `stream` into chunks of `chunk_size` bytes, to be sent to a
+/// resumable upload session one after another.
+/// At most one chunk is held in memory at a time. As the size of the media isn't known before
+/// the stream ends, only the last chunk carries the `total_length`.
+pub fn upload_chunks<S>(stream: S, chunk_size: u64) -> impl Stream<Item = io::Result<UploadChunk>>
+where
+    S: Stream<Item = io::Result<Bytes>> + Unpin,
+{
+    let chunk_size = chunk_size.max(1) as usize;
+    stream::try_unfold(Some((stream, Vec::new(), 0u64)), move |state| async move {
+        let (mut stream, mut buf, offset) = match state {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        // Only send a full chunk once more bytes follow, as the last one has to be marked.
+        while buf.len() <= chunk_size {
+            match stream.next().await.transpose()? {
+                Some(bytes) => buf.extend_from_slice(&bytes),
+                None => {
+                    let total_length = offset + buf.len() as u64;
+                    let chunk = UploadChunk::new(offset, Some(total_length), buf.into());
+                    return Ok(Some((chunk, None)));
+                }
+            }
+        }
+        let rest = buf.split_off(chunk_size);
+        let chunk = UploadChunk::new(offset, None, buf.into());
+        Ok(Some((
+            chunk,
+            Some((stream, rest, offset + chunk_size as u64)),
+        )))
+    })
+}
+
+/// Returns the amount of bytes the upload session persisted, as told by the `Range` header of
+/// its 308 response.
+fn persisted_length(res: &hyper::Response<hyper::body::Body>) -> u64 {
+    res.headers()
+        .get("Range")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.strip_prefix("bytes=")
+                .or_else(|| v.strip_prefix("bytes "))
+        })
+        .and_then(|v| Chunk::from_str(v).ok())
+        .map_or(0, |c| c.last + 1)
+}
+
+/// Copies the media at `source_url`, like a Cloud Storage signed URL, into the resumable upload
+/// session at `upload_url`, which was started for a media endpoint before.
+/// The media is streamed from the source and sent in chunks of the delegate's `chunk_size()`,
+/// so it is never held in memory as a whole. Chunks the session didn't persist are sent again,
+/// and failed requests are retried as the delegate decides.
+/// Returns the response of the session to the last chunk, which holds the uploaded resource.
+/// Retries wait with the `runtime::default_runtime()`.
+pub async fn upload_from_url(
+    client: &hyper::Client<Connector, hyper::body::Body>,
+    delegate: &mut dyn Delegate,
+    source_url: &str,
+    upload_url: &str,
+) -> Result<hyper::Response<hyper::body::Body>> {
+    let runtime = runtime::default_runtime();
+    let source = client
+        .request(
+            hyper::Request::get(source_url)
+                .body(hyper::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .map_err(Error::HttpError)?;
+    if !source.status().is_success() {
+        return Err(Error::Failure(source));
+    }
+
+    send_upload_chunks(
+        client,
+        delegate,
+        source.into_body().map_err(io::Error::other),
+        upload_url,
+        &*runtime,
+    )
+    .await
+}
+
+/// Sends the media produced by `media` to the resumable upload session at `upload_url`, which was
+/// started for a media endpoint before, like the one of an `upload_resumable_stream()` call.
+/// The media is sent in chunks of the delegate's `chunk_size()`, so only one of them is held in
+/// memory at a time, and its size doesn't have to be known in advance. Chunks the session didn't
+/// persist are sent again, and failed requests are retried as the delegate decides.
+/// Returns the response of the session to the last chunk, which holds the uploaded resource.
+/// Fails if the stream was taken before.
+pub async fn upload_stream(
+    client: &hyper::Client<Connector, hyper::body::Body>,
+    delegate: &mut dyn Delegate,
+    media: &mut MediaStream,
+    upload_url: &str,
+    runtime: &dyn runtime::Runtime,
+) -> Result<hyper::Response<hyper::body::Body>> {
+    let stream = media.take().map_err(Error::Io)?;
+    send_upload_chunks(client, delegate, stream, upload_url, runtime).await
+}
+
+// Sends the media of `stream` to the upload session at `upload_url`, chunk by chunk, as done by
+// `upload_from_url()` and `upload_stream()`.
+async fn send_upload_chunks<S>(
+    client: &hyper::Client<Connector, hyper::body::Body>,
+    delegate: &mut dyn Delegate,
+    stream: S,
+    upload_url: &str,
+    runtime: &dyn runtime::Runtime,
+) -> Result<hyper::Response<hyper::body::Body>>
+where
+    S: Stream<Item = io::Result<Bytes>> + Unpin,
+{
+    let chunk_size = delegate.chunk_size().max(MIN_UPLOAD_CHUNK_SIZE) / MIN_UPLOAD_CHUNK_SIZE
+        * MIN_UPLOAD_CHUNK_SIZE;
+    let mut chunks = Box::pin(upload_chunks(stream, chunk_size));
+    while let Some(chunk) = chunks.next().await {
+        let mut chunk = chunk?;
+        loop {
+            let req = hyper::Request::put(upload_url)
+                .header("Content-Range", chunk.header_value())
+                .header(CONTENT_LENGTH, chunk.bytes.len())
+                .body(hyper::body::Body::from(chunk.bytes.clone()))
+                .unwrap();
+            let res = match client.request(req).await {
+                Ok(res) => res,
+                Err(err) => {
+                    if let Retry::After(d) = delegate.http_error(&err) {
+                        runtime.sleep(d).await;
+                        continue;
+                    }
+                    return Err(Error::HttpError(err));
+                }
+            };
+
+            // 308 = resume-incomplete == PermanentRedirect
+            if res.status() == StatusCode::PERMANENT_REDIRECT {
+                match chunk.remainder(persisted_length(&res)) {
+                    Some(rest) => {
+                        chunk = rest;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            if res.status().is_success() {
+                return Ok(res);
+            }
+
+            let (restored_response, res_body) =
+                buffer_response(res).await.map_err(Error::HttpError)?;
+            let server_response = json::from_slice::<json::Value>(&res_body).ok();
+            if let Retry::After(d) =
+                delegate.http_failure(&restored_response, server_response.clone())
+            {
+                let d = retry_delay(&restored_response, server_response.as_ref(), d);
+                delegate.retrying(d);
+                runtime.sleep(d).await;
+                continue;
+            }
+            return Err(match server_response {
+                Some(error_value) => Error::BadRequest(error_value),
+                None => Error::Failure(restored_response),
+            });
+        }
+    }
+    Err(Error::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "the upload session didn't finish after the last chunk",
+    )))
+}
+
+const RETRY_INFO_TYPE: &str = "type.googleapis.com/google.rpc.RetryInfo";
+
+/// Returns the delay the server asked for before retrying the request which failed with `res`.
+/// It is taken from the `Retry-After` header, in seconds or as an HTTP-date, or from the
+/// `google.rpc.RetryInfo` of the decoded error response `err`, whichever is longer.
+pub fn server_retry_delay(
+    res: &hyper::Response<hyper::body::Body>,
+    err: Option<&json::Value>,
+) -> Option<Duration> {
+    let header_delay = res
+        .headers()
+        .get(hyper::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .and_then(|v| match v.parse() {
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            // a date which passed already asks for no delay
+            Err(_) => httpdate::parse_http_date(v)
+                .ok()
+                .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default()),
+        });
+    let retry_info_delay = err
+        .and_then(|e| e["error"]["details"].as_array())
+        .and_then(|details| {
+            details
+                .iter()
+                .find(|d| d["@type"] == RETRY_INFO_TYPE)
+                .and_then(|d| d["retryDelay"].as_str())
+                .and_then(serde_duration::from_str)
+        });
+    header_delay.into_iter().chain(retry_info_delay).max()
+}
+
+/// Returns the delay to wait for before retrying the request which failed with `res`, if the
+/// delegate asked to retry after `delay`. It is the server's delay, if it asked for a longer one.
+pub fn retry_delay(
+    res: &hyper::Response<hyper::body::Body>,
+    err: Option<&json::Value>,
+    delay: Duration,
+) -> Duration {
+    server_retry_delay(res, err).map_or(delay, |d| d.max(delay))
+}
+
+/// Returns a new random UUID, like `0f8fad5b-d9cb-469f-a165-70867728950e`, to set as `requestId`
+/// of calls creating resources. The server ignores retries of a call with the same id, which
+/// would otherwise create the resource twice if the response of the first attempt was lost.
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The header attributing requests to the library and the application sending them, as shown in
+/// the quota dashboards of the Cloud Console.
+pub const API_CLIENT_HEADER: &str = "x-goog-api-client";
+
+/// Returns the `name/version` token identifying an application in the `User-Agent` and the
+/// `x-goog-api-client` header. Characters other than ASCII letters, digits, `.`, `_` and `-`
+/// would break the format of the headers, and are replaced by `-`.
+pub fn client_info_token(name: &str, version: &str) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+                _ => '-',
+            })
+            .collect()
+    };
+    format!("{}/{}", sanitize(name), sanitize(version))
+}
+
+/// The headers calls set themselves, which can't be set with the `header()` method of call
+/// builders.
+pub const RESERVED_HEADERS: &[&str] = &[
+    "accept-encoding",
+    "authorization",
+    "content-encoding",
+    "content-length",
+    "content-type",
+    "host",
+    "transfer-encoding",
+    "user-agent",
+    "x-goog-user-project",
+    API_CLIENT_HEADER,
+];
+
+/// Returns the headers set with the `header()` method of a call builder, which fails if one of
+/// them is reserved or invalid.
+#[allow(clippy::result_large_err)]
+pub fn header_map(headers: &[(String, String)]) -> Result<HeaderMap> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let invalid = || Error::InvalidHeader(name.clone());
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+        if RESERVED_HEADERS.contains(&name.as_str()) {
+            return Err(invalid());
+        }
+        let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+/// The header taking the read mask of a call, for APIs which don't take it as `fields` parameter.
+pub const FIELD_MASK_HEADER: &str = "x-goog-fieldmask";
+
+/// The fields of a response to read, given by their paths in its JSON, like `name` or
+/// `config.labels`. The server leaves out all other fields.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldMask(Vec<String>);
+
+impl FieldMask {
+    /// Creates a mask reading the given `fields` only.
+    pub fn new<T: AsRef<str>>(fields: &[T]) -> FieldMask {
+        FieldMask(fields.iter().map(|f| f.as_ref().to_string()).collect())
+    }
+
+    /// The paths of the fields, in the order they were given.
+    pub fn paths(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl Display for FieldMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0.join(","))
+    }
+}
+
+/// The value of the `Accept-Encoding` header of all requests, naming the encodings
+/// `decode_response()` decompresses.
+pub const ACCEPT_ENCODING_VALUE: &str = "gzip, deflate";
+
+/// The size in bytes from which request bodies are gzip compressed by hubs of APIs accepting
+/// compressed requests. Compressing smaller ones isn't worth the time it takes.
+pub const REQUEST_COMPRESSION_MIN_SIZE: usize = 8 * 1024;
+
+enum Decoder {
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibDecoder<Vec<u8>>),
+}
+
+impl Decoder {
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Decoder::Gzip(d) => d.write_all(chunk).map(|_| mem::take(d.get_mut())),
+            Decoder::Deflate(d) => d.write_all(chunk).map(|_| mem::take(d.get_mut())),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match self {
+            Decoder::Gzip(d) => d.try_finish().map(|_| mem::take(d.get_mut())),
+            Decoder::Deflate(d) => d.try_finish().map(|_| mem::take(d.get_mut())),
+        }
+    }
+}
+
+/// Returns `res` with a body which is decompressed while it is read, if the server compressed
+/// it with one of the encodings of the `ACCEPT_ENCODING_VALUE`. Otherwise it is returned as is.
+pub fn decode_response(
+    res: hyper::Response<hyper::body::Body>,
+) -> hyper::Response<hyper::body::Body> {
+    let encoding = res
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase());
+    let decoder = match encoding.as_deref() {
+        Some("gzip") | Some("x-gzip") => Decoder::Gzip(flate2::write::GzDecoder::new(Vec::new())),
+        Some("deflate") => Decoder::Deflate(flate2::write::ZlibDecoder::new(Vec::new())),
+        _ => return res,
+    };
+    let (mut parts, body) = res.into_parts();
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+    let decoded = stream::unfold(Some((body, decoder)), |state| async move {
+        let (mut body, mut decoder) = state?;
+        let decoded = match body.next().await {
+            Some(Ok(chunk)) => decoder.decode(&chunk),
+            // keeps the error as its source, like a `ChecksumMismatch`
+            Some(Err(err)) => return Some((Err(BoxError::from(err)), None)),
+            None => {
+                return Some((
+                    decoder.finish().map(Bytes::from).map_err(BoxError::from),
+                    None,
+                ))
+            }
+        };
+        Some((
+            decoded.map(Bytes::from).map_err(BoxError::from),
+            Some((body, decoder)),
+        ))
+    });
+    hyper::Response::from_parts(parts, hyper::body::Body::wrap_stream(decoded))
+}
+
+type BoxError = Box<dyn error::Error + Send + Sync>;
+
+/// The header holding the checksums of downloaded media, like `crc32c=n03x6A==, md5=Ojk9c3dhfxgoKVVHYwFbHQ==`.
+pub const GOOG_HASH_HEADER: &str = "x-goog-hash";
+
+/// The checksum of downloaded media, as computed while it was received, differs from the one
+/// the server sent in the `x-goog-hash` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    /// The algorithm of the checksum, `crc32c` or `md5`.
+    pub algorithm: &'static str,
+    /// The base64 encoded checksum sent by the server.
+    pub expected: String,
+    /// The base64 encoded checksum of the received media.
+    pub actual: String,
+}
+
+impl Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The {} checksum of the downloaded media is {}, but the server sent {}",
+            self.algorithm, self.actual, self.expected
+        )
+    }
+}
+
+impl error::Error for ChecksumMismatch {}
+
+/// Returns the `ChecksumMismatch` which caused `err`, if any.
+fn checksum_mismatch<'a>(err: &'a (dyn error::Error + 'static)) -> Option<&'a ChecksumMismatch> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(mismatch) = err.downcast_ref::<ChecksumMismatch>() {
+            return Some(mismatch);
+        }
+        source = err.source();
+    }
+    None
+}
+
+enum Checksum {
+    Crc32c(u32),
+    Md5(md5::Md5),
+}
+
+impl Checksum {
+    fn update(&mut self, chunk: &[u8]) {
+        match self {
+            Checksum::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, chunk),
+            Checksum::Md5(md5) => md5::Digest::update(md5, chunk),
+        }
+    }
+
+    fn algorithm(&self) -> &'static str {
+        match self {
+            Checksum::Crc32c(_) => "crc32c",
+            Checksum::Md5(_) => "md5",
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Checksum::Crc32c(crc) => base64::encode(crc.to_be_bytes()),
+            Checksum::Md5(md5) => base64::encode(md5::Digest::finalize(md5)),
+        }
+    }
+}
+
+/// Returns `res` with a body which is verified while it is read, if the server sent the checksum
+/// of the media in the `x-goog-hash` header. Reading its end fails with an error converting into
+/// `Error::ChecksumMismatch` if the checksum of the received bytes differs, which is preferably
+/// the `crc32c` one. Partial responses, and ones whose encoding differs from the one the media
+/// is stored with, are returned as is, as the checksum is the one of the stored media.
+pub fn verify_response(
+    res: hyper::Response<hyper::body::Body>,
+) -> hyper::Response<hyper::body::Body> {
+    let header = |name: &str| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| v != "identity")
+    };
+    if res.status() != StatusCode::OK
+        || header("x-goog-stored-content-encoding") != header(CONTENT_ENCODING.as_str())
+    {
+        return res;
+    }
+    let hashes: Vec<(String, String)> = res
+        .headers()
+        .get_all(GOOG_HASH_HEADER)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|hash| hash.trim().split_once('='))
+        .map(|(algorithm, value)| (algorithm.to_ascii_lowercase(), value.to_string()))
+        .collect();
+    let find = |algorithm: &str| {
+        hashes
+            .iter()
+            .find(|(a, _)| a == algorithm)
+            .map(|(_, v)| v.clone())
+    };
+    let (checksum, expected) = match (find("crc32c"), find("md5")) {
+        (Some(expected), _) => (Checksum::Crc32c(0), expected),
+        (None, Some(expected)) => (Checksum::Md5(md5::Digest::new()), expected),
+        (None, None) => return res,
+    };
+    let (parts, body) = res.into_parts();
+    let verified = stream::unfold(Some((body, checksum)), move |state| {
+        let expected = expected.clone();
+        async move {
+            let (mut body, mut checksum) = state?;
+            match body.next().await {
+                Some(Ok(chunk)) => {
+                    checksum.update(&chunk);
+                    Some((Ok(chunk), Some((body, checksum))))
+                }
+                Some(Err(err)) => Some((Err(BoxError::from(err)), None)),
+                None => {
+                    let algorithm = checksum.algorithm();
+                    let actual = checksum.finish();
+                    if actual == expected {
+                        return None;
+                    }
+                    let mismatch = ChecksumMismatch {
+                        algorithm,
+                        expected,
+                        actual,
+                    };
+                    Some((Err(BoxError::from(mismatch)), None))
+                }
+            }
+        }
+    });
+    hyper::Response::from_parts(parts, hyper::body::Body::wrap_stream(verified))
+}
+
+/// Sets `body` as the body of the request built by `req_builder`. It is gzip compressed if
+/// `compression_min_size` is set and the body has at least as many bytes.
+pub fn body_request(
+    req_builder: hyper::http::request::Builder,
+    body: Vec<u8>,
+    compression_min_size: Option<usize>,
+) -> hyper::http::Result<hyper::Request<hyper::body::Body>> {
+    let compress = compression_min_size.is_some_and(|min_size| body.len() >= min_size);
+    if !compress {
+        return req_builder
+            .header(CONTENT_LENGTH, body.len() as u64)
+            .body(hyper::body::Body::from(body));
+    }
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let compressed = encoder
+        .write_all(&body)
+        .and_then(|_| encoder.finish())
+        .expect("writing to memory to work");
+    req_builder
+        .header(CONTENT_ENCODING, "gzip")
+        .header(CONTENT_LENGTH, compressed.len() as u64)
+        .body(hyper::body::Body::from(compressed))
+}
+
+/// The future returned by `TokenProvider::token()`.
+pub type TokenFuture<'a> = Pin<Box<dyn Future<Output = io::Result<String>> + Send + 'a>>;
+
+/// Supplies the access tokens authorizing requests, in place of the authenticator of a hub.
+/// Implement it to take tokens from elsewhere, like a workload identity sidecar or a cache of the
+/// application, without depending on the types of the `yup-oauth2` crate.
+pub trait TokenProvider: Send + Sync {
+    /// Returns an access token granting the given scopes.
+    fn token<'a>(&'a self, scopes: &'a [&str]) -> TokenFuture<'a>;
+}
+
+/// Provides a token which was fetched beforehand, for all scopes. Requests fail with the error of
+/// the server once it expired.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticToken(pub String);
+
+impl TokenProvider for StaticToken {
+    fn token<'a>(&'a self, _scopes: &'a [&str]) -> TokenFuture<'a> {
+        Box::pin(future::ready(Ok(self.0.clone())))
+    }
+}
+
+impl TokenProvider for oauth2::authenticator::Authenticator<Connector> {
+    fn token<'a>(&'a self, scopes: &'a [&str]) -> TokenFuture<'a> {
+        Box::pin(async move {
+            oauth2::authenticator::Authenticator::token(self, scopes)
+                .await
+                .map(|token| token.as_str().to_string())
+                .map_err(io::Error::other)
+        })
+    }
+}
+
+/// The consent an installed flow authenticator asks the user for, as passed to the callback of a
+/// `ConsentDelegate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsentRequest {
+    /// The URL of the consent screen the user needs to visit.
+    pub url: String,
+    /// True if the authenticator expects the authorization code back, as with
+    /// `InstalledFlowReturnMethod::Interactive`. Otherwise it receives the redirect itself, and the
+    /// callback only presents the URL.
+    pub needs_code: bool,
+}
+
+/// The future returned by the callback of a `ConsentDelegate`, resolving to the authorization
+/// code, or to the reason the consent failed.
+pub type ConsentFuture = Pin<Box<dyn Future<Output = std::result::Result<String, String>> + Send>>;
+
+/// Hands the consent URL of an installed flow authenticator to an async callback, instead of
+/// printing it to stdout and reading the code from stdin. This embeds the flow in a service, like
+/// a web UI which shows the URL, and supplies the code its redirect handler received.
+///
+/// ```no_run
+/// # async fn doit(secret: google_apis_common::oauth2::ApplicationSecret) {
+/// use google_apis_common::{oauth2, ConnectionOptions, ConsentDelegate};
+///
+/// let delegate = ConsentDelegate::new(|consent| async move {
+///     // show `consent.url` to the user, and wait for the redirect to arrive
+///     Ok::<_, String>("code".to_string())
+/// })
+/// .redirect_uri("https://example.com/oauth2/callback");
+/// let auth = oauth2::InstalledFlowAuthenticator::builder(
+///     secret,
+///     oauth2::InstalledFlowReturnMethod::Interactive,
+/// )
+/// .hyper_client(ConnectionOptions::default().client())
+/// .flow_delegate(Box::new(delegate))
+/// .build()
+/// .await
+/// .unwrap();
+/// # }
+/// ```
+pub struct ConsentDelegate {
+    callback: Box<dyn Fn(ConsentRequest) -> ConsentFuture + Send + Sync>,
+    redirect_uri: Option<String>,
+}
+
+impl fmt::Debug for ConsentDelegate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConsentDelegate")
+            .field("redirect_uri", &self.redirect_uri)
+            .finish()
+    }
+}
+
+impl ConsentDelegate {
+    /// Returns a delegate calling `callback` whenever the user needs to consent.
+    pub fn new<F, Fut>(callback: F) -> ConsentDelegate
+    where
+        F: Fn(ConsentRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<String, String>> + Send + 'static,
+    {
+        ConsentDelegate {
+            callback: Box::new(move |consent| Box::pin(callback(consent))),
+            redirect_uri: None,
+        }
+    }
+
+    /// Sets the URI the consent screen redirects to, like the one of the redirect handler of a
+    /// web UI, which must be one of the redirect URIs of the application secret. By default, it is
+    /// the one of the application secret, or the local server of the authenticator.
+    pub fn redirect_uri<T: Into<String>>(mut self, uri: T) -> ConsentDelegate {
+        self.redirect_uri = Some(uri.into());
+        self
+    }
+}
+
+impl oauth2::authenticator_delegate::InstalledFlowDelegate for ConsentDelegate {
+    fn redirect_uri(&self) -> Option<&str> {
+        self.redirect_uri.as_deref()
+    }
+
+    fn present_user_url<'a>(
+        &'a self,
+        url: &'a str,
+        need_code: bool,
+    ) -> Pin<Box<dyn Future<Output = std::result::Result<String, String>> + Send + 'a>> {
+        (self.callback)(ConsentRequest {
+            url: url.to_string(),
+            needs_code: need_code,
+        })
+    }
+}
+
+/// Routes calls to the token provider of the tenant they are made for, so one hub serves the calls
+/// of many tenants, like the customers of a service, with their own credentials. Calls choose their
+/// tenant with `with_credentials()`, and use the credentials of the hub if they don't.
+///
+/// Tenants can be added and removed while the hub is in use. Clones share the tenants, and each
+/// provider caches the tokens of its tenant, like an authenticator does.
+#[derive(Clone, Default)]
+pub struct CredentialRouter {
+    providers: Arc<RwLock<HashMap<String, Arc<dyn TokenProvider>>>>,
+}
+
+impl fmt::Debug for CredentialRouter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let providers = self.providers.read().unwrap();
+        f.debug_struct("CredentialRouter")
+            .field("tenants", &providers.keys().sorted().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl CredentialRouter {
+    /// Returns a router without tenants.
+    pub fn new() -> CredentialRouter {
+        CredentialRouter::default()
+    }
+
+    /// Sets the provider of the tokens of `tenant`, returning the previous one.
+    pub fn insert<T: Into<String>>(
+        &self,
+        tenant: T,
+        provider: Arc<dyn TokenProvider>,
+    ) -> Option<Arc<dyn TokenProvider>> {
+        self.providers
+            .write()
+            .unwrap()
+            .insert(tenant.into(), provider)
+    }
+
+    /// Removes `tenant`, returning its provider. Calls made for it afterwards fail.
+    pub fn remove(&self, tenant: &str) -> Option<Arc<dyn TokenProvider>> {
+        self.providers.write().unwrap().remove(tenant)
+    }
+
+    /// Returns the provider of the tokens of `tenant`.
+    pub fn get(&self, tenant: &str) -> Option<Arc<dyn TokenProvider>> {
+        self.providers.read().unwrap().get(tenant).cloned()
+    }
+
+    /// The amount of tenants.
+    pub fn len(&self) -> usize {
+        self.providers.read().unwrap().len()
+    }
+
+    /// Returns true if there are no tenants.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns the token provider of the `tenant` a call is made for, if any, which fails with an error
+/// of kind `NotFound` if there is no `router` or it doesn't know the tenant.
+pub fn route_credentials(
+    router: Option<&CredentialRouter>,
+    tenant: Option<&str>,
+) -> io::Result<Option<Arc<dyn TokenProvider>>> {
+    let tenant = match tenant {
+        Some(tenant) => tenant,
+        None => return Ok(None),
+    };
+    router
+        .and_then(|router| router.get(tenant))
+        .map(Some)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("There are no credentials for tenant '{}'", tenant),
+            )
+        })
+}
+
+/// An event of obtaining the access token of a call, as reported to an `AuthObserver`. `scopes`
+/// are the ones the token was asked for, and `elapsed` is the time it took.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthEvent {
+    /// The token is the one of the previous call with the same scopes, which the token source
+    /// took from its cache.
+    CacheHit {
+        scopes: Vec<String>,
+        elapsed: Duration,
+    },
+    /// The token differs from the one of the previous call with the same scopes, or is the first
+    /// one, which the token source fetched or refreshed.
+    CacheMiss {
+        scopes: Vec<String>,
+        elapsed: Duration,
+    },
+    /// No token was obtained. `consent_required` is set if the grant of the credentials was
+    /// revoked, expired or denied, which needs the user to authorize again.
+    Failed {
+        scopes: Vec<String>,
+        consent_required: bool,
+        error: String,
+        elapsed: Duration,
+    },
+}
+
+impl AuthEvent {
+    /// The scopes the token was asked for.
+    pub fn scopes(&self) -> &[String] {
+        match self {
+            AuthEvent::CacheHit { scopes, .. }
+            | AuthEvent::CacheMiss { scopes, .. }
+            | AuthEvent::Failed { scopes, .. } => scopes,
+        }
+    }
+
+    /// The time it took to obtain the token, or to fail.
+    pub fn elapsed(&self) -> Duration {
+        match *self {
+            AuthEvent::CacheHit { elapsed, .. }
+            | AuthEvent::CacheMiss { elapsed, .. }
+            | AuthEvent::Failed { elapsed, .. } => elapsed,
+        }
+    }
+}
+
+/// Receives the events of obtaining the access tokens of the calls of a hub, to trace them or to
+/// alert on failing authorization apart from the errors of the API.
+/// It is called on the task of the call, so it should return quickly.
+pub trait AuthObserver: Send + Sync {
+    fn on_auth_event(&self, event: &AuthEvent);
+}
+
+/// Counts the auth events of a hub, as metrics to export. Clones share the counts.
+#[derive(Clone, Debug, Default)]
+pub struct AuthMetrics {
+    counts: Arc<Mutex<AuthCounts>>,
+}
+
+/// The counts of an `AuthMetrics` at one point in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuthCounts {
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub failures: u64,
+    /// The failures requiring the user to authorize again.
+    pub consent_required: u64,
+    /// The total time taken by the cache misses, which fetched or refreshed tokens.
+    pub refresh_time: Duration,
+    /// The longest time taken by a cache miss.
+    pub max_refresh_time: Duration,
+}
+
+impl AuthMetrics {
+    /// Returns metrics without events.
+    pub fn new() -> AuthMetrics {
+        AuthMetrics::default()
+    }
+
+    /// Returns the counts of the events so far.
+    pub fn counts(&self) -> AuthCounts {
+        *self.counts.lock().unwrap()
+    }
+}
+
+impl AuthObserver for AuthMetrics {
+    fn on_auth_event(&self, event: &AuthEvent) {
+        let mut counts = self.counts.lock().unwrap();
+        match *event {
+            AuthEvent::CacheHit { .. } => counts.cache_hits += 1,
+            AuthEvent::CacheMiss { elapsed, .. } => {
+                counts.cache_misses += 1;
+                counts.refresh_time += elapsed;
+                counts.max_refresh_time = counts.max_refresh_time.max(elapsed);
+            }
+            AuthEvent::Failed {
+                consent_required, ..
+            } => {
+                counts.failures += 1;
+                counts.consent_required += consent_required as u64;
+            }
+        }
+    }
+}
+
+/// Returns true if `err` tells the grant of the credentials was revoked, expired or denied, so the
+/// user has to authorize again, unlike failures which may pass, like those of the network.
+pub fn consent_required(err: &oauth2::Error) -> bool {
+    use oauth2::error::AuthErrorCode;
+
+    match err {
+        oauth2::Error::AuthError(err) => matches!(
+            err.error,
+            AuthErrorCode::InvalidGrant | AuthErrorCode::AccessDenied | AuthErrorCode::ExpiredToken
+        ),
+        _ => false,
+    }
+}
+
+/// Reports the access tokens obtained for the calls of a hub to its `AuthObserver`, telling cached
+/// tokens from fetched ones by comparing them with the previous token for the same scopes.
+/// Clones share the previous tokens.
+#[derive(Clone, Default)]
+pub struct AuthTracker {
+    observer: Option<Arc<dyn AuthObserver>>,
+    // the hashes of the previous tokens, by their space separated scopes
+    tokens: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl fmt::Debug for AuthTracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuthTracker")
+            .field("observed", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl AuthTracker {
+    /// Sets the observer to report to, returning the previous one.
+    pub fn set_observer(
+        &mut self,
+        observer: Option<Arc<dyn AuthObserver>>,
+    ) -> Option<Arc<dyn AuthObserver>> {
+        mem::replace(&mut self.observer, observer)
+    }
+
+    /// Awaits `token`, the access token for `scopes`, and reports its event to the observer.
+    /// Without an observer, it just awaits it.
+    pub async fn observe<F>(
+        &self,
+        scopes: &[&str],
+        token: F,
+    ) -> std::result::Result<String, oauth2::Error>
+    where
+        F: Future<Output = std::result::Result<String, oauth2::Error>>,
+    {
+        let observer = match self.observer {
+            Some(ref observer) => observer,
+            None => return token.await,
+        };
+        let start = Instant::now();
+        let result = token.await;
+        let elapsed = start.elapsed();
+        let event = match result {
+            Ok(ref token) => {
+                use std::hash::{Hash, Hasher};
+
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                token.hash(&mut hasher);
+                let hash = hasher.finish();
+                let previous = self.tokens.lock().unwrap().insert(scopes.join(" "), hash);
+                let scopes = scopes.iter().map(|s| s.to_string()).collect();
+                if previous == Some(hash) {
+                    AuthEvent::CacheHit { scopes, elapsed }
+                } else {
+                    AuthEvent::CacheMiss { scopes, elapsed }
+                }
+            }
+            Err(ref err) => AuthEvent::Failed {
+                scopes: scopes.iter().map(|s| s.to_string()).collect(),
+                consent_required: consent_required(err),
+                error: err.to_string(),
+                elapsed,
+            },
+        };
+        observer.on_auth_event(&event);
+        result
+    }
+}
+
+/// The time self-signed JWTs are valid for, which is the maximum Google accepts.
+const SELF_SIGNED_JWT_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// Signs JWTs authorizing requests with the key of a service account locally, which APIs accepting
+/// self-signed JWTs take in place of an access token. Unlike access tokens, they are obtained
+/// without a round trip to the token endpoint.
+/// Each JWT is made for the audience of one API, and reused until shortly before it expires.
+/// Clones share the JWTs signed before.
+#[derive(Clone)]
+pub struct SelfSignedJwt {
+    key: Arc<oauth2::ServiceAccountKey>,
+    signer: Arc<dyn rustls::sign::Signer>,
+    // the audience, the JWT and when it expires
+    signed: Arc<Mutex<Option<(String, String, SystemTime)>>>,
+}
+
+impl SelfSignedJwt {
+    /// Creates a signer using the private key of the given service account key, which must be
+    /// an RSA key in PEM-encoded PKCS8 format, as downloaded from the Cloud Console.
+    pub fn new(key: oauth2::ServiceAccountKey) -> io::Result<SelfSignedJwt> {
+        let invalid_key = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let private_key =
+            rustls::internal::pemfile::pkcs8_private_keys(&mut key.private_key.as_bytes())
+                .ok()
+                .and_then(|keys| keys.into_iter().next())
+                .ok_or_else(|| invalid_key("no PKCS8 private key in the service account key"))?;
+        let signer = rustls::sign::RSASigningKey::new(&private_key)
+            .ok()
+            .and_then(|signing_key| {
+                rustls::sign::SigningKey::choose_scheme(
+                    &signing_key,
+                    &[rustls::SignatureScheme::RSA_PKCS1_SHA256],
+                )
+            })
+            .ok_or_else(|| invalid_key("the private key can't sign with RS256"))?;
+        Ok(SelfSignedJwt {
+            key: Arc::new(key),
+            signer: Arc::from(signer),
+            signed: Default::default(),
+        })
+    }
+
+    /// Returns a JWT for the given audience, which is the root url of an API, like
+    /// `https://pubsub.googleapis.com/`.
+    pub fn token(&self, audience: &str) -> io::Result<String> {
+        let now = SystemTime::now();
+        let mut signed = self.signed.lock().unwrap();
+        if let Some((signed_audience, jwt, expires_at)) = signed.as_ref() {
+            // leave time to send it before it expires
+            if signed_audience == audience && now + Duration::from_secs(60) < *expires_at {
+                return Ok(jwt.clone());
+            }
+        }
+
+        let iat = now
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?
+            .as_secs();
+        let mut header = json::json!({"alg": "RS256", "typ": "JWT"});
+        if let Some(ref kid) = self.key.private_key_id {
+            header["kid"] = json::Value::from(kid.as_str());
+        }
+        let claims = json::json!({
+            "iss": self.key.client_email,
+            "sub": self.key.client_email,
+            "aud": audience,
+            "iat": iat,
+            "exp": iat + SELF_SIGNED_JWT_LIFETIME.as_secs(),
+        });
+        let mut jwt = format!(
+            "{}.{}",
+            base64::encode_config(header.to_string(), base64::URL_SAFE_NO_PAD),
+            base64::encode_config(claims.to_string(), base64::URL_SAFE_NO_PAD)
+        );
+        let signature = self.signer.sign(jwt.as_bytes()).map_err(io::Error::other)?;
+        jwt.push('.');
+        jwt.push_str(&base64::encode_config(signature, base64::URL_SAFE_NO_PAD));
+
+        *signed = Some((
+            audience.to_string(),
+            jwt.clone(),
+            now + SELF_SIGNED_JWT_LIFETIME,
+        ));
+        Ok(jwt)
+    }
+}
+
+/// Obtains access tokens with the first of many service account keys the token endpoint accepts,
+/// so calls keep working while keys are rotated. Keys it rejects, like revoked or expired ones, are
+/// skipped, and the one which worked last is tried first afterwards.
+/// Each key caches its tokens, like an authenticator does. Clones share them.
+#[derive(Clone)]
+pub struct ServiceAccountKeys {
+    keys: Arc<Vec<ServiceAccountKeyAuth>>,
+    // the index of the key which worked last
+    current: Arc<Mutex<usize>>,
+}
+
+struct ServiceAccountKeyAuth {
+    key: oauth2::ServiceAccountKey,
+    auth: tokio::sync::OnceCell<oauth2::authenticator::Authenticator<Connector>>,
+}
+
+impl fmt::Debug for ServiceAccountKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ServiceAccountKeys")
+            .field(
+                "keys",
+                &self
+                    .keys
+                    .iter()
+                    .map(|k| k.key.private_key_id.as_deref().unwrap_or_default())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl ServiceAccountKeys {
+    /// Uses the given keys, of which the first one is tried first.
+    pub fn new(keys: Vec<oauth2::ServiceAccountKey>) -> ServiceAccountKeys {
+        ServiceAccountKeys {
+            keys: Arc::new(
+                keys.into_iter()
+                    .map(|key| ServiceAccountKeyAuth {
+                        key,
+                        auth: Default::default(),
+                    })
+                    .collect(),
+            ),
+            current: Default::default(),
+        }
+    }
+
+    /// Uses the keys in the JSON files of `dir`, as downloaded from the Cloud Console, of which the
+    /// most recently modified one is tried first. Other JSON files are ignored.
+    /// Fails with an error of kind `NotFound` if there are no keys.
+    pub fn from_dir(dir: &Path) -> io::Result<ServiceAccountKeys> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let value: json::Value = match json::from_slice(&fs::read(&path)?) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            if value["type"] != "service_account" {
+                continue;
+            }
+            let key: oauth2::ServiceAccountKey = json::from_value(value).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), err),
+                )
+            })?;
+            keys.push((fs::metadata(&path)?.modified()?, key));
+        }
+        if keys.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("There are no service account keys in '{}'", dir.display()),
+            ));
+        }
+        keys.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        Ok(ServiceAccountKeys::new(
+            keys.into_iter().map(|(_, key)| key).collect(),
+        ))
+    }
+
+    /// The amount of keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The id of the key which worked last, or which is tried first if none was used yet.
+    pub fn current_key_id(&self) -> Option<&str> {
+        let current = *self.current.lock().unwrap();
+        self.keys.get(current)?.key.private_key_id.as_deref()
+    }
+}
+
+impl TokenProvider for ServiceAccountKeys {
+    fn token<'a>(&'a self, scopes: &'a [&str]) -> TokenFuture<'a> {
+        Box::pin(async move {
+            let first = *self.current.lock().unwrap();
+            let mut rejections = Vec::new();
+            for index in (first..self.keys.len()).chain(0..first) {
+                let key = &self.keys[index];
+                let auth = key
+                    .auth
+                    .get_or_try_init(|| {
+                        oauth2::ServiceAccountAuthenticator::builder(key.key.clone())
+                            .hyper_client(ConnectionOptions::default().client())
+                            .build()
+                    })
+                    .await;
+                let rejection = match auth {
+                    Ok(auth) => match auth.token(scopes).await {
+                        Ok(token) => {
+                            *self.current.lock().unwrap() = index;
+                            return Ok(token.as_str().to_string());
+                        }
+                        // the key was rejected, or can't sign
+                        Err(
+                            err @ (oauth2::Error::AuthError(_)
+                            | oauth2::Error::UserError(_)
+                            | oauth2::Error::LowLevelError(_)),
+                        ) => err.to_string(),
+                        Err(err) => return Err(io::Error::other(err)),
+                    },
+                    Err(err) => err.to_string(),
+                };
+                rejections.push(format!(
+                    "{}: {}",
+                    key.key.private_key_id.as_deref().unwrap_or("?"),
+                    rejection
+                ));
+            }
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "All service account keys were rejected: {}",
+                    rejections.join(", ")
+                ),
+            ))
+        })
+    }
+}
+
+/// The environment variable which must be set to `1` to allow running the executables supplying
+/// the credentials of external accounts.
+pub const ALLOW_EXECUTABLES_ENV: &str = "GOOGLE_EXTERNAL_ACCOUNT_ALLOW_EXECUTABLES";
+
+/// The time executables supplying credentials may take if the configuration doesn't say otherwise.
+const EXECUTABLE_DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The scope of the access tokens exchanged for subject tokens before impersonating a service account.
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Obtains access tokens for an external account, like one of a workload or workforce identity
+/// pool, whose credentials are supplied by an executable. This is the pluggable auth flow of
+/// credential configuration files like
+/// `{"type": "external_account", "credential_source": {"executable": {"command": ...}}}`, which
+/// allows custom token brokers to supply credentials.
+///
+/// The subject token printed by the executable is exchanged for an access token at the security
+/// token service, and for the one of a service account if the configuration impersonates one.
+/// As the executable runs arbitrary commands, this must be allowed by setting the environment
+/// variable `GOOGLE_EXTERNAL_ACCOUNT_ALLOW_EXECUTABLES` to `1`.
+/// Access tokens are reused until shortly before they expire. Clones share them.
+#[derive(Clone)]
+pub struct ExecutableCredentials {
+    audience: String,
+    subject_token_type: String,
+    token_url: String,
+    impersonation_url: Option<String>,
+    command: String,
+    timeout: Duration,
+    output_file: Option<String>,
+    // the space-separated scopes, the access token and when it expires
+    token: Arc<Mutex<Option<(String, String, SystemTime)>>>,
+}
+
+impl ExecutableCredentials {
+    /// Reads the credential configuration file at `path`, see `from_json()`.
+    pub fn from_file(path: &Path) -> io::Result<ExecutableCredentials> {
+        ExecutableCredentials::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a credential configuration of an external account whose `credential_source` is an
+    /// executable.
+    pub fn from_json(config: &str) -> io::Result<ExecutableCredentials> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let config: json::Value = json::from_str(config)?;
+        if config["type"] != "external_account" {
+            return Err(invalid(
+                "the credentials are not those of an external account".into(),
+            ));
+        }
+        let field = |name: &str| {
+            config[name]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid(format!("the credential configuration lacks '{}'", name)))
+        };
+        let executable = &config["credential_source"]["executable"];
+        let command = executable["command"]
+            .as_str()
+            .filter(|command| !command.trim().is_empty())
+            .ok_or_else(|| invalid("the credential source is not an executable".into()))?;
+        Ok(ExecutableCredentials {
+            audience: field("audience")?,
+            subject_token_type: field("subject_token_type")?,
+            token_url: field("token_url")?,
+            impersonation_url: field("service_account_impersonation_url").ok(),
+            command: command.to_string(),
+            timeout: executable["timeout_millis"]
+                .as_u64()
+                .map_or(EXECUTABLE_DEFAULT_TIMEOUT, Duration::from_millis),
+            output_file: executable["output_file"].as_str().map(str::to_string),
+            token: Default::default(),
+        })
+    }
+
+    /// Returns an access token for the given scopes, which is obtained using `client`.
+    pub async fn token<T: AsRef<str>>(
+        &self,
+        client: &hyper::Client<Connector, hyper::body::Body>,
+        scopes: &[T],
+    ) -> io::Result<String> {
+        let scopes: Vec<&str> = scopes.iter().map(|s| s.as_ref()).collect();
+        let scope = scopes.join(" ");
+        let now = SystemTime::now();
+        if let Some((cached_scopes, token, expires_at)) = self.token.lock().unwrap().as_ref() {
+            // leave time to send it before it expires
+            if *cached_scopes == scope && now + Duration::from_secs(60) < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let subject_token = self.subject_token().await?;
+        let sts_scope = match self.impersonation_url {
+            Some(_) => CLOUD_PLATFORM_SCOPE.to_string(),
+            None => scope.clone(),
+        };
+        let body = [
+            (
+                "grant_type",
+                "urn:ietf:params:oauth:grant-type:token-exchange",
+            ),
+            ("audience", &self.audience),
+            ("scope", &sts_scope),
+            (
+                "requested_token_type",
+                "urn:ietf:params:oauth:token-type:access_token",
+            ),
+            ("subject_token", &subject_token),
+            ("subject_token_type", &self.subject_token_type),
+        ]
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, form_urlencode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+        let req = hyper::Request::post(self.token_url.as_str())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(hyper::body::Body::from(body))
+            .map_err(io::Error::other)?;
+        let response = token_response(client, req).await?;
+        let (mut token, mut expires_at) = match (
+            response["access_token"].as_str(),
+            response["expires_in"].as_u64(),
+        ) {
+            (Some(token), expires_in) => (
+                token.to_string(),
+                now + Duration::from_secs(expires_in.unwrap_or(3600)),
+            ),
+            _ => {
+                return Err(io::Error::other(
+                    "the token exchange yielded no access token",
+                ))
+            }
+        };
+
+        if let Some(ref url) = self.impersonation_url {
+            let req = hyper::Request::post(url.as_str())
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .header(CONTENT_TYPE, "application/json")
+                .body(hyper::body::Body::from(
+                    json::json!({ "scope": scopes }).to_string(),
+                ))
+                .map_err(io::Error::other)?;
+            let response = token_response(client, req).await?;
+            token = match response["accessToken"].as_str() {
+                Some(token) => token.to_string(),
+                None => {
+                    return Err(io::Error::other(
+                        "the impersonation yielded no access token",
+                    ))
+                }
+            };
+            // the expiry is an RFC 3339 timestamp, which is conservatively assumed to be an hour
+            // from now, the lifetime of impersonated access tokens unless configured otherwise
+            expires_at = now + Duration::from_secs(3600);
+        }
+
+        *self.token.lock().unwrap() = Some((scope, token.clone(), expires_at));
+        Ok(token)
+    }
+
+    /// Returns the subject token supplied by the executable, which is taken from its output file
+    /// instead if that holds one which didn't expire yet.
+    pub async fn subject_token(&self) -> io::Result<String> {
+        if let Some(ref path) = self.output_file {
+            if let Ok(response) = fs::read(path) {
+                if let Ok(Some(token)) = executable_subject_token(&response, SystemTime::now()) {
+                    return Ok(token);
+                }
+            }
+        }
+        if env::var(ALLOW_EXECUTABLES_ENV).ok().as_deref() != Some("1") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "executables supplying credentials only run if {} is set to 1",
+                    ALLOW_EXECUTABLES_ENV
+                ),
+            ));
+        }
+
+        let mut args = self.command.split_whitespace();
+        let mut command = tokio::process::Command::new(args.next().unwrap_or_default());
+        command
+            .args(args)
+            .env("GOOGLE_EXTERNAL_ACCOUNT_AUDIENCE", &self.audience)
+            .env(
+                "GOOGLE_EXTERNAL_ACCOUNT_TOKEN_TYPE",
+                &self.subject_token_type,
+            )
+            .env("GOOGLE_EXTERNAL_ACCOUNT_INTERACTIVE", "0")
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+        if let Some(ref path) = self.output_file {
+            command.env("GOOGLE_EXTERNAL_ACCOUNT_OUTPUT_FILE", path);
+        }
+        if let Some(email) = self
+            .impersonation_url
+            .as_deref()
+            .and_then(|url| url.rsplit('/').next())
+            .and_then(|action| action.strip_suffix(":generateAccessToken"))
+        {
+            command.env("GOOGLE_EXTERNAL_ACCOUNT_IMPERSONATED_EMAIL", email);
+        }
+        let output = tokio::time::timeout(self.timeout, command.output())
+            .await
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the executable supplying credentials timed out",
+                )
+            })??;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "the executable supplying credentials failed with {}",
+                output.status
+            )));
+        }
+        executable_subject_token(&output.stdout, SystemTime::now())?
+            .ok_or_else(|| io::Error::other("the executable supplied an expired token"))
+    }
+}
+
+/// Returns the subject token in the `response` of an executable supplying credentials, or None if
+/// it expired. Fails if the response isn't valid, or reports a failure.
+pub fn executable_subject_token(response: &[u8], now: SystemTime) -> io::Result<Option<String>> {
+    let invalid = |msg: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid response of the executable: {}", msg),
+        )
+    };
+    let response: json::Value = json::from_slice(response)?;
+    if response["version"] != 1 {
+        return Err(invalid("unsupported version"));
+    }
+    match response["success"].as_bool() {
+        Some(true) => {}
+        Some(false) => {
+            return Err(io::Error::other(format!(
+                "the executable supplying credentials failed: {} ({})",
+                response["message"].as_str().unwrap_or("no message"),
+                response["code"].as_str().unwrap_or("no code")
+            )))
+        }
+        None => return Err(invalid("missing 'success'")),
+    }
+    if let Some(expiration_time) = response["expiration_time"].as_u64() {
+        if UNIX_EPOCH + Duration::from_secs(expiration_time) <= now {
+            return Ok(None);
+        }
+    }
+    let token = match response["token_type"].as_str() {
+        Some("urn:ietf:params:oauth:token-type:id_token")
+        | Some("urn:ietf:params:oauth:token-type:jwt") => response["id_token"].as_str(),
+        Some("urn:ietf:params:oauth:token-type:saml2") => response["saml_response"].as_str(),
+        _ => return Err(invalid("unsupported 'token_type'")),
+    };
+    token
+        .map(|token| Some(token.to_string()))
+        .ok_or_else(|| invalid("missing token"))
+}
+
+// Sends the request for a token, and returns the JSON of the successful response.
+async fn token_response(
+    client: &hyper::Client<Connector, hyper::body::Body>,
+    req: hyper::Request<hyper::body::Body>,
+) -> io::Result<json::Value> {
+    let res = client.request(req).await.map_err(io::Error::other)?;
+    let status = res.status();
+    let body = hyper::body::to_bytes(res.into_body())
+        .await
+        .map_err(io::Error::other)?;
+    if !status.is_success() {
+        return Err(io::Error::other(format!(
+            "the token request failed with {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        )));
+    }
+    Ok(json::from_slice(&body)?)
+}
+
+fn form_urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                (b as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// The amount of items `Items` processes at once, unless configured otherwise.
+pub const DEFAULT_ITEM_CONCURRENCY: usize = 8;
+
+/// The items of all pages of a list call, as a stream which fetches the next page once the items
+/// of the previous one were taken from it.
+///
+/// Typed functions processing the items are registered with `map_items()`, `filter_items()` and
+/// `filter_map_items()`. The asynchronous ones process up to `concurrency()` items at once, while
+/// further pages are fetched, which lets heavy processing, like verifying signatures or looking up
+/// related resources, overlap with the requests. The items keep their order.
+#[must_use = "streams do nothing unless polled"]
+pub struct Items<S> {
+    stream: Pin<Box<S>>,
+    concurrency: usize,
+}
+
+impl<S, T> Items<S>
+where
+    S: Stream<Item = Result<T>>,
+{
+    /// Returns the items of the given stream, processing `DEFAULT_ITEM_CONCURRENCY` of them at
+    /// once.
+    pub fn new(stream: S) -> Items<S> {
+        Items::with_stream(stream, DEFAULT_ITEM_CONCURRENCY)
+    }
+
+    /// Process up to `concurrency` items at once in the asynchronous functions registered
+    /// afterwards, at least one.
+    pub fn concurrency(mut self, concurrency: usize) -> Items<S> {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Replace each item with the output of `f`. The stream fails with the first error `f`
+    /// returns.
+    pub fn map_items<U, F, Fut>(self, f: F) -> Items<impl Stream<Item = Result<U>>>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = Result<U>>,
+    {
+        let concurrency = self.concurrency;
+        let stream = self.stream.map_ok(f).try_buffered(concurrency);
+        Items::with_stream(stream, concurrency)
+    }
+
+    /// Keep the items for which `f` returns true.
+    pub fn filter_items<F>(self, mut f: F) -> Items<impl Stream<Item = Result<T>>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let concurrency = self.concurrency;
+        let stream = self.stream.try_filter(move |item| future::ready(f(item)));
+        Items::with_stream(stream, concurrency)
+    }
+
+    /// Replace each item with the output of `f`, dropping the ones it returns `None` for. The
+    /// stream fails with the first error `f` returns.
+    pub fn filter_map_items<U, F, Fut>(self, f: F) -> Items<impl Stream<Item = Result<U>>>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = Result<Option<U>>>,
+    {
+        let concurrency = self.concurrency;
+        let stream = self
+            .stream
+            .map_ok(f)
+            .try_buffered(concurrency)
+            .try_filter_map(future::ok);
+        Items::with_stream(stream, concurrency)
+    }
+}
+
+impl<S> Items<S> {
+    fn with_stream(stream: S, concurrency: usize) -> Items<S> {
+        Items {
+            stream: Box::pin(stream),
+            concurrency,
+        }
+    }
+}
+
+impl<S, T> Stream for Items<S>
+where
+    S: Stream<Item = Result<T>>,
+{
+    type Item = Result<T>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<T>>> {
+        self.stream.as_mut().poll_next(cx)
+    }
+}
+
+/// A page of a list call, with the metadata its response holds about the whole list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    /// The items of the page.
+    pub items: Vec<T>,
+    /// The token of the next page, or `None` on the last one.
+    pub next_page_token: Option<String>,
+    /// The amount of items of all pages, for APIs whose list responses have a `totalSize` field.
+    pub total_size: Option<u64>,
+}
+
+impl<T> From<(Vec<T>, Option<String>)> for Page<T> {
+    fn from((items, next_page_token): (Vec<T>, Option<String>)) -> Page<T> {
+        Page {
+            items,
+            next_page_token,
+            total_size: None,
+        }
+    }
+}
+
+/// Returns all pages fetched by `fetch`, starting at `page_token`, or at the first page if it is
+/// `None`. `fetch` is given the token of a page, and returns the page, which names the token of
+/// the next one, if there is one.
+pub fn pages<T, F, Fut>(page_token: Option<String>, fetch: F) -> impl Stream<Item = Result<Page<T>>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Page<T>>>,
+{
+    stream::try_unfold(
+        (fetch, Some(page_token)),
+        |(mut fetch, page_token)| async move {
+            let page_token = match page_token {
+                Some(page_token) => page_token,
+                None => return Ok(None),
+            };
+            let mut page = fetch(page_token).await?;
+            // the last page may have an empty token rather than none
+            page.next_page_token = page.next_page_token.filter(|t| !t.is_empty());
+            let next_page_token = page.next_page_token.clone().map(Some);
+            Ok::<_, Error>(Some((page, (fetch, next_page_token))))
+        },
+    )
+}
+
+/// Returns all pages fetched by `fetch`, like `pages()` does, with sizes chosen by `page_size`.
+/// `fetch` is given the token and the size of a page. Pages which were too large to be delivered
+/// are requested again with a smaller size, see `AdaptivePageSize`.
+pub fn adaptive_pages<T, F, Fut>(
+    page_token: Option<String>,
+    page_size: AdaptivePageSize,
+    fetch: F,
+) -> impl Stream<Item = Result<Page<T>>>
+where
+    F: FnMut(Option<String>, u32) -> Fut,
+    Fut: Future<Output = Result<Page<T>>>,
+{
+    stream::try_unfold(
+        (fetch, page_size, Some(page_token)),
+        |(mut fetch, mut page_size, page_token)| async move {
+            let page_token = match page_token {
+                Some(page_token) => page_token,
+                None => return Ok(None),
+            };
+            let mut page = loop {
+                let started = Instant::now();
+                match fetch(page_token.clone(), page_size.size()).await {
+                    Ok(page) => {
+                        page_size.on_success(started.elapsed());
+                        break page;
+                    }
+                    Err(err) if page_size.on_error(&err) => continue,
+                    Err(err) => return Err(err),
+                }
+            };
+            // the last page may have an empty token rather than none
+            page.next_page_token = page.next_page_token.filter(|t| !t.is_empty());
+            let next_page_token = page.next_page_token.clone().map(Some);
+            Ok::<_, Error>(Some((page, (fetch, page_size, next_page_token))))
+        },
+    )
+}
+
+/// Returns the items of the given `pages`, one page after another.
+pub fn page_items<T, S>(pages: S) -> Items<impl Stream<Item = Result<T>>>
+where
+    S: Stream<Item = Result<Page<T>>>,
+{
+    Items::new(
+        pages
+            .map_ok(|page| stream::iter(page.items.into_iter().map(Ok)))
+            .try_flatten(),
+    )
+}
+
+/// Returns the items of all pages fetched by `fetch`, like `pages()` does, as `Items`.
+pub fn paginate<T, F, Fut>(
+    page_token: Option<String>,
+    fetch: F,
+) -> Items<impl Stream<Item = Result<T>>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Page<T>>>,
+{
+    page_items(pages(page_token, fetch))
+}
+
+/// The amount of names `batch_get_many()` gets with one batch call at most.
+pub const MAX_BATCH_GET_NAMES: usize = 100;
+
+/// Returns the results of `get` for each of the `names`, in their order, calling it for up to
+/// `concurrency` of them at once, at least one.
+pub async fn get_many<T, F, Fut>(names: Vec<String>, concurrency: usize, get: F) -> Vec<Result<T>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    stream::iter(names)
+        .map(get)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Returns the parent of the resource `name`, like `projects/p` of `projects/p/topics/t`, or an
+/// empty string if it has none.
+pub fn resource_parent(name: &str) -> &str {
+    let mut segments = name.rsplitn(3, '/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(_), Some(_), Some(parent)) => parent,
+        _ => "",
+    }
+}
+
+/// Like `get_many()`, but gets the names sharing a parent with `batch_get`, up to
+/// `MAX_BATCH_GET_NAMES` of them per call. `batch_get` is given the parent and the names, and
+/// returns their items in the order of the names, like batch get methods do. The names of failed
+/// batches, or of those returning another amount of items, are gotten one by one with `get`,
+/// which tells which of them failed.
+pub async fn batch_get_many<T, B, BFut, F, Fut>(
+    names: Vec<String>,
+    concurrency: usize,
+    mut batch_get: B,
+    get: F,
+) -> Vec<Result<T>>
+where
+    B: FnMut(String, Vec<String>) -> BFut,
+    BFut: Future<Output = Result<Vec<T>>>,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    // (parent, indices of the names)
+    let mut batches: Vec<(&str, Vec<usize>)> = Vec::new();
+    let mut remaining = Vec::new();
+    for (index, name) in names.iter().enumerate() {
+        let parent = resource_parent(name);
+        if parent.is_empty() {
+            remaining.push(index);
+            continue;
+        }
+        match batches
+            .iter_mut()
+            .find(|(p, indices)| *p == parent && indices.len() < MAX_BATCH_GET_NAMES)
+        {
+            Some((_, indices)) => indices.push(index),
+            None => batches.push((parent, vec![index])),
+        }
+    }
+
+    let batches: Vec<_> = stream::iter(batches)
+        .map(|(parent, indices)| {
+            let batch_names = indices.iter().map(|&i| names[i].clone()).collect();
+            let items = batch_get(parent.to_string(), batch_names);
+            async move { (indices, items.await) }
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+    let mut results: Vec<Option<Result<T>>> = names.iter().map(|_| None).collect();
+    for (indices, items) in batches {
+        match items {
+            Ok(items) if items.len() == indices.len() => {
+                for (index, item) in indices.into_iter().zip(items) {
+                    results[index] = Some(Ok(item));
+                }
+            }
+            _ => remaining.extend(indices),
+        }
+    }
+
+    let remaining_names = remaining.iter().map(|&i| names[i].clone()).collect();
+    let gotten = get_many(remaining_names, concurrency, get).await;
+    for (index, result) in remaining.into_iter().zip(gotten) {
+        results[index] = Some(result);
+    }
+    results.into_iter().flatten().collect()
+}
+
+/// Adapts the page size of paginated list calls to how the server copes with it.
+///
+/// It starts at a configurable size, which is halved whenever a page turned out to be too
+/// large to be delivered, and doubled up to the maximum of the API whenever a page arrived
+/// quickly. Set the page size of each call to `size()`, and report its outcome using
+/// `on_success()` or `on_error()` before requesting the next page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptivePageSize {
+    size: u32,
+    max: u32,
+    fast: Duration,
+}
+
+impl AdaptivePageSize {
+    /// Pages arriving within this duration are considered fast, unless configured otherwise.
+    pub const FAST_PAGE: Duration = Duration::from_secs(2);
+
+    /// Start at `initial` items per page, never requesting more than `max` of them.
+    pub fn new(initial: u32, max: u32) -> AdaptivePageSize {
+        let max = max.max(1);
+        AdaptivePageSize {
+            size: initial.clamp(1, max),
+            max,
+            fast: Self::FAST_PAGE,
+        }
+    }
+
+    /// Consider pages arriving within `fast` fast, instead of within `FAST_PAGE`.
+    pub fn with_fast_page(mut self, fast: Duration) -> AdaptivePageSize {
+        self.fast = fast;
+        self
+    }
+
+    /// The page size to use for the next call.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Record that a page arrived after `elapsed`, increasing the size if it was fast.
+    pub fn on_success(&mut self, elapsed: Duration) {
+        if elapsed <= self.fast {
+            self.size = self.size.saturating_mul(2).min(self.max);
+        }
+    }
+
+    /// Record that fetching a page failed with `err`. Returns true if the failure is due to the
+    /// size of the page, and the size was reduced, which makes it worth requesting it again.
+    pub fn on_error(&mut self, err: &Error) -> bool {
+        if !is_page_too_large(err) || self.size == 1 {
+            return false;
+        }
+        self.size /= 2;
+        true
+    }
+}
+
+/// Returns true if `err` indicates that the requested page was too large to be delivered,
+/// which is a `413 Payload Too Large` status, or a timeout of the server or the connection.
+pub fn is_page_too_large(err: &Error) -> bool {
+    const TOO_LARGE: [StatusCode; 3] = [
+        StatusCode::REQUEST_TIMEOUT,
+        StatusCode::PAYLOAD_TOO_LARGE,
+        StatusCode::GATEWAY_TIMEOUT,
+    ];
+    match err {
+        Error::HttpError(err) => err.is_timeout(),
+        Error::Failure(res) => TOO_LARGE.contains(&res.status()),
+        Error::BadRequest(value) => value["error"]["code"]
+            .as_u64()
+            .is_some_and(|code| TOO_LARGE.iter().any(|s| u64::from(s.as_u16()) == code)),
+        _ => false,
+    }
+}
+
+/// Serde adapter for `Option<Duration>` fields, which are represented as strings in the
+/// protobuf duration syntax, like `"3.5s"`. Negative durations can't be represented, and fail
+/// to deserialize.
+pub mod serde_duration {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S>(value: &Option<Duration>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(d) => s.serialize_str(&to_string(d)),
+            None => s.serialize_none(),
+        }
+    }
+
+    /// Negative durations, like `"-1.5s"`, can't be represented by `Duration`, and are read as
+    /// zero, which is when they elapse.
+    pub fn deserialize<'de, D>(d: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(d)? {
+            Some(s) => from_str(&s)
+                .or_else(|| {
+                    s.strip_prefix('-')
+                        .and_then(from_str)
+                        .map(|_| Duration::ZERO)
+                })
+                .map(Some)
+                .ok_or_else(|| de::Error::custom(format!("invalid duration: '{}'", s))),
+            None => Ok(None),
+        }
+    }
+
+    /// Format `d` like `"3.5s"`, using 0, 3, 6 or 9 fractional digits.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn to_string(d: &Duration) -> String {
+        let nanos = d.subsec_nanos();
+        if nanos == 0 {
+            format!("{}s", d.as_secs())
+        } else if nanos % 1_000_000 == 0 {
+            format!("{}.{:03}s", d.as_secs(), nanos / 1_000_000)
+        } else if nanos % 1_000 == 0 {
+            format!("{}.{:06}s", d.as_secs(), nanos / 1_000)
+        } else {
+            format!("{}.{:09}s", d.as_secs(), nanos)
+        }
+    }
+
+    /// Parse a duration like `"3.5s"`, returning `None` if it is invalid or negative.
+    pub fn from_str(s: &str) -> Option<Duration> {
+        let s = s.strip_suffix('s')?;
+        let (secs, frac) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+        let all_digits = |v: &str| v.bytes().all(|b| b.is_ascii_digit());
+        if secs.is_empty() || !all_digits(secs) || frac.len() > 9 || !all_digits(frac) {
+            return None;
+        }
+        let nanos = if frac.is_empty() {
+            0
+        } else {
+            frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32)
+        };
+        Some(Duration::new(secs.parse().ok()?, nanos))
+    }
+}
+
+// Copy of src/rust/cli/client.rs
+pub fn remove_json_null_values(value: &mut json::value::Value) {
+    match *value {
+        json::value::Value::Object(ref mut map) => {
+            let mut for_removal = Vec::new();
+
+            for (key, value) in map.iter_mut() {
+                if value.is_null() {
+                    for_removal.push(key.clone());
+                } else {
+                    remove_json_null_values(value);
+                }
+            }
+
+            for key in &for_removal {
+                map.remove(key);
+            }
+        }
+        json::value::Value::Array(ref mut arr) => {
+            let mut i = 0;
+            while i < arr.len() {
+                if arr[i].is_null() {
+                    arr.remove(i);
+                } else {
+                    remove_json_null_values(&mut arr[i]);
+                    i += 1;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Decodes the JSON of a response. If it fails, the error is returned along with the path of the
+/// value that couldn't be decoded, like `notes[3].kind`, or `.` if it is the response itself.
+pub fn decode_json<T>(json_str: &str) -> std::result::Result<T, (String, json::Error)>
+where
+    T: serde::de::DeserializeOwned,
+{
+    decode_json_from(json::Deserializer::from_str(json_str))
+}
+
+/// The key of the JSON objects of messages packed into a `google.protobuf.Any`, like the response
+/// and metadata of long running operations, which holds the URL of their type, like
+/// `type.googleapis.com/google.protobuf.Empty`.
+pub const ANY_TYPE_KEY: &str = "@type";
+
+/// A message which may be packed into a `google.protobuf.Any`, like the response and metadata of
+/// long running operations, which names it by its fully qualified protobuf name.
+pub trait AnyMessage: serde::de::DeserializeOwned {
+    /// The fully qualified name of the message, like `google.protobuf.Empty`.
+    const TYPE_NAME: &'static str;
+}
+
+/// A packed message names another type in `@type` than the one it was decoded as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnyTypeMismatch {
+    /// The fully qualified name of the type it was decoded as.
+    pub expected: String,
+    /// The type URL of the message, which is `None` if it has none.
+    pub actual: Option<String>,
+}
+
+impl Display for AnyTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.actual {
+            Some(ref actual) => write!(
+                f,
+                "The packed message is a '{}', but was decoded as '{}'",
+                actual, self.expected
+            ),
+            None => write!(
+                f,
+                "The packed message has no type, but was decoded as '{}'",
+                self.expected
+            ),
+        }
+    }
+}
+
+impl error::Error for AnyTypeMismatch {}
+
+/// Decodes the packed message `any` as `T`, after checking that its type URL ends with
+/// `type_name`, like `google.protobuf.Empty`, if given. A message of another type fails with
+/// `Error::AnyTypeMismatch`, and one which doesn't decode with `Error::JsonDecodeError`.
+#[allow(clippy::result_large_err)]
+pub fn decode_any<T>(any: &HashMap<String, json::Value>, type_name: Option<&str>) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let type_url = any.get(ANY_TYPE_KEY).and_then(json::Value::as_str);
+    if let Some(type_name) = type_name {
+        // like `type.googleapis.com/google.protobuf.Empty`
+        let actual = type_url.map(|url| url.rsplit('/').next().unwrap_or(url));
+        if actual != Some(type_name) {
+            return Err(Error::AnyTypeMismatch(AnyTypeMismatch {
+                expected: type_name.to_string(),
+                actual: type_url.map(str::to_string),
+            }));
+        }
+    }
+    let fields: json::Map<String, json::Value> = any
+        .iter()
+        .filter(|(key, _)| *key != ANY_TYPE_KEY)
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let body = json::Value::Object(fields).to_string();
+    decode_json(&body).map_err(|(path, err)| Error::JsonDecodeError(body, path, err))
+}
+
+/// Like `decode_json()`, but decodes the bytes of a response as read by `read_body()`, without
+/// copying them into a string first.
+pub fn decode_json_slice<T>(json: &[u8]) -> std::result::Result<T, (String, json::Error)>
+where
+    T: serde::de::DeserializeOwned,
+{
+    // validating all of it at once is faster than validating each string while decoding, and
+    // only invalid input is left to the deserializer to report where it is
+    match std::str::from_utf8(json) {
+        Ok(json_str) => decode_json(json_str),
+        Err(_) => decode_json_from(json::Deserializer::from_slice(json)),
+    }
+}
+
+fn decode_json_from<'de, R, T>(
+    mut deserializer: json::Deserializer<R>,
+) -> std::result::Result<T, (String, json::Error)>
+where
+    R: json::de::Read<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let value = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|err| (err.path().to_string(), err.into_inner()))?;
+    // like `json::from_str()`, only whitespace may follow the value
+    deserializer.end().map_err(|err| (".".to_string(), err))?;
+    Ok(value)
+}
+
+/// The largest buffer `read_body()` allocates up front, whatever size the response announced.
+pub const MAX_PREALLOCATED_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// Reads the whole body of a response into a buffer allocated for the size announced by its
+/// `Content-Length`, so that large responses aren't copied whenever the buffer grows.
+pub async fn read_body(res_body: &mut hyper::Body) -> Result<Vec<u8>> {
+    read_body_limited(res_body, None).await
+}
+
+/// Like `read_body()`, but fails with `Error::ResponseTooLarge` if the body is larger than
+/// `max_size` bytes, without reading more than that.
+pub async fn read_body_limited(
+    res_body: &mut hyper::Body,
+    max_size: Option<u64>,
+) -> Result<Vec<u8>> {
+    use hyper::body::HttpBody;
+
+    let size = HttpBody::size_hint(res_body).lower();
+    if let Some(max_size) = max_size {
+        if size > max_size {
+            return Err(Error::ResponseTooLarge(max_size));
+        }
+    }
+    let mut buf = Vec::with_capacity((size as usize).min(MAX_PREALLOCATED_BODY_SIZE));
+    while let Some(chunk) = HttpBody::data(res_body).await {
+        let chunk = chunk?;
+        if let Some(max_size) = max_size {
+            if (buf.len() + chunk.len()) as u64 > max_size {
+                return Err(Error::ResponseTooLarge(max_size));
+            }
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(buf)
+}
+
+/// Reads the whole body of `res`, returning the response with a body made of the same bytes,
+/// which can be read again without copying them, along with the bytes.
+pub async fn buffer_response(
+    res: hyper::Response<hyper::Body>,
+) -> hyper::Result<(hyper::Response<hyper::Body>, Bytes)> {
+    let (parts, body) = res.into_parts();
+    let bytes = hyper::body::to_bytes(body).await?;
+    let restored = hyper::Response::from_parts(parts, hyper::Body::from(bytes.clone()));
+    Ok((restored, bytes))
+}
+
+/// Like `buffer_response()`, but fails with `Error::ResponseTooLarge` if the body is larger than
+/// `max_size` bytes, without reading more than that.
+pub async fn buffer_response_limited(
+    res: hyper::Response<hyper::Body>,
+    max_size: Option<u64>,
+) -> Result<(hyper::Response<hyper::Body>, Bytes)> {
+    let (parts, mut body) = res.into_parts();
+    let bytes = Bytes::from(read_body_limited(&mut body, max_size).await?);
+    let restored = hyper::Response::from_parts(parts, hyper::Body::from(bytes.clone()));
+    Ok((restored, bytes))
+}
+
+// Borrowing the body object as mutable and converts it to a string
+pub async fn get_body_as_string(res_body: &mut hyper::Body) -> String {
+    let res_body_buf = hyper::body::to_bytes(res_body).await.unwrap();
+    let res_body_string = String::from_utf8_lossy(&res_body_buf);
+    res_body_string.to_string()
+}
+
+/// What a call cost on the wire, for logging it without a proxy. The `doit()` methods of call
+/// builders add them to the extensions of the response they return, see `WireStats::of()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WireStats {
+    /// The bytes of the bodies of all requests, including the ones which were retried, and the
+    /// media of resumable uploads.
+    pub bytes_sent: u64,
+    /// The bytes of the bodies of all responses the call read, after decompressing them. The
+    /// body of a response which is returned unread isn't included.
+    pub bytes_received: u64,
+    /// The time from sending the last request until its response started to arrive.
+    pub time_to_first_byte: Duration,
+    /// The time the whole call took, including obtaining tokens, retries and reading the
+    /// response.
+    pub latency: Duration,
+    /// How often the call was retried.
+    pub retries: u32,
+}
+
+impl WireStats {
+    /// The statistics of the call which returned `res`, if it came from one.
+    pub fn of<B>(res: &hyper::Response<B>) -> Option<WireStats> {
+        res.extensions().get().copied()
+    }
+}
+
+/// The size of the body of `req`, which is known unless it is streamed without a
+/// `Content-Length`.
+pub fn request_body_size(req: &hyper::Request<hyper::Body>) -> u64 {
+    use hyper::body::HttpBody;
+
+    HttpBody::size_hint(req.body()).exact().unwrap_or_else(|| {
+        req.headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    })
+}
+
+/// The status and headers of a response, as returned by the `doit_raw()` method of call builders
+/// along with its body.
+#[derive(Debug, Clone)]
+pub struct ResponseMetadata {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// What the call cost on the wire, including reading the body.
+    pub stats: WireStats,
+}
+
+impl ResponseMetadata {
+    /// Reads the body of `res`, returning it along with the metadata of the response.
+    #[deprecated(note = "reads bodies of any size, use `read_response_limited()` instead")]
+    pub async fn read_response(
+        res: hyper::Response<hyper::body::Body>,
+    ) -> Result<(ResponseMetadata, Bytes)> {
+        ResponseMetadata::read_response_limited(res, None).await
+    }
+
+    /// Like `read_response()`, but fails with `Error::ResponseTooLarge` if the body is larger
+    /// than `max_size` bytes.
+    pub async fn read_response_limited(
+        res: hyper::Response<hyper::body::Body>,
+        max_size: Option<u64>,
+    ) -> Result<(ResponseMetadata, Bytes)> {
+        let started = Instant::now();
+        let (parts, mut body) = res.into_parts();
+        let bytes = read_body_limited(&mut body, max_size).await?;
+        let metadata = ResponseMetadata::new(parts, bytes.len(), started);
+        Ok((metadata, Bytes::from(bytes)))
+    }
+
+    // Adds reading the body, which started at `started`, to the statistics of the call
+    fn new(
+        parts: hyper::http::response::Parts,
+        body_size: usize,
+        started: Instant,
+    ) -> ResponseMetadata {
+        let mut stats = parts
+            .extensions
+            .get::<WireStats>()
+            .copied()
+            .unwrap_or_default();
+        stats.bytes_received += body_size as u64;
+        stats.latency += started.elapsed();
+        ResponseMetadata {
+            status: parts.status,
+            headers: parts.headers,
+            stats,
+        }
+    }
+}
+
+/// A single event of a `text/event-stream` response, as produced by the `doit_sse()` method of
+/// call builders for streaming methods.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerSentEvent {
+    /// The type of the event, if it was set.
+    pub event: Option<String>,
+    /// The data of all `data` fields of the event, separated by newlines.
+    pub data: String,
+    /// The id of the event, if it was set.
+    pub id: Option<String>,
+}
+
+/// The events of a `text/event-stream` response, in the order they were received.
+pub type ServerSentEventStream =
+    Pin<Box<dyn Stream<Item = std::result::Result<ServerSentEvent, hyper::Error>> + Send>>;
+
+/// Parses server-sent events from the chunks of a response body, as they arrive.
+#[derive(Default)]
+pub struct ServerSentEventParser {
+    buf: Vec<u8>,
+    event: ServerSentEvent,
+    has_data: bool,
+}
+
+impl ServerSentEventParser {
+    /// Feed the next `chunk` of the body, and return all events it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<ServerSentEvent> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // an event without data is dropped, but resets the fields set so far
+                let event = mem::take(&mut self.event);
+                if mem::replace(&mut self.has_data, false) {
+                    events.push(event);
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.find(':') {
+                Some(i) => {
+                    let value = &line[i + 1..];
+                    (&line[..i], value.strip_prefix(' ').unwrap_or(value))
+                }
+                None => (line, ""),
+            };
+            match field {
+                "data" => {
+                    if self.has_data {
+                        self.event.data.push('\n');
+                    }
+                    self.event.data.push_str(value);
+                    self.has_data = true;
+                }
+                "event" => self.event.event = Some(value.to_string()),
+                "id" => self.event.id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Turn the body of a `text/event-stream` response into a stream of its events.
+pub fn server_sent_events(body: hyper::Body) -> ServerSentEventStream {
+    use hyper::body::HttpBody;
+
+    let chunks = stream::unfold(
+        (body, ServerSentEventParser::default()),
+        |(mut body, mut parser)| async move {
+            let events = body.data().await?.map(|chunk| parser.feed(&chunk));
+            Some((events, (body, parser)))
+        },
+    );
+    Box::pin(
+        chunks
+            .map_ok(|events| stream::iter(events.into_iter().map(Ok)))
+            .try_flatten(),
+    )
+}
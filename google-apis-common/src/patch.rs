@@ -0,0 +1,92 @@
+//! Computes the patch and the update mask of a resource which was modified locally, for the
+//! `patch` methods of APIs, which only change the fields named by the mask.
+//!
+//! Resources are compared by their JSON, just like they are sent, so fields which are `None` and
+//! the ones which are missing are the same. Nested messages are compared field by field, while lists
+//! and other values are replaced as a whole. Maps, like `labels`, are compared key by key, which
+//! the masks of most APIs accept as `labels.env`.
+//!
+//! ```ignore
+//! let (_, topic) = hub.projects().topics_get("projects/p/topics/t").doit().await?;
+//! let mut modified = topic.clone();
+//! modified.labels.get_or_insert_with(Default::default).insert("env".into(), "prod".into());
+//! let (patch, mask) = client::patch::diff(&topic, &modified);
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json as json;
+
+use crate::FieldMask;
+
+/// Returns the fields of `new` which differ from the ones of `old`, along with the mask naming
+/// them, like `config.retries,labels.env`. Fields which `new` lacks, but `old` has, are named by
+/// the mask and left out of the patch, which makes the server clear them.
+///
+/// # Panics
+///
+/// If `T` can't be deserialized from some of its fields, which all generated types can.
+pub fn diff<T>(old: &T, new: &T) -> (T, FieldMask)
+where
+    T: Serialize + DeserializeOwned,
+{
+    let old = json::to_value(old).expect("serde to work");
+    let new = json::to_value(new).expect("serde to work");
+    let mut paths = Vec::new();
+    let patch = diff_values(&old, &new, "", &mut paths)
+        .unwrap_or_else(|| json::Value::Object(Default::default()));
+    let patch =
+        json::from_value(patch).expect("the fields of a value to be a valid value of its type");
+    (patch, FieldMask::new(&paths))
+}
+
+// Returns the part of `new` which differs from `old`, if any, and adds the paths of the differing
+// fields to `paths`.
+fn diff_values(
+    old: &json::Value,
+    new: &json::Value,
+    path: &str,
+    paths: &mut Vec<String>,
+) -> Option<json::Value> {
+    match (old, new) {
+        (json::Value::Object(old), json::Value::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            let mut patch = json::Map::new();
+            for key in keys {
+                let old = old.get(key).unwrap_or(&json::Value::Null);
+                let new = new.get(key).unwrap_or(&json::Value::Null);
+                if let Some(value) = diff_values(old, new, &field_path(path, key), paths) {
+                    patch.insert(key.clone(), value);
+                }
+            }
+            if patch.is_empty() {
+                None
+            } else {
+                Some(json::Value::Object(patch))
+            }
+        }
+        _ if old == new => None,
+        _ => {
+            paths.push(path.to_string());
+            Some(new.clone())
+        }
+    }
+}
+
+// Keys which aren't identifiers, like the ones of maps, are quoted with backticks.
+fn field_path(path: &str, key: &str) -> String {
+    let is_identifier = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let key = if is_identifier {
+        key.to_string()
+    } else {
+        format!("`{}`", key)
+    };
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -0,0 +1,135 @@
+//! The asynchronous runtime hubs wait for retries and timeouts with, and spawn the tasks of their
+//! connections on, so that these don't need to run within a tokio runtime.
+//!
+//! Tokio is used by default. The `async-std` and `smol` features add the runtimes of these crates,
+//! which `default_runtime()` picks if it isn't called from within a tokio runtime.
+//!
+//! This doesn't make this crate independent of tokio, which stays a dependency: the connector of
+//! hyper, and thus the authenticators of yup-oauth2, still need a tokio reactor, which async-std
+//! provides with its `tokio1` feature, while smol needs a compatibility layer like `async-compat`.
+//! So does `ExecutableCredentials`, which runs its command with tokio. The synchronization
+//! primitives of tokio used by hubs, like the semaphore of `InFlightLimit`, work on any runtime.
+//!
+//! ```ignore
+//! let mut hub = Pubsub::new(client, auth);
+//! hub.runtime(Arc::new(client::runtime::AsyncStdRuntime));
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::Future;
+
+/// An asynchronous runtime, which sleeps and runs tasks in the background.
+pub trait Runtime: Send + Sync {
+    /// Returns a future which completes after the given `duration`.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Runs `future` to completion in the background.
+    fn spawn(&self, future: BoxFuture<'static, ()>);
+}
+
+/// The tokio runtime, which must be running the tasks of hubs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        tokio::spawn(future);
+    }
+}
+
+/// The global runtime of async-std.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        async_std::task::spawn(future);
+    }
+}
+
+/// The global executor of smol.
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl Runtime for SmolRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let timer = smol::Timer::after(duration);
+        Box::pin(async move {
+            timer.await;
+        })
+    }
+
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        smol::spawn(future).detach();
+    }
+}
+
+/// Returns the runtime hubs use by default, which is tokio if it is called from within a tokio
+/// runtime, or if no other runtime is enabled. Otherwise it is async-std, or smol, whichever of
+/// their features is enabled, in that order.
+pub fn default_runtime() -> Arc<dyn Runtime> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        Arc::new(TokioRuntime)
+    } else {
+        enabled_runtime()
+    }
+}
+
+#[cfg(feature = "async-std")]
+fn enabled_runtime() -> Arc<dyn Runtime> {
+    Arc::new(AsyncStdRuntime)
+}
+
+#[cfg(all(feature = "smol", not(feature = "async-std")))]
+fn enabled_runtime() -> Arc<dyn Runtime> {
+    Arc::new(SmolRuntime)
+}
+
+#[cfg(not(any(feature = "async-std", feature = "smol")))]
+fn enabled_runtime() -> Arc<dyn Runtime> {
+    Arc::new(TokioRuntime)
+}
+
+/// Spawns the background tasks of hyper clients, like the ones driving their connections, on the
+/// given runtime. By default, it is the `default_runtime()` when a task is spawned, which is the
+/// runtime the request spawning it is made in.
+#[derive(Clone, Default)]
+pub struct Executor {
+    runtime: Option<Arc<dyn Runtime>>,
+}
+
+impl Executor {
+    /// Returns an executor spawning all tasks on `runtime`.
+    pub fn new(runtime: Arc<dyn Runtime>) -> Executor {
+        Executor {
+            runtime: Some(runtime),
+        }
+    }
+}
+
+impl<F> hyper::rt::Executor<F> for Executor
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    fn execute(&self, future: F) {
+        match self.runtime {
+            Some(ref runtime) => runtime.spawn(Box::pin(future)),
+            None => default_runtime().spawn(Box::pin(future)),
+        }
+    }
+}
@@ -1,4 +1,6 @@
-<%! from util import (estr, enclose_in, hash_comment, library_to_crate_name, to_extern_crate_name) %>\
+<%! from util import (estr, enclose_in, hash_comment, library_to_crate_name, to_extern_crate_name, library_name,
+                      crate_version, target_directory_name, conversion_data_namespace, FAKE_FEATURE,
+                      TLS_FEATURES, RUNTIME_FEATURES, CASSETTE_FEATURE) %>\
 <%namespace name="util" file="lib/util.mako"/>\
 <%block filter="hash_comment">\
 <%util:gen_info source="${self.uri}" />\
@@ -32,11 +34,40 @@ mime = "^ 0.2.0"
 serde = "^ 1.0"
 serde_json = "^ 1.0"
 serde_derive = "^ 1.0"
-yup-oauth2 = "^ 6.0"
 itertools = "^ 0.10"
 % for dep in cargo.get('dependencies', list()):
 ${dep}
 % endfor
+<%
+  # crates of the other versions of the API whose structs convert into ours, by version
+  conversion_crates = dict()
+  if make.id == 'api':
+    for other_version in api.get('conversions', list()):
+      other = context.get(conversion_data_namespace(other_version))
+      conversion_crates[other_version] = (library_to_crate_name(library_name(name, other_version), make.target_suffix),
+                                          target_directory_name(name, other_version, make.target_suffix),
+                                          crate_version(cargo.build_version, other.get('revision')))
+%>\
+% if make.id == 'api':
+% for feature, deps in sorted(api.get('extension_features', dict()).items()):
+% for dep in deps:
+## the other entries name the features this one implies
+% if '=' in dep:
+${dep}
+% endif
+% endfor
+% endfor
+% for other_version, (other_crate, other_dir, other_crate_version) in sorted(conversion_crates.items()):
+${other_crate} = { path = "../${other_dir}", version = "${other_crate_version}", optional = true }
+% endfor
+% endif
+% if cargo.get('common_crate'):
+
+[dependencies.${cargo.common_crate.name}]
+path = "../${directories.common}/${cargo.common_crate.name}"
+version = "${cargo.common_crate.version}"
+default-features = false
+% endif
 
 <%
   api_name = util.library_name()
@@ -52,4 +83,47 @@ ${dep}
 [dependencies.${crate_name_we_depend_on}]
 path = "../${api_name}"
 version = "${util.crate_version()}"
+default-features = false
+features = ["${CASSETTE_FEATURE}"]
+
+[features]
+default = ["${TLS_FEATURES[0]}"]
+% for feature in TLS_FEATURES:
+${feature} = ["${crate_name_we_depend_on}/${feature}"]
+% endfor
+% endif
+% if make.id == 'api':
+
+[features]
+% if cargo.get('common_crate'):
+default = ["${TLS_FEATURES[0]}"]
+% for feature in TLS_FEATURES + RUNTIME_FEATURES:
+${feature} = ["${cargo.common_crate.name}/${feature}"]
+% endfor
+${FAKE_FEATURE} = ["${cargo.common_crate.name}/${FAKE_FEATURE}"]
+${CASSETTE_FEATURE} = ["${cargo.common_crate.name}/${CASSETTE_FEATURE}"]
+% endif
+% for feature, deps in sorted(api.get('extension_features', dict()).items()):
+${feature} = [${', '.join('"%s"' % d.split('=')[0].strip() for d in deps)}]
+% endfor
+% for other_version, (other_crate, _, _) in sorted(conversion_crates.items()):
+${other_version} = ["${other_crate}"]
+% endfor
+
+[package.metadata.docs.rs]
+all-features = true
+% endif
+% if cargo.get('dev_dependencies'):
+
+[dev-dependencies]
+% for dep in cargo.dev_dependencies:
+${dep}
+% endfor
+% endif
+% if make.id == 'api' and cargo.get('common_crate'):
+
+## The doc tests run against the fake of the server
+[dev-dependencies.${util.crate_name()}]
+path = "."
+features = ["${FAKE_FEATURE}"]
 % endif
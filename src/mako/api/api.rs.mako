@@ -3,29 +3,40 @@
 <%namespace name="rbuild" file="lib/rbuild.mako"/>\
 <%namespace name="mbuild" file="lib/mbuild.mako"/>\
 <%namespace name="schema" file="lib/schema.mako"/>\
+<%namespace name="conversions" file="lib/conversions.mako"/>\
+<%namespace name="fake" file="lib/fake.mako"/>\
 <%
+    import json
     from util import (new_context, rust_comment, rust_doc_comment, rust_module_doc_comment,
                       rb_type, hub_type, mangle_ident, hub_type_params_s, 
                       rb_type_params_s, find_fattest_resource, HUB_TYPE_PARAMETERS, METHODS_RESOURCE,
-                      UNUSED_TYPE_MARKER, schema_markers)
+                      UNUSED_TYPE_MARKER, schema_markers, QUOTA_PROJECT_HEADER, conversion_data_namespace,
+                      supports_media_download, supports_scopes, json_schema, rust_raw_string,
+                      operation_schemas, any_types, mb_type, to_fqan, method_media_params)
 
     c = new_context(schemas, resources, context.get('methods'))
     hub_type = hub_type(c.schemas, util.canonical_name())
     ht_params = hub_type_params_s()
 
     default_user_agent = "google-api-rust-client/" + cargo.build_version
+    # APIs accepting compressed requests say so in their overrides
+    default_request_compression = api.get('request_compression') and 'Some(client::REQUEST_COMPRESSION_MIN_SIZE)' or 'None'
+    default_propagate_deadline = api.get('deadline_header') and 'true' or 'false'
+    # APIs with interface versions or preview features may opt into them in their overrides
+    default_api_version = api.get('api_version') and 'Some("%s".to_string())' % api.api_version or 'None'
+    default_visibility_labels = 'vec![%s]' % ', '.join('"%s".to_string()' % l for l in api.get('visibility_labels') or [])
 %>\
 use std::collections::HashMap;
-use std::cell::RefCell;
 use std::default::Default;
 use std::collections::BTreeMap;
 use serde_json as json;
 use std::io;
 use std::fs;
 use std::mem;
-use std::thread::sleep;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::client;
+use crate::{client, oauth2};
 
 // ##############
 // UTILITIES ###
@@ -49,27 +60,83 @@ ${lib.hub_usage_example(c)}\
 </%block>
 #[derive(Clone)]
 pub struct ${hub_type}${ht_params} {
-    pub client: hyper::Client<hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>, hyper::body::Body>,
-    pub auth: oauth2::authenticator::Authenticator<hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>>,
+    pub client: hyper::Client<client::Connector, hyper::body::Body>,
+    pub auth: oauth2::authenticator::Authenticator<client::Connector>,
     _user_agent: String,
     _base_url: String,
     _root_url: String,
+    _quota_project: Option<String>,
+    _api_version: Option<String>,
+    _visibility_labels: Vec<String>,
+    _request_compression_min_size: Option<usize>,
+    _self_signed_jwt: Option<client::SelfSignedJwt>,
+    _external_account: Option<client::ExecutableCredentials>,
+    _token_provider: Option<Arc<dyn client::TokenProvider>>,
+    _credential_router: Option<client::CredentialRouter>,
+    _auth_tracker: client::AuthTracker,
+    _client_info: Option<String>,
+    _cancellation_token: client::CancellationToken,
+    _timeout: Option<Duration>,
+    _propagate_deadline: bool,
+    _max_response_size: Option<u64>,
+    _strict_decoding: bool,
+    _max_in_flight: Option<client::InFlightLimit>,
+    _connection_rebuild: Option<client::ConnectionRebuild>,
+    _runtime: Arc<dyn client::runtime::Runtime>,
+    _discovery_check: Option<client::discovery::DiscoveryCheck>,
+% if supports_media_download(c):
+    _verify_checksums: bool,
+% endif
 }
 
-impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> client::Hub for ${hub_type}${ht_params} {}
+impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> client::Hub for ${hub_type}${ht_params} {
+    fn from_parts(client: hyper::Client<client::Connector, hyper::body::Body>, auth: oauth2::authenticator::Authenticator<client::Connector>) -> ${hub_type}${ht_params} {
+        ${hub_type}::new(client, auth)
+    }
+}
 
 impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> ${hub_type}${ht_params} {
 
-    pub fn new(client: hyper::Client<hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>, hyper::body::Body>, authenticator: oauth2::authenticator::Authenticator<hyper_rustls::HttpsConnector<hyper::client::connect::HttpConnector>>) -> ${hub_type}${ht_params} {
+    pub fn new(client: hyper::Client<client::Connector, hyper::body::Body>, authenticator: oauth2::authenticator::Authenticator<client::Connector>) -> ${hub_type}${ht_params} {
         ${hub_type} {
             client,
             auth: authenticator,
             _user_agent: "${default_user_agent}".to_string(),
             _base_url: "${baseUrl}".to_string(),
             _root_url: "${rootUrl}".to_string(),
+            _quota_project: None,
+            _api_version: ${default_api_version},
+            _visibility_labels: ${default_visibility_labels},
+            _request_compression_min_size: ${default_request_compression},
+            _self_signed_jwt: None,
+            _external_account: None,
+            _token_provider: None,
+            _credential_router: None,
+            _auth_tracker: Default::default(),
+            _client_info: None,
+            _cancellation_token: client::CancellationToken::new(),
+            _timeout: None,
+            _propagate_deadline: ${default_propagate_deadline},
+            _max_response_size: None,
+            _strict_decoding: false,
+            _max_in_flight: None,
+            _connection_rebuild: None,
+            _runtime: client::runtime::default_runtime(),
+            _discovery_check: None,
+% if supports_media_download(c):
+            _verify_checksums: true,
+% endif
         }
     }
 
+    /// Creates a hub using a new hyper client, which is configured with the given connection `options`, as are the
+    /// clients replacing it after repeated connection failures.
+    pub fn with_connection_options(authenticator: oauth2::authenticator::Authenticator<client::Connector>, options: &client::ConnectionOptions) -> ${hub_type}${ht_params} {
+        let mut hub = ${hub_type}::new(options.client(), authenticator);
+        hub.connection_rebuild(Some(client::ConnectionRebuild::new(options.clone(), client::ConnectionRebuild::DEFAULT_MAX_FAILURES)));
+        hub
+    }
+
     % for resource in sorted(c.rta_map.keys()):
     pub fn ${mangle_ident(resource)}(&'a self) -> ${rb_type(resource)}${rb_type_params_s(resource, c)} {
         ${rb_type(resource)} { hub: &self }
@@ -84,6 +151,81 @@ impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> ${hub_type}${ht_params} {
         mem::replace(&mut self._user_agent, agent_name)
     }
 
+    /// Set the name and version of your application, which are appended to the user-agent and the
+    /// `x-goog-api-client` header of all requests to attribute them to it, like in the quota dashboards of the Cloud Console.
+    /// It defaults to `None`, which only identifies this library.
+    ///
+    /// Returns the previously set `name/version`.
+    pub fn client_info(&mut self, app_name: &str, version: &str) -> Option<String> {
+        mem::replace(&mut self._client_info, Some(client::client_info_token(app_name, version)))
+    }
+
+    /// Prepares the hub for its first call, which would otherwise pay for all of it at once, like after a cold start.
+% if supports_scopes(auth):
+    /// It connects to the server, which resolves its name and negotiates TLS, and obtains an access token for the given
+    /// `scopes` at the same time, which is cached for calls with the same scopes, like `&[Scope::default()]`.
+    /// The token is the one of the credentials of `tenant` if it is set, see `credential_router()`, like the one of calls
+    /// made `with_credentials()` of the tenant. No token is obtained if `scopes` is empty.
+% else:
+    /// It connects to the server, which resolves its name and negotiates TLS.
+% endif
+    /// The connection is reused by the calls made afterwards, as long as the pool keeps it open.
+    /// It is subject to the cancellation token and the timeout of the hub.
+% if supports_scopes(auth):
+    pub async fn warm_up<St: AsRef<str>>(&self, scopes: &[St], tenant: ${"Option<&str>"}) -> client::Result<()> {
+% else:
+    pub async fn warm_up(&self) -> client::Result<()> {
+% endif
+% if supports_scopes(auth):
+        let fetch_token = async {
+            if scopes.is_empty() {
+                return Ok(());
+            }
+            let scopes: ${"Vec<&str>"} = scopes.iter().map(AsRef::as_ref).collect();
+            self.token(&scopes, tenant).await.map(|_| ()).map_err(client::Error::MissingToken)
+        };
+% else:
+        let fetch_token = async { Ok(()) };
+% endif
+        let user_agent = self.user_agent_header();
+        let client = self.connection();
+        let warm_up = client::warm_up(&client, &self._root_url, &user_agent, fetch_token);
+        client::cancellable(warm_up, &self._cancellation_token, None, client::deadline(self._timeout, None), &*self._runtime).await?
+    }
+% if supports_scopes(auth):
+
+    /// Returns the access token for `scopes`, which comes from the credentials of `tenant` if it is set, or from the ones
+    /// set on this hub, in the order of their setters, or from its authenticator.
+    async fn token(&self, scopes: &[&str], tenant: ${"Option<&str>"}) -> Result<String, oauth2::Error> {
+        self._auth_tracker.observe(scopes, async {
+            let tenant_provider = client::route_credentials(self._credential_router.as_ref(), tenant)
+                .map_err(oauth2::Error::LowLevelError)?;
+            match (tenant_provider.as_ref().or(self._token_provider.as_ref()), self._self_signed_jwt.as_ref(), self._external_account.as_ref()) {
+                (Some(provider), _, _) => provider.token(scopes).await.map_err(oauth2::Error::LowLevelError),
+                (None, Some(jwt), _) => jwt.token("${rootUrl}").map_err(oauth2::Error::LowLevelError),
+                (None, None, Some(account)) => account.token(&self.connection(), scopes).await.map_err(oauth2::Error::LowLevelError),
+                (None, None, None) => self.auth.token(scopes).await.map(|token| token.as_str().to_string()),
+            }
+        }).await
+    }
+% endif
+
+    /// Returns the value of the user-agent header, with the client info appended.
+    fn user_agent_header(&self) -> String {
+        match self._client_info {
+            Some(ref info) => format!("{} {}", self._user_agent, info),
+            None => self._user_agent.clone(),
+        }
+    }
+
+    /// Returns the value of the `x-goog-api-client` header, naming this library and the client info.
+    fn api_client_header(&self) -> String {
+        match self._client_info {
+            Some(ref info) => format!("gdcl/${cargo.build_version} {}", info),
+            None => "gdcl/${cargo.build_version}".to_string(),
+        }
+    }
+
     /// Set the base url to use in all requests to the server.
     /// It defaults to `${baseUrl}`.
     ///
@@ -99,8 +241,232 @@ impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> ${hub_type}${ht_params} {
     pub fn root_url(&mut self, new_root_url: String) -> String {
         mem::replace(&mut self._root_url, new_root_url)
     }
+
+    /// Set the project to bill for all requests to the server, and whose quota they count against,
+    /// using the `${QUOTA_PROJECT_HEADER}` header. Call builders may override it.
+    /// It defaults to `None`, which bills the project the credentials belong to.
+    ///
+    /// Returns the previously set quota project.
+    pub fn quota_project(&mut self, new_quota_project: Option<String>) -> Option<String> {
+        mem::replace(&mut self._quota_project, new_quota_project)
+    }
+
+    /// Set the interface version of the API all calls use, like `v1_20240101`, using the `client::API_VERSION_HEADER`,
+    /// which keeps the behavior of methods which changed incompatibly since.
+    /// It defaults to `${default_api_version}`\
+% if default_api_version == 'None':
+, which uses the default version of the server.
+% else:
+.
+% endif
+    ///
+    /// Returns the previously set version.
+    pub fn api_version(&mut self, new_api_version: Option<String>) -> Option<String> {
+        mem::replace(&mut self._api_version, new_api_version)
+    }
+
+    /// Set the visibility labels of all calls, like `PREVIEW`, using the `client::VISIBILITIES_HEADER`, which opts into
+    /// the preview features of the API they gate, if the project of the call has access to them.
+    /// It defaults to `${default_visibility_labels}`\
+% if default_visibility_labels == 'vec![]':
+, which uses the public features only.
+% else:
+.
+% endif
+    ///
+    /// Returns the previously set labels.
+    pub fn visibility_labels(&mut self, new_visibility_labels: Vec<String>) -> Vec<String> {
+        mem::replace(&mut self._visibility_labels, new_visibility_labels)
+    }
+
+    /// Set the size in bytes from which request bodies are gzip compressed, or `None` to never compress them.
+    /// Only use it with APIs accepting compressed requests.
+    /// It defaults to `${default_request_compression}`. Responses are decompressed in any case.
+    ///
+    /// Returns the previously set size.
+    pub fn request_compression_min_size(&mut self, new_min_size: Option<usize>) -> Option<usize> {
+        mem::replace(&mut self._request_compression_min_size, new_min_size)
+    }
+
+    /// Set the signer of self-signed JWTs to authorize all requests with, in place of the access tokens of the
+    /// authenticator, which saves the round trip to the token endpoint. Only use it with APIs accepting self-signed JWTs.
+    /// It defaults to `None`, which uses the authenticator.
+    ///
+    /// Returns the previously set signer.
+    pub fn self_signed_jwt(&mut self, new_self_signed_jwt: Option<client::SelfSignedJwt>) -> Option<client::SelfSignedJwt> {
+        mem::replace(&mut self._self_signed_jwt, new_self_signed_jwt)
+    }
+
+    /// Set the credentials of an external account to authorize all requests with, in place of the access tokens of the
+    /// authenticator. Their subject tokens are supplied by an executable, like the one of a custom token broker.
+    /// It defaults to `None`, which uses the authenticator. A self-signed JWT signer takes precedence.
+    ///
+    /// Returns the previously set credentials.
+    pub fn external_account(&mut self, new_external_account: Option<client::ExecutableCredentials>) -> Option<client::ExecutableCredentials> {
+        mem::replace(&mut self._external_account, new_external_account)
+    }
+
+    /// Set the provider of the access tokens to authorize all requests with, in place of the authenticator, like one
+    /// taking them from a workload identity sidecar or a cache of your application.
+    /// It defaults to `None`, which uses the authenticator. It takes precedence over self-signed JWTs and external accounts.
+    ///
+    /// Returns the previously set provider.
+    pub fn token_provider(&mut self, new_token_provider: Option<Arc<dyn client::TokenProvider>>) -> Option<Arc<dyn client::TokenProvider>> {
+        mem::replace(&mut self._token_provider, new_token_provider)
+    }
+
+    /// Set the router providing the credentials of the tenants calls are made for with their `with_credentials()` method,
+    /// which lets one hub serve many tenants, like the customers of a service, with their own tokens.
+    /// It defaults to `None`, which fails calls made for a tenant. Calls made for no tenant use the credentials of the hub.
+    ///
+    /// Returns the previously set router.
+    pub fn credential_router(&mut self, new_credential_router: Option<client::CredentialRouter>) -> Option<client::CredentialRouter> {
+        mem::replace(&mut self._credential_router, new_credential_router)
+    }
+
+    /// Set the observer of obtaining the access tokens of all calls, which reports whether they were cached or fetched,
+    /// how long that took, and failures requiring the user to authorize again, like a `client::AuthMetrics`.
+    /// It defaults to `None`, which reports nothing.
+    ///
+    /// Returns the previously set observer.
+    pub fn auth_observer(&mut self, new_auth_observer: Option<Arc<dyn client::AuthObserver>>) -> Option<Arc<dyn client::AuthObserver>> {
+        self._auth_tracker.set_observer(new_auth_observer)
+    }
+
+    /// Set the token cancelling all calls made by this hub, which aborts their requests and makes them fail
+    /// with `client::Error::Cancelled`. Call builders may add their own token, like a `child_token()` of this one.
+    /// It defaults to a new token, which is never cancelled.
+    ///
+    /// Returns the previously set token.
+    pub fn cancellation_token(&mut self, new_token: client::CancellationToken) -> client::CancellationToken {
+        mem::replace(&mut self._cancellation_token, new_token)
+    }
+
+    /// Set the maximum amount of requests in flight at the same time, which is shared with the clones of this hub
+    /// made afterwards. Calls wait for a request to finish before sending theirs, which counts against their timeout.
+    /// A request finishes once the body of its response was read, or dropped, like the media returned by `doit_media()`.
+    /// It defaults to `None`, which doesn't limit them.
+    ///
+    /// Returns the previously set maximum.
+    pub fn max_in_flight(&mut self, new_max: Option<usize>) -> Option<usize> {
+        mem::replace(&mut self._max_in_flight, new_max.map(client::InFlightLimit::new)).map(|limit| limit.max())
+    }
+
+    /// Set the time all calls made by this hub may take, including their retries, after which they fail
+    /// with `client::Error::Timeout`. Call builders may set a shorter one.
+    /// It defaults to `None`, which never times out.
+    ///
+    /// Returns the previously set timeout.
+    pub fn timeout(&mut self, new_timeout: Option<Duration>) -> Option<Duration> {
+        mem::replace(&mut self._timeout, new_timeout)
+    }
+
+    /// Set whether calls with a timeout send the time they have left with the `client::SERVER_TIMEOUT_HEADER`,
+    /// which lets the server stop working on calls the client doesn't wait for anymore.
+    /// It defaults to `${default_propagate_deadline}`\
+% if default_propagate_deadline == 'true':
+.
+% else:
+, as this API doesn't honor the header.
+% endif
+    ///
+    /// Returns the previous setting.
+    pub fn propagate_deadline(&mut self, new_propagate_deadline: bool) -> bool {
+        mem::replace(&mut self._propagate_deadline, new_propagate_deadline)
+    }
+
+    /// Set the size in bytes the body of a response may have, beyond which calls fail with `client::Error::ResponseTooLarge`
+    /// instead of reading it into memory, like when a mistaken filter makes a list return gigabytes of JSON. It applies to the
+    /// responses which are decoded, the ones of `doit_raw()` and the ones of errors, but not to the media streamed by
+    /// `doit_media()`.
+    /// It defaults to `None`, which doesn't limit them.
+    ///
+    /// Returns the previously set maximum.
+    pub fn max_response_size(&mut self, new_max: Option<u64>) -> Option<u64> {
+        mem::replace(&mut self._max_response_size, new_max)
+    }
+
+    /// Set whether calls fail with `client::Error::UnknownEnumValue`, naming the path of the value, if their response holds
+    /// a value which is none of the values of the enumeration of its field, instead of returning it as it is. It tells
+    /// that the server knows more values than this crate, which is due to be regenerated.
+    /// It defaults to `false`, which returns such values like all others.
+    ///
+    /// Returns the previous setting.
+    pub fn strict_decoding(&mut self, new_strict_decoding: bool) -> bool {
+        mem::replace(&mut self._strict_decoding, new_strict_decoding)
+    }
+
+    /// Set when the hyper client of this hub is replaced by a new one, after its connections failed repeatedly, like after
+    /// the network changed, which would otherwise leave long-running processes with requests that keep failing.
+    /// Hubs created by `new()` keep the client given to them, as the options it was built with are unknown, and the ones of
+    /// `with_connection_options()` rebuild it with their options after `client::ConnectionRebuild::DEFAULT_MAX_FAILURES`
+    /// consecutive connection failures. `None` never rebuilds it.
+    ///
+    /// Returns the previous setting.
+    pub fn connection_rebuild(&mut self, rebuild: Option<client::ConnectionRebuild>) -> Option<client::ConnectionRebuild> {
+        mem::replace(&mut self._connection_rebuild, rebuild)
+    }
+
+    /// Set the runtime this hub waits for retries and timeouts with, like `client::runtime::AsyncStdRuntime` if the
+    /// `async-std` feature is enabled, which is shared with the clones of this hub made afterwards.
+    /// It defaults to `client::runtime::default_runtime()`, as it was when the hub was created.
+    ///
+    /// Returns the previously set runtime.
+    pub fn runtime(&mut self, new_runtime: Arc<dyn client::runtime::Runtime>) -> Arc<dyn client::runtime::Runtime> {
+        mem::replace(&mut self._runtime, new_runtime)
+    }
+
+    /// Returns the client to send requests with, which is a new one once the connections of the previous one failed repeatedly.
+    fn connection(&self) -> hyper::Client<client::Connector, hyper::body::Body> {
+        match self._connection_rebuild {
+            Some(ref rebuild) => rebuild.client(&self.client),
+            None => self.client.clone(),
+        }
+    }
+
+    /// Set the check comparing the methods called on this hub to the live discovery document of the API, which reports
+    /// the ones that changed since revision *${revision is UNDEFINED and '00000000' or revision}* this crate was generated from,
+    /// once per method. The document is cached on disk, see `client::discovery::DiscoveryCache`.
+    /// It defaults to `None`, which doesn't fetch the document.
+    ///
+    /// Returns the previously set check.
+    pub fn discovery_check(&mut self, check: Option<client::discovery::DiscoveryCheck>) -> Option<client::discovery::DiscoveryCheck> {
+        mem::replace(&mut self._discovery_check, check)
+    }
+% if supports_media_download(c):
+
+    /// Set whether downloaded media is verified against the checksums the server sends in the `x-goog-hash` header.
+    /// If it doesn't match, reading the end of the body fails with an error converting into
+    /// `client::Error::ChecksumMismatch`. It defaults to `true`.
+    ///
+    /// Returns the previous setting.
+    pub fn verify_checksums(&mut self, verify: bool) -> bool {
+        mem::replace(&mut self._verify_checksums, verify)
+    }
+% endif
 }
 
+// ##################
+// THREAD SAFETY ###
+// ################
+
+const _: () = client::assert_send_sync::<${hub_type}${ht_params}>();
+
+// The futures of calls with uploads are generic over their reader, so only their builders are checked.
+#[allow(dead_code)]
+fn _assert_send_calls() {
+% for resource, methods in c.rta_map.items():
+    client::assert_send_sync::<${rb_type(resource)}<'static>>();
+% for method in methods:
+<% m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, method)] %>\
+% if method_media_params(m):
+    client::assert_send::<${mb_type(resource, method)}<'static>>();
+% else:
+    client::assert_send_future(|call: ${mb_type(resource, method)}<'static>| call.${api.terms.action}());
+% endif
+% endfor
+% endfor
+}
 
 % if c.schemas:
 // ############
@@ -113,6 +479,65 @@ ${schema.new(s, c)}
 % endfor
 % endif
 
+% if operation_schemas(c):
+// ###############
+// OPERATIONS ###
+// #############
+
+% for s, fields in operation_schemas(c):
+impl ${s.id} {
+% for field in fields:
+    % if not loop.first:
+
+    % endif
+    /// Decodes the `${field}` of the operation as the message `T`, after checking that it is the one it names in `@type`.
+    /// It is `None` if there is no ${field} (yet). A message of another type fails with `client::Error::AnyTypeMismatch`.
+    pub fn decode_${field}<T: client::AnyMessage>(&self) -> client::Result<Option<T>> {
+        self.${field}.as_ref().map(|any| client::decode_any(any, Some(T::TYPE_NAME))).transpose()
+    }
+% endfor
+}
+
+% endfor
+% for sid, type_name in any_types(c, api):
+impl client::AnyMessage for ${sid} {
+    const TYPE_NAME: &'static str = "${type_name}";
+}
+
+% endfor
+% endif
+% if api.get('conversions'):
+// ################
+// CONVERSIONS ###
+// ##############
+% for other_version in api.conversions:
+${conversions.new(c, other_version, context.get(conversion_data_namespace(other_version)))}
+% endfor
+% endif
+
+// ################
+// JSON SCHEMA ###
+// ##############
+
+/// The JSON Schema of all schemas of this API, as they are sent and received, which are kept under their name in its
+/// `definitions`. Use it to validate payloads in other languages, or to generate forms consistent with the schemas.
+/// See the `client::json_schema` module to make it a schema of a single value, or the components of an OpenAPI document.
+pub const JSON_SCHEMA: &str = ${rust_raw_string(json.dumps(json_schema(schemas, id, context.get('description', '')), sort_keys=True))};
+
+/// Returns the JSON Schema of the schema `name`, which is the one of its struct, that validates a single value of it.
+/// It is `None` if there is no such schema.
+pub fn json_schema(name: &str) -> Option<json::Value> {
+    client::json_schema::definition(JSON_SCHEMA, name)
+}
+
+/// The `JSON_SCHEMA`, parsed by the first call checking its response strictly.
+static JSON_SCHEMA_DOCUMENT: client::json_schema::Document = client::json_schema::Document::new(JSON_SCHEMA);
+
+// #########
+// FAKE ###
+// #######
+${fake.new(c, util.crate_name())}
+
 // ###################
 // MethodBuilders ###
 // #################
@@ -0,0 +1,33 @@
+<%namespace name="util" file="../../lib/util.mako"/>\
+<%namespace name="mbuild" file="../lib/mbuild.mako"/>\
+<%!
+    from util import (new_context, rust_comment, build_all_params, parts_from_params, to_fqan,
+                      example_activities, EXAMPLE_SECRET_FILE)
+    from cli import (SPLIT_START, SPLIT_END)
+%>\
+<%
+    c = new_context(schemas, resources, context.get('methods'))
+%>\
+## One runnable program per example activity, each written to its own file by the post-processor
+% for name, resource, method in example_activities(c):
+<%
+    m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, method)]
+    params, request_value = build_all_params(c, m)
+    part_prop, parts = parts_from_params(params)
+%>\
+${SPLIT_START} ${name}.rs
+<%block filter="rust_comment">\
+<%util:gen_info source="${self.uri}" />\
+</%block>
+
+//! Calls *${m.id}* with example values.
+//!
+//! Put the application secret of your project into *${EXAMPLE_SECRET_FILE}*, adjust the values below and
+//! run it with `cargo run --example ${name}`.
+
+// The example shows all available imports and setup steps, even though a few of them might not be used.
+#![allow(unused_imports, unused_mut)]
+
+${mbuild.usage(resource, method, m, params, request_value, parts, show_all=True, rust_doc=False, handle_result=True, main_fn=True)}
+${SPLIT_END}
+% endfor
@@ -17,7 +17,8 @@ ${lib.docs(c)}
 // We don't warn about this, as depending on the API, some data structures or facilities are never used.
 // Instead of pre-determining this, we just disable the lint. It's manually tuned to not have any
 // unused imports in fully featured APIs. Same with unused_mut ... .
-#![allow(unused_imports, unused_mut, dead_code)]
+// Items deprecated by the API are still used internally, which is why we don't warn about them either.
+#![allow(unused_imports, unused_mut, dead_code, deprecated)]
 
 <%namespace name="lib" file="lib/lib.mako"/>\
 <%namespace name="util" file="../lib/util.mako"/>\
@@ -25,7 +26,7 @@ ${lib.docs(c)}
 <%namespace name="mbuild" file="lib/mbuild.mako"/>\
 <%namespace name="schema" file="lib/schema.mako"/>\
 <%
-    from util import (new_context, rust_comment, rust_doc_comment, rust_module_doc_comment,
+    from util import (new_context, rust_comment, rust_doc_comment, rust_module_doc_comment, to_extern_crate_name,
                       rb_type, hub_type, mangle_ident, hub_type_params_s,
                       rb_type_params_s, find_fattest_resource, HUB_TYPE_PARAMETERS, METHODS_RESOURCE,
                       UNUSED_TYPE_MARKER, schema_markers)
@@ -48,13 +49,20 @@ pub extern crate hyper;
 pub extern crate hyper_rustls;
 extern crate serde;
 extern crate serde_json;
-// Re-export the yup_oauth2 crate, that is required to call some methods of the hub and the client
-pub extern crate yup_oauth2 as oauth2;
 extern crate mime;
 extern crate url;
 
 pub mod api;
-pub mod client;
+% for ext in api.get('extensions', list()):
+% if ext in api.get('extension_features', dict()):
+#[cfg(feature = "${ext}")]
+% endif
+pub mod ${ext};
+% endfor
+// The client code is shared by all API crates, which makes its types the same across all hubs
+pub use ${to_extern_crate_name(cargo.common_crate.name)} as client;
+// Re-export the yup_oauth2 crate, that is required to call some methods of the hub and the client
+pub use client::oauth2;
 
 // Re-export the hub type and some basic client structs
 pub use api::${hub_type};
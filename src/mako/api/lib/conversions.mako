@@ -0,0 +1,79 @@
+<%!
+    from util import (schema_conversions, library_name, library_to_crate_name, to_extern_crate_name,
+                      CONVERSION_FROM, new_context)
+%>\
+## Conversions between the structs of this version of the API and the ones of another version, which are
+## the same but for fields one of them lacks.
+## `other` is the data of the other version, `other_version` its version.
+###############################################################################################
+###############################################################################################
+<%def name="new(c, other_version, other)">\
+<%
+    module = other_version.replace('.', '_').replace('-', '_')
+    other_crate = to_extern_crate_name(library_to_crate_name(library_name(name, other_version), make.target_suffix))
+    other_c = new_context(other.schemas, other.resources, other.get('methods'))
+    into_this = schema_conversions(other.schemas, other_c, schemas, c, 'crate::api')
+    into_other = schema_conversions(schemas, c, other.schemas, other_c, other_crate + '::api')
+%>\
+/// Conversions between the structs of this version of the API and the ones of the same name of *${other_version}*,
+/// provided by the `${other_version}` feature.
+///
+/// A struct converts using `From` if each of its fields exists in the other version, with the same type. Fields
+/// only the destination has are unset. If the destination lacks any of the fields, it converts using `TryFrom`,
+/// which fails with a `ConversionError` if one of them is set. Structs differing in any other way don't convert.
+#[cfg(feature = "${other_version}")]
+pub mod ${module} {
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use crate::client::ConversionError;
+    use ${other_crate} as other;
+
+% for sid, kind, fields, dropped in into_this:
+${_impl(sid, 'other::api::' + sid, 'crate::api::' + sid, kind, fields, dropped)}
+% endfor
+% for sid, kind, fields, dropped in into_other:
+${_impl(sid, 'crate::api::' + sid, 'other::api::' + sid, kind, fields, dropped)}
+% endfor
+}
+</%def>
+
+<%def name="_impl(sid, src_type, dst_type, kind, fields, dropped)">\
+<%
+    value = (fields or dropped) and 'value' or '_value'
+%>\
+% if kind == CONVERSION_FROM:
+    impl From<${src_type}> for ${dst_type} {
+        fn from(${value}: ${src_type}) -> ${dst_type} {
+            ${_struct(dst_type, fields, '%s')}
+        }
+    }
+% else:
+    impl TryFrom<${src_type}> for ${dst_type} {
+        type Error = ConversionError;
+
+        fn try_from(${value}: ${src_type}) -> Result<${dst_type}, ConversionError> {
+    % for pn, check in dropped:
+            if ${check} {
+                return Err(ConversionError::new("${sid}", "${pn}"));
+            }
+    % endfor
+            ${_struct(dst_type, fields, 'Ok(%s)')}
+        }
+    }
+% endif
+</%def>
+
+## Structs are non-exhaustive, which only allows creating the ones of other crates by changing their defaults.
+## `result` formats the expression returning the struct.
+<%def name="_struct(dst_type, fields, result)">\
+% if not fields:
+${result % (dst_type + '::default()')}\
+% else:
+let mut converted = ${dst_type}::default();
+    % for ident, expr, fallible in fields:
+            converted.${ident} = ${expr}${fallible and '?' or ''};
+    % endfor
+            ${result % 'converted'}\
+% endif
+</%def>
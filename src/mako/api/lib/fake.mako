@@ -0,0 +1,48 @@
+<%!
+    from util import (fake_routes, to_extern_crate_name, supports_scopes, FAKE_FEATURE)
+%>\
+## The routes of all methods of the API, served by the fake of its server.
+###############################################################################################
+###############################################################################################
+<%def name="new(c, crate_name)">\
+/// A fake of the server of this API, keeping its resources in memory, provided by the `${FAKE_FEATURE}` feature.
+///
+/// Use it to test code using the hub hermetically, by setting the root and base url of the hub to the
+/// address of the fake.
+///
+/// ```Rust,ignore
+/// let fake = ${to_extern_crate_name(crate_name)}::api::fake::server();
+/// let address = fake.spawn();
+/// hub.root_url(format!("http://{}/", address));
+/// hub.base_url(format!("http://{}/${servicePath}", address));
+/// ```
+#[cfg(feature = "${FAKE_FEATURE}")]
+pub mod fake {
+    use crate::client::fake::{Action, FakeServer, Route};
+    use crate::{client, oauth2};
+
+    /// The routes of all methods of the API, relative to its root url.
+    pub const ROUTES: &[Route] = &[
+% for http_method, path, action, list_field in fake_routes(c, servicePath):
+        Route { method: "${http_method}", path: "${path}", action: Action::${action}, list_field: ${list_field and 'Some("%s")' % list_field or 'None'} },
+% endfor
+    ];
+
+    /// Returns a fake without resources, serving all `ROUTES`.
+    pub fn server() -> FakeServer {
+        FakeServer::new(ROUTES)
+    }
+
+    /// Returns an authenticator with a token for all scopes of the API, to make calls to the fake with.
+    pub async fn authenticator() -> std::io::Result<oauth2::authenticator::Authenticator<client::Connector>> {
+        let scopes: &[&str] = &[
+% if supports_scopes(auth):
+% for url in auth.oauth2.scopes.keys():
+            "${url}",
+% endfor
+% endif
+        ];
+        client::fake::authenticator(scopes).await
+    }
+}
+</%def>
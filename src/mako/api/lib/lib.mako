@@ -7,7 +7,9 @@
                       REQUEST_MARKER_TRAIT, RESPONSE_MARKER_TRAIT, supports_scopes, to_api_version,
                       to_fqan, METHODS_RESOURCE, ADD_PARAM_MEDIA_EXAMPLE, PROTOCOL_TYPE_INFO, enclose_in,
                       upload_action_fn, METHODS_BUILDER_MARKER_TRAIT, DELEGATE_TYPE,
-                      to_extern_crate_name, rust_doc_sanitize)  
+                      to_extern_crate_name, rust_doc_sanitize, EXAMPLE_SECRET_FILE, EXAMPLE_TOKEN_FILE,
+                      example_activities, STREAM_UPLOAD_SUFFIX, FILE_UPLOAD_SUFFIX, ADD_SCOPES_FN, FAKE_FEATURE,
+                      operation_schemas, ADD_PARAM_FN)  
 
     def pretty_name(name):
         return ' '.join(split_camelcase_s(name).split('.'))
@@ -144,6 +146,74 @@ The API is structured into the following primary items:
     * operations to apply to *Resources*
 
 All *structures* are marked with applicable traits to further categorize them and ease browsing.
+String fields which may only have certain values are accompanied by an enumeration of them, named after the structure
+and the field, like `<Structure><Field>Enum`. It converts to the value of the field with `to_string()`, and from it with `parse()`.
+% if api.get('extensions'):
+
+Besides the generated code, the crate has the following hand-written modules:
+
+% for ext in api.extensions:
+* **${link(ext, ext + '/index.html')}**\
+% if ext in api.get('extension_features', dict()):
+ *(requires the `${ext}` feature)*
+% else:
+
+% endif
+% endfor
+% endif
+% if api.get('conversions'):
+
+Structs can be converted into the ones of the same name of ${', '.join('*%s*' % v for v in api.conversions)} \
+using `From` or `TryFrom`, and back, which helps migrating from one version to another incrementally. \
+Each version requires the feature of the same name, and its conversions are documented in the module of the same name.
+% endif
+
+Methods with an empty response, like most delete methods, return the `hyper::Response` alone, as there is nothing
+to decode, even if the server sends no body at all.
+
+Where the generated types lack a field, or have one of the wrong type, `body_json()` sets the JSON to send in place of
+the request of a call builder, and `doit_raw()` returns the status, headers and body of the response without decoding it.
+Both keep the authorization and retries of the call. Similarly, `header()` adds a header to the request, like one
+a gateway in front of the API requires, as long as it isn't set by the call itself.
+
+To log what calls cost without a proxy, `client::WireStats::of(&response)` returns the bytes sent and received, the time
+to the first byte, the latency and the retries of the call which returned the `hyper::Response`. The `ResponseMetadata`
+`doit_raw()` returns has them in its `stats` field, including the body it read.
+
+Call builders of methods with a response have a `read_mask(&[...])` setter, which makes the server only return the
+given fields of the response, like `name` or `config.labels`. \
+% if api.get('read_mask_header'):
+This API takes the read mask with the `X-Goog-FieldMask` header.
+% else:
+This API takes the read mask as `fields` parameter.
+% endif
+
+To send a call with your own transport, like one signing, queuing or batching requests, `build_request()` returns the
+`hyper::Request` it would send, with its url, headers, authorization and body, instead of sending it. Methods uploading
+media don't have it.
+
+Calls can be cancelled with a `client::CancellationToken`, set on the hub for all of its calls, or on a call builder for
+that call only. Cancelling it aborts the request in flight, and the call fails with `Error::Cancelled`. Similarly,
+timeouts set on the hub or a call builder limit the time a call may take including its retries, and the shorter one applies.
+% if api.get('deadline_header'):
+Each request of a call with a timeout tells the server the time it has left with the `X-Server-Timeout` header, so that it
+stops working on calls the client doesn't wait for anymore, unless it is turned off with `hub.propagate_deadline(false)`.
+% endif
+APIs versioning their interface beyond the version of the crate take the one calls use with `hub.api_version(Some(v))`,
+which is sent with the `X-Goog-Api-Version` header. Preview features gated behind visibility labels are opted into with
+`hub.visibility_labels(labels)`, which are sent with the `X-Goog-Visibilities` header.
+Applications spawning many calls at once can limit the requests in flight with `hub.max_in_flight(Some(n))`, which the
+hub shares with its clones. Calls beyond the limit wait for a request to finish before sending theirs.
+Responses can be limited in size with `hub.max_response_size(Some(bytes))`, which makes calls fail with
+`Error::ResponseTooLarge` instead of reading larger bodies into memory.
+Fields with an enumeration of values hold them as strings, so values added to the API after this crate was generated
+are returned as they are. With `hub.strict_decoding(true)`, calls fail with `Error::UnknownEnumValue` instead, naming the
+path of the value, which tells that the crate is due to be regenerated.
+
+The `${FAKE_FEATURE}` feature provides a ${link('fake', 'api/fake/index.html')} of the server of this API, which serves the routes of
+all its methods and keeps the resources they create in memory. Point the hub at it to test code using the hub hermetically.
+The examples of this documentation are tested that way, as `cargo test` runs them against the fake, with the token of
+`api::fake::authenticator()`. Only the ones uploading files aren't run.
 
 Generally speaking, you can invoke *Activities* like this:
 
@@ -184,6 +254,122 @@ ${'##'} A complete example
 
 ${self.hub_usage_example(c, rust_doc, fr=fr)}\
 
+<% examples = example_activities(c) %>\
+% if examples:
+${'##'} Runnable Examples
+
+The `examples` directory of the crate contains a program for up to one listing, getter and mutating method of each resource,
+like ${put_and(enclose_in('*', (n for n, _, _ in examples[:3])))}. Put the application secret of your project into
+*${EXAMPLE_SECRET_FILE}* and run one of them with `cargo run --example ${examples[0][0]}`. Tokens are kept in *${EXAMPLE_TOKEN_FILE}*.
+
+% endif
+${'##'} Sharing a Client among Hubs
+
+If your application talks to multiple APIs, create all of their hubs from a single ${link('GoogleClient', 'client::GoogleClient')}.
+They will share its connection pool and authenticator, and thus its token cache.
+
+```Rust,ignore
+let google = ${util.library_name()}::client::GoogleClient::new(auth);
+let hub = google.hub::<${hub_url}>();
+```
+
+${'##'} Tuning Connections
+
+Hyper clients created with ${link('ConnectionOptions', 'client::ConnectionOptions')} negotiate HTTP/2 with the server, which
+multiplexes all requests over a single connection per host. Its fields configure the connection pool, like the maximum
+amount of idle connections per host, as well as the keep-alive interval and flow control of HTTP/2 connections, to improve
+the throughput of batch workloads making many requests at once.
+
+```Rust,ignore
+let options = ${util.library_name()}::client::ConnectionOptions {
+    pool_max_idle_per_host: Some(16),
+    http2_keep_alive_interval: Some(std::time::Duration::from_secs(30)),
+    ..Default::default()
+};
+let hub = ${hub_url}::with_connection_options(auth, &options);
+```
+
+All requests accept gzip or deflate compressed responses, which are decompressed transparently while they are read, and
+which substantially reduce the transfer time of large list pages. \
+% if api.get('request_compression'):
+Request bodies of at least ${link('REQUEST_COMPRESSION_MIN_SIZE', 'client::REQUEST_COMPRESSION_MIN_SIZE')} bytes
+are gzip compressed, too. Use `request_compression_min_size()` of the hub to change the size, or `None` to turn it off.
+% else:
+Large request bodies can be gzip compressed as well, using `request_compression_min_size()` of the hub, if the API accepts
+compressed requests.
+% endif
+
+Services sensitive to the latency of their first call, like ones starting cold on demand, can `warm_up()` the hub while
+they start. It connects to the server, which resolves its name and negotiates TLS, \
+% if supports_scopes(auth):
+and fetches a token for the given scopes at the same time, with the credentials of the given tenant if there is one, \
+% endif
+so the first call reuses the connection.
+
+```Rust,ignore
+% if supports_scopes(auth):
+hub.warm_up(&[${util.library_name()}::api::Scope::default()], None).await?;
+% else:
+hub.warm_up().await?;
+% endif
+```
+
+Failed connections are told apart by ${link('Error::connection_failure()', 'client::Error::connection_failure')}, which
+classifies them as failures to resolve the name of the server, to connect, to negotiate TLS, or as connections which were
+reset or timed out. As a pool may keep handing out connections which are broken, like after the network of the host
+changed, a hub created by `with_connection_options()` creates a new client once `DEFAULT_MAX_FAILURES` calls in a row
+failed to connect, using the options it was created with, as configured by
+${link('ConnectionRebuild', 'client::ConnectionRebuild')}. Failed TLS handshakes don't count, as a new client wouldn't
+change the certificate of the server. Use `connection_rebuild()` of the hub to change the amount, to rebuild the client
+of a hub created by `new()`, or `None` to keep the client given to it.
+
+${'##'} Detecting Outdated Methods
+
+As APIs evolve, this crate may fall behind the methods it calls. A hub with a
+${link('DiscoveryCheck', 'client::discovery::DiscoveryCheck')} compares each method on its first call to the live discovery
+document of the API, and reports the method if its path, parameters, request or response changed since the revision
+this crate was generated from. The document is cached on disk, in `~/.cache/google-apis-rs/discovery` unless
+`GOOGLE_APIS_DISCOVERY_CACHE_DIR` says otherwise, and revalidated with the server once a day.
+
+```Rust,ignore
+hub.discovery_check(Some(${util.library_name()}::client::discovery::DiscoveryCheck::default()));
+```
+
+${'##'} Choosing the TLS Implementation
+
+Connections use rustls, trusting the bundled Mozilla root certificates, by default. Environments requiring the trust store
+of the OS can enable the `rustls-native-roots` feature instead, and ones requiring the TLS library of the OS, like a FIPS
+validated OpenSSL, the `native-tls` feature, which doesn't negotiate HTTP/2:
+
+```toml
+[dependencies]
+${util.crate_name()} = { version = "*", default-features = false, features = ["native-tls"] }
+```
+
+The ${link('Connector', 'client::Connector')} of the hub then uses that implementation. Authenticators must be built
+with a client of that connector, like `.hyper_client(ConnectionOptions::default().client())`, as their type doesn't
+match the hub otherwise, unless the default `rustls` feature is used.
+
+${'##'} Choosing the Runtime
+
+Hubs wait for retries and timeouts, and spawn the tasks of their connections, on a
+${link('Runtime', 'client::runtime::Runtime')}, which is tokio by default. The `async-std` and `smol` features add the
+runtimes of these crates, which are used if the hub is created outside of a tokio runtime, or set with `runtime()` of the
+hub.
+
+Tokio remains a dependency of this crate, though. The connector of hyper and the authenticators still need a tokio
+reactor, which async-std provides with its `tokio1` feature, and smol with a compatibility layer like `async-compat`.
+
+```Rust,ignore
+hub.runtime(std::sync::Arc::new(${util.library_name()}::client::runtime::AsyncStdRuntime));
+```
+
+${'##'} Sharing the Hub between Threads
+
+The hub is `Send + Sync`, so multi-threaded services may put it into an `Arc` and make calls from any of their tasks.
+Call builders are `Send`, as are the futures of their `${api.terms.action}()` methods, which may thus be spawned onto
+multi-threaded runtimes. This is asserted when the library compiles.
+
 ${'##'} Handling Errors
 
 All errors produced by the system are provided either as ${link('Result', 'client::Result')} enumeration as return value of
@@ -193,16 +379,88 @@ ${link('Hub Delegate', delegate_url)}, or the ${link('Authenticator Delegate', u
 When delegates handle errors or intermediate values, they may have a chance to instruct the system to retry. This 
 makes the system potentially resilient to all kinds of errors.
 
+Errors the server responded with are decoded into an ${link('ApiError', 'client::ApiError')} by `err.api_error()`. Its
+reasons are ${link('ErrorReason', 'client::ErrorReason')} variants, which retry and alerting logic can match on, like
+`api_err.has_reason(client::ErrorReason::RateLimitExceeded)`, instead of searching the message.
+
+Calls ask for version 2 of the error format by setting the `$.xgafv` parameter, unless another one is set with
+`${ADD_PARAM_FN}()`. Its details are decoded as well, like the invalid fields of the request as `field_violations`, the
+exceeded quotas as `quota_violations`, and the `request_id` support needs to look up the call.
+
+${'##'} Paginated Lists
+
+Methods returning pages of items, like most `list` methods, have a `doit_items()` method, which fetches the pages one
+after another as its ${link('Items', 'client::Items')} stream is consumed, and yields their items. Typed functions
+registered with `map_items()`, `filter_items()` and `filter_map_items()` process the items while further pages are fetched,
+up to ${link('DEFAULT_ITEM_CONCURRENCY', 'client::DEFAULT_ITEM_CONCURRENCY')} of them at once, unless `concurrency()` says
+otherwise. This overlaps heavy processing, like verifying signatures or looking up related resources, with the requests.
+
+```Rust,ignore
+let items = call.doit_items()
+                .concurrency(16)
+                .map_items(|item| async move { enrich(item).await });
+```
+
+`doit_pages()` yields the pages themselves, as ${link('Page', 'client::Page')}s holding the items along with the metadata of
+their responses. Its `total_size` is the amount of items of all pages, for APIs whose list responses report it in their
+`totalSize` field. Methods taking a page size also have `doit_pages_adaptive(initial)`, which adapts the size of the pages
+to how the server copes with them, like ${link('AdaptivePageSize', 'client::AdaptivePageSize')} does: pages which were too
+large to be delivered are requested again with half their size, and the size grows while pages arrive quickly.
+
+${'##'} Patching Resources
+
+Methods updating only some fields of a resource take a mask naming them, like `update_mask`. Instead of tracking which
+fields were changed, fetch the resource, modify a copy of it, and let ${link('patch::diff()', 'client::patch::diff')}
+compare both. It returns the patch, holding only the changed fields, along with the mask naming them, whose `to_string()`
+is the value of the mask parameter. Fields which were cleared are named by the mask, but left out of the patch, which
+makes the server clear them as well.
+
+${'##'} Getting Many Resources
+
+Resources with a `get` method taking only a name also have a `get_many()` method, like `topics_get_many()` next to
+`topics_get()`, which gets the resources with the given names, up to
+${link('DEFAULT_ITEM_CONCURRENCY', 'client::DEFAULT_ITEM_CONCURRENCY')} of them at once, and returns their results in the
+order of the names. If the resource has a `batchGet` method, the names sharing a parent are gotten with it instead.
+
+```Rust,ignore
+for result in hub.projects().topics_get_many(names.iter().map(String::as_str)).await {
+    // a failed get only fails the result of its name
+}
+```
+
+${'##'} Waiting for Resources
+
+Calls reading a resource, like `get` methods, have a `poll_until()` method, which makes the call again and again until
+the given function accepts its response, and returns that one. The delay between calls starts at the given interval and
+grows with each of them, varying randomly, see ${link('poll_delay()', 'client::poll_delay')}.
+
+```Rust,ignore
+let (_, occurrence) = call.poll_until(Duration::from_secs(5), |o| o.status.as_deref() == Some("FINISHED_SUCCESS")).await?;
+```
+
 ${'##'} Uploads and Downloads
 If a method supports downloads, the response body, which is part of the ${link('Result', 'client::Result')}, should be
 read by you to obtain the media.
 If such a method also supports a ${link('Response Result', 'client::ResponseResult')}, it will return that by default.
 You can see it as meta-data for the actual media. To trigger a media download, you will have to set up the builder by making
 this call: `${ADD_PARAM_MEDIA_EXAMPLE}`.
+Downloaded media is verified against the checksum the server sends in the `x-goog-hash` header while it is read. If they
+differ, reading the end of the body fails with an error converting into ${link('ChecksumMismatch', 'client::Error::ChecksumMismatch')}.
+The hub's `verify_checksums(false)` turns this off.
 
 Methods supporting uploads can do so using up to ${len(PROTOCOL_TYPE_INFO)} different protocols: 
 ${put_and(md_italic(PROTOCOL_TYPE_INFO.keys()))}. The distinctiveness of each is represented by customized 
 `${api.terms.action}(...)` methods, which are then named ${put_and(enclose_in('`', ("%s(...)" % upload_action_fn(api.terms.upload_action, v['suffix']) for v in PROTOCOL_TYPE_INFO.values())))} respectively.
+Media for the *simple* protocol may also be streamed from an asynchronous source, like a `tokio::io::AsyncRead`, using
+`${upload_action_fn(api.terms.upload_action, PROTOCOL_TYPE_INFO['simple']['suffix'] + STREAM_UPLOAD_SUFFIX)}(...)` with a ${link('MediaStream', 'client::MediaStream')}.
+Streamed media of unknown size is sent to a *resumable* session chunk by chunk with
+`${upload_action_fn(api.terms.upload_action, PROTOCOL_TYPE_INFO['resumable']['suffix'] + STREAM_UPLOAD_SUFFIX)}(...)`, which holds only one chunk in memory, and sends it again
+if the session didn't persist it.
+Files can be uploaded by path with `${upload_action_fn(api.terms.upload_action, PROTOCOL_TYPE_INFO['simple']['suffix'] + FILE_UPLOAD_SUFFIX)}(path)`,
+and its counterparts for the other protocols, which detect the MIME type from the extension of the file, or its content.
+Media stored elsewhere, like an object behind a Cloud Storage signed URL, can be copied into a resumable upload
+session with ${link('upload_from_url()', 'client::upload_from_url')}. It streams the media chunk by chunk, without
+holding all of it in memory.
 
 ${'##'} Customization and Callbacks
 
@@ -212,6 +470,44 @@ Respective methods will be called to provide progress information, as well as de
 retry on failure.
 
 The ${link('delegate trait', delegate_url)} is default-implemented, allowing you to customize it with minimal effort.
+Calls with a delegate of `POST` methods taking a `requestId`, which the server uses to ignore repeated requests, set it
+to a new UUID unless it was set with `request_id()`, so that retrying them doesn't create a resource twice.
+% if supports_scopes(auth):
+
+Each call is authorized with the minimal scope for its method, as documented on its ${link('Method Builder', call_builder_url)}.
+Use its `${ADD_SCOPES_FN}(&[...])` method to authorize it with other variants of the ${link('Scope', 'api::Scope')} enum instead.
+Its `required_scopes()` method returns the scopes it will request, so that applications can check them against the
+scopes they were granted, or ask for consent up front, before making the call.
+
+The installed flow authenticator prints the consent URL to stdout when it has no token for the scopes of a call yet.
+Services, like a web UI, pass a ${link('ConsentDelegate', 'client::ConsentDelegate')} to its `flow_delegate()` method
+instead, whose async callback presents the URL, and supplies the code the redirect to its `redirect_uri()` carried, with
+the `InstalledFlowReturnMethod::Interactive`.
+
+If this API accepts self-signed JWTs, calls may be authorized with the key of a service account without a round trip to
+the token endpoint, by passing a ${link('SelfSignedJwt', 'client::SelfSignedJwt')} to the `self_signed_jwt()` method of the hub.
+
+External accounts whose credentials are supplied by an executable, like a custom token broker, authorize calls once
+their credential configuration file is loaded into ${link('ExecutableCredentials', 'client::ExecutableCredentials')} and passed
+to the `external_account()` method of the hub. Running the executable must be allowed by setting the environment
+variable `GOOGLE_EXTERNAL_ACCOUNT_ALLOW_EXECUTABLES` to `1`.
+
+Tokens obtained in any other way, like from a workload identity sidecar or a cache of your application, authorize calls
+once the ${link('TokenProvider', 'client::TokenProvider')} supplying them is passed to the `token_provider()` method of the hub.
+A token which was fetched beforehand can be passed as a ${link('StaticToken', 'client::StaticToken')}.
+Service accounts whose keys are rotated can pass all their current keys as
+${link('ServiceAccountKeys', 'client::ServiceAccountKeys')}, like the ones of a directory, which skip keys the token
+endpoint rejects, so calls keep working once the old key was revoked.
+
+Services acting for many tenants, like the customers whose projects they manage, don't need a hub per tenant. A
+${link('CredentialRouter', 'client::CredentialRouter')} passed to the `credential_router()` method of the hub keeps the token
+provider of each tenant, and calls pick theirs with `with_credentials(tenant)`.
+
+To monitor authorization apart from the errors of the API, pass an ${link('AuthObserver', 'client::AuthObserver')} to the
+`auth_observer()` method of the hub. It receives an ${link('AuthEvent', 'client::AuthEvent')} for each token a call obtains,
+telling whether it was cached or fetched and how long that took, and for each failure, telling whether the user has to
+authorize again. ${link('AuthMetrics', 'client::AuthMetrics')} counts them, to export as metrics or to alert on.
+% endif
 
 ${'##'} Optional Parts in Server-Requests
 
@@ -221,17 +517,34 @@ are valid.
 Most optionals are are considered ${link('Parts', part_trait_url)} which are identifiable by name, which will be sent to 
 the server to indicate either the set parts of the request or the desired parts in the response.
 
+Structures and the ${link('Error', 'client::Error')} enumeration are `#[non_exhaustive]`, as fields and variants are added
+whenever the API evolves, without a new major version of this crate. Create structures with `Default::default()` and
+set their fields afterwards, or use their `with(|v| ...)` constructor, and add a catch-all arm when matching errors.
+
+The JSON Schema of all structures is available as ${link('JSON_SCHEMA', 'api/constant.JSON_SCHEMA.html')}, and the one of a
+single structure from `api::json_schema("<name>")`, to validate payloads or generate forms in other languages consistently
+with them. `client::json_schema::openapi_components()` turns it into the components of an OpenAPI document.
+% for s, fields in operation_schemas(c):
+
+The ${' and '.join('`%s`' % f for f in fields)} of a ${link(s.id, 'api/struct.%s.html' % s.id)} are messages packed with their type,
+which decode into the structure of that type with ${' and '.join('`decode_%s::<T>()`' % f for f in fields)}. Decoding it as another
+type fails with `client::Error::AnyTypeMismatch`.
+% endfor
+
 ${'##'} Builder Arguments
 
 Using ${link('method builders', call_builder_url)}, you are able to prepare an action call by repeatedly calling it's methods.
 These will always take a single argument, for which the following statements are true.
 
 * [PODs][wiki-pod] are handed by copy
-* strings are passed as `&str`
+* strings are passed as `&str`, but the values of repeated parameters as anything `Into<String>`, which moves a `String`
 * ${link('request values', request_trait_url)} are moved
 
 Arguments will always be copied or cloned into the builder, to make them independent of their original life times.
 
+Repeated parameters are appended one value at a time with their `add_*()` setter, all values of an iterator at once with
+`add_*_iter()`, and removed again with `clear_*()`.
+
 [wiki-pod]: http://en.wikipedia.org/wiki/Plain_old_data_structure
 [builder-pattern]: http://en.wikipedia.org/wiki/Builder_pattern
 [google-go-api]: https://github.com/google/google-api-go-client
@@ -241,15 +554,23 @@ Arguments will always be copied or cloned into the builder, to make them indepen
 ## Needs test_prelude.
 ###############################################################################################
 ###############################################################################################
-<%def name="test_hub(hub_type, comments=True)">\
+<%def name="test_hub(hub_type, comments=True, from_files=False)">\
 use std::default::Default;
-use ${util.library_name()}::{${hub_type}, oauth2, hyper, hyper_rustls};
+use ${util.library_name()}::{${hub_type}, oauth2, hyper, hyper_rustls, client::ConnectionOptions};
 
+% if from_files:
+% if comments:
+// Read the ApplicationSecret of your project, as downloaded from the Google Developers Console.
+// It contains the `client_id` and `client_secret`, among other things.
+% endif
+let secret = oauth2::read_application_secret("${EXAMPLE_SECRET_FILE}").await.expect("${EXAMPLE_SECRET_FILE}");
+% else:
 % if comments:
 // Get an ApplicationSecret instance by some means. It contains the `client_id` and 
 // `client_secret`, among other things.
 % endif
 let secret: oauth2::ApplicationSecret = Default::default();
+% endif
 % if comments:
 // Instantiate the authenticator. It will choose a suitable authentication flow for you, 
 // unless you replace  `None` with the desired Flow.
@@ -257,11 +578,19 @@ let secret: oauth2::ApplicationSecret = Default::default();
 // what's going on. You probably want to bring in your own `TokenStorage` to persist tokens and
 // retrieve them from storage.
 % endif
+% if comments:
+// The authenticator must use the client of the hub, whose connector depends on the TLS feature.
+% endif
+let client = ConnectionOptions::default().client();
 let auth = oauth2::InstalledFlowAuthenticator::builder(
         secret,
         oauth2::InstalledFlowReturnMethod::HTTPRedirect,
-    ).build().await.unwrap();
-let mut hub = ${hub_type}::new(hyper::Client::builder().build(hyper_rustls::HttpsConnector::with_native_roots()), auth);\
+% if from_files:
+    ).hyper_client(client.clone()).persist_tokens_to_disk("${EXAMPLE_TOKEN_FILE}").build().await.unwrap();
+% else:
+    ).hyper_client(client.clone()).build().await.unwrap();
+% endif
+let mut hub = ${hub_type}::new(client, auth);\
 </%def>
 
 ## You will still have to set the filter for your comment type - either nothing, or rust_doc_comment !
@@ -331,7 +660,7 @@ You can read the full text at the repository's [license file][repo-license].
 /// Identifies the an OAuth2 authorization scope.
 /// A scope is needed when requesting an
 /// [authorization token](https://developers.google.com/youtube/v3/guides/authentication).
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Scope {
 % for url, scope in auth.oauth2.scopes.items():
     ${scope.description | rust_doc_sanitize, rust_doc_comment}
@@ -1,5 +1,6 @@
 <%!
-    from util import (put_and, rust_test_fn_invisible, rust_doc_test_norun, rust_doc_comment,
+    from util import (put_and, rust_test_fn_invisible, rust_doc_test_norun, rust_doc_comment, rust_doc_test,
+                      rust_test_main_invisible, read_mask_example,
                       rb_type, mb_type, singular, hub_type, to_fqan, indent_all_but_first_by,
                       activity_rust_type, mangle_ident, activity_input_type, get_word,
                       split_camelcase_s, property, is_pod_property, TREF, IO_REQUEST,
@@ -11,7 +12,11 @@
                       DELEGATE_PROPERTY_NAME, struct_type_bounds_s, scope_url_to_variant,
                       re_find_replacements, ADD_PARAM_FN, ADD_PARAM_MEDIA_EXAMPLE, upload_action_fn, METHODS_RESOURCE,
                       method_name_to_variant, size_to_bytes, method_default_scope,
-                      is_repeated_property, setter_fn_name, ADD_SCOPE_FN, rust_doc_sanitize, items)
+                      is_repeated_property, setter_fn_name, ADD_SCOPE_FN, ADD_SCOPES_FN, rust_doc_sanitize, items,
+                      method_supports_streaming, QUOTA_PROJECT_PROPERTY_NAME, QUOTA_PROJECT_HEADER,
+                      rust_example_main_fn, STREAM_UPLOAD_SUFFIX, FILE_UPLOAD_SUFFIX, is_deprecated, DEPRECATED_ATTRIBUTE,
+                      method_signature, list_items_property, page_token_param, is_request_id_param,
+                      ERROR_FORMAT_PARAM, method_supports_polling, page_size_param)
 
     def get_parts(part_prop):
         if not part_prop:
@@ -72,8 +77,15 @@ ${m.description | rust_doc_sanitize, rust_doc_comment}
 /// Please note that due to missing multi-part support on the server side, you will only receive the media,
 /// but not the `${response_schema.id}` structure that you would usually get. The latter will be a default value.
 % endif
+% if not method_media_params(m):
+/// Alternatively, use `doit_media()` to receive the media as a stream of bytes.
+% endif
 ///
 % endif ## supports media download
+% if method_supports_streaming(m):
+/// This method **streams** its response. Use `doit_sse()` to receive it as a stream of server-sent events.
+///
+% endif
 % if resource == METHODS_RESOURCE:
 /// A builder for the *${method}* method.
 % else:
@@ -84,6 +96,7 @@ ${m.description | rust_doc_sanitize, rust_doc_comment}
 % if part_desc:
 ${part_desc | rust_doc_sanitize, rust_doc_comment}
 ///
+% endif
 % if m.get('scopes'):
 /// # Scopes
 ///
@@ -92,16 +105,17 @@ ${part_desc | rust_doc_sanitize, rust_doc_comment}
 at least one of the following scopes to make a valid call, possibly depending on *parts*:
 ///
 % for s in m.scopes:
-/// * *${s}*
+/// * `${scope_url_to_variant(name, s, fully_qualified=True)}` (*${s}*)
 % endfor
 % else:
-the *${m.scopes[0]}* scope to make a valid call.
+the `${scope_url_to_variant(name, m.scopes[0], fully_qualified=True)}` (*${m.scopes[0]}*) scope to make a valid call.
 % endif # len(scopes) > 1
 ///
-/// The default scope will be `${scope_url_to_variant(name, method_default_scope(m), fully_qualified=True)}`.
-% endif # have scopes
+/// The default scope will be `${scope_url_to_variant(name, method_default_scope(m, c), fully_qualified=True)}`, the minimal one
+/// for this method. Use `${ADD_SCOPES_FN}()` to authorize the call with others. The scopes the call requests are returned by
+/// `required_scopes()`, and the ones authorizing it by `accepted_scopes()`.
 ///
-% endif
+% endif # have scopes
 /// # Example
 ///
 /// Instantiate a resource method builder
@@ -109,6 +123,9 @@ the *${m.scopes[0]}* scope to make a valid call.
 <%block filter="rust_doc_comment">\
 ${self.usage(resource, method, m, params, request_value, parts)}\
 </%block>
+% if is_deprecated(m):
+${DEPRECATED_ATTRIBUTE}
+% endif
 pub struct ${ThisType}
     where ${struct_type_bounds_s()} {
 
@@ -124,7 +141,18 @@ pub struct ${ThisType}
 % endfor
 ## A generic map for additinal parameters. Sometimes you can set some that are documented online only
     ${api.properties.params}: HashMap<String, String>,
-    % if method_default_scope(m):
+    ${api.properties.headers}: Vec<(String, String)>,
+    ${api.properties.cancellation_token}: Option<client::CancellationToken>,
+    ${api.properties.timeout}: Option<std::time::Duration>,
+    % if response_schema:
+    ${api.properties.raw_response}: bool,
+    ${api.properties.read_mask}: Option<client::FieldMask>,
+    % endif
+    % if request_value:
+    ${api.properties.body_json}: Option<json::Value>,
+    % endif
+    % if method_default_scope(m, c):
+    ${api.properties.credentials}: Option<String>,
 ## We need the scopes sorted, to not unnecessarily query new tokens
     ${api.properties.scopes}: BTreeMap<String, ()>
     % endif
@@ -138,6 +166,14 @@ ${self._action_fn(c, resource, method, m, params, request_value, parts, doit_wit
 % endif
 
 ${self._action_fn(c, resource, method, m, params, request_value, parts)}\
+% if not method_media_params(m):
+${self._alt_action_fns(c, m, alt_param)}\
+${self._items_fns(c, resource, method, m, params, request_value)}\
+${self._poll_fns(c, m)}\
+${self._copy_call_fn(c, resource, method, m, params, request_value)}\
+
+${self._action_fn(c, resource, method, m, params, request_value, parts, build_request = True)}\
+% endif
 
 ## SETTERS ###############
 % for p in params:
@@ -155,7 +191,12 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     /// # Additional Parameters
     ///
     % for opn, op in list((opn, op) for (opn, op) in parameters.items() if opn not in [p.name for p in params]):
-    /// * *${opn}* (${op.location}-${op.type}) - ${op.description}
+    /// * *${opn}* (${op.location}-${op.type}) - ${op.description}\
+    % if opn == ERROR_FORMAT_PARAM:
+ Defaults to `2`, whose details `ApiError` decodes.
+    % else:
+
+    % endif
     % endfor
     % endif
     pub fn ${ADD_PARAM_FN}<T>(mut self, name: T, value: T) -> ${ThisType}
@@ -164,11 +205,59 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
         self
     }
 
-    % if method_default_scope(m):
+    /// Set an additional header of the request, like one required by a gateway in front of the API.
+    ///
+    /// Headers set by the call itself, which are listed in `client::RESERVED_HEADERS`, can't be set.
+    /// If done anyway, or if the name or value is invalid, the call fails with `client::Error::InvalidHeader`.
+    pub fn header<T>(mut self, name: T, value: T) -> ${ThisType}
+                                                        where T: AsRef<str> {
+        self.${api.properties.headers}.push((name.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    % if request_value:
+    /// Sets the JSON to send in place of the request, as is. Use it to send fields the request
+    /// doesn't have yet, or values of a type other than the one of its field.
+    pub fn body_json(mut self, value: json::Value) -> ${ThisType} {
+        self.${api.properties.body_json} = Some(value);
+        self
+    }
+
+    % endif
+    /// Sets the token cancelling this call, in addition to the one of the hub. Cancelling it aborts the
+    /// request in flight, and the call fails with `client::Error::Cancelled`.
+    pub fn cancellation_token(mut self, token: client::CancellationToken) -> ${ThisType} {
+        self.${api.properties.cancellation_token} = Some(token);
+        self
+    }
+
+    /// Sets the time this call may take, including its retries, after which it fails with `client::Error::Timeout`.
+    /// If the hub has a shorter timeout, that one applies.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> ${ThisType} {
+        self.${api.properties.timeout} = Some(timeout);
+        self
+    }
+
+    % if response_schema:
+    /// Sets the fields of the response to read, given by their paths in its JSON like `${read_mask_example(response_schema)}`,
+    /// which makes the server leave out all other fields. They are sent \
+% if api.get('read_mask_header'):
+with the `X-Goog-FieldMask` header, as this API requires.
+% else:
+as `fields` parameter, which must not be set too.
+% endif
+    pub fn read_mask<T: AsRef<str>>(mut self, fields: &[T]) -> ${ThisType} {
+        self.${api.properties.read_mask} = Some(client::FieldMask::new(fields));
+        self
+    }
+
+    % endif
+
+    % if method_default_scope(m, c):
     /// Identifies the authorization scope for the method you are building.
     ///
     /// Use this method to actively specify which scope should be used, instead the default `Scope` variant
-    /// `${scope_url_to_variant(name, method_default_scope(m), fully_qualified=True)}`.
+    /// `${scope_url_to_variant(name, method_default_scope(m, c), fully_qualified=True)}`.
     ///
     /// The `scope` will be added to a set of scopes. This is important as one can maintain access
     /// tokens for more than one scope.
@@ -188,11 +277,215 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
         };
         self
     }
+
+    /// Identifies the authorization scopes for the method you are building, like
+    /// `&[${scope_url_to_variant(name, method_default_scope(m, c), fully_qualified=True)}]`.
+    ///
+    /// The `scopes` will be added to the set of scopes, just like with `${ADD_SCOPE_FN}()`.
+    pub fn ${ADD_SCOPES_FN}<I, St>(mut self, scopes: I) -> ${ThisType}
+                                                        where I: IntoIterator<Item = St>,
+                                                              St: AsRef<str> {
+        self.${api.properties.scopes}
+            .extend(scopes.into_iter().map(|s| (s.as_ref().to_string(), ())));
+        self
+    }
+
+    /// Returns the scopes this call requests its token for, which are the ones added with `${ADD_SCOPE_FN}()` and
+    /// `${ADD_SCOPES_FN}()`, or the minimal scope of this method,
+    /// `${scope_url_to_variant(name, method_default_scope(m, c), fully_qualified=True)}`, if none were added.
+    /// Check the scopes granted to an application against them to ask for consent before making the call.
+    pub fn required_scopes(&self) -> Vec<String> {
+        if self.${api.properties.scopes}.is_empty() {
+            vec![${scope_url_to_variant(name, method_default_scope(m, c), fully_qualified=True)}.as_ref().to_string()]
+        } else {
+            self.${api.properties.scopes}.keys().cloned().collect()
+        }
+    }
+
+    /// Returns all scopes which authorize this call, any one of them suffices.
+    pub fn accepted_scopes(&self) -> &'static [&'static str] {
+        &[${', '.join('"%s"' % s for s in m.scopes)}]
+    }
+
+    /// Authorizes this call with the credentials of the given `tenant`, which the `client::CredentialRouter` of the hub
+    /// provides, in place of the ones of the hub. If the router doesn't know the tenant, the call fails with
+    /// `client::Error::MissingToken`, unless the delegate provides a token.
+    pub fn with_credentials<T: AsRef<str>>(mut self, tenant: T) -> ${ThisType} {
+        self.${api.properties.credentials} = Some(tenant.as_ref().to_string());
+        self
+    }
     % endif
 }
 </%def>
 
 
+## creates the variants of the action function which change the format of the response
+###############################################################################################
+###############################################################################################
+<%def name="_alt_action_fns(c, m, alt_param)">\
+<%
+    response_schema = method_response(c, m)
+    set_alt = lambda v: alt_param and '%s("%s")' % (mangle_ident(setter_fn_name(alt_param)), v) or '%s("alt", "%s")' % (ADD_PARAM_FN, v)
+    response = response_schema and '(res, _)' or 'res'
+%>\
+
+    /// Perform the operation you have build so far, and return the status, headers and body of the
+    /// response as they are, without decoding it. Use it if the response has fields, or values of a
+    /// type, the response type doesn't have yet.
+    pub async fn doit_raw(${response_schema and 'mut ' or ''}self) -> client::Result<(client::ResponseMetadata, hyper::body::Bytes)> {
+    % if response_schema:
+        self.${api.properties.raw_response} = true;
+    % endif
+        let max_response_size = self.hub._max_response_size;
+        let ${response} = self.${api.terms.action}().await?;
+        client::ResponseMetadata::read_response_limited(res, max_response_size).await
+    }
+% if m.get('supportsMediaDownload', False):
+
+    /// Perform the operation you have build so far, and return the media instead of the
+    /// decoded response. Its body can be consumed as a stream of bytes as it arrives.
+    pub async fn doit_media(self) -> client::Result<hyper::Response<hyper::body::Body>> {
+        let ${response} = self.${set_alt('media')}.${api.terms.action}().await?;
+        Ok(res)
+    }
+% endif
+% if method_supports_streaming(m):
+
+    /// Perform the operation you have build so far, and return the streamed response as the
+    /// server-sent events it consists of, in the order they arrive.
+    pub async fn doit_sse(self) -> client::Result<client::ServerSentEventStream> {
+        let ${response} = self.${set_alt('sse')}.${api.terms.action}().await?;
+        Ok(client::server_sent_events(res.into_body()))
+    }
+% endif
+</%def>
+
+
+## creates the function returning the items of all pages of list methods, and the one creating the call of each page
+###############################################################################################
+###############################################################################################
+<%def name="_items_fns(c, resource, method, m, params, request_value)">\
+<%
+    response_schema = method_response(c, m)
+    page_token_p = page_token_param(params, response_schema)
+    if page_token_p:
+        items_property = list_items_property(response_schema)
+        item_type = response_schema.properties[items_property]['items'][TREF]
+        page_size_p, max_page_size = page_size_param(params)
+%>\
+% if page_token_p:
+
+    /// Fetch the pages of the list one after another, as the returned stream is consumed, starting at the page
+    /// token of this call, if set. Each page holds its items along with the metadata of its response, like the
+    /// amount of items of all pages.
+    ///
+    /// Each page is fetched by a copy of this call. The delegate isn't consulted, as it can't be shared among them.
+    pub fn doit_pages(mut self) -> impl client::Stream<Item = client::Result<client::Page<${item_type}>>> + 'a {
+        let page_token = self.${property(page_token_p.name)}.take();
+        client::pages(page_token, move |page_token| {
+            let mut call = self.copy_call();
+            call.${property(page_token_p.name)} = page_token;
+            call.doit_page()
+        })
+    }
+    % if page_size_p:
+
+    /// Fetch the pages of the list like `doit_pages()`, but adapt their size to how the server copes with them, starting
+    /// at `initial` items per page. Pages which were too large to be delivered are requested again with half their size,
+    /// and the size doubles while pages arrive quickly, up to the maximum of ${max_page_size} items of the API. See
+    /// `client::AdaptivePageSize`, and `client::page_items()` for their items.
+    pub fn doit_pages_adaptive(mut self, initial: u32) -> impl client::Stream<Item = client::Result<client::Page<${item_type}>>> + 'a {
+        let page_token = self.${property(page_token_p.name)}.take();
+        let page_size = client::AdaptivePageSize::new(initial, ${max_page_size});
+        client::adaptive_pages(page_token, page_size, move |page_token, page_size| {
+            let mut call = self.copy_call();
+            call.${property(page_token_p.name)} = page_token;
+            call.${property(page_size_p.name)} = Some(page_size as _);
+            call.doit_page()
+        })
+    }
+    % endif
+
+    /// Fetch the pages of the list one after another, as the returned stream is consumed, and return their items,
+    /// like `doit_pages()` does. Use `map_items()` or `filter_map_items()` of `client::Items` to process them while
+    /// further pages are fetched.
+    pub fn doit_items(self) -> client::Items<impl client::Stream<Item = client::Result<${item_type}>> + 'a> {
+        client::page_items(self.doit_pages())
+    }
+
+    // Fetch the page of this call
+    async fn doit_page(self) -> client::Result<client::Page<${item_type}>> {
+        let (_, page) = self.${api.terms.action}().await?;
+        Ok(client::Page {
+            items: page.${mangle_ident(items_property)}.unwrap_or_default(),
+            next_page_token: page.next_page_token,
+        % if 'totalSize' in response_schema.properties:
+            total_size: page.total_size.filter(|&n| n >= 0).map(|n| n as u64),
+        % else:
+            total_size: None,
+        % endif
+        })
+    }
+% endif
+</%def>
+
+
+## creates the function polling a call until its response is as expected
+###############################################################################################
+###############################################################################################
+<%def name="_poll_fns(c, m)">\
+<%
+    response_schema = method_response(c, m)
+%>\
+% if method_supports_polling(c, m):
+
+    /// Perform the operation you have build so far again and again, until the response is `done`, and return that
+    /// response. The delay between calls starts at `interval`, and grows with each of them up to a few times of it,
+    /// varying randomly so clients don't poll in lockstep, see `client::poll_delay()`.
+    ///
+    /// Each call is made by a copy of this call, and the first failing one ends the polling with its error. The
+    /// delegate isn't consulted, as it can't be shared among them. To stop waiting for a response that never comes,
+    /// cancel the token of this call, see `cancellation_token()`, which fails the next call with `client::Error::Cancelled`.
+    pub async fn poll_until<F>(self, interval: Duration, mut done: F) -> client::Result<(hyper::Response<hyper::body::Body>, ${response_schema.id})>
+        where F: FnMut(&${response_schema.id}) -> bool {
+        client::poll_until(interval, &*self.hub._runtime, || self.copy_call().${api.terms.action}(), |(_, response)| done(response)).await
+    }
+% endif
+</%def>
+
+
+## creates the function copying the call, for the ones making it again
+###############################################################################################
+###############################################################################################
+<%def name="_copy_call_fn(c, resource, method, m, params, request_value)">\
+<%
+    response_schema = method_response(c, m)
+%>\
+% if page_token_param(params, response_schema) or method_supports_polling(c, m):
+
+    fn copy_call(&self) -> ${mb_type(resource, method)}${mb_type_params_s(m)} {
+        ${mb_type(resource, method)} {
+            hub: self.hub,
+    % for p in params:
+        % if p.name == DELEGATE_PROPERTY_NAME:
+            ${property(p.name)}: None,
+        % else:
+            ${property(p.name)}: self.${property(p.name)}.clone(),
+        % endif
+    % endfor
+    % for prop_key, custom_name in api.properties.items():
+        % if prop_key in ('scopes', 'credentials') and not method_default_scope(m, c) or \
+             prop_key == 'body_json' and not request_value:
+<% continue %>\
+        % endif
+            ${custom_name}: self.${custom_name}.clone(),
+    % endfor
+        }
+    }
+% endif
+</%def>
+
+
 ## creates a setter for the call builder
 ###############################################################################################
 ###############################################################################################
@@ -217,6 +510,15 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     if not is_required_property(p) and not is_repeated_property(p):
         new_value_copied = 'Some(%s)' % new_value_copied
 
+    # repeated strings take anything turning into a string, which saves callers owning one an allocation
+    ValueType = InType
+    type_params = ''
+    if is_repeated_property(p) and InType == '&str' and 'clone_value' not in p:
+        ValueType = 'T'
+        type_params = '<T: Into<String>>'
+        new_value_copied = '%s.into()' % value_name
+    setter_name = mangle_ident(setter_fn_name(p))
+
     part_desc = None
     if part_prop is not None and p.name in ('part', REQUEST_VALUE_PROPERTY_NAME):
         part_desc = make_parts_desc(part_prop)
@@ -244,12 +546,19 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     ///
     /// Even though the property as already been set when instantiating this call,
     /// we provide this method for API completeness.
+    % elif is_request_id_param(m, p):
+    ///
+    /// If it isn't set, calls with a delegate, which may retry them, set it to a new UUID, so that the server ignores
+    /// the retries of a request it already received.
     % endif
     % if part_desc:
     ///
     ${part_desc | rust_doc_sanitize, rust_doc_comment, indent_all_but_first_by(1)}
     % endif
-    pub fn ${mangle_ident(setter_fn_name(p))}(mut self, ${value_name}: ${InType}) -> ${ThisType} {
+    % if is_deprecated(p):
+    ${DEPRECATED_ATTRIBUTE}
+    % endif
+    pub fn ${setter_name}${type_params}(mut self, ${value_name}: ${ValueType}) -> ${ThisType} {
         % if p.get('repeated', False):
         self.${property(p.name)}.push(${new_value_copied});
         % else:
@@ -257,15 +566,35 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
         % endif
         self
     }
+    % if is_repeated_property(p):
+    /// Append all given values to the *${split_camelcase_s(p.name)}* ${get_word(p, 'location')}property, in their order,
+    /// like `${setter_name}()` does for each of them.
+    % if is_deprecated(p):
+    ${DEPRECATED_ATTRIBUTE}
+    % endif
+    pub fn ${setter_name}_iter${type_params}(mut self, new_values: impl IntoIterator<Item = ${ValueType}>) -> ${ThisType} {
+        self.${property(p.name)}.extend(new_values.into_iter().map(|${value_name}| ${new_value_copied}));
+        self
+    }
+    /// Removes all values appended to the *${split_camelcase_s(p.name)}* ${get_word(p, 'location')}property before.
+    % if is_deprecated(p):
+    ${DEPRECATED_ATTRIBUTE}
+    % endif
+    pub fn ${mangle_ident('clear_' + p.name)}(mut self) -> ${ThisType} {
+        self.${property(p.name)}.clear();
+        self
+    }
+    % endif
 </%def>
 
 
 ## creates usage docs the method builder
 ## show_all: If True, we will show all comments and hide no prelude. It's good to build a complete,
 ## documented example for a given method.
+## main_fn: If True, the result is a plain rust program with an async main function, as used by cargo examples.
 ###############################################################################################
 ###############################################################################################
-<%def name="usage(resource, method, m, params, request_value, parts=None, show_all=False, rust_doc=True, handle_result=False)">\
+<%def name="usage(resource, method, m, params, request_value, parts=None, show_all=False, rust_doc=True, handle_result=False, main_fn=False)">\
 <%
     hub_type_name = hub_type(schemas, util.canonical_name())
     required_props, optional_props, part_prop = organize_params(params, request_value)
@@ -314,6 +643,14 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     hide_filter = show_all and pass_through or hide_rust_doc_test
     test_block_filter = rust_doc and rust_doc_test_norun or markdown_rust_block
     test_fn_filter = rust_doc and rust_test_fn_invisible or pass_through
+    # Doc tests are run against the fake of the server, unless they upload files that don't exist
+    run_against_fake = rust_doc and not main_fn and not media_params and cargo.get('common_crate')
+    if run_against_fake:
+        test_block_filter = rust_doc_test
+        test_fn_filter = rust_test_main_invisible
+    if main_fn:
+        hide_filter = test_block_filter = pass_through
+        test_fn_filter = rust_example_main_fn
 
     if request_value:
         request_value_type = request_value.id
@@ -330,7 +667,13 @@ use ${util.library_name()}::{Result, Error};
 use std::fs;
 % endif
 <%block filter="test_fn_filter">\
-${capture(lib.test_hub, hub_type_name, comments=show_all) | hide_filter}
+${capture(lib.test_hub, hub_type_name, comments=show_all, from_files=main_fn) | hide_filter}
+% if run_against_fake:
+# hub.auth = ${util.library_name()}::api::fake::authenticator().await.unwrap();
+# let address = ${util.library_name()}::api::fake::server().spawn();
+# hub.root_url(format!("http://{}/", address));
+# hub.base_url(format!("http://{}/${servicePath}", address));
+% endif
 % if request_value:
 // As the method needs a request, you would usually fill it with the desired information
 // into the respective structure. Some of the parts shown here might not be applicable !
@@ -375,20 +718,12 @@ ${'.' + action_name | indent_by(13)}(${action_args}).await;
 % if handle_result:
 
 match result {
-    Err(e) => match e {
-        // The Error enum provides details about what exactly happened.
-        // You can also just use its `Debug`, `Display` or `Error` traits
-         Error::HttpError(_)
-        |Error::Io(_)
-        |Error::MissingAPIKey
-        |Error::MissingToken(_)
-        |Error::Cancelled
-        |Error::UploadSizeLimitExceeded(_, _)
-        |Error::Failure(_)
-        |Error::BadRequest(_)
-        |Error::FieldClash(_)
-        |Error::JsonDecodeError(_, _) => println!("{}", e),
-    },
+    // The Error enum provides details about what exactly happened.
+    // You can also just use its `Debug`, `Display` or `Error` traits.
+    // Variants may be added to it, which is why it must be matched with a catch-all arm.
+    Err(Error::BadRequest(details)) => println!("The server rejected the call: {}", details),
+    Err(Error::Timeout(timeout)) => println!("The call didn't complete within {:?}", timeout),
+    Err(e) => println!("{}", e),
     Ok(res) => println!("Success: {:?}", res),
 }
 % endif
@@ -400,11 +735,11 @@ match result {
 ## create an entire 'api.terms.action' method
 ###############################################################################################
 ###############################################################################################
-<%def name="_action_fn(c, resource, method, m, params, request_value, parts, doit_without_upload = False)">\
+<%def name="_action_fn(c, resource, method, m, params, request_value, parts, doit_without_upload = False, build_request = False)">\
 <%
     import os.path
     join_url = lambda b, e: b.strip('/') + e
-    if doit_without_upload:
+    if doit_without_upload or build_request:
         media_params = []
     else:
         media_params = method_media_params(m)
@@ -416,7 +751,8 @@ match result {
     rtype = 'client::Result<hyper::Response<hyper::body::Body>>'
     response_schema = method_response(c, m)
 
-    supports_download = m.get('supportsMediaDownload', False);
+    # the response is not decoded if 'alt' is set to anything but 'json'
+    supports_download = m.get('supportsMediaDownload', False) or method_supports_streaming(m)
     reserved_params = []
     if response_schema:
         if not supports_download:
@@ -438,10 +774,15 @@ match result {
                 simple_media_param = p
             elif p.protocol == 'resumable':
                 resumable_media_param = p
+        # simple and resumable uploads can also be streamed from asynchronous sources
+        if simple_media_param or resumable_media_param:
+            add_args += ', mut media_stream: Option<client::MediaStream>'
     # end handle media params
 
     if doit_without_upload:
         action_fn = qualifier + 'async fn ' + "doit_without_upload" + type_params + '(mut self)' + ' -> ' + rtype + where
+    elif build_request:
+        action_fn = qualifier + 'async fn build_request(mut self) -> client::Result<hyper::Request<hyper::body::Body>>'
     else:
         action_fn = qualifier + 'async fn ' + api.terms.action + type_params + ('(mut self%s)' % add_args) + ' -> ' + rtype + where
 
@@ -453,7 +794,7 @@ match result {
     delegate_finish = 'dlg.finished'
     auth_call = 'self.hub.auth'
 
-    default_scope = method_default_scope(m)
+    default_scope = method_default_scope(m, c)
 
     # s = '{foo}' -> ('{foo}', 'foo') -> (find_this, replace_with)
     seen = set()
@@ -463,6 +804,7 @@ match result {
     URL_ENCODE = 'url-encode'
 
     READER_SEEK = "let size = reader.seek(io::SeekFrom::End(0)).unwrap();\nreader.seek(io::SeekFrom::Start(0)).unwrap();\n"
+    max_size = 0
     if media_params:
         max_size = media_params[0].max_size
         if max_size > 0:
@@ -497,6 +839,10 @@ match result {
 %>
     % if doit_without_upload:
     /// Perform the operation you have build so far, but without uploading. This is used to e.g. renaming or updating the description for a file
+    % elif build_request:
+    /// Build the request of the operation you have build so far, including its url, headers, authorization and body,
+    /// without sending it. This allows sending it with your own transport, like one signing, queuing or batching
+    /// requests. The response is neither checked nor decoded, and calls aren't retried.
     % else:
     /// Perform the operation you have build so far.
     % endif
@@ -505,8 +851,26 @@ match result {
         use url::percent_encoding::{percent_encode, DEFAULT_ENCODE_SET};
         % endif
         use std::io::{Read, Seek};
-        use hyper::header::{CONTENT_TYPE, CONTENT_LENGTH, AUTHORIZATION, USER_AGENT, LOCATION};
+        use hyper::header::{CONTENT_TYPE, CONTENT_LENGTH, AUTHORIZATION, USER_AGENT, LOCATION, ACCEPT_ENCODING};
         use client::ToParts;
+        % if not build_request:
+        if let Some(check) = self.hub._discovery_check.as_ref() {
+            check.check(&self.hub.client, &client::discovery::GeneratedMethod {
+                api: "${id}",
+                revision: "${revision is UNDEFINED and '00000000' or revision}",
+                id: "${m.id}",
+                signature: "${method_signature(m)}",
+            }).await;
+        }
+        % endif
+        % for p in field_params:
+        % if is_request_id_param(m, p):
+        ## only delegates retry calls, which mustn't create the resource twice
+        if self.${property(p.name)}.is_none() && ${delegate}.is_some() {
+            self.${property(p.name)} = Some(client::new_request_id());
+        }
+        % endif
+        % endfor
         let mut dd = client::DefaultDelegate;
         let mut dlg: &mut dyn client::Delegate = match ${delegate} {
             Some(d) => d,
@@ -514,6 +878,11 @@ match result {
         };
         dlg.begin(client::MethodInfo { id: "${m.id}",
                                http_method: ${method_name_to_variant(m.httpMethod)} });
+        % if not build_request:
+        let deadline = client::deadline(self.hub._timeout, self.${api.properties.timeout});
+        let started = Instant::now();
+        let mut wire_stats = client::WireStats::default();
+        % endif
         let mut params: Vec<(&str, String)> = Vec::with_capacity(${len(params) + len(reserved_params)} + ${paddfields}.len());
 <%
     if media_params and 'mediaUpload' in m:
@@ -568,13 +937,29 @@ match result {
                 return Err(client::Error::FieldClash(field));
             }
         }
+        let additional_headers = match client::header_map(&self.${api.properties.headers}) {
+            Ok(headers) => headers,
+            Err(err) => {
+                ${delegate_finish}(false);
+                return Err(err);
+            }
+        };
         for (name, value) in ${paddfields}.iter() {
             params.push((&name, value.clone()));
         }
+        % if response_schema and not api.get('read_mask_header'):
+        if let Some(read_mask) = self.${api.properties.read_mask}.as_ref() {
+            if ${paddfields}.contains_key("fields") {
+                ${delegate_finish}(false);
+                return Err(client::Error::FieldClash("fields"));
+            }
+            params.push(("fields", read_mask.to_string()));
+        }
+        % endif
 
         % if response_schema:
         % if supports_download:
-        let (json_field_missing, enable_resource_parsing) = {
+        let (json_field_missing, ${build_request and '_' or 'enable_resource_parsing'}) = {
             let mut enable = true;
             let mut field_present = true;
             for &(name, ref value) in params.iter() {
@@ -595,6 +980,12 @@ match result {
         params.push(("alt", "json".to_string()));
         % endif ## supportsMediaDownload
         % endif ## response schema
+        % if parameters is not UNDEFINED and ERROR_FORMAT_PARAM in parameters:
+        // ask for the format of errors whose details client::ApiError decodes, unless another one is set
+        if !${paddfields}.contains_key(client::ERROR_FORMAT_PARAM) {
+            params.push((client::ERROR_FORMAT_PARAM, client::DEFAULT_ERROR_FORMAT.to_string()));
+        }
+        % endif
 
         % if media_params:
         let (mut url, upload_type) =
@@ -682,15 +1073,32 @@ else {
         let mut json_mime_type: mime::Mime = "application/json".parse().unwrap();
         let mut request_value_reader =
             {
-                let mut value = json::value::to_value(&self.${property(REQUEST_VALUE_PROPERTY_NAME)}).expect("serde to work");
-                client::remove_json_null_values(&mut value);
+                ## nulls of the JSON set by the user are kept, as they may clear fields
+                let value = match self.${api.properties.body_json}.take() {
+                    Some(value) => value,
+                    None => {
+                        let mut value = json::value::to_value(&self.${property(REQUEST_VALUE_PROPERTY_NAME)}).expect("serde to work");
+                        client::remove_json_null_values(&mut value);
+                        value
+                    }
+                };
                 let mut dst = io::Cursor::new(Vec::with_capacity(128));
                 json::to_writer(&mut dst, &value).unwrap();
                 dst
             };
+        % if simple_media_param:
         let request_size = request_value_reader.seek(io::SeekFrom::End(0)).unwrap();
         request_value_reader.seek(io::SeekFrom::Start(0)).unwrap();
         % endif
+        % endif
+
+        % if (simple_media_param or resumable_media_param) and max_size > 0:
+        if let Some(size) = media_stream.as_ref().and_then(|s| s.size()) {
+            if size > ${max_size} {
+                return Err(client::Error::UploadSizeLimitExceeded(size, ${max_size}))
+            }
+        }
+        % endif
 
         % if resumable_media_param:
         let mut should_ask_dlg_for_url = false;
@@ -698,13 +1106,17 @@ else {
         let mut upload_url: Option<String> = None;
         % endif
 
+        % if not build_request:
         loop {
+        % endif
             % if default_scope:
-            let token = match ${auth_call}.token(&self.${api.properties.scopes}.keys().collect::<Vec<_>>()[..]).await {
-                Ok(token) => token.clone(),
+            let scopes = self.${api.properties.scopes}.keys().map(String::as_str).collect::<Vec<_>>();
+            let token = self.hub.token(&scopes, self.${api.properties.credentials}.as_deref()).await;
+            let token = match token {
+                Ok(token) => token,
                 Err(err) => {
                     match  dlg.token(&err) {
-                        Some(token) => token,
+                        Some(token) => token.as_str().to_string(),
                         None => {
                             ${delegate_finish}(false);
                             return Err(client::Error::MissingToken(err))
@@ -716,7 +1128,9 @@ else {
             % if request_value:
             request_value_reader.seek(io::SeekFrom::Start(0)).unwrap();
             % endif
+            % if not build_request:
             let mut req_result = {
+            % endif
             % if resumable_media_param:
                 if should_ask_dlg_for_url && (upload_url = dlg.upload_url()) == () && upload_url.is_some() {
                     should_ask_dlg_for_url = false;
@@ -729,6 +1143,16 @@ else {
                 } else {
             % endif
 <%block filter="indent_by(resumable_media_param and 4 or 0)">\
+            % if simple_media_param:
+                let streamed_body = match media_stream.as_mut() {
+                    % if request_value:
+                    Some(media_stream) if protocol == "${simple_media_param.protocol}" => Some(media_stream.take_multi_part_body(request_value_reader.get_ref(), &json_mime_type, &reader_mime_type)?),
+                    % else:
+                    Some(media_stream) if protocol == "${simple_media_param.protocol}" => Some(media_stream.take_body(&reader_mime_type)?),
+                    % endif
+                    _ => None,
+                };
+            % endif
             % if request_value and simple_media_param:
                 let mut mp_reader: client::MultiPartReader = Default::default();
                 let (mut body_reader, content_type) = match protocol {
@@ -743,10 +1167,14 @@ else {
                     _ => (&mut request_value_reader as &mut (dyn io::Read + Send), (CONTENT_TYPE, json_mime_type.to_string())),
                 };
             % endif
-                let client = &self.hub.client;
+                % if not build_request:
+                let client = self.hub.connection();
+                % endif
                 dlg.pre_request();
                 let mut req_builder = hyper::Request::builder().method(${method_name_to_variant(m.httpMethod)}).uri(url.clone().into_string())
-                        .header(USER_AGENT, self.hub._user_agent.clone())\
+                        .header(USER_AGENT, self.hub.user_agent_header())
+                        .header(client::API_CLIENT_HEADER, self.hub.api_client_header())
+                        .header(ACCEPT_ENCODING, client::ACCEPT_ENCODING_VALUE)\
                         % if default_scope:
                             .header(AUTHORIZATION, format!("Bearer {}", token.as_str()))\
                         % endif
@@ -758,23 +1186,51 @@ else {
                     req_builder = req_builder.header("X-Upload-Content-Type", format!("{}", reader_mime_type));
                 }
                 % endif
+                if let Some(quota_project) = self.${property(QUOTA_PROJECT_PROPERTY_NAME)}.as_ref().or(self.hub._quota_project.as_ref()) {
+                    req_builder = req_builder.header("${QUOTA_PROJECT_HEADER}", quota_project.as_str());
+                }
+                if let Some(api_version) = self.hub._api_version.as_ref() {
+                    req_builder = req_builder.header(client::API_VERSION_HEADER, api_version.as_str());
+                }
+                if let Some(visibilities) = client::visibilities(&self.hub._visibility_labels) {
+                    req_builder = req_builder.header(client::VISIBILITIES_HEADER, visibilities);
+                }
+                % if not build_request:
+                let propagate_deadline = self.hub._propagate_deadline;
+                if let Some(server_timeout) = deadline.filter(|_| propagate_deadline).map(client::server_timeout) {
+                    req_builder = req_builder.header(client::SERVER_TIMEOUT_HEADER, server_timeout);
+                }
+                % endif
+                % if response_schema and api.get('read_mask_header'):
+                if let Some(read_mask) = self.${api.properties.read_mask}.as_ref() {
+                    req_builder = req_builder.header(client::FIELD_MASK_HEADER, read_mask.to_string());
+                }
+                % endif
+                if let Some(headers) = req_builder.headers_mut() {
+                    headers.extend(additional_headers.clone());
+                }
 
                 % if request_value:
                     % if not simple_media_param:
-                        let request = req_builder
-                        .header(CONTENT_TYPE, format!("{}", json_mime_type.to_string()))
-                        .header(CONTENT_LENGTH, request_size as u64)
-                        .body(hyper::body::Body::from(request_value_reader.get_ref().clone()))\
+                        let request = client::body_request(req_builder.header(CONTENT_TYPE, format!("{}", json_mime_type.to_string())),
+                                                           request_value_reader.get_ref().clone(),
+                                                           self.hub._request_compression_min_size)\
                     % else:
-                        let mut body_reader_bytes = vec![];
-                        body_reader.read_to_end(&mut body_reader_bytes).unwrap();
-                        let request = req_builder
-                        .header(content_type.0, content_type.1.to_string())
-                        .body(hyper::body::Body::from(body_reader_bytes))\
+                        let request = if let Some(body) = streamed_body {
+                            body.into_request(req_builder)
+                        } else {
+                            let mut body_reader_bytes = vec![];
+                            body_reader.read_to_end(&mut body_reader_bytes).unwrap();
+                            req_builder
+                            .header(content_type.0, content_type.1.to_string())
+                            .body(hyper::body::Body::from(body_reader_bytes))
+                        }\
                     % endif ## not simple_media_param
                 % else:
                     % if simple_media_param:
-                        let request = if protocol == "${simple_media_param.protocol}" {
+                        let request = if let Some(body) = streamed_body {
+                            body.into_request(req_builder)
+                        } else if protocol == "${simple_media_param.protocol}" {
                             ${READER_SEEK | indent_all_but_first_by(4)}
                             let mut bytes = Vec::with_capacity(size as usize);
                             reader.read_to_end(&mut bytes)?;
@@ -791,18 +1247,51 @@ else {
                 % endif
 ;
 
-                client.request(request.unwrap()).await
+                % if build_request:
+                ${delegate_finish}(true);
+                Ok(request.unwrap())
+                % else:
+                let request = request.unwrap();
+                wire_stats.bytes_sent += client::request_body_size(&request);
+                let sent = Instant::now();
+                let response = client::cancellable(client::in_flight(self.hub._max_in_flight.as_ref(), client.request(request)),
+                                                   &self.hub._cancellation_token,
+                                                   self.${api.properties.cancellation_token}.as_ref(),
+                                                   deadline,
+                                                   &*self.hub._runtime).await;
+                wire_stats.time_to_first_byte = sent.elapsed();
+                match response {
+                    % if m.get('supportsMediaDownload', False):
+                    ## the checksums are the ones of the media, which isn't received if the resource is parsed
+                    Ok(response) => {
+                        let verify = self.hub._verify_checksums${response_schema and ' && !enable_resource_parsing' or ''};
+                        response.map(|res| client::decode_response(if verify { client::verify_response(res) } else { res }))
+                    }
+                    % else:
+                    Ok(response) => response.map(client::decode_response),
+                    % endif
+                    Err(err) => {
+                        ${delegate_finish}(false);
+                        return Err(err)
+                    }
+                }
+                % endif
                 
 </%block>\
                 % if resumable_media_param:
             }
                 % endif
+            % if not build_request:
             };
+            if let Some(rebuild) = self.hub._connection_rebuild.as_ref() {
+                rebuild.record(req_result.as_ref().err());
+            }
 
             match req_result {
                 Err(err) => {
                     if let client::Retry::After(d) = dlg.http_error(&err) {
-                        sleep(d);
+                        self.hub._runtime.sleep(d).await;
+                        wire_stats.retries += 1;
                         continue;
                     }
                     ${delegate_finish}(false);
@@ -810,15 +1299,23 @@ else {
                 }
                 Ok(mut res) => {
                     if !res.status().is_success() {
-                        let res_body_string = client::get_body_as_string(res.body_mut()).await;
-                        let (parts, _) = res.into_parts();
-                        let body = hyper::Body::from(res_body_string.clone());
-                        let restored_response = hyper::Response::from_parts(parts, body);
+                        // the body of an error is read with the same limit as the one of a success
+                        let (restored_response, res_body) = match client::buffer_response_limited(res, self.hub._max_response_size).await {
+                            Ok(buffered) => buffered,
+                            Err(err) => {
+                                ${delegate_finish}(false);
+                                return Err(err)
+                            }
+                        };
 
-                        let server_response = json::from_str::<serde_json::Value>(&res_body_string).ok();
+                        wire_stats.bytes_received += res_body.len() as u64;
+                        let server_response = json::from_slice::<serde_json::Value>(&res_body).ok();
 
                         if let client::Retry::After(d) = dlg.http_failure(&restored_response, server_response.clone()) {
-                            sleep(d);
+                            let d = client::retry_delay(&restored_response, server_response.as_ref(), d);
+                            dlg.retrying(d);
+                            self.hub._runtime.sleep(d).await;
+                            wire_stats.retries += 1;
                             continue;
                         }
 
@@ -830,25 +1327,43 @@ else {
                         }
                     }
                     % if resumable_media_param:
-                    if protocol == "${resumable_media_param.protocol}" {
+                    if let Some(media_stream) = media_stream.as_mut().filter(|_| protocol == "${resumable_media_param.protocol}") {
+                        let upload_url = res.headers().get("Location").expect("LOCATION header is part of protocol").to_str().unwrap().to_string();
+                        if upload_url_from_server {
+                            dlg.store_upload_url(Some(upload_url.as_str()));
+                        }
+                        let client = self.hub.connection();
+                        wire_stats.bytes_sent += media_stream.size().unwrap_or(0);
+                        res = match client::upload_stream(&client, dlg, media_stream, &upload_url, &*self.hub._runtime).await {
+                            Ok(res) => res,
+                            Err(err) => {
+                                ${delegate_finish}(false);
+                                return Err(err)
+                            }
+                        };
+                    } else if protocol == "${resumable_media_param.protocol}" {
                         ${READER_SEEK | indent_all_but_first_by(6)}
                         let upload_result = {
                             let url_str = &res.headers().get("Location").expect("LOCATION header is part of protocol").to_str().unwrap();
                             if upload_url_from_server {
                                 dlg.store_upload_url(Some(url_str));
                             }
+                            let user_agent = self.hub.user_agent_header();
+                            let client = self.hub.connection();
 
                             client::ResumableUploadHelper {
-                                client: &self.hub.client,
+                                client: &client,
                                 delegate: dlg,
                                 start_at: if upload_url_from_server { Some(0) } else { None },
                                 auth: &${auth_call},
-                                user_agent: &self.hub._user_agent,
+                                user_agent: &user_agent,
                                 auth_header: format!("Bearer {}", token.as_str()),
                                 url: url_str,
                                 reader: &mut reader,
                                 media_type: reader_mime_type.clone(),
-                                content_length: size
+                                content_length: size,
+                                in_flight: self.hub._max_in_flight.as_ref(),
+                                runtime: &*self.hub._runtime,
                             }.upload().await
                         };
                         match upload_result {
@@ -864,6 +1379,7 @@ else {
                             ## Now the result contains the actual resource, if any ... it will be
                             ## decoded next
                             Some(Ok(upload_result)) => {
+                                wire_stats.bytes_sent += size;
                                 res = upload_result;
                                 if !res.status().is_success() {
                                     ## delegate was called in upload() already - don't tell him again
@@ -877,34 +1393,59 @@ else {
                     % endif
                 % if response_schema:
                     ## If 'alt' is not json, we cannot attempt to decode the response
-                    let result_value = \
+                    let mut result_value = \
                     % if supports_download:
-if enable_resource_parsing \
+if enable_resource_parsing && !self.${api.properties.raw_response} \
+                    % else:
+if !self.${api.properties.raw_response} \
                     % endif
 {
-                        let res_body_string = client::get_body_as_string(res.body_mut()).await;
-
-                        match json::from_str(&res_body_string) {
-                            Ok(decoded) => (res, decoded),
+                        ## large responses take a while to arrive, so they can be cancelled too
+                        let res_body = match client::cancellable(client::read_body_limited(res.body_mut(), self.hub._max_response_size),
+                                                                 &self.hub._cancellation_token,
+                                                                 self.${api.properties.cancellation_token}.as_ref(),
+                                                                 deadline,
+                                                                 &*self.hub._runtime).await.and_then(|res_body| res_body) {
+                            Ok(res_body) => res_body,
                             Err(err) => {
+                                ${delegate_finish}(false);
+                                return Err(err)
+                            }
+                        };
+                        wire_stats.bytes_received += res_body.len() as u64;
+
+                        match client::decode_json_slice(&res_body) {
+                            Ok(decoded) => {
+                                ## the body is decoded again, as a whole, to find the values the generated struct holds as strings
+                                if self.hub._strict_decoding {
+                                    let unknown = json::from_slice(&res_body).ok()
+                                        .and_then(|value| JSON_SCHEMA_DOCUMENT.unknown_enum_value("${response_schema.id}", &value));
+                                    if let Some((path, value)) = unknown {
+                                        ${delegate_finish}(false);
+                                        return Err(client::Error::UnknownEnumValue(path, value));
+                                    }
+                                }
+                                (res, decoded)
+                            },
+                            Err((path, err)) => {
+                                let res_body_string = String::from_utf8_lossy(&res_body).into_owned();
                                 dlg.response_json_decode_error(&res_body_string, &err);
-                                return Err(client::Error::JsonDecodeError(res_body_string, err));
+                                return Err(client::Error::JsonDecodeError(res_body_string, path, err));
                             }
                         }
-                    }\
-                    % if supports_download:
- else { (res, Default::default()) }\
-                    % endif
-;
+                    } else { (res, Default::default()) };
                 % else:
-                    let result_value = res;
+                    let mut result_value = res;
                 % endif
+                    wire_stats.latency = started.elapsed();
+                    ${response_schema and 'result_value.0' or 'result_value'}.extensions_mut().insert(wire_stats);
 
                     ${delegate_finish}(true);
                     return Ok(result_value)
                 }
             }
         }
+            % endif
     }
 
     % for p in media_params:
@@ -915,7 +1456,42 @@ if enable_resource_parsing \
     % endfor
     pub async fn ${upload_action_fn(api.terms.upload_action, p.type.suffix)}<${mtype_param}>(self, ${p.type.arg_name}: ${mtype_param}, mime_type: mime::Mime) -> ${rtype}
                 where ${mtype_param}: client::ReadSeek {
-        self.${api.terms.action}(${p.type.arg_name}, mime_type, "${p.protocol}").await
+        self.${api.terms.action}(${p.type.arg_name}, mime_type, "${p.protocol}"${(simple_media_param or resumable_media_param) and ', None' or ''}).await
+    }
+
+    /// Like `${upload_action_fn(api.terms.upload_action, p.type.suffix)}()`, but uploads the file at `path`, whose MIME type is detected from its
+    /// extension, or from its content if the extension is unknown.
+    pub async fn ${upload_action_fn(api.terms.upload_action, p.type.suffix + FILE_UPLOAD_SUFFIX)}<P: AsRef<std::path::Path>>(self, path: P) -> ${rtype} {
+        let (file, mime_type) = client::open_upload_file(path.as_ref())?;
+        self.${upload_action_fn(api.terms.upload_action, p.type.suffix)}(file, mime_type).await
     }
     % endfor
+    % if simple_media_param:
+
+    /// Upload media all at once, as it is produced by the given stream.
+    /// Use it for large media or media originating from asynchronous sources, like a `tokio::io::AsyncRead`
+    /// wrapped by `client::MediaStream::from_async_read()`, as it doesn't block the executor.
+    /// The upload fails if the request has to be retried, as the stream can't be replayed.
+    ///
+    % for item_name, item in simple_media_param.info.items():
+    /// * *${split_camelcase_s(item_name)}*: ${isinstance(item, (list, tuple)) and put_and(enclose_in("'", item)) or str(item)}
+    % endfor
+    pub async fn ${upload_action_fn(api.terms.upload_action, simple_media_param.type.suffix + STREAM_UPLOAD_SUFFIX)}(self, stream: client::MediaStream, mime_type: mime::Mime) -> ${rtype} {
+        self.${api.terms.action}(io::Cursor::new(Vec::new()), mime_type, "${simple_media_param.protocol}", Some(stream)).await
+    }
+    % endif
+    % if resumable_media_param:
+
+    /// Upload media to a resumable session, in chunks of the delegate's `chunk_size()`, as it is produced by the given
+    /// stream, like the standard input wrapped by `client::MediaStream::from_async_read()`.
+    /// Only one chunk is held in memory at a time, and sent again if the session didn't persist it, so the size of the
+    /// media doesn't have to be known in advance.
+    ///
+    % for item_name, item in resumable_media_param.info.items():
+    /// * *${split_camelcase_s(item_name)}*: ${isinstance(item, (list, tuple)) and put_and(enclose_in("'", item)) or str(item)}
+    % endfor
+    pub async fn ${upload_action_fn(api.terms.upload_action, resumable_media_param.type.suffix + STREAM_UPLOAD_SUFFIX)}(self, stream: client::MediaStream, mime_type: mime::Mime) -> ${rtype} {
+        self.${api.terms.action}(io::Cursor::new(Vec::new()), mime_type, "${resumable_media_param.protocol}", Some(stream)).await
+    }
+    % endif
 </%def>
@@ -6,7 +6,8 @@
                       rust_copy_value_s, organize_params, REQUEST_VALUE_PROPERTY_NAME,
                       build_all_params, rb_type_params_s, hub_type_params_s, mb_type_params_s, mb_additional_type_params, 
                       struct_type_bounds_s, METHODS_RESOURCE, SPACES_PER_TAB, prefix_all_but_first_with,
-                      METHODS_BUILDER_MARKER_TRAIT, remove_empty_lines, method_default_scope, rust_doc_sanitize)
+                      METHODS_BUILDER_MARKER_TRAIT, remove_empty_lines, method_default_scope, rust_doc_sanitize,
+                      is_deprecated, DEPRECATED_ATTRIBUTE, method_response, get_many_activities)
 %>\
 <%namespace name="util" file="../../lib/util.mako"/>\
 <%namespace name="lib" file="lib.mako"/>\
@@ -70,7 +71,7 @@ impl${rb_params} ${ThisType} {
     mb_tparams = mb_type_params_s(m)
     # we would could have information about data requirements for each property in it's dict.
     # for now, we just hardcode it, and treat the entries as way to easily change param names
-    assert len(api.properties) == 2, "Hardcoded for now, thanks to scope requirements"
+    assert len(api.properties) == 9, "Hardcoded for now, thanks to scope requirements"
 
     type_params = ''
     if mb_additional_type_params(m):
@@ -94,6 +95,9 @@ impl${rb_params} ${ThisType} {
         | remove_empty_lines, prefix_all_but_first_with(' ' * SPACES_PER_TAB + '///'  + ' ' * (len(arg_prefix) - len('///')))}
     % endfor
     % endif
+    % if is_deprecated(m):
+    ${DEPRECATED_ATTRIBUTE}
+    % endif
     pub fn ${mangle_ident(a)}${type_params}(&self${method_args}) -> ${RType}${mb_tparams} {
         % if part_prop and request_value:
         use client::ToParts;
@@ -120,7 +124,9 @@ impl${rb_params} ${ThisType} {
             ${property(p.name)}: Default::default(),
             % endfor
 % for prop_key, custom_name in api.properties.items():
-            % if prop_key == 'scopes' and not method_default_scope(m):
+            % if prop_key in ('scopes', 'credentials') and not method_default_scope(m, c) or \
+                 prop_key in ('raw_response', 'read_mask') and not method_response(c, m) or \
+                 prop_key == 'body_json' and not request_value:
 <% continue %>\
             % endif
             ${custom_name}: Default::default(),
@@ -128,5 +134,37 @@ impl${rb_params} ${ThisType} {
         }
     }
     % endfor ## for each activity
+    % for a, batch_a, items_property in get_many_activities(c, resource):
+<%
+    m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, a)]
+    item_type = method_response(c, m).id
+%>
+    /// Get the resources with the given names, like `${mangle_ident(a)}()` does for each of them, and return the results
+    /// in the order of the names.
+    % if batch_a:
+    /// Names sharing a parent are gotten with `${mangle_ident(batch_a)}()`, up to `client::MAX_BATCH_GET_NAMES` at once.
+    /// Those of failed batch calls are gotten one by one, to tell which of them failed.
+    % endif
+    /// Up to `client::DEFAULT_ITEM_CONCURRENCY` calls are made at once.
+    pub async fn ${mangle_ident(a[:-len('get')] + 'getMany')}<'n>(&self, names: impl IntoIterator<Item = &'n str>) -> Vec<client::Result<${item_type}>> {
+        let names = names.into_iter().map(str::to_string).collect();
+        let get = |name: String| async move {
+            self.${mangle_ident(a)}(&name).${api.terms.action}().await.map(|(_, item)| item)
+        };
+    % if batch_a:
+        let batch_get = |parent: String, names: Vec<String>| async move {
+            let mut call = self.${mangle_ident(batch_a)}(&parent);
+            for name in &names {
+                call = call.add_names(name);
+            }
+            let (_, response) = call.${api.terms.action}().await?;
+            Ok(response.${mangle_ident(items_property)}.unwrap_or_default())
+        };
+        client::batch_get_many(names, client::DEFAULT_ITEM_CONCURRENCY, batch_get, get).await
+    % else:
+        client::get_many(names, client::DEFAULT_ITEM_CONCURRENCY, get).await
+    % endif
+    }
+    % endfor ## for each get method
 }
 </%def>
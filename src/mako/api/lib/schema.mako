@@ -3,23 +3,48 @@
                       IO_TYPES, activity_split, enclose_in, REQUEST_MARKER_TRAIT, mb_type, indent_all_but_first_by,
                       NESTED_TYPE_SUFFIX, RESPONSE_MARKER_TRAIT, split_camelcase_s, METHODS_RESOURCE,
                       PART_MARKER_TRAIT, canonical_type_name, TO_PARTS_MARKER, UNUSED_TYPE_MARKER, is_schema_with_optionals,
-                      rust_doc_sanitize, items)
+                      rust_doc_sanitize, items, is_duration_property, is_deprecated, DEPRECATED_ATTRIBUTE,
+                      enum_properties, enum_type_name, enum_variants)
 %>\
 ## Build a schema which must be an object
 ###################################################################################################################
 ###################################################################################################################
 <%def name="_new_object(s, properties, c, allow_optionals)">\
-<% struct = 'pub struct ' + s.id %>\
+<%
+    struct = 'pub struct ' + s.id
+    enum_fields = set(pn for pn, _, _ in enum_properties(s))
+%>\
 % if properties:
+#[non_exhaustive]
 ${struct} {
 % for pn, p in items(properties):
     ${p.get('description', 'no description provided') | rust_doc_sanitize, rust_doc_comment, indent_all_but_first_by(1)}
+    % if pn in enum_fields:
+    ///
+    /// Its values are enumerated by `${enum_type_name(s.id, pn)}`.
+    % endif
     % if pn != mangle_ident(pn):
     #[serde(rename="${pn}")]
     % endif
+    % if is_duration_property(p):
+    #[serde(default, with="client::serde_duration")]
+    % endif
+    % if is_deprecated(p):
+    ${DEPRECATED_ATTRIBUTE}
+    % endif
     pub ${mangle_ident(pn)}: ${to_rust_type(schemas, s.id, pn, p, allow_optionals=allow_optionals)},
 % endfor
 }
+
+impl ${s.id} {
+    /// Returns a default `${s.id}` changed by `init`, to create one in a single expression. Struct expressions
+    /// can't be used outside of this crate, as fields may be added to it.
+    pub fn with(init: impl FnOnce(&mut ${s.id})) -> ${s.id} {
+        let mut value = ${s.id}::default();
+        init(&mut value);
+        value
+    }
+}
 % elif 'additionalProperties' in s:
 ${struct}(${to_rust_type(schemas, s.id, NESTED_TYPE_SUFFIX, s, allow_optionals=allow_optionals)});
 % elif 'variant' in s:
@@ -44,6 +69,7 @@ impl Default for ${et} {
 }
 % else: ## it's an empty struct, i.e. struct Foo;
         ## However, to enable the empty JSON object to be parsed, we set one unused optional parameter.
+#[non_exhaustive]
 ${struct} { _never_set: Option<bool> }
 % endif ## 'properties' in s
 </%def>
@@ -127,6 +153,65 @@ impl ${TO_PARTS_MARKER} for ${s_type} {
     }
 }
 % endif
+% if s.type == 'object':
+% for pn, values, descriptions in enum_properties(s):
+${_enum(s, pn, values, descriptions)}
+% endfor
+% endif
+</%def>
+
+## An enumeration of the values of the field `pn` of the schema `s`, which is a string, or a list of them.
+###################################################################################################################
+###################################################################################################################
+<%def name="_enum(s, pn, values, descriptions)">\
+<%
+    et = enum_type_name(s.id, pn)
+    variants = enum_variants(values)
+%>\
+/// The values of the `${pn}` field of `${s.id}`, which holds them as strings.
+/// Use `to_string()` to set the field, and `parse()` to check its value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ${et} {
+% for vi, (variant, value) in enumerate(zip(variants, values)):
+    ${(vi < len(descriptions) and descriptions[vi] or value) | rust_doc_sanitize, rust_doc_comment, indent_all_but_first_by(1)}
+    ${variant},
+% endfor
+}
+
+impl ${et} {
+    /// All values of the field, in the order the API documents them.
+    pub const VALUES: &'static [&'static str] = &[${', '.join('"%s"' % v for v in values)}];
+    /// All variants, in the order of `VALUES`.
+    pub const VARIANTS: &'static [${et}] = &[${', '.join('%s::%s' % (et, v) for v in variants)}];
+
+    /// Returns the value of the field.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+% for variant, value in zip(variants, values):
+            ${et}::${variant} => "${value}",
+% endfor
+        }
+    }
+}
+
+impl std::fmt::Display for ${et} {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ${et} {
+    type Err = client::UnknownEnumValue;
+
+    fn from_str(value: &str) -> std::result::Result<${et}, client::UnknownEnumValue> {
+        match value {
+% for variant, value in zip(variants, values):
+            "${value}" => Ok(${et}::${variant}),
+% endfor
+            _ => Err(client::UnknownEnumValue::new(value, ${et}::VALUES)),
+        }
+    }
+}
 </%def>
 
 #########################################################################################################
@@ -0,0 +1,37 @@
+<%namespace name="util" file="../../lib/util.mako"/>\
+<%!
+    import json
+    from util import (new_context, rust_comment, round_trip_schemas, schema_sample, rust_raw_string,
+                      camel_to_under)
+%>\
+<%
+    c = new_context(schemas, resources, context.get('methods'))
+%>\
+<%block filter="rust_comment">\
+<%util:gen_info source="${self.uri}" />\
+</%block>
+
+//! Round-trips a sample of each schema through serde, which fails if a field was renamed or has
+//! another type than before. The JSON of each schema is also compared to its snapshot in
+//! *tests/snapshots*. Run `cargo insta review` to accept the changes of the generator.
+
+${util.test_prelude()}\
+extern crate insta;
+extern crate serde_json;
+
+use serde_json as json;
+
+use ${util.library_name()}::{api, client};
+
+% for s in round_trip_schemas(c):
+
+#[test]
+fn ${camel_to_under(s.id)}_round_trip() {
+    let sample: json::Value = json::from_str(${rust_raw_string(json.dumps(schema_sample(c.schemas, s), sort_keys=True))}).unwrap();
+    let value: api::${s.id} = json::from_value(sample.clone()).unwrap();
+    let mut round_tripped = json::to_value(&value).unwrap();
+    client::remove_json_null_values(&mut round_tripped);
+    assert_eq!(round_tripped, sample);
+    insta::assert_json_snapshot!(round_tripped);
+}
+% endfor
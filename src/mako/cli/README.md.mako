@@ -1,6 +1,17 @@
 <%
-    from util import (markdown_comment, new_context)
-    from cli import (CONFIG_DIR, CONFIG_DIR_FLAG, SCOPE_FLAG, application_secret_path, DEBUG_FLAG)
+    from util import (markdown_comment, new_context, supports_media_download)
+    from cli import (CONFIG_DIR, CONFIG_DIR_FLAG, SCOPE_FLAG, application_secret_path, DEBUG_FLAG, STREAM_FLAG,
+                     ALL_PAGES_FLAG, PROJECTS_FROM_FLAG, has_auth_command, AUTH_CMD, BILLING_PROJECT_FLAG,
+                     AUTH_SCOPES_FLAG, AUDIENCE_FLAG, ADAPTIVE_PAGE_SIZE_FLAG, PAGE_SIZE_ARG, SORT_BY_FLAG,
+                     SORT_BY_ARG, LIMIT_FLAG, LIMIT_ARG, PROGRESS_JSON_FLAG, PROGRESS_JSON_ARG,
+                     vulnerability_report_method, ema_avail_methods, REPORT_CMD, VULNERABILITIES_CMD, IMAGE_FLAG, REPORT_PROJECT_FLAG,
+                     REPORT_FORMAT_FLAG, OUTPUT_FLAG, DIFF_CMD, FROM_FLAG, TO_FLAG, PARAM_FILE_FLAG,
+                     param_file_basename, alias_file_basename, COUNT_FLAG, FAIL_ON_EMPTY_FLAG, EXTERNAL_ACCOUNT_FLAG,
+                     CANONICAL_JSON_FLAG, NO_VERIFY_FLAG, EXEC_FLAG, has_describe_command, DESCRIBE_CMD,
+                     OUTPUT_FORMAT_FLAG, COLUMNS_FLAG, MAX_WIDTH_FLAG, NO_TRUNCATE_FLAG, DEFAULT_MAX_COLUMN_WIDTH,
+                     SA_KEY_DIR_FLAG, ERROR_FORMAT_FLAG, WATCH_FLAG, INTERVAL_FLAG, DEFAULT_WATCH_INTERVAL,
+                     REPLAY_FILE_FLAG, RECORD_FILE_FLAG, AUTH_FLOW_FLAG, VERBOSE_FLAG, mangle_subcommand, PARAM_FLAG,
+                     UPLOAD_FLAG, FILE_FLAG, UPLOAD_SUBCOMMAND_SUFFIX, DOWNLOAD_SUBCOMMAND_SUFFIX, OUTPUT_TEMPLATE_FLAG)
 
     c = new_context(schemas, resources, context.get('methods'))
 %>\
@@ -29,6 +40,10 @@ Install the command-line interface with cargo using:
 cargo install ${util.crate_name()}
 ```
 
+It connects using rustls, which trusts the bundled Mozilla root certificates. To trust the certificates of the OS instead,
+or to use its TLS library, like OpenSSL, install it with `--no-default-features --features rustls-native-roots`, or
+`--no-default-features --features native-tls` respectively.
+
 Find the source code [on github](${util.github_source_root_url()}).
 
 # Usage
@@ -43,6 +58,18 @@ ${argparse.grammar(c)}
 
 The program will store all persistent data in the `${CONFIG_DIR}` directory in *JSON* files prefixed with `${util.program_name()}-`.  You can change the directory used to store configuration with the `--${CONFIG_DIR_FLAG}` flag on a per-invocation basis.
 
+Commonly used optional parameters, like filters or page sizes, may be kept in `${param_file_basename(util.program_name())}.json`
+or `${param_file_basename(util.program_name())}.toml` in that directory, or in any file given with `--${PARAM_FILE_FLAG}`. Each
+top-level key names a `-${PARAM_FLAG}` parameter, and its value is used by all calls supporting it, unless the parameter is set on
+the command-line, e.g. `{"page-size": 100, "quota-user": "ci"}`. Lists set parameters taking multiple values.
+
+Long command-lines which are used repeatedly may be given a name in `${alias_file_basename(util.program_name())}.toml` or
+`${alias_file_basename(util.program_name())}.json` in that directory. Each top-level key names an alias, and its value is the
+command-line it stands for, either as a string which is split like a shell would, or as a list of arguments. Invoking the
+program with an alias in place of a command runs the command-line, with any additional arguments inserted before its first
+option, e.g. `crit-vulns = ["projects", "occurrences-list", "-${PARAM_FLAG}", 'filter=kind="VULNERABILITY"']`. Aliases may start with
+other aliases, but never replace the commands of the program.
+
 More information about the various kinds of persistent data are given in the following paragraphs.
 
 # Authentication
@@ -58,12 +85,49 @@ All applicable scopes are documented in the respective method's CLI documentatio
 The first time a scope is used, the user is asked for permission. Follow the instructions given 
 by the CLI to grant permissions, or to decline.
 
+By default, the permission is granted in a browser, which redirects to a server the CLI runs locally. On hosts without
+browser, or whose ports can't be forwarded, `--${AUTH_FLOW_FLAG} device` shows a code on stderr instead, which is
+entered at a verification URL on any other device, while the CLI waits for it. This flow needs the application secret
+of an OAuth client of the *TVs and Limited Input devices* type, and only supports some scopes.
+
 If a scope was authenticated by the user, the respective information will be stored as *JSON* in the configuration
-directory, e.g. `${CONFIG_DIR}/${util.program_name()}-token-<scope-hash>.json`. No manual management of these tokens
-is necessary.
+directory, in the `${CONFIG_DIR}/${util.program_name()}` file. No manual management of these tokens
+is necessary. Invocations running at the same time, like the jobs of a build matrix, each work on a copy of the tokens,
+and add the ones they obtained to the file in the end, while holding a lock on the `${util.program_name()}.lock` file next
+to it. The operating system releases the lock of an invocation which crashed.
+% if has_auth_command(c, auth):
+
+The `${AUTH_CMD}` command manages these tokens, for the scopes given with `--${SCOPE_FLAG}`:
+
+* `${AUTH_CMD} login` asks for permission right away, instead of on the first call.
+* `${AUTH_CMD} print-access-token` prints an access token, e.g. for use with `curl`. Further scopes may be given with
+  `--${AUTH_SCOPES_FLAG}`.
+* `${AUTH_CMD} print-identity-token` prints an identity token of the user, for services checking these instead, like
+  the ones behind an identity-aware proxy. It is always issued for the client id of the application secret, so only
+  services accepting that audience accept it. `--${AUDIENCE_FLAG} <aud>` fails instead of printing a token issued for
+  another audience, but can't choose the audience, which would take the token of a service account.
+* `${AUTH_CMD} revoke` (or `${AUTH_CMD} logout`) revokes the tokens, and removes them from the configuration directory.
+  Without `--${SCOPE_FLAG}`, all tokens are revoked.
+* `${AUTH_CMD} list` lists the scopes of all persisted tokens, without revealing the tokens themselves.
+% endif
 
 To revoke granted authentication, please refer to the [official documentation][revoke-access].
 
+Instead of the credentials of a user, calls may be authorized with the ones of an external account, like those of a
+workload identity pool, whose credentials are supplied by an executable, as custom token brokers do. Pass its credential
+configuration file, of `"type": "external_account"` with an `"executable"` as `"credential_source"`, with
+`--${EXTERNAL_ACCOUNT_FLAG} <file>`. As this runs the configured command, it must be allowed by setting the environment
+variable `GOOGLE_EXTERNAL_ACCOUNT_ALLOW_EXECUTABLES` to `1`.
+
+Unattended jobs can authorize calls with the keys of a service account instead, which `--${SA_KEY_DIR_FLAG} <dir>` loads
+from the JSON files of a directory, as downloaded from the Cloud Console. The most recently modified key is tried first,
+and keys the token endpoint rejects, like revoked or expired ones, are skipped. When rotating keys, add the new key to the
+directory before revoking the old one, and the program keeps working without changing its invocation.
+
+Calls are billed to the project the credentials belong to, which for user credentials is the one of the
+application secret. Use `--${BILLING_PROJECT_FLAG} <project-id>` to bill another project, whose quota the calls then count
+against, as some APIs require.
+
 # Application Secrets
 
 In order to allow any application to use Google services, it will need to be registered using the 
@@ -82,6 +146,144 @@ was enabled for it. Such a secret file can be downloaded in the *Google Develope
 Learn more about how to setup Google projects and enable APIs using the [official documentation][google-project-new].
 
 
+# Output
+
+Methods returning a list of items do so one page at a time. Use the `--${ALL_PAGES_FLAG}` flag to keep fetching pages
+until the last one was received.
+
+When fetching all pages, `--${ADAPTIVE_PAGE_SIZE_FLAG} <${PAGE_SIZE_ARG}>` sets the amount of items to request per page
+initially. It is halved whenever the server fails to deliver a page because it is too large, or takes too long, and
+doubled up to the maximum of the API whenever a page arrived quickly.
+
+The items of list responses can be sorted with `--${SORT_BY_FLAG} <${SORT_BY_ARG}>[:desc]`, where the field is a path into the
+JSON of each item, like `vulnerability.cvssScore`, and `--${LIMIT_FLAG} <${LIMIT_ARG}>` writes no more than the given amount of
+them, e.g. `${util.program_name()} --${ALL_PAGES_FLAG} --${SORT_BY_FLAG} createTime:desc --${LIMIT_FLAG} 20 <resource> <list-method> [options]`.
+When sorting, all pages are received before any item is written. Without sorting, no more pages are fetched once the
+limit was reached.
+
+To find out how many items a list holds, `--${COUNT_FLAG}` writes only their amount, across all received pages, e.g.
+`${util.program_name()} --${COUNT_FLAG} --${ALL_PAGES_FLAG} <resource> <list-method> [options]`. Scripts checking whether any
+item matches a filter can use `--${FAIL_ON_EMPTY_FLAG}`, which makes the program exit with a non-zero status if the list
+contains no items.
+With `--${VERBOSE_FLAG}`, the amount of fetched items is written to stderr once the last page was received, along with the
+amount of items of the whole list for APIs reporting it in the `totalSize` field of their responses, like
+`10 of 42 items fetched`.
+
+To keep the output of repeated calls in version control, e.g. as golden files, use `--${CANONICAL_JSON_FLAG}`. It sorts the
+keys of all JSON objects and writes numbers without fraction as integers, like `1` instead of `1.0`, so the output only
+changes if the data does.
+
+For humans, `--${OUTPUT_FORMAT_FLAG} table` writes a table with a row per item of list responses, or a single row for other
+responses, once all pages were received. Each field is a column, with nested fields flattened into paths like
+`metadata.name`, unless `--${COLUMNS_FLAG}` selects them, e.g.
+`${util.program_name()} --${OUTPUT_FORMAT_FLAG} table --${COLUMNS_FLAG} name,metadata.createTime <resource> <list-method> [options]`.
+Cells are truncated to ${DEFAULT_MAX_COLUMN_WIDTH} characters, or the amount given with `--${MAX_WIDTH_FLAG} <chars>`, while
+`--${NO_TRUNCATE_FLAG}` writes them as a whole.
+
+For spreadsheets, `--${OUTPUT_FORMAT_FLAG} csv` writes the same rows and columns as comma-separated values, without truncating them.
+% if ema_avail_methods(c, api):
+Avails are written in the columns of the [EMA Avails][ema-avails] spreadsheets, version 1.6b, in the order of the spec, unless
+`--${COLUMNS_FLAG}` selects others, e.g.
+`${util.program_name()} --${OUTPUT_FORMAT_FLAG} csv --${ALL_PAGES_FLAG} ${' '.join(mangle_subcommand(n) for n in ema_avail_methods(c, api)[-1])} [options] > avails.csv`.
+
+[ema-avails]: http://www.movielabs.com/md/avails/
+% endif
+
+For reports, `--${OUTPUT_TEMPLATE_FLAG} <file>` renders results with a [TinyTemplate][tinytemplate] template, like a Markdown
+or HTML file, once all pages were received. The response is `{response}`, and if the call was made for one of many projects,
+its id is `{project}`. A template listing the names of all items may look like
+
+```
+# Items
+{{ for item in response.items }}
+* {item.name}
+{{ endfor }}
+```
+
+Values are inserted as they are, unless the template has the `.html` or `.htm` extension, which HTML-escapes them.
+Rendering fails if a field the template refers to is missing from the response, except for the items of list responses,
+which are an empty list if there are none.
+
+[tinytemplate]: https://docs.rs/tinytemplate
+
+Large lists are best consumed with the `--${STREAM_FLAG}` flag, which writes each item as a single line of JSON as soon as
+its page was decoded, e.g. `${util.program_name()} --${STREAM_FLAG} --${ALL_PAGES_FLAG} <resource> <list-method> [options] | jq .name`.
+
+While a list changes, like the occurrences of an image which is being scanned, `--${WATCH_FLAG}` polls it again and again with
+the same parameters, until interrupted, and writes only the items which are new or changed since the previous poll, by
+their `name` and `updateTime`, as single lines of JSON. It waits ${DEFAULT_WATCH_INTERVAL} between polls, or the time given with
+`--${INTERVAL_FLAG}`, like `5m` or `500ms`, e.g.
+`${util.program_name()} --${WATCH_FLAG} --${INTERVAL_FLAG} 10s <resource> <list-method> [options]`.
+
+To integrate with other tools, `--${EXEC_FLAG} <command>` pipes each response, or each item when streaming, to the standard
+input of a shell command, which runs once per result, and writes what it outputs instead. For example,
+`${util.program_name()} --${STREAM_FLAG} --${EXEC_FLAG} 'curl -s -d @- https://hooks.example.com/notify' <resource> <list-method> [options]`
+posts each item to a webhook. The call fails if the command exits with a non-zero status.
+
+Interrupting a call with `Ctrl-C` aborts its request in flight. When fetching all pages, the items of the pages received
+so far are written before the program exits with an error. Interrupting it a second time exits right away.
+
+Arguments naming a resource, like `<parent>`, are checked against the resource names the method accepts before any
+request is made. Where the API offers the same method for projects, folders, organizations or billing accounts, each
+of them accepts the names of all these kinds, e.g. `folders/<id>/locations/global` as well as
+`projects/<id>/locations/global`.
+
+Methods operating on a single project accept `--${PROJECTS_FROM_FLAG} <file>` in place of the project, to run the same call
+for each project id listed in the file, one per line. A bounded number of calls runs at the same time, and each
+result is wrapped into an object like `{"project": "<id>", "response": ...}` (or `"item"` when streaming), which is useful
+for organization-wide audits.
+
+Wrappers and CI systems can follow the progress of long operations using `--${PROGRESS_JSON_FLAG} <${PROGRESS_JSON_ARG}>`, which
+writes one JSON object per line to the given file, or to stderr if it is `-`. Each object names its `event`:
+`page_fetched` with the number of the `page` and its amount of `items`, `items_processed` with the `count` of items so
+far, `retry` with the `reason` and the `delay_ms` before the request is sent again, and `upload_progress` with the
+`uploaded_bytes`, `total_bytes` and `percent` of resumable uploads.
+
+Uploads and downloads show their progress on a terminal, with the transferred bytes, their rate, and the time left if
+the size of the media is known, unless progress events are written to stderr. Methods uploading media have a subcommand
+dedicated to it, like `insert${UPLOAD_SUBCOMMAND_SUFFIX} <file>` next to `insert -${UPLOAD_FLAG} simple -${FILE_FLAG} <file>`, and methods
+downloading media only if asked to with `-${PARAM_FLAG} alt=media` have one like `get${DOWNLOAD_SUBCOMMAND_SUFFIX}`, which sets it.
+% if supports_media_download(c):
+
+Downloaded media is verified against the `crc32c` or `md5` checksum the server sends along with it, and the program exits
+with an error if they don't match, as the download was corrupted. Use `--${NO_VERIFY_FLAG}` to skip the verification.
+% endif
+% if vulnerability_report_method(c, api):
+
+# Vulnerability Reports and Diffs
+
+The `${REPORT_CMD} ${VULNERABILITIES_CMD} --${IMAGE_FLAG} <url>` command fetches all pages of the vulnerability occurrences
+of an image, and groups them by CVE, keeping the highest severity and all affected packages of each. It prints the number
+of vulnerabilities per severity, followed by a table of all of them, most severe first. `--${OUTPUT_TEMPLATE_FLAG} <file>`
+renders them with a template instead, as `{{ for finding in findings }}` with the `cve`, `severity`, `cvss_score`,
+`fix_available`, `packages` and `description` of each, and the `{image}` they were found in.
+
+With `--${REPORT_FORMAT_FLAG} sarif`, the vulnerabilities are written as [SARIF 2.1.0][sarif] log instead, which code scanning
+dashboards can import, and with `--${REPORT_FORMAT_FLAG} vex` as [CycloneDX][cyclonedx-vex] VEX document, e.g. `${util.program_name()} ${REPORT_CMD} ${VULNERABILITIES_CMD} --${IMAGE_FLAG} https://gcr.io/<project>/<image>@sha256:<digest> --${REPORT_FORMAT_FLAG} sarif -${OUTPUT_FLAG} report.sarif`.
+
+The occurrences are looked up in the project hosting the image, which can be overridden with `--${REPORT_PROJECT_FLAG} <id>`.
+
+The `${DIFF_CMD} --${FROM_FLAG} <url> --${TO_FLAG} <url>` command compares the vulnerabilities of two images, like the digest
+of a release candidate with the one in production. It lists the vulnerabilities only found in the new image as added (`+`),
+the ones only found in the old image as removed (`-`), and the ones whose severity, score, fix or affected packages changed
+(`~`), along with what they were before. Use `--${REPORT_FORMAT_FLAG} json` to process the difference further, e.g. to fail
+a release if vulnerabilities were added.
+% endif
+
+% if has_describe_command(c):
+
+# Describing Commands
+
+The `${DESCRIBE_CMD}` command writes the description of all commands as JSON, for tools and user interfaces building upon
+this program without parsing its help. `${DESCRIBE_CMD} <resource>` describes only the methods of a resource, and
+`${DESCRIBE_CMD} <resource> <method>` a single one, e.g. `${util.program_name()} ${DESCRIBE_CMD} <resource> <method>`.
+
+A method is described by the id of its API method, its positional arguments, its `-p` parameters, including the ones all
+methods accept, and the fields of its request, which are nested like their keys given to `-r`, along with their types,
+formats, enumerated values and descriptions, as well as the scopes it accepts. It is generated from the same revision of the
+API as the program, and needs no credentials.
+% endif
+
 # Debugging
 
 Even though the CLI does its best to provide usable error messages, sometimes it might be desirable to know
@@ -92,8 +294,23 @@ The `--${DEBUG_FLAG}` flag will print errors using the `Debug` representation to
 
 You may consider redirecting standard error into a file for ease of use, e.g. `${util.program_name()} --${DEBUG_FLAG} <resource> <method> [options] 2>debug.txt`.
 
+Scripts can handle failures with `--${ERROR_FORMAT_FLAG} json`, which writes each of them as a single line of JSON instead,
+like `{"exitCode":1,"httpStatus":404,"message":"Topic not found","reason":"NOT_FOUND","requestId":null}`. Its
+`reason` is the one the server gave, or the canonical status of the error, and failures of calls made for several
+projects name their `project`. The invalid fields of requests, and the exceeded quotas, are listed as `violations`, like
+`["name: must not be empty"]`, as calls ask for version 2 of the error format by default, which has these details. Set
+`-p $-xgafv=1` to ask for version 1 instead.
+
+Calls can be saved as they happened with `--${RECORD_FILE_FLAG} calls.json`, which writes their requests and responses to a
+cassette file, without the headers of the requests and thus without credentials. `--${REPLAY_FILE_FLAG} calls.json` responds
+to the same calls with the saved responses instead, needing neither credentials nor network, which allows to work on the
+formatting and filtering of responses offline, and to attach a reproducible cassette to bug reports, e.g.
+`${util.program_name()} --${REPLAY_FILE_FLAG} calls.json <resource> <method> [options]`. Calls which weren't recorded fail with a `404` error.
+
 
 [scopes]: https://developers.google.com/+/api/oauth#scopes
+[sarif]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+[cyclonedx-vex]: https://cyclonedx.org/capabilities/vex/
 [revoke-access]: http://webapps.stackexchange.com/a/30849
 [google-dev-console]: https://console.developers.google.com/
 [google-project-new]: https://developers.google.com/console/help/new/
\ No newline at end of file
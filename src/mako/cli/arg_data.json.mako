@@ -0,0 +1,7 @@
+<%namespace name="argparse" file="lib/argparse.mako"/>\
+<%
+    from util import new_context
+
+    c = new_context(schemas, resources, context.get('methods'))
+%>\
+${argparse.arg_data(c)}\
@@ -1,12 +1,15 @@
 <%namespace name="util" file="../../lib/util.mako"/>\
 <%!
     from mako.filters import xml_escape
-    from util import (hash_comment, new_context, method_default_scope, indent_all_but_first_by, is_repeated_property, custom_sorted)
+    from util import (hash_comment, new_context, method_default_scope, indent_all_but_first_by, is_repeated_property, custom_sorted,
+                      is_deprecated)
     from cli import (subcommand_md_filename, new_method_context, SPLIT_START, SPLIT_END, pretty, SCOPE_FLAG,
                      mangle_subcommand, is_request_value_property, FIELD_SEP, PARAM_FLAG, UPLOAD_FLAG, docopt_mode,
                      FILE_ARG, MIME_ARG, OUT_ARG, OUTPUT_FLAG, to_cli_schema, cli_schema_to_yaml, SchemaEntry,
                      STRUCT_FLAG, field_to_value, CTYPE_ARRAY, CTYPE_MAP, to_docopt_arg, FILE_FLAG, MIME_FLAG, 
-                     DEFAULT_MIME)
+                     DEFAULT_MIME, project_param, resource_name_templates, resource_templates_doc, CLI_LIST_KEY,
+                     CLI_MAP_KEY, SKELETON_FLAG, REQUEST_FILE_FLAG, REQUEST_FILE_ARG, EXPAND_ENV_FLAG,
+                     UPLOAD_SUBCOMMAND_SUFFIX, DOWNLOAD_SUBCOMMAND_SUFFIX, has_download_subcommand)
 
     from copy import deepcopy
 
@@ -26,6 +29,10 @@ ${SPLIT_START} ${subcommand_md_filename(resource, method)}
 % if 'description' in mc.m:
 ${mc.m.description | xml_escape}
 % endif # show method description
+% if is_deprecated(mc.m):
+
+**Deprecated**: this method is marked as deprecated by the API, which might remove it. Consider migrating away from it.
+% endif
 % if mc.m.get('scopes'):
 # Scopes
 
@@ -40,11 +47,12 @@ at least one of the following scopes to make a valid call:
 the *${mc.m.scopes[0]}* scope to make a valid call.
 % endif # len(scopes) > 1
 
-If unset, the scope for this method defaults to *${method_default_scope(mc.m)}*.
+If unset, the scope for this method defaults to *${method_default_scope(mc.m, c)}*.
 You can set the scope for this method like this: `${util.program_name()} --${SCOPE_FLAG} <scope> ${mangle_subcommand(resource)} ${mangle_subcommand(method)} ...`
 % endif # have method scopes
 <%
     rprops = [p for p in mc.required_props if not is_request_value_property(mc, p)]
+    project_p, _ = project_param(mc)
     oprops = [p for p in mc.optional_props if not p.get('skip_example', False)]
 
     smd = mc.m.get('supportsMediaDownload', False)
@@ -57,6 +65,12 @@ You can set the scope for this method like this: `${util.program_name()} --${SCO
 % if p.get('repeated'):
     - This property can be specified one or more times
 % endif
+<%
+    templates = p is not project_p and resource_name_templates(c, mc, p)
+%>\
+% if templates:
+    - ${resource_templates_doc(templates)}
+% endif
 % endfor  # each required property (which is not the request value)
 % endif # have required properties
 % if mc.request_value:
@@ -85,6 +99,33 @@ The cursor position is key to comfortably set complex nested structures. The fol
 * The cursor position is set relative to the top-level structure if it starts with `${FIELD_SEP}`, e.g. `-${STRUCT_FLAG} ${FIELD_SEP}s${FIELD_SEP}s`
 * You can also set nested fields without setting the cursor explicitly. For example, to set a value relative to the current cursor position, you would specify `-${STRUCT_FLAG} struct${FIELD_SEP}sub_struct=bar`.
 * You can move the cursor one level up by using `${FIELD_SEP}${FIELD_SEP}`. Each additional `${FIELD_SEP}` moves it up one additional level. E.g. `${FIELD_SEP}${FIELD_SEP}${FIELD_SEP}` would go three levels up.
+* Structures in lists are indexed, like in `-${STRUCT_FLAG} bindings[0]${FIELD_SEP}role=owner`. The index of the length of a list adds a structure to it, and `bindings[]` is its last structure, which is added if the list is empty.
+* Values are added to lists with `+=`, like in `-${STRUCT_FLAG} bindings[]${FIELD_SEP}members+=user:me@example.com`.
+* Entries of maps are set by their key, like in `-${STRUCT_FLAG} labels${FIELD_SEP}env=prod` or `-${STRUCT_FLAG} labels=env=prod`, and the fields of the structures in maps after it, like in `-${STRUCT_FLAG} file-hashes${FIELD_SEP}app${FIELD_SEP}file-hash[0]${FIELD_SEP}type=SHA256`. Keys containing `${FIELD_SEP}` can only be set like `-${STRUCT_FLAG} labels=app${FIELD_SEP}kubernetes${FIELD_SEP}io/name=web`, which only maps of values support.
+
+${'###'} About Values
+
+* Values are read from files with `@`, like in `-${STRUCT_FLAG} description=@notes.txt`, which also works for `-${PARAM_FLAG}` parameters.
+* Values are given as raw JSON with `:=`, like in `-${STRUCT_FLAG} enabled:=true`, `-${STRUCT_FLAG} tags:='["a", "b"]'` or `-${STRUCT_FLAG} labels:='{"env": "prod"}'`. Arrays extend lists, and objects add their entries to maps.
+* Values of lists are split at commas, like in `-${STRUCT_FLAG} tags=a,b`.
+* `\=`, `\,`, `\@` and `\\` stand for `=`, `,`, `@` and `\`, like in `-${STRUCT_FLAG} tags=a\,b`, which adds a single value, or `-${STRUCT_FLAG} labels=a\=b=c`, which sets the entry `a=b`.
+
+${'###'} Request Files
+
+`--${SKELETON_FLAG}` prints the structure with a sample value in each field, instead of calling the method. Once edited, it can be passed with `--${REQUEST_FILE_FLAG} <${REQUEST_FILE_ARG}>`, and the fields set with `-${STRUCT_FLAG}` replace the ones in it, like in
+
+```bash
+${util.program_name()} ${mangle_subcommand(resource)} ${mangle_subcommand(method)} --${SKELETON_FLAG} > request.json
+${util.program_name()} ${mangle_subcommand(resource)} ${mangle_subcommand(method)} ... --${REQUEST_FILE_FLAG} request.json
+```
+
+${'###'} Environment Variables
+
+With `--${EXPAND_ENV_FLAG}`, each `${'${NAME}'}` in the values of `-${STRUCT_FLAG}` and the content of request files is replaced with the environment variable `NAME`, and the call fails if it isn't set. Variables are inserted into request files as they are, so the ones holding strings belong between quotes, and `${'$${'}` stands for a literal `${'${'}`, like in
+
+```bash
+${util.program_name()} ${mangle_subcommand(resource)} ${mangle_subcommand(method)} ... --${REQUEST_FILE_FLAG} request.json -${STRUCT_FLAG} 'etag=${'${ETAG}'}' --${EXPAND_ENV_FLAG}
+```
 
 % endif # have request value
 % if mc.media_params:
@@ -100,12 +141,19 @@ This method supports the upload of data, which *requires* all of the following f
     - **${mp.protocol}** - ${mp.get('description', NO_DESC).split('\n')[0] | xml_escape}
 % endfor # each media param
 * **-${FILE_FLAG} ${escape_html(FILE_ARG)}**
-    - Path to file to upload. It must be seekable.
+    - Path to file to upload, or `-` to stream the standard input, like in `tar c dir | ${util.program_name()} ${mangle_subcommand(resource)} ${mangle_subcommand(method)} ... -${UPLOAD_FLAG} ${protocols[-1]} -`.
+      With the *resumable* protocol, the input is sent in chunks, without holding all of it in memory.
 
 The following flag *may* be set: 
 
 * **-${MIME_FLAG} ${escape_html(MIME_ARG)}**
-    - the mime type, like '${DEFAULT_MIME}', which is the default
+    - the mime type, like 'image/png'. If unset, it is detected from the extension of the file, or its content, and
+      defaults to '${DEFAULT_MIME}', which is the one of the standard input as well
+
+The `${mangle_subcommand(method)}${UPLOAD_SUBCOMMAND_SUFFIX}` subcommand takes the same arguments, but the file to upload
+follows the required arguments instead of `-${UPLOAD_FLAG}`, like in
+`${util.program_name()} ${mangle_subcommand(resource)} ${mangle_subcommand(method)}${UPLOAD_SUBCOMMAND_SUFFIX} ... <${FILE_ARG}>`, and it shows the progress
+of the upload on a terminal.
 
 % endif # have upload capabilities
 % if mc.response_schema or smd:
@@ -119,6 +167,9 @@ The method's return value a JSON encoded structure, which will be written to sta
 
 % if mc.response_schema:
 As this method supports **media download**, you may specify the `-${PARAM_FLAG} alt=media` flag to set the output to be an octet stream of the underlying media. In that case, you will not receive JSON output anymore.
+% if has_download_subcommand(mc, parameters is not UNDEFINED and list(parameters.keys()) or []):
+The `${mangle_subcommand(method)}${DOWNLOAD_SUBCOMMAND_SUFFIX}` subcommand takes the same arguments, and always downloads the media.
+% endif
 % else:
 The method's return value is a byte stream of the downloadable resource.
 % endif # handle response schema
@@ -203,13 +254,14 @@ ${SPLIT_END}
 * `${first_flag}${cursor_arg(mangle_subcommand(fn))}=${field_to_value(f)}`
     - ${f.property.get('description', NO_DESC) | xml_escape, indent_all_but_first_by(2)}
 % if f.container_type == CTYPE_ARRAY:
-    - Each invocation of this argument appends the given value to the array.
+    - Each invocation of this argument appends the given value to the array, which `+=` does explicitly.
 % elif f.container_type == CTYPE_MAP:
-    - the value will be associated with the given `key`
+    - the value will be associated with the given `key`, which may also be set like `${mangle_subcommand(fn)}.key=value`
 % endif # handle container type
 % else:
 <%
-    cursor_tokens.append(mangle_subcommand(fn))
+    cursor_tokens.append(mangle_subcommand(fn) + (f.get(CLI_LIST_KEY, False) and '[0]' or '')
+                         + (f.get(CLI_MAP_KEY, False) and FIELD_SEP + 'key' or ''))
 %>\
 ${self._list_schem_args(f, cursor_tokens, first_flag)}
 <%
@@ -217,6 +269,9 @@ ${self._list_schem_args(f, cursor_tokens, first_flag)}
     if not cursor_tokens:
         cursor_tokens.append(FIELD_SEP)
     cursor_tokens.append(FIELD_SEP) 
+    # the key of a map is left, too
+    if f.get(CLI_MAP_KEY, False):
+        cursor_tokens.append(FIELD_SEP)
 %>\
 % endif
 % endfor
@@ -1,26 +1,36 @@
 <%namespace name="util" file="../../lib/util.mako"/>\
 <%!
     import os
+    import json
 
-    from util import (put_and, supports_scopes, api_index, indent_by, enclose_in, put_and, escape_rust_string)
+    from util import (put_and, supports_scopes, api_index, indent_by, enclose_in, put_and, escape_rust_string,
+                      supports_media_download)
     from cli import (mangle_subcommand, new_method_context, PARAM_FLAG, STRUCT_FLAG, UPLOAD_FLAG, OUTPUT_FLAG, VALUE_ARG,
                      CONFIG_DIR, SCOPE_FLAG, is_request_value_property, FIELD_SEP, docopt_mode, FILE_ARG, MIME_ARG, OUT_ARG,
                      CONFIG_DIR_FLAG, KEY_VALUE_ARG, to_docopt_arg, DEBUG_FLAG, MODE_ARG, SCOPE_ARG,
-                     CONFIG_DIR_ARG, FILE_FLAG, MIME_FLAG, subcommand_md_filename)
+                     CONFIG_DIR_ARG, FILE_FLAG, MIME_FLAG, subcommand_md_filename, STREAM_FLAG, ALL_PAGES_FLAG,
+                     ADAPTIVE_PAGE_SIZE_FLAG, PAGE_SIZE_ARG, SORT_BY_FLAG, SORT_BY_ARG, LIMIT_FLAG, LIMIT_ARG,
+                     PROJECTS_FROM_FLAG, PROJECTS_FROM_ARG, project_param, has_auth_command, AUTH_CMD,
+                     AUTH_SUBCOMMANDS, AUTH_SUBCOMMAND_ARGS, BILLING_PROJECT_FLAG, BILLING_PROJECT_ARG, vulnerability_report_method,
+                     REPORT_CMD, VULNERABILITIES_CMD, IMAGE_FLAG, REPORT_PROJECT_FLAG, REPORT_FORMAT_FLAG,
+                     REPORT_FORMATS, DIFF_CMD, FROM_FLAG, TO_FLAG, DIFF_FORMATS, PROGRESS_JSON_FLAG,
+                     PROGRESS_JSON_ARG, PARAM_FILE_FLAG, PARAM_FILE_ARG, COUNT_FLAG, FAIL_ON_EMPTY_FLAG, CANONICAL_JSON_FLAG,
+                     resource_name_templates, resource_templates_doc, EXTERNAL_ACCOUNT_FLAG, EXTERNAL_ACCOUNT_ARG,
+                     NO_VERIFY_FLAG, EXEC_FLAG, EXEC_ARG, has_describe_command, DESCRIBE_CMD, DESCRIBE_RESOURCE_ARG,
+                     DESCRIBE_METHOD_ARG, OUTPUT_FORMAT_FLAG, OUTPUT_FORMAT_ARG, OUTPUT_FORMATS, ERROR_FORMAT_FLAG,
+                     ERROR_FORMAT_ARG, ERROR_FORMATS, WATCH_FLAG, INTERVAL_FLAG, INTERVAL_ARG, DEFAULT_WATCH_INTERVAL, COLUMNS_FLAG, COLUMNS_ARG,
+                     MAX_WIDTH_FLAG, MAX_WIDTH_ARG, NO_TRUNCATE_FLAG, DEFAULT_MAX_COLUMN_WIDTH, SA_KEY_DIR_FLAG,
+                     SA_KEY_DIR_ARG, AUTH_FLOW_FLAG, AUTH_FLOW_ARG, AUTH_FLOWS, REPLAY_FILE_FLAG, REPLAY_FILE_ARG, RECORD_FILE_FLAG, RECORD_FILE_ARG,
+                     REQUEST_FILE_FLAG, REQUEST_FILE_ARG, SKELETON_FLAG, EXPAND_ENV_FLAG, VERBOSE_FLAG,
+                     UPLOAD_SUBCOMMAND_SUFFIX, DOWNLOAD_SUBCOMMAND_SUFFIX, has_download_subcommand,
+                     OUTPUT_TEMPLATE_FLAG, OUTPUT_TEMPLATE_ARG)
+
+    def arg_doc_base_url(cargo, name, version, make, revision):
+        return cargo.doc_base_url + '/' + os.path.dirname(api_index(cargo.doc_base_url, name, version, make, cargo,
+                                                                    revision, check_exists=False))
 
     def rust_boolean(v):
         return v and 'true' or 'false'
-
-    def rust_optional(v):
-        if v is None:
-            return 'None'
-        if isinstance(v, bool):
-            v = v and 'true' or 'false'
-        elif isinstance(v, str):
-            v = 'r##"%s"##' % v
-        elif isinstance(v, list):
-            v = 'vec![%s]' % ','.join('UploadProtocol::%s' % p.capitalize() for p in v)
-        return 'Some(%s)' % v
 %>\
 <%def name="grammar(c)">\
 ${util.program_name()} [options]
@@ -30,21 +40,30 @@ ${util.program_name()} [options]
 <%
     mc = new_method_context(resource, method, c)
 
+    project_p, _ = project_param(mc)
+
     args = list()
     for p in mc.required_props:
         if is_request_value_property(mc, p):
             continue
+        if p is project_p:
+            args.append('(%s | --%s <%s>)' % (to_docopt_arg(p), PROJECTS_FROM_FLAG, PROJECTS_FROM_ARG))
+            continue
         args.append(to_docopt_arg(p))
     # end for each required property
 
     if mc.request_value:
-        args.append('(-%s %s)...' % (STRUCT_FLAG, '<%s>' % KEY_VALUE_ARG))
+        args.append('((-%s <%s>)... | --%s <%s> [-%s <%s>]...)' % (STRUCT_FLAG, KEY_VALUE_ARG, REQUEST_FILE_FLAG,
+                                                                   REQUEST_FILE_ARG, STRUCT_FLAG, KEY_VALUE_ARG))
+        args.append('[--%s]' % EXPAND_ENV_FLAG)
     # end request_value
 
+    upload_arg = None
     if mc.media_params:
         upload_protocols = [mp.protocol for mp in mc.media_params]
         mode = docopt_mode(upload_protocols)
-        args.append('(-%s %s -%s <%s> [-%s <%s>])' % (UPLOAD_FLAG, mode, FILE_FLAG, FILE_ARG, MIME_FLAG, MIME_ARG))
+        upload_arg = '(-%s %s -%s <%s> [-%s <%s>])' % (UPLOAD_FLAG, mode, FILE_FLAG, FILE_ARG, MIME_FLAG, MIME_ARG)
+        args.append(upload_arg)
     # end upload handling
 
     if mc.optional_props or parameters is not UNDEFINED:
@@ -54,10 +73,36 @@ ${util.program_name()} [options]
     if mc.response_schema or mc.m.get('supportsMediaDownload', False):
         args.append('[-%s <%s>]' % (OUTPUT_FLAG, OUT_ARG))
     # handle output
+
+    # the upload subcommand takes the file as argument instead
+    upload_args = [arg == upload_arg and '<%s> [-%s <%s>]' % (FILE_ARG, MIME_FLAG, MIME_ARG) or arg for arg in args]
 %>\
                 ${mangle_subcommand(method)} ${' '.join(args)}
+    % if mc.request_value:
+                ${mangle_subcommand(method)} --${SKELETON_FLAG}
+    % endif
+    % if mc.media_params:
+                ${mangle_subcommand(method)}${UPLOAD_SUBCOMMAND_SUFFIX} ${' '.join(upload_args)}
+    % endif
+    % if has_download_subcommand(mc, parameters is not UNDEFINED and list(parameters.keys()) or []):
+                ${mangle_subcommand(method)}${DOWNLOAD_SUBCOMMAND_SUFFIX} ${' '.join(args)}
+    % endif
     % endfor # each method
 % endfor # end for each resource
+% if has_describe_command(c):
+        ${DESCRIBE_CMD} [<${DESCRIBE_RESOURCE_ARG}> [<${DESCRIBE_METHOD_ARG}>]]
+% endif
+% if has_auth_command(c, auth):
+        ${AUTH_CMD}
+    % for auth_name, alias, desc in AUTH_SUBCOMMANDS:
+                ${auth_name}${''.join(' [--%s <%s>]%s' % (flag, value_name, multiple and '...' or '') for flag, value_name, _, multiple in AUTH_SUBCOMMAND_ARGS.get(auth_name, ()))}
+    % endfor
+% endif
+% if vulnerability_report_method(c, api):
+        ${REPORT_CMD}
+                ${VULNERABILITIES_CMD} --${IMAGE_FLAG} <url> [--${REPORT_PROJECT_FLAG} <id>] [--${REPORT_FORMAT_FLAG} ${'|'.join(REPORT_FORMATS)}] [-${OUTPUT_FLAG} <${OUT_ARG}>]
+        ${DIFF_CMD} --${FROM_FLAG} <url> --${TO_FLAG} <url> [--${REPORT_PROJECT_FLAG} <id>] [--${REPORT_FORMAT_FLAG} ${'|'.join(DIFF_FORMATS)}] [-${OUTPUT_FLAG} <${OUT_ARG}>]
+% endif
   ${util.program_name()} --help
 
 Configuration:
@@ -66,20 +111,97 @@ Configuration:
             Specify the authentication a method should be executed in. Each scope
             requires the user to grant this application permission to use it.
             If unset, it defaults to the shortest scope url for a particular method.
+  [--${EXTERNAL_ACCOUNT_FLAG} <${EXTERNAL_ACCOUNT_ARG}>]
+            Authorize calls with the credentials of an external account, whose
+            configuration file names the executable supplying them.
+  [--${SA_KEY_DIR_FLAG} <${SA_KEY_DIR_ARG}>]
+            Authorize calls with the first service account key in the given
+            directory the token endpoint accepts, newest first.
+  [--${AUTH_FLOW_FLAG} ${'|'.join(AUTH_FLOWS)}]
+            Authorize with a browser redirecting to a local server, or with a code
+            to enter on any device, for hosts without browser or reachable port.
 % endif scopes
   --${CONFIG_DIR_FLAG} <${CONFIG_DIR_ARG}>
             A directory into which we will store our persistent data. Defaults to
             a user-writable directory that we will create during the first invocation.
             [default: ${CONFIG_DIR}]
+  [--${STREAM_FLAG}]
+            Write each item of a list response as a single line of JSON as soon
+            as it was decoded, instead of pretty-printing the whole response.
+  [--${ALL_PAGES_FLAG}]
+            Keep fetching the pages of list responses until the last one was
+            received.
+  [--${ADAPTIVE_PAGE_SIZE_FLAG} <${PAGE_SIZE_ARG}>]
+            Start fetching all pages with the given amount of items per page, and
+            adapt it to how fast the server delivers them.
+  [--${SORT_BY_FLAG} <${SORT_BY_ARG}>]
+            Sort the items of list responses by the given field, like `name` or
+            `nested.field:desc` to sort them in descending order.
+  [--${LIMIT_FLAG} <${LIMIT_ARG}>]
+            Write no more than the given amount of items of list responses.
+  [--${COUNT_FLAG}]
+            Write only the amount of items of list responses, across all received
+            pages, instead of the items themselves.
+  [--${FAIL_ON_EMPTY_FLAG}]
+            Exit with a non-zero status if a list response contains no items.
+  [--${VERBOSE_FLAG}]
+            Write how many items of list responses were fetched to stderr, and
+            how many there are in total, if the API reports it.
+  [--${CANONICAL_JSON_FLAG}]
+            Sort the keys of all JSON objects, and write numbers without fraction
+            as integers, to make the output of repeated calls comparable.
+  [--${OUTPUT_FORMAT_FLAG} ${'|'.join(OUTPUT_FORMATS)}]
+            Write results as JSON, or as a table with a row per item of list
+            responses, whose nested fields are flattened into columns.
+  [--${COLUMNS_FLAG} <${COLUMNS_ARG}>]
+            The comma-separated columns of tables, as paths into the JSON of each
+            row, like `name,metadata.createTime`.
+  [--${MAX_WIDTH_FLAG} <${MAX_WIDTH_ARG}>]
+            Truncate the cells of tables to the given amount of characters.
+            [default: ${DEFAULT_MAX_COLUMN_WIDTH}]
+  [--${NO_TRUNCATE_FLAG}]
+            Never truncate the cells of tables.
+  [--${OUTPUT_TEMPLATE_FLAG} <${OUTPUT_TEMPLATE_ARG}>]
+            Render results with the given template file, like a Markdown or HTML
+            report, instead of writing them as JSON.
+  [--${WATCH_FLAG}]
+            Poll list responses until interrupted, and write only the items which
+            are new or changed since the previous poll, as lines of JSON.
+  [--${INTERVAL_FLAG} <${INTERVAL_ARG}>]
+            The time to wait between the polls of --${WATCH_FLAG}, like 30s or 5m.
+            [default: ${DEFAULT_WATCH_INTERVAL}]
+  [--${ERROR_FORMAT_FLAG} ${'|'.join(ERROR_FORMATS)}]
+            Write failures to stderr as text, or as a line of JSON each, with the
+            exit code, HTTP status, reason, message and request id.
+  [--${EXEC_FLAG} <${EXEC_ARG}>]
+            Pipe each response, or each item when streaming, to the standard input
+            of the given shell command, and write its output instead.
+  [--${BILLING_PROJECT_FLAG} <${BILLING_PROJECT_ARG}>]
+            The project to bill for all calls, and whose quota they count against,
+            instead of the one the credentials belong to.
+  [--${PROGRESS_JSON_FLAG} <${PROGRESS_JSON_ARG}>]
+            Write progress events, like fetched pages, retries and the progress of
+            uploads, as lines of JSON to the given file, or to stderr if it is '-'.
+  [--${PARAM_FILE_FLAG} <${PARAM_FILE_ARG}>]
+            Load default values of the -${VALUE_ARG} parameters of all calls from the given
+            JSON or TOML file. Parameters set on the command-line take precedence.
+  [--${REPLAY_FILE_FLAG} <${REPLAY_FILE_ARG}>]
+            Respond to all calls with the responses saved to the given cassette by
+            --${RECORD_FILE_FLAG}, without credentials or network.
+  [--${RECORD_FILE_FLAG} <${RECORD_FILE_ARG}>]
+            Save the requests and responses of all calls, without their headers, to
+            the given cassette.
+% if supports_media_download(c):
+  [--${NO_VERIFY_FLAG}]
+            Don't verify downloaded media against the checksums sent by the server.
+% endif
 </%def>
 
 
 
 <%def name="new(c)" buffered="True">\
 <%
-    doc_base_url = cargo.doc_base_url + '/' + os.path.dirname(api_index(cargo.doc_base_url, name,
-                                                                        version, make, cargo, revision, check_exists=False))
-    url_info = "All documentation details can be found at " + doc_base_url
+    url_info = "All documentation details can be found at " + arg_doc_base_url(cargo, name, version, make, revision)
 
     # list of tuples
     # (0) = long name
@@ -96,6 +218,31 @@ Configuration:
             SCOPE_ARG,
             True
         ))
+        global_args.append((
+            EXTERNAL_ACCOUNT_FLAG,
+            "Authorize calls with the credentials of an external account, whose configuration file names the "
+            "executable supplying them, instead of the ones of the user. Running it must be allowed by setting "
+            "GOOGLE_EXTERNAL_ACCOUNT_ALLOW_EXECUTABLES to 1",
+            EXTERNAL_ACCOUNT_ARG,
+            False
+        ))
+        global_args.append((
+            SA_KEY_DIR_FLAG,
+            "Authorize calls with the service account keys in the JSON files of the given directory, instead of the "
+            "credentials of the user. The most recently modified key is tried first, and keys the token endpoint "
+            "rejects, like revoked or expired ones, are skipped, which keeps calls working while keys are rotated",
+            SA_KEY_DIR_ARG,
+            False
+        ))
+        global_args.append((
+            AUTH_FLOW_FLAG,
+            "How the user grants permission for a scope the first time it is used: by consenting in a browser, which "
+            "redirects to a local server, or by entering a code shown on stderr at a verification URL on any device, "
+            "which works on hosts without browser or reachable port. The device flow needs the application secret "
+            "of a client for TVs and limited input devices. One of %s" % ', '.join(AUTH_FLOWS),
+            AUTH_FLOW_ARG,
+            False
+        ))
     # end add scope arg
     global_args.append((
         CONFIG_DIR_FLAG,
@@ -112,6 +259,199 @@ Configuration:
         None,
         False,
     ))
+
+    global_args.append((
+        STREAM_FLAG,
+        "Write each item of a list response as a single line of JSON as soon as it was decoded",
+        None,
+        False,
+    ))
+
+    global_args.append((
+        ALL_PAGES_FLAG,
+        "Keep fetching the pages of list responses until the last one was received",
+        None,
+        False,
+    ))
+
+    global_args.append((
+        ADAPTIVE_PAGE_SIZE_FLAG,
+        "Start fetching all pages with the given amount of items per page, which is halved whenever a page is too "
+        "large to be delivered in time, and doubled up to the maximum of the API whenever a page arrives quickly",
+        PAGE_SIZE_ARG,
+        False,
+    ))
+
+    global_args.append((
+        SORT_BY_FLAG,
+        "Sort the items of list responses by the given field, like `name` or `nested.field:desc` to sort them in "
+        "descending order. All pages are received before the items are written",
+        SORT_BY_ARG,
+        False,
+    ))
+
+    global_args.append((
+        LIMIT_FLAG,
+        "Write no more than the given amount of items of list responses, after sorting them",
+        LIMIT_ARG,
+        False,
+    ))
+
+    global_args.append((
+        COUNT_FLAG,
+        "Write only the amount of items of list responses, across all received pages, instead of the items "
+        "themselves",
+        None,
+        False,
+    ))
+
+    global_args.append((
+        FAIL_ON_EMPTY_FLAG,
+        "Exit with a non-zero status if a list response contains no items",
+        None,
+        False,
+    ))
+
+    global_args.append((
+        VERBOSE_FLAG,
+        "Write how many items of list responses were fetched to stderr once the last page was received, like "
+        "`10 of 42 items fetched`, if the API reports their total",
+        None,
+        False,
+    ))
+
+    global_args.append((
+        CANONICAL_JSON_FLAG,
+        "Sort the keys of all JSON objects, and write numbers without fraction as integers, to make the output of "
+        "repeated calls comparable",
+        None,
+        False,
+    ))
+
+    global_args.append((
+        OUTPUT_FORMAT_FLAG,
+        "Write results as pretty-printed JSON, or as a table or CSV with a row per item of list responses, whose nested "
+        "fields are flattened into columns like `metadata.name`. Tables and CSV are written once all pages were received. "
+        "One of %s"
+        % ', '.join(OUTPUT_FORMATS),
+        OUTPUT_FORMAT_ARG,
+        False,
+    ))
+
+    global_args.append((
+        COLUMNS_FLAG,
+        "The comma-separated columns of tables, as paths into the JSON of each row, like `name,metadata.createTime`, "
+        "instead of all fields",
+        COLUMNS_ARG,
+        False,
+    ))
+
+    global_args.append((
+        MAX_WIDTH_FLAG,
+        "Truncate the cells of tables to the given amount of characters [default: %s]" % DEFAULT_MAX_COLUMN_WIDTH,
+        MAX_WIDTH_ARG,
+        False,
+    ))
+
+    global_args.append((
+        NO_TRUNCATE_FLAG,
+        "Never truncate the cells of tables, which takes precedence over --%s" % MAX_WIDTH_FLAG,
+        None,
+        False,
+    ))
+
+    global_args.append((
+        OUTPUT_TEMPLATE_FLAG,
+        "Render results with the given template file, like a Markdown or HTML report, instead of writing them as JSON. "
+        "It inserts fields of the response like `{response.name}`, and loops over items like "
+        "`{{ for item in response.items }}{item.name}{{ endfor }}`, once all pages were received. "
+        "Values are HTML-escaped in `.html` templates",
+        OUTPUT_TEMPLATE_ARG,
+        False,
+    ))
+
+    global_args.append((
+        WATCH_FLAG,
+        "Poll list responses again and again, until interrupted, and write only the items which are new or changed "
+        "since the previous poll, by their name and update time, as single lines of JSON. Implies --%s and --%s"
+        % (STREAM_FLAG, ALL_PAGES_FLAG),
+        None,
+        False,
+    ))
+
+    global_args.append((
+        INTERVAL_FLAG,
+        "The time to wait between the polls of --%s, like `30s`, `5m` or `500ms` [default: %s]"
+        % (WATCH_FLAG, DEFAULT_WATCH_INTERVAL),
+        INTERVAL_ARG,
+        False,
+    ))
+
+    global_args.append((
+        ERROR_FORMAT_FLAG,
+        "Write failures to stderr as text, or as a single line of JSON each, with the exit code, the HTTP status, the "
+        "reason and message of the error, and the id of the request. One of %s" % ', '.join(ERROR_FORMATS),
+        ERROR_FORMAT_ARG,
+        False,
+    ))
+
+    global_args.append((
+        EXEC_FLAG,
+        "Pipe each response, or each item of list responses when streaming, to the standard input of the given "
+        "shell command, which runs once per result, and write what it outputs instead. The call fails if the "
+        "command does not exit successfully",
+        EXEC_ARG,
+        False,
+    ))
+
+    global_args.append((
+        BILLING_PROJECT_FLAG,
+        "The project to bill for all calls, and whose quota they count against, instead of the one the credentials "
+        "belong to",
+        BILLING_PROJECT_ARG,
+        False,
+    ))
+
+    global_args.append((
+        PROGRESS_JSON_FLAG,
+        "Write progress events, like fetched pages, processed items, retries and the progress of uploads, as lines "
+        "of JSON to the given file, or to stderr if it is '-'",
+        PROGRESS_JSON_ARG,
+        False,
+    ))
+
+    global_args.append((
+        PARAM_FILE_FLAG,
+        "Load default values of the -%s parameters of all calls from the given JSON or TOML file, instead of the "
+        "one in the config directory. Parameters set on the command-line take precedence" % PARAM_FLAG,
+        PARAM_FILE_ARG,
+        False,
+    ))
+
+    global_args.append((
+        REPLAY_FILE_FLAG,
+        "Respond to all calls with the responses saved to the given cassette by --%s, in the order they were "
+        "recorded, without credentials or network. Takes precedence over --%s" % (RECORD_FILE_FLAG, RECORD_FILE_FLAG),
+        REPLAY_FILE_ARG,
+        False,
+    ))
+
+    global_args.append((
+        RECORD_FILE_FLAG,
+        "Save the requests and responses of all calls to the given cassette, replacing it, to replay them with "
+        "--%s. The headers of requests aren't saved, which keeps the credentials out of it" % REPLAY_FILE_FLAG,
+        RECORD_FILE_ARG,
+        False,
+    ))
+
+    if supports_media_download(c):
+        global_args.append((
+            NO_VERIFY_FLAG,
+            "Don't verify downloaded media against the crc32c or md5 checksums sent by the server, which otherwise "
+            "makes the download fail if they don't match",
+            None,
+            False,
+        ))
 %>\
 <%
     have_media_params = False
@@ -128,96 +468,7 @@ Configuration:
 % if have_media_params:
 let upload_value_names = ["${MODE_ARG}", "${FILE_ARG}"];
 % endif
-let arg_data = [
-% for resource in sorted(c.rta_map.keys()):
-<%
-    methods = sorted(c.rta_map[resource])
-%>\
-<%block filter="indent_by(4)">\
-("${mangle_subcommand(resource)}", "methods: ${put_and(["'%s'" % mangle_subcommand(m) for m in methods])}", vec![
-    % for method in methods:
-<%
-    mc = new_method_context(resource, method, c)
-
-    # A list of tuples
-    # (0) = short flag, like -c
-    # (1) = param description or None
-    # (2) = argument name, or None if there is no argument
-    # (3) = is required (bool)
-    # (4) = allow multi-use
-    args = list()
-    for p in mc.required_props:
-        if is_request_value_property(mc, p):
-            continue
-        args.append((
-            None,
-            p.get('description'),
-            mangle_subcommand(p.name),
-            True,
-            False,
-        ))
-    # end for each required property
-
-    if mc.request_value:
-        args.append((
-                STRUCT_FLAG,
-                "Set various fields of the request structure, matching the key=value form",
-                KEY_VALUE_ARG,
-                True,
-                True,
-            ))
-    # end request_value
-
-    if mc.media_params:
-        args.append((
-                UPLOAD_FLAG,
-                "Specify the upload protocol (%s) and the file to upload" % '|'.join(mp.protocol for mp in mc.media_params),
-                MODE_ARG,
-                True,
-                True,
-            ))
-    # end upload handling
-
-    if mc.optional_props or parameters is not UNDEFINED:
-        args.append((
-                PARAM_FLAG,
-                "Set various optional parameters, matching the key=value form",
-                VALUE_ARG,
-                False,
-                True,
-            ))
-    # end parameters
-
-    if mc.response_schema or mc.m.get('supportsMediaDownload', False):
-        args.append((
-                OUTPUT_FLAG,
-                "Specify the file into which to write the program's output",
-                OUT_ARG,
-                False,
-                False,
-            ))
-    # handle output
-%>\
-    ("${mangle_subcommand(method)}",
-            ${rust_optional(mc.m.get('description'))},
-            "Details at ${doc_base_url}/${os.path.splitext(subcommand_md_filename(resource, method))[0]}",
-          vec![
-            % for flag, desc, arg_name, required, multi in args:
-            (${rust_optional(arg_name)},
-             ${rust_optional(flag)},
-             ${rust_optional(desc)},
-             ${rust_optional(required)},
-             ${rust_optional(multi)}),
-            % if not loop.last:
-
-            % endif
-            % endfor
-          ]),
-    % endfor # each method
-    ]),
-</%block>
-% endfor # end for each resource
-];
+let arg_data = client::load_arg_data(include_str!("arg_data.json"));
 
 let mut app = App::new("${util.program_name()}")
 <%block filter="indent_by(7)">\
@@ -240,42 +491,20 @@ let mut app = App::new("${util.program_name()}")
 % endif
 % endfor
 
-for &(main_command_name, about, ref subcommands) in arg_data.iter() {
-    let mut mcmd = SubCommand::with_name(main_command_name).about(about);
+for command in &arg_data {
+    let mut mcmd = SubCommand::with_name(&command.name).about(&*command.about);
 
-    for &(sub_command_name, ref desc, url_info, ref args) in subcommands {
-        let mut scmd = SubCommand::with_name(sub_command_name);
-        if let &Some(desc) = desc {
-            scmd = scmd.about(desc);
+    for method in &command.methods {
+        let mut scmd = SubCommand::with_name(&method.name);
+        if let Some(ref about) = method.about {
+            scmd = scmd.about(&**about);
         }
-        scmd = scmd.after_help(url_info);
-
-        for &(ref arg_name, ref flag, ref desc, ref required, ref multi) in args {
-            let arg_name_str =
-                match (arg_name, flag) {
-                        (&Some(an), _       ) => an,
-                        (_        , &Some(f)) => f,
-                         _                    => unreachable!(),
-                 };
-            let mut arg = Arg::with_name(arg_name_str)
-                              .empty_values(false);
-            if let &Some(short_flag) = flag {
-                arg = arg.short(short_flag);
-            }
-            if let &Some(desc) = desc {
-                arg = arg.help(desc);
-            }
-            if arg_name.is_some() && flag.is_some() {
-                arg = arg.takes_value(true);
-            }
-            if let &Some(required) = required {
-                arg = arg.required(required);
-            }
-            if let &Some(multi) = multi {
-                arg = arg.multiple(multi);
-            }
+        scmd = scmd.after_help(&*method.after_help);
+
+        for spec in &method.args {
             % if have_media_params:
-            if arg_name_str == "${MODE_ARG}" {
+            let mut arg = spec.to_arg();
+            if spec.id() == "${MODE_ARG}" {
                 arg = arg.number_of_values(2);
                 arg = arg.value_names(&upload_value_names);
 
@@ -283,15 +512,298 @@ for &(main_command_name, about, ref subcommands) in arg_data.iter() {
                                     .short("${MIME_FLAG}")
                                     .requires("${MODE_ARG}")
                                     .required(false)
-                                    .help("The file's mime time, like 'application/octet-stream'")
+                                    .help("The file's mime type, like 'image/png'. Detected from the file's extension or content if unset")
                                     .takes_value(true));
             }
-            % endif
             scmd = scmd.arg(arg);
+            % else:
+            scmd = scmd.arg(spec.to_arg());
+            % endif
         }
         mcmd = mcmd.subcommand(scmd);
     }
     app = app.subcommand(mcmd);
 }
+
+// the options taking a value, and the commands which aliases can't replace
+let global_value_flags = [${', '.join('"%s"' % flag for flag, _, arg_name, _ in global_args if arg_name)}];
+let mut commands: ${"Vec<&str>"} = arg_data.iter().map(|command| command.name.as_str()).collect();
+commands.push("help");
+% if has_describe_command(c):
+
+commands.push("${DESCRIBE_CMD}");
+app = app.subcommand(SubCommand::with_name("${DESCRIBE_CMD}")
+                        .about("Describe the commands as JSON, with their arguments, parameters, request fields and scopes, for tools building upon this program")
+                        .arg(Arg::with_name("${DESCRIBE_RESOURCE_ARG}")
+                                .help("Describe only the methods of the given resource, like the first argument of calls")
+                                .index(1))
+                        .arg(Arg::with_name("${DESCRIBE_METHOD_ARG}")
+                                .help("Describe only the given method of the resource")
+                                .requires("${DESCRIBE_RESOURCE_ARG}")
+                                .index(2)));
+% endif
+% if has_auth_command(c, auth):
+
+commands.push("${AUTH_CMD}");
+app = app.subcommand(SubCommand::with_name("${AUTH_CMD}")
+                        .about("Manage the tokens persisted in the configuration directory. Use --${SCOPE_FLAG} to select the scopes.")
+    % for auth_name, alias, desc in AUTH_SUBCOMMANDS:
+                        .subcommand(SubCommand::with_name("${auth_name}")\
+        % if alias:
+.alias("${alias}")\
+        % endif
+.about("${desc}")\
+        % for flag, value_name, arg_desc, multiple in AUTH_SUBCOMMAND_ARGS.get(auth_name, ()):
+
+                            .arg(Arg::with_name("${flag}")
+                                    .long("${flag}")
+                                    .value_name("${value_name}")
+                                    .help("${arg_desc}")
+                                    .takes_value(true)
+                                    .multiple(${rust_boolean(multiple)}))\
+        % endfor
+)${loop.last and ');' or ''}
+    % endfor
+% endif
+% if vulnerability_report_method(c, api):
+
+commands.extend(&["${REPORT_CMD}", "${DIFF_CMD}"]);
+app = app.subcommand(SubCommand::with_name("${REPORT_CMD}")
+                        .about("Summarize the results of the analysis of a resource")
+                        .subcommand(SubCommand::with_name("${VULNERABILITIES_CMD}")
+                            .about("Group the vulnerabilities found in an image by severity and CVE, and render them as table, SARIF or VEX")
+                            .arg(Arg::with_name("${IMAGE_FLAG}")
+                                    .long("${IMAGE_FLAG}")
+                                    .help("The url of the image, like 'https://gcr.io/<project>/<image>@sha256:<digest>'")
+                                    .required(true)
+                                    .takes_value(true))
+                            .arg(Arg::with_name("${REPORT_PROJECT_FLAG}")
+                                    .long("${REPORT_PROJECT_FLAG}")
+                                    .help("The project holding the occurrences. Defaults to the one hosting the image")
+                                    .takes_value(true))
+                            .arg(Arg::with_name("${REPORT_FORMAT_FLAG}")
+                                    .long("${REPORT_FORMAT_FLAG}")
+                                    .help("Render a summary table, a SARIF log for code scanning dashboards, or a CycloneDX VEX document")
+                                    .possible_values(&[${', '.join('"%s"' % f for f in REPORT_FORMATS)}])
+                                    .default_value("${REPORT_FORMATS[0]}")
+                                    .takes_value(true))
+                            .arg(Arg::with_name("${OUT_ARG}")
+                                    .short("${OUTPUT_FLAG}")
+                                    .help("Specify the file into which to write the program's output")
+                                    .takes_value(true))));
+
+app = app.subcommand(SubCommand::with_name("${DIFF_CMD}")
+                        .about("Compare the vulnerabilities of two images, like two digests, and show the added, removed and changed ones")
+                        .arg(Arg::with_name("${FROM_FLAG}")
+                                .long("${FROM_FLAG}")
+                                .help("The url of the old image, like 'https://gcr.io/<project>/<image>@sha256:<digest>'")
+                                .required(true)
+                                .takes_value(true))
+                        .arg(Arg::with_name("${TO_FLAG}")
+                                .long("${TO_FLAG}")
+                                .help("The url of the new image")
+                                .required(true)
+                                .takes_value(true))
+                        .arg(Arg::with_name("${REPORT_PROJECT_FLAG}")
+                                .long("${REPORT_PROJECT_FLAG}")
+                                .help("The project holding the occurrences. Defaults to the one hosting each image")
+                                .takes_value(true))
+                        .arg(Arg::with_name("${REPORT_FORMAT_FLAG}")
+                                .long("${REPORT_FORMAT_FLAG}")
+                                .help("Render a table, or JSON for further processing")
+                                .possible_values(&[${', '.join('"%s"' % f for f in DIFF_FORMATS)}])
+                                .default_value("${DIFF_FORMATS[0]}")
+                                .takes_value(true))
+                        .arg(Arg::with_name("${OUT_ARG}")
+                                .short("${OUTPUT_FLAG}")
+                                .help("Specify the file into which to write the program's output")
+                                .takes_value(true)));
+% endif
 </%block>
 </%def>
+
+## The arguments of all commands as compact JSON, which the program embeds and reads with `load_arg_data()`, as
+## compiling the equivalent literal takes a lot longer. See `CommandArgs` in the client module for its structure.
+<%def name="arg_data(c)">\
+<%
+    doc_base_url = arg_doc_base_url(cargo, name, version, make, revision)
+
+    def arg_spec(flag, desc, arg_name, required, multi, required_unless=()):
+        spec = (arg_name, flag, desc, required, multi)
+        # the arguments it isn't required with are left out if there are none, which keeps the file compact
+        if required and required_unless:
+            spec += (list(required_unless),)
+        return spec
+
+    # the subcommands dedicated to media can't write the skeleton of the request
+    def without_skeleton(args):
+        return [arg[:5] + ([a for a in arg[5] if a != SKELETON_FLAG],) if len(arg) > 5 else arg
+                for arg in args if arg[0] != SKELETON_FLAG]
+
+    global_params = parameters is not UNDEFINED and list(parameters.keys()) or []
+    commands = list()
+    for resource in sorted(c.rta_map.keys()):
+        methods = sorted(c.rta_map[resource])
+        command_methods = list()
+        commands.append((
+            mangle_subcommand(resource),
+            "methods: %s" % put_and(["'%s'" % mangle_subcommand(m) for m in methods]),
+            command_methods,
+        ))
+        for method in methods:
+            mc = new_method_context(resource, method, c)
+
+            project_p, _ = project_param(mc)
+
+            # A list of tuples
+            # (0) = flag, like -c, or --long-flag if it has more than one character
+            # (1) = param description or None
+            # (2) = argument name, or None if there is no argument
+            # (3) = is required (bool)
+            # (4) = allow multi-use
+            # (5) = the arguments it isn't required with, which only methods taking a request have
+            args = list()
+            # writing the skeleton of the request needs no other argument
+            unless_skeleton = mc.request_value and [SKELETON_FLAG] or []
+            for p in mc.required_props:
+                if is_request_value_property(mc, p):
+                    continue
+                desc = p.get('description')
+                templates = p is not project_p and resource_name_templates(c, mc, p)
+                if templates:
+                    desc = ' '.join(d for d in (desc, resource_templates_doc(templates)) if d)
+                args.append((
+                    None,
+                    desc,
+                    mangle_subcommand(p.name),
+                    # may be provided by --projects-from instead, which is verified when the call is made
+                    p is not project_p,
+                    False,
+                    unless_skeleton,
+                ))
+            # end for each required property
+
+            if project_p:
+                args.append((
+                        PROJECTS_FROM_FLAG,
+                        "Run the call for each project id listed in the given file, one per line, "
+                        "and tag each result with the id of its project",
+                        PROJECTS_FROM_ARG,
+                        False,
+                        False,
+                    ))
+            # end project fan-out
+
+            if mc.request_value:
+                args.append((
+                        STRUCT_FLAG,
+                        "Set various fields of the request structure, matching the key=value form",
+                        KEY_VALUE_ARG,
+                        True,
+                        True,
+                        [REQUEST_FILE_ARG, SKELETON_FLAG],
+                    ))
+                args.append((
+                        REQUEST_FILE_FLAG,
+                        "Read the request structure from the given JSON file, like an edited one of --%s. "
+                        "Fields set with -%s take precedence" % (SKELETON_FLAG, STRUCT_FLAG),
+                        REQUEST_FILE_ARG,
+                        False,
+                        False,
+                    ))
+                args.append((
+                        EXPAND_ENV_FLAG,
+                        "Replace each ${NAME} in the values of -%s and the content of --%s with the environment "
+                        "variable NAME, failing if it isn't set. Write $${ for a literal ${" % (STRUCT_FLAG, REQUEST_FILE_FLAG),
+                        None,
+                        False,
+                        False,
+                    ))
+                args.append((
+                        SKELETON_FLAG,
+                        "Write the request structure as JSON with a placeholder value for each field, to edit it and "
+                        "pass it with --%s, instead of calling the method" % REQUEST_FILE_FLAG,
+                        None,
+                        False,
+                        False,
+                    ))
+            # end request_value
+
+            if mc.media_params:
+                args.append((
+                        UPLOAD_FLAG,
+                        "Specify the upload protocol (%s) and the file to upload, or - to stream the standard input" % '|'.join(mp.protocol for mp in mc.media_params),
+                        MODE_ARG,
+                        True,
+                        True,
+                        unless_skeleton,
+                    ))
+            # end upload handling
+
+            if mc.optional_props or parameters is not UNDEFINED:
+                args.append((
+                        PARAM_FLAG,
+                        "Set various optional parameters, matching the key=value form",
+                        VALUE_ARG,
+                        False,
+                        True,
+                    ))
+            # end parameters
+
+            if mc.response_schema or mc.m.get('supportsMediaDownload', False):
+                args.append((
+                        OUTPUT_FLAG,
+                        "Specify the file into which to write the program's output",
+                        OUT_ARG,
+                        False,
+                        False,
+                    ))
+            # handle output
+
+            after_help = "Details at %s/%s" % (doc_base_url, os.path.splitext(subcommand_md_filename(resource, method))[0])
+            command_methods.append((
+                mangle_subcommand(method),
+                mc.m.get('description'),
+                after_help,
+                [arg_spec(*arg) for arg in args],
+            ))
+
+            # the upload subcommand takes the file after the required arguments
+            if mc.media_params:
+                upload_args = [arg for arg in without_skeleton(args) if arg[0] != UPLOAD_FLAG]
+                positionals = len([arg for arg in upload_args if arg[0] is None])
+                upload_args.insert(positionals, (
+                        None,
+                        "The file to upload, or - to stream the standard input",
+                        FILE_ARG,
+                        True,
+                        False,
+                    ))
+                upload_args.append((
+                        MIME_FLAG,
+                        "The file's mime type, like 'image/png'. Detected from the file's extension or content if unset",
+                        MIME_ARG,
+                        False,
+                        False,
+                    ))
+                command_methods.append((
+                    mangle_subcommand(method) + UPLOAD_SUBCOMMAND_SUFFIX,
+                    "Upload the given file with '%s', showing its progress" % mangle_subcommand(method),
+                    after_help,
+                    [arg_spec(*arg) for arg in upload_args],
+                ))
+            # end upload subcommand
+
+            if has_download_subcommand(mc, global_params):
+                command_methods.append((
+                    mangle_subcommand(method) + DOWNLOAD_SUBCOMMAND_SUFFIX,
+                    "Download the media of '%s' instead of its response, showing its progress" % mangle_subcommand(method),
+                    after_help,
+                    [arg_spec(*arg) for arg in without_skeleton(args)],
+                ))
+            # end download subcommand
+        # end for each method
+    # end for each resource
+%>\
+${json.dumps(commands, separators=(',', ':'))}
+</%def>
@@ -1,15 +1,25 @@
 <%namespace name="util" file="../../lib/util.mako"/>\
 <%!
     from util import (hub_type, mangle_ident, indent_all_but_first_by, activity_rust_type, setter_fn_name, ADD_PARAM_FN,
-                      upload_action_fn, is_schema_with_optionals, schema_markers, indent_by, method_default_scope,
-                      ADD_SCOPE_FN, TREF, enclose_in)
+                      upload_action_fn, indent_by, method_default_scope, STREAM_UPLOAD_SUFFIX,
+                      ADD_SCOPE_FN, TREF, enclose_in, is_deprecated, supports_scopes, supports_media_download,
+                      schema_sample, rust_raw_string)
     from cli import (mangle_subcommand, new_method_context, PARAM_FLAG, STRUCT_FLAG, OUTPUT_FLAG, VALUE_ARG,
                      CONFIG_DIR, SCOPE_FLAG, is_request_value_property, FIELD_SEP, docopt_mode, FILE_ARG, MIME_ARG, OUT_ARG,
                      call_method_ident, POD_TYPES, opt_value, ident, JSON_TYPE_VALUE_MAP,
-                     KEY_VALUE_ARG, to_cli_schema, SchemaEntry, CTYPE_POD, actual_json_type, CTYPE_MAP, CTYPE_ARRAY,
+                     KEY_VALUE_ARG, to_cli_schema, CTYPE_POD, actual_json_type, CTYPE_MAP, CTYPE_ARRAY,
                      application_secret_path, CONFIG_DIR_FLAG, req_value, MODE_ARG,
-                     opt_values, SCOPE_ARG, CONFIG_DIR_ARG, DEFAULT_MIME, field_vec, comma_sep_fields, JSON_TYPE_TO_ENUM_MAP,
-                     CTYPE_TO_ENUM_MAP)
+                     opt_values, SCOPE_ARG, CONFIG_DIR_ARG, DEFAULT_MIME, field_vec, comma_sep_fields,
+                     list_items_property, page_token_param, page_size_param, project_param,
+                     PROJECTS_FROM_ARG, has_auth_command, default_auth_scope, AUTH_CMD, BILLING_PROJECT_ARG,
+                     AUTH_SCOPES_FLAG, AUDIENCE_FLAG, DEPRECATION_WARNING, vulnerability_report_method, ema_avail_methods, REPORT_CMD,
+                     VULNERABILITIES_CMD, IMAGE_FLAG, REPORT_PROJECT_FLAG, REPORT_FORMAT_FLAG, DIFF_CMD,
+                     FROM_FLAG, TO_FLAG, PROGRESS_JSON_ARG, PARAM_FILE_ARG, param_file_basename,
+                     resource_name_templates, EXTERNAL_ACCOUNT_ARG, SA_KEY_DIR_ARG, NO_VERIFY_FLAG,
+                     cli_schema_field_table, field_table_ident, REPLAY_FILE_ARG, RECORD_FILE_ARG,
+                     REQUEST_FILE_ARG, SKELETON_FLAG, EXPAND_ENV_FLAG,
+                     UPLOAD_SUBCOMMAND_SUFFIX, DOWNLOAD_SUBCOMMAND_SUFFIX, has_download_subcommand)
+    import json
 
     v_arg = '<%s>' % VALUE_ARG
     SOPT = 'self.opt'
@@ -31,12 +41,21 @@
 <%
     hub_type_name = 'api::' + hub_type(c.schemas, util.canonical_name())
 %>\
-use client::{InvalidOptionsError, CLIError, arg_from_str, writer_from_opts, parse_kv_arg,
-          input_file_from_opts, input_mime_from_opts, FieldCursor, FieldError, CallType, UploadProtocol,
-          calltype_from_str, remove_json_null_values, ComplexType, JsonType, JsonTypeInfo};
+use client::{InvalidOptionsError, CLIError, arg_from_str, check_resource_name, writer_from_opts, parse_kv_arg,
+          input_file_from_opts, input_mime_from_opts, FieldCursor, FieldError, CallType, UploadProtocol, DeprecationWarnings,
+          calltype_from_str, remove_json_null_values, ComplexType, JsonType, JsonTypeInfo, FieldInfo, find_cursor_field,
+          request_from_file, expand_env, merge_json, InputError, kv_value, AuthFlow, StderrDeviceFlowDelegate,
+          OutputOptions, CollectedPages, ItemWatcher, write_json_response, write_item_count, appending_writer_from_opts, projects_from_file,
+          for_each_project, PROJECT_FAN_OUT_CONCURRENCY, read_persisted_tokens, update_persisted_tokens, TokenSession,
+          take_tokens_for_scopes, token_to_revoke, token_summary, revocation_request, vulnerability_filter,
+          project_from_resource_url, refresh_token_for_scopes, identity_token_request, identity_token_from_response,
+          IDENTITY_TOKEN_SCOPES, total_size, items_summary, ProgressEvent, ProgressReporter, ConfigurationError, ParamDefaults, success_message, CsvLayout,
+          TransferProgress, ProgressReader};
 
+use std::borrow::Cow;
 use std::default::Default;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use serde_json as json;
 use clap::ArgMatches;
@@ -44,6 +63,41 @@ use clap::ArgMatches;
 enum DoitError {
     IoError(String, io::Error),
     ApiError(Error),
+    ProjectErrors(Vec<(String, DoitError)>),
+    TokenError(String),
+    NoItems,
+}
+
+// Reports retries and the progress of resumable uploads on the progress channel
+struct ProgressDelegate<'a> {
+    progress: &'a ProgressReporter,
+    upload_size: Option<u64>,
+}
+
+impl<'a> ProgressDelegate<'a> {
+    fn new(progress: &'a ProgressReporter) -> ProgressDelegate<'a> {
+        ProgressDelegate { progress, upload_size: None }
+    }
+}
+
+impl<'a> Delegate for ProgressDelegate<'a> {
+    fn retrying(&mut self, delay: Duration) {
+        self.progress.emit(&ProgressEvent::Retry { reason: "request failed".to_string(), delay });
+    }
+
+    fn cancel_chunk_upload(&mut self, chunk: &ContentRange) -> bool {
+        if let Some(ref range) = chunk.range {
+            self.upload_size = Some(chunk.total_length);
+            self.progress.emit(&ProgressEvent::UploadProgress { uploaded: range.first, total: chunk.total_length });
+        }
+        false
+    }
+
+    fn finished(&mut self, is_success: bool) {
+        if let Some(total) = self.upload_size.take().filter(|_| is_success) {
+            self.progress.emit(&ProgressEvent::UploadProgress { uploaded: total, total });
+        }
+    }
 }
 
 struct Engine<'n> {
@@ -51,19 +105,125 @@ struct Engine<'n> {
     hub: ${hub_type_name},
     gp: ${"Vec<&'static str>"},
     gpm: Vec<(&'static str, &'static str)>,
+    output: OutputOptions,
+    progress: ProgressReporter,
+    // whether the progress of uploads and downloads is drawn, which progress events written to stderr would garble
+    show_transfers: bool,
+    param_defaults: ParamDefaults,
+    token_storage: String,
+    // the copy of the tokens the authenticator writes to, which are added to the token storage in the end
+    token_session: TokenSession,
+    cancellation_token: CancellationToken,
+    // the deprecated fields and parameters which were warned about
+    deprecations: DeprecationWarnings,
+% if has_auth_command(c, auth):
+    secret: oauth2::ApplicationSecret,
+% endif
 }
 
 
 impl<'n> Engine<'n> {
 % for resource in sorted(c.rta_map.keys()):
     % for method in sorted(c.rta_map[resource]):
-    async fn ${call_method_ident(resource, method)}(&self, opt: &ArgMatches<'n>, dry_run: bool, err: &mut InvalidOptionsError)
+<%
+    mc = new_method_context(resource, method, c)
+    project_p, _ = project_param(mc)
+%>\
+    async fn ${call_method_ident(resource, method)}(&self, opt: &ArgMatches<'n>, dry_run: bool, err: &mut InvalidOptionsError\
+% if has_download_subcommand(mc, gen_global_parameter_names(parameters)):
+, download: bool\
+% endif
+% if project_p:
+, project: ${"Option<&str>"}\
+% endif
+)
                                                     -> Result<(), DoitError> {
         ${self._method_call_impl(c, resource, method) | indent_all_but_first_by(2)}
     }
 
     % endfor # each method
 % endfor
+% if has_auth_command(c, auth):
+    async fn _auth(&self, opt: &ArgMatches<'n>, dry_run: bool, err: &mut InvalidOptionsError)
+                                                    -> Result<(), DoitError> {
+        let (cmd, cmd_opt) = match opt.subcommand() {
+            (cmd, Some(cmd_opt)) => (cmd, cmd_opt),
+            _ => {
+                err.issues.push(CLIError::MissingMethodError("${AUTH_CMD}".to_string()));
+                writeln!(io::stderr(), "{}\n", opt.usage()).ok();
+                return Ok(());
+            }
+        };
+        if dry_run {
+            return Ok(());
+        }
+        let scopes: ${"Vec<&str>"} = ${SOPT}.values_of("${SCOPE_ARG}").map(|i|i.collect()).unwrap_or_default();
+        let storage_err = |io_err| DoitError::IoError(self.token_storage.clone(), io_err);
+        match cmd {
+            "login" | "print-access-token" => {
+                let mut scopes = scopes;
+                scopes.extend(cmd_opt.values_of("${AUTH_SCOPES_FLAG}").into_iter().flatten());
+                let scopes = if scopes.is_empty() { vec!["${default_auth_scope(auth)}"] } else { scopes };
+                let token = match self.hub.auth.token(&scopes).await {
+                    Ok(token) => token,
+                    Err(auth_err) => return Err(DoitError::ApiError(Error::MissingToken(auth_err))),
+                };
+                if cmd == "print-access-token" {
+                    println!("{}", token.as_str());
+                }
+            },
+            "print-identity-token" => {
+                // obtaining the token asks for consent to the identity scopes, if there is none yet
+                if let Err(auth_err) = self.hub.auth.token(&IDENTITY_TOKEN_SCOPES).await {
+                    return Err(DoitError::ApiError(Error::MissingToken(auth_err)));
+                }
+                let tokens = read_persisted_tokens(self.token_session.private_path())
+                    .map_err(|io_err| DoitError::IoError(self.token_session.private_path().to_string(), io_err))?;
+                let refresh_token = match refresh_token_for_scopes(&tokens, &IDENTITY_TOKEN_SCOPES) {
+                    Some(token) => token,
+                    None => return Err(DoitError::TokenError("There is no refresh token for the identity scopes".to_string())),
+                };
+                let res = match self.hub.client.request(identity_token_request(&self.secret, refresh_token)).await {
+                    Err(http_err) => return Err(DoitError::ApiError(Error::HttpError(http_err))),
+                    Ok(res) if !res.status().is_success() => return Err(DoitError::ApiError(Error::Failure(res))),
+                    Ok(res) => res,
+                };
+                let body = match hyper::body::to_bytes(res.into_body()).await {
+                    Ok(body) => body,
+                    Err(http_err) => return Err(DoitError::ApiError(Error::HttpError(http_err))),
+                };
+                let id_token = identity_token_from_response(&body, cmd_opt.value_of("${AUDIENCE_FLAG}"))
+                    .map_err(DoitError::TokenError)?;
+                println!("{}", id_token);
+            },
+            "revoke" => {
+                // forget the tokens first, they are unusable even if revoking them fails
+                let revoked = update_persisted_tokens(&self.token_storage, |tokens| take_tokens_for_scopes(tokens, &scopes))
+                    .map_err(storage_err)?;
+                for token in revoked.iter().filter_map(token_to_revoke) {
+                    match self.hub.client.request(revocation_request(token)).await {
+                        Err(http_err) => return Err(DoitError::ApiError(Error::HttpError(http_err))),
+                        Ok(res) if !res.status().is_success() => return Err(DoitError::ApiError(Error::Failure(res))),
+                        Ok(_) => {},
+                    }
+                }
+            },
+            _ => {
+                let tokens = read_persisted_tokens(&self.token_storage).map_err(storage_err)?;
+                let summaries: Vec<_> = tokens.iter().map(token_summary).collect();
+                let mut ostream = io::stdout();
+                json::to_writer_pretty(&mut ostream, &summaries).expect("serde to work");
+                writeln!(ostream).ok();
+            },
+        }
+        Ok(())
+    }
+
+% endif
+% if vulnerability_report_method(c, api):
+    ${self._report_impl(c) | indent_all_but_first_by(1)}
+
+% endif
     async fn _doit(&self, dry_run: bool) -> Result<Result<(), DoitError>, Option<InvalidOptionsError>> {
         let mut err = InvalidOptionsError::new();
         let mut call_result: Result<(), DoitError> = Ok(());
@@ -74,9 +234,45 @@ impl<'n> Engine<'n> {
             ("${mangle_subcommand(resource)}", Some(opt)) => {
                 match opt.subcommand() {
                     % for method in sorted(c.rta_map[resource]):
+<%
+    mc = new_method_context(resource, method, c)
+    project_p, _ = project_param(mc)
+    download_p = has_download_subcommand(mc, gen_global_parameter_names(parameters))
+%>\
+                    % if mc.request_value:
+                    ("${mangle_subcommand(method)}", Some(opt)) if opt.is_present("${SKELETON_FLAG}") => {
+                        if !dry_run {
+                            call_result = writeln!(io::stdout(), "{}", ${field_table_ident(mc.request_value, 'SKELETON')})
+                                .map_err(|io_err| DoitError::IoError("-".to_string(), io_err));
+                        }
+                    },
+                    % endif
                     ("${mangle_subcommand(method)}", Some(opt)) => {
+                    % if is_deprecated(mc.m):
+                        if !dry_run {
+                            writeln!(io::stderr(), "${DEPRECATION_WARNING % (mangle_subcommand(resource) + ' ' + mangle_subcommand(method))}").ok();
+                        }
+                    % endif
+                    % if project_p:
+                        ${self._project_fan_out(resource, method, project_p, download_p and ', false' or '') | indent_all_but_first_by(6)}
+                    % else:
+                        call_result = self.${call_method_ident(resource, method)}(opt, dry_run, &mut err${download_p and ', false' or ''}).await;
+                    % endif
+                    },
+                    % if mc.media_params:
+                    ("${mangle_subcommand(method)}${UPLOAD_SUBCOMMAND_SUFFIX}", Some(opt)) => {
                         call_result = self.${call_method_ident(resource, method)}(opt, dry_run, &mut err).await;
                     },
+                    % endif
+                    % if download_p:
+                    ("${mangle_subcommand(method)}${DOWNLOAD_SUBCOMMAND_SUFFIX}", Some(opt)) => {
+                    % if project_p:
+                        ${self._project_fan_out(resource, method, project_p, ', true') | indent_all_but_first_by(6)}
+                    % else:
+                        call_result = self.${call_method_ident(resource, method)}(opt, dry_run, &mut err, true).await;
+                    % endif
+                    },
+                    % endif
                     % endfor # each method
                     _ => {
                         err.issues.push(CLIError::MissingMethodError("${mangle_subcommand(resource)}".to_string()));
@@ -85,6 +281,19 @@ impl<'n> Engine<'n> {
                 }
             },
 % endfor # each resource
+% if has_auth_command(c, auth):
+            ("${AUTH_CMD}", Some(opt)) => {
+                call_result = self._auth(opt, dry_run, &mut err).await;
+            },
+% endif
+% if vulnerability_report_method(c, api):
+            ("${REPORT_CMD}", Some(opt)) => {
+                call_result = self._report(opt, dry_run, &mut err).await;
+            },
+            ("${DIFF_CMD}", Some(opt)) => {
+                call_result = self._diff(opt, dry_run, &mut err).await;
+            },
+% endif
             _ => {
                 err.issues.push(CLIError::MissingCommandError);
                 writeln!(io::stderr(), "{}\n", ${SOPT}.usage()).ok();
@@ -103,6 +312,31 @@ impl<'n> Engine<'n> {
 
     // Please note that this call will fail if any part of the opt can't be handled
     async fn new(opt: ArgMatches<'n>) -> Result<Engine<'n>, InvalidOptionsError> {
+        let mut output = match OutputOptions::from_opts(&opt) {
+            Ok(output) => output,
+            Err(e) => return Err(InvalidOptionsError::single(e, 1)),
+        };
+% if ema_avail_methods(c, api):
+        // Avails are written as CSV in the columns of the EMA spreadsheets
+        if let (resource, Some(method_opt)) = opt.subcommand() {
+            match (resource, method_opt.subcommand_name()) {
+                % for resource, method in ema_avail_methods(c, api):
+                ("${mangle_subcommand(resource)}", Some("${mangle_subcommand(method)}")) => {
+                    output.csv_layout = Some(CsvLayout { columns: avails::EMA_COLUMNS, cell: avails::ema_cell });
+                },
+                % endfor
+                _ => {},
+            }
+        }
+% endif
+        let progress = match ProgressReporter::from_opts(opt.value_of("${PROGRESS_JSON_ARG}")) {
+            Ok(progress) => progress,
+            Err(io_err) => {
+                let path = opt.value_of("${PROGRESS_JSON_ARG}").unwrap_or_default().to_string();
+                return Err(InvalidOptionsError::single(CLIError::Configuration(ConfigurationError::Io((path, io_err))), 1));
+            },
+        };
+        let show_transfers = opt.value_of("${PROGRESS_JSON_ARG}") != Some("-");
         let (config_dir, secret) = {
             let config_dir = match client::assure_config_dir_exists(opt.value_of("${CONFIG_DIR_ARG}").unwrap_or("${CONFIG_DIR}")) {
                 Err(e) => return Err(InvalidOptionsError::single(e, 3)),
@@ -116,22 +350,118 @@ impl<'n> Engine<'n> {
             }
         };
 
-        let auth = oauth2::InstalledFlowAuthenticator::builder(
-            secret,
-            oauth2::InstalledFlowReturnMethod::HTTPRedirect,
-        ).persist_tokens_to_disk(format!("{}/${util.program_name()}", config_dir)).build().await.unwrap();
+        let param_defaults = match ParamDefaults::from_opts(opt.value_of("${PARAM_FILE_ARG}"), &config_dir,
+                                                            "${param_file_basename(util.program_name())}") {
+            Ok(param_defaults) => param_defaults,
+            Err(e) => return Err(InvalidOptionsError::single(e, 1)),
+        };
+
+        let auth_flow = match AuthFlow::from_opts(&opt) {
+            Ok(auth_flow) => auth_flow,
+            Err(e) => return Err(InvalidOptionsError::single(e, 1)),
+        };
+        let token_storage = format!("{}/${util.program_name()}", config_dir);
+        // Other invocations may use the token storage at the same time, so the authenticator gets a copy of its own
+        let token_session = match TokenSession::start(&token_storage) {
+            Ok(token_session) => token_session,
+            Err(io_err) => return Err(InvalidOptionsError::single(CLIError::Configuration(ConfigurationError::Io((token_storage, io_err))), 1)),
+        };
+        // The authenticator uses the TLS implementation chosen by the features of this program, too
+        let client = ConnectionOptions::default().client();
+<% auth_secret = has_auth_command(c, auth) and 'secret.clone()' or 'secret' %>\
+        let auth = match auth_flow {
+            AuthFlow::Redirect => oauth2::InstalledFlowAuthenticator::builder(
+                ${auth_secret},
+                oauth2::InstalledFlowReturnMethod::HTTPRedirect,
+            ).hyper_client(client.clone()).persist_tokens_to_disk(token_session.private_path()).build().await,
+            AuthFlow::Device => oauth2::DeviceFlowAuthenticator::builder(${auth_secret})
+                .flow_delegate(Box::new(StderrDeviceFlowDelegate))
+                .hyper_client(client.clone()).persist_tokens_to_disk(token_session.private_path()).build().await,
+        }.unwrap();
 
-        let client = hyper::Client::builder().build(hyper_rustls::HttpsConnector::with_native_roots());
+        let mut hub = ${hub_type_name}::new(client, auth);
+        // --watch keeps calling the API, even after the network changed
+        hub.connection_rebuild(Some(ConnectionRebuild::new(ConnectionOptions::default(), ConnectionRebuild::DEFAULT_MAX_FAILURES)));
+        hub.quota_project(opt.value_of("${BILLING_PROJECT_ARG}").map(|p| p.to_string()));
+% if supports_media_download(c):
+        hub.verify_checksums(!opt.is_present("${NO_VERIFY_FLAG}"));
+% endif
+% if supports_scopes(auth):
+        if let Some(path) = opt.value_of("${EXTERNAL_ACCOUNT_ARG}") {
+            match ExecutableCredentials::from_file(std::path::Path::new(path)) {
+                Ok(credentials) => {
+                    hub.external_account(Some(credentials));
+                },
+                Err(io_err) => return Err(InvalidOptionsError::single(CLIError::Configuration(ConfigurationError::Io((path.to_string(), io_err))), 1)),
+            }
+        }
+        if let Some(dir) = opt.value_of("${SA_KEY_DIR_ARG}") {
+            match ServiceAccountKeys::from_dir(std::path::Path::new(dir)) {
+                Ok(keys) => {
+                    hub.token_provider(Some(std::sync::Arc::new(keys)));
+                },
+                Err(io_err) => return Err(InvalidOptionsError::single(CLIError::Configuration(ConfigurationError::Io((dir.to_string(), io_err))), 1)),
+            }
+        }
+% endif
+        // Calls go to a local server replaying the responses of a cassette, or recording them
+        let root_url = hub.root_url(String::new());
+        let base_url = hub.base_url(String::new());
+        let cassette_address = if let Some(path) = opt.value_of("${REPLAY_FILE_ARG}") {
+            match cassette::Cassette::load(std::path::Path::new(path)) {
+                Ok(cassette) => {
+                    hub.token_provider(Some(std::sync::Arc::new(StaticToken("replay".to_string()))));
+                    Some(cassette::Player::new(cassette).spawn())
+                },
+                Err(io_err) => return Err(InvalidOptionsError::single(CLIError::Configuration(ConfigurationError::Io((path.to_string(), io_err))), 1)),
+            }
+        } else {
+            opt.value_of("${RECORD_FILE_ARG}")
+               .map(|path| cassette::Recorder::new(&root_url, std::path::Path::new(path)).spawn())
+        };
+        let (root_url, base_url) = match cassette_address {
+            Some(address) => client::local_urls(address, &root_url, &base_url),
+            None => (root_url, base_url),
+        };
+        hub.root_url(root_url);
+        hub.base_url(base_url);
+        // The first interrupt cancels the calls in flight, which still writes the items received so far,
+        // the second one exits right away
+        let cancellation_token = CancellationToken::new();
+        hub.cancellation_token(cancellation_token.clone());
+        let interrupt_token = cancellation_token.clone();
+        let private_tokens = token_session.private_path().to_owned();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                interrupt_token.cancel();
+            }
+            if tokio::signal::ctrl_c().await.is_ok() {
+                // exit() skips the drop of the token session
+                std::fs::remove_file(&private_tokens).ok();
+                std::process::exit(130);
+            }
+        });
 <% gpm = gen_global_parameter_names(parameters) %>\
         let engine = Engine {
             opt: opt,
-            hub: ${hub_type_name}::new(client, auth),
+            hub,
             gp: ${field_vec(gpm)},
             gpm: vec![
                 % for pn in list(pn for pn in gpm if mangle_subcommand(pn) != pn):
                     ("${mangle_subcommand(pn)}", "${pn}"),
                 % endfor # each global parameter
-                ]
+                ],
+            output,
+            progress,
+            show_transfers,
+            param_defaults,
+            token_storage,
+            token_session,
+            deprecations: Default::default(),
+            cancellation_token,
+% if has_auth_command(c, auth):
+            secret,
+% endif
         };
 
         match engine._doit(true).await {
@@ -142,12 +472,42 @@ impl<'n> Engine<'n> {
     }
 
     async fn doit(&self) -> Result<(), DoitError> {
-        match self._doit(false).await {
+        let result = match self._doit(false).await {
             Ok(res) => res,
             Err(_) => unreachable!(),
-        }
+        };
+        let persisted = self.token_session.finish()
+                                          .map_err(|io_err| DoitError::IoError(self.token_storage.clone(), io_err));
+        result.and(persisted)
     }
 }
+${self._request_field_tables(c)}\
+</%def>
+
+<%def name="_request_field_tables(c)">\
+<%
+    request_values = dict()
+    for resource in c.rta_map.keys():
+        for method in c.rta_map[resource]:
+            mc = new_method_context(resource, method, c)
+            if mc.request_value:
+                request_values[mc.request_value.id] = mc.request_value
+%>\
+% for schema_id in sorted(request_values.keys()):
+<%
+    request_value = request_values[schema_id]
+    rows = cli_schema_field_table(to_cli_schema(c, request_value))
+%>\
+
+// The fields of `${schema_id}` which may be set with `-${STRUCT_FLAG}`, shared by all methods taking one
+static ${field_table_ident(request_value)}: &[FieldInfo] = &[
+    % for cli_path, path, jtype, ctype, values, deprecated in rows:
+    FieldInfo { cli_path: "${cli_path}", path: "${path}", type_info: JsonTypeInfo { jtype: JsonType::${jtype}, ctype: ComplexType::${ctype}, values: &[${', '.join('"%s"' % v for v in values)}] }, deprecated: ${deprecated and 'true' or 'false'} },
+    % endfor
+];
+// A `${schema_id}` with a sample value in each field, printed by `--${SKELETON_FLAG}`
+static ${field_table_ident(request_value, 'SKELETON')}: &str = ${rust_raw_string(json.dumps(schema_sample(c.schemas, request_value), indent=2, sort_keys=True))};
+% endfor
 </%def>
 
 <%def name="_method_call_impl(c, resource, method)" buffered="True">\
@@ -162,12 +522,28 @@ impl<'n> Engine<'n> {
                            supports_media_download and
                           (parameters is not UNDEFINED and 'alt' in parameters) or ('alt' in optional_prop_names))
     handle_props = optional_props or parameters is not UNDEFINED
-    if mc.request_value:
-        request_cli_schema = to_cli_schema(c, mc.request_value)
 
     request_prop_type = None
     global_parameter_names = gen_global_parameter_names(parameters)
+
+    download_p = has_download_subcommand(mc, global_parameter_names)
+
+    items_property = list_items_property(mc.response_schema)
+    page_token_p = page_token_param(mc)
+    page_size_p, max_page_size = page_size_param(mc)
+    project_p, project_format = project_param(mc)
+    out_arg_value = opt_value(OUT_ARG, default='-')
+    writer_fn = 'writer_from_opts(opt.value_of("%s"))' % OUT_ARG
+    if project_p:
+        # calls for many projects share the output
+        writer_fn = 'match project { Some(_) => appending_writer_from_opts(opt.value_of("%s")), None => %s }' % (OUT_ARG, writer_fn)
 %>\
+% if project_p:
+let ${mangle_ident(project_p.name)} = match project {
+    Some(id) => format!("${project_format}", id),
+    None => ${opt_value(project_p.name)}.to_string(),
+};
+% endif
     ## REQUIRED PARAMETERS
 % for p in mc.required_props:
 <%
@@ -176,7 +552,7 @@ impl<'n> Engine<'n> {
 %>\
     % if is_request_value_property(mc, p):
 <% request_prop_type = prop_type %>\
-${self._request_value_impl(c, request_cli_schema, prop_name, request_prop_type)}\
+${self._request_value_impl(c, mc.request_value, prop_name, request_prop_type)}\
     % elif p.type != 'string':
     % if p.get('repeated', False):
 let ${prop_name}: Vec<${prop_type} = Vec::new();
@@ -186,6 +562,8 @@ for (arg_id, arg) in ${opt_values(mangle_subcommand(p.name))}.enumerate() {
     % else:
 let ${prop_name}: ${prop_type} = arg_from_str(&${opt_value(p.name)}, err, "<${mangle_subcommand(p.name)}>", "${p.type}");
     % endif # handle repeated values
+    % elif p is not project_p and resource_name_templates(c, mc, p):
+check_resource_name(${opt_value(p.name)}, "<${mangle_subcommand(p.name)}>", &[${', '.join('"%s"' % t for t in resource_name_templates(c, mc, p))}], err);
     % endif # handle request value
 % endfor # each required parameter
 <%
@@ -195,21 +573,56 @@ let ${prop_name}: ${prop_type} = arg_from_str(&${opt_value(p.name)}, err, "<${ma
         # if type is not available, we know it's the request value, which should also be borrowed
         borrow = borrow_prefix(p)
         arg_name = mangle_ident(p.name)
-        if p.get('type', '') == 'string':
+        if p is project_p:
+            arg_name = '&' + arg_name
+        elif p.get('type', '') == 'string':
             if p.get('repeated', False):
                 arg_name = opt_values(p.name) + '.map(|&v| v.to_string()).collect::<Vec<String>>()'
             else:
                 arg_name = opt_value(p.name)
+        elif page_token_p and is_request_value_property(mc, p):
+            # the call is rebuilt for each page, and takes ownership of the request
+            arg_name += '.clone()'
         call_args.append(borrow + arg_name)
     # end for each required prop
 %>\
-% if track_download_flag:
+% if download_p:
+let mut download_mode = download;
+% elif track_download_flag:
 let mut download_mode = false;
 % endif
-let mut call = self.hub.${mangle_ident(resource)}().${mangle_ident(method)}(${', '.join(call_args)});
+% if page_token_p:
+let mut ostream = if dry_run {
+    Box::new(io::sink()) as Box<dyn Write>
+} else {
+    match ${writer_fn} {
+        Ok(f) => f,
+        Err(io_err) => return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err)),
+    }
+};
+let mut page_token: Option<String> = None;
+let mut collected = CollectedPages::default();
+let (mut pages, mut items) = (0u64, 0u64);
+let mut total_items = None;
+let mut watcher = self.output.watch.map(|_| ItemWatcher::default());
+% if page_size_p:
+let mut page_size = match self.output.adaptive_page_size {
+    Some(size) if self.output.all_pages => Some(AdaptivePageSize::new(size, ${max_page_size})),
+    _ => None,
+};
+% endif
+loop {
+% endif
+let mut dlg = ProgressDelegate::new(&self.progress);
+let mut call = self.hub.${mangle_ident(resource)}().${mangle_ident(method)}(${', '.join(call_args)})
+                   .delegate(&mut dlg);
 % if handle_props:
-for parg in ${opt_values(VALUE_ARG)} {
+let pargs = self.param_defaults.args(&${opt_values(VALUE_ARG)}.copied().collect::<Vec<_>>(),
+                                     &[${comma_sep_fields(optional_prop_names)}].iter().chain(self.gp.iter()).copied().collect::<Vec<_>>());
+for parg in pargs.iter() {
     let (key, value) = parse_kv_arg(&*parg, err, false);
+    let (key, value) = (&*key, value.map(|v| kv_value(v, err)));
+    let value = value.as_deref();
     match key {
 % for p in optional_props:
 <%
@@ -217,6 +630,11 @@ for parg in ${opt_values(VALUE_ARG)} {
     value_unwrap = 'value.unwrap_or("%s")' % JSON_TYPE_VALUE_MAP[ptype]
 %>\
         "${mangle_subcommand(p.name)}" => {
+        % if is_deprecated(p):
+            if !dry_run {
+                self.deprecations.warn("parameter '${mangle_subcommand(p.name)}'");
+            }
+        % endif
         % if p.name == 'alt':
             if ${value_unwrap} == "media" {
                 download_mode = true;
@@ -261,111 +679,382 @@ ${value_unwrap}\
     }
 }
 % endif # handle call parameters
+% if download_p:
+if download {
+    % if 'alt' in optional_prop_names:
+    call = call.${mangle_ident(setter_fn_name([p for p in optional_props if p.name == 'alt'][0]))}("media");
+    % else:
+    call = call.${ADD_PARAM_FN}("alt", "media");
+    % endif
+}
+% endif
+% if page_token_p:
+if let Some(ref token) = page_token {
+    call = call.${mangle_ident(setter_fn_name(page_token_p))}(token);
+}
+% endif
+% if page_size_p:
+if let Some(ref page_size) = page_size {
+    call = call.${mangle_ident(setter_fn_name(page_size_p))}(page_size.size() as ${activity_rust_type(c.schemas, page_size_p, allow_optionals=False)});
+}
+% endif
 % if mc.media_params:
-let vals = opt.values_of("${MODE_ARG}").unwrap().collect::<Vec<${'&'}str>>();
+let vals = match opt.values_of("${MODE_ARG}") {
+    Some(vals) => vals.collect::<Vec<${'&'}str>>(),
+    // the upload subcommand takes the file as argument
+    None => vec!["${mc.media_params[0].protocol}", ${opt_value(FILE_ARG)}],
+};
 let protocol = calltype_from_str(vals[0], [${', '.join('"%s"' % mp.protocol for mp in mc.media_params)}].iter().map(|&v| v.to_string()).collect(), err);
-let mut input_file = input_file_from_opts(vals[1], err);
-let mime_type = input_mime_from_opts(${opt_value(MIME_ARG, default=DEFAULT_MIME)}, err);
+// `-` streams the standard input, which can't be sniffed for its mime type
+let from_stdin = vals[1] == "-";
+let mut input_file = if from_stdin { None } else { input_file_from_opts(vals[1], err) };
+let mime_type = input_mime_from_opts(opt.value_of("${MIME_ARG}").or(Some("${DEFAULT_MIME}").filter(|_| from_stdin)),
+                                     vals[1], input_file.as_mut(), sniff_mime_type, err);
 % else:
 let protocol = CallType::Standard;
 % endif # support upload
 if dry_run {
-    Ok(())
-} else {
-    assert!(err.issues.len() == 0);
-    % if method_default_scope(mc.m):
-    for scope in ${opt_values(SCOPE_ARG, opt=SOPT)} {
-        call = call.${ADD_SCOPE_FN}(scope);
-    }
+    return Ok(());
+}
+assert!(err.issues.len() == 0);
+% if method_default_scope(mc.m, c):
+for scope in ${opt_values(SCOPE_ARG, opt=SOPT)} {
+    call = call.${ADD_SCOPE_FN}(scope);
+}
+% endif
+## Make the call, handle uploads, handle downloads (also media downloads|json decoding)
+% if handle_output and not page_token_p:
+let mut ostream = match ${writer_fn} {
+    Ok(mut f) => f,
+    Err(io_err) => return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err)),
+};
+% endif # handle output
+% if page_token_p:
+let started = Instant::now();
+% endif
+% if mc.media_params:
+// the media is streamed, which lets its progress follow the bytes as they are sent
+let progress = TransferProgress::new("uploading", None, self.show_transfers);
+let media = match input_file {
+    Some(file) => {
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        progress.set_total(size);
+        MediaStream::from_async_read(ProgressReader::new(tokio::fs::File::from_std(file), progress), size)
+    },
+    None => MediaStream::from_async_read(ProgressReader::new(tokio::io::stdin(), progress), None),
+};
+% endif
+match match protocol {
+    % if mc.media_params:
+    % for p in mc.media_params:
+    CallType::Upload(UploadProtocol::${p.protocol.capitalize()}) => call.${upload_action_fn(api.terms.upload_action, p.type.suffix + STREAM_UPLOAD_SUFFIX)}(media, mime_type.unwrap()).await,
+    % endfor
+    CallType::Standard => unreachable!()
+    % else:
+    CallType::Standard => call.${api.terms.action}().await,
+    _ => unreachable!()
     % endif
-    ## Make the call, handle uploads, handle downloads (also media downloads|json decoding)
-    % if handle_output:
-    let mut ostream = match writer_from_opts(opt.value_of("${(OUT_ARG)}")) {
-        Ok(mut f) => f,
-        Err(io_err) => return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
-    };
-    % endif # handle output
-    match match protocol {
-        % if mc.media_params:
-        % for p in mc.media_params:
-        CallType::Upload(UploadProtocol::${p.protocol.capitalize()}) => call.${upload_action_fn(api.terms.upload_action, p.type.suffix)}(input_file.unwrap(), mime_type.unwrap()).await,
-        % endfor
-        CallType::Standard => unreachable!()
-        % else:
-        CallType::Standard => call.${api.terms.action}().await,
-        _ => unreachable!()
+} {
+    % if page_token_p:
+    // watching ends when interrupted
+    Err(Error::Cancelled) if watcher.is_some() => return Ok(()),
+    Err(Error::Cancelled) if pages > 0 => {
+        // sorted or limited items received before the cancellation are written nonetheless
+        if self.output.count {
+            if let Err(io_err) = write_item_count(&mut ostream, items, ${project_p and 'project' or 'None'}, &self.output) {
+                return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+            }
+        } else if let Some(value) = collected.into_response("${items_property}") {
+            if let Err(io_err) = write_json_response(&mut ostream, value, Some("${items_property}"), ${project_p and 'project' or 'None'}, &self.output) {
+                return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+            }
+        }
+        return Err(DoitError::ApiError(Error::Cancelled));
+    },
+    % endif
+    % if page_size_p:
+    Err(api_err) => {
+        if let Some(ref mut page_size) = page_size {
+            // fetch the same page again, with fewer items
+            if page_size.on_error(&api_err) {
+                self.progress.emit(&ProgressEvent::Retry { reason: "page too large".to_string(), delay: Duration::default() });
+                continue;
+            }
+        }
+        return Err(DoitError::ApiError(api_err));
+    },
+    % else:
+    Err(api_err) => return Err(DoitError::ApiError(api_err)),
+    % endif
+    % if mc.response_schema:
+    Ok((mut response, output_schema)) => {
+    % else:
+    Ok(mut response) => {
+    % endif # handle output structure
+        ## We are not generating optimal code, but hope it will still be logically correct.
+        ## If not, we might build the code in python
+        ## TODO: Fix this
+        % if track_download_flag:
+        if !download_mode {
         % endif
-    } {
-        Err(api_err) => Err(DoitError::ApiError(api_err)),
         % if mc.response_schema:
-        Ok((mut response, output_schema)) => {
+        % if page_token_p:
+        page_token = output_schema.next_page_token.clone().filter(|t| !t.is_empty());
+        % endif
+        % if page_size_p:
+        if let Some(ref mut page_size) = page_size {
+            page_size.on_success(started.elapsed());
+        }
+        % endif
+        % if page_token_p:
+        let mut value = json::value::to_value(&output_schema).expect("serde to work");
+        let page_items = value["${items_property}"].as_array().map_or(0, |a| a.len());
+        pages += 1;
+        items += page_items as u64;
+        total_items = total_size(&value).or(total_items);
+        self.progress.emit(&ProgressEvent::PageFetched { page: pages, items: page_items, elapsed: started.elapsed() });
+        self.progress.emit(&ProgressEvent::ItemsProcessed { count: items });
+        if let Some(ref mut watcher) = watcher {
+            watcher.retain_changed(&mut value, "${items_property}");
+        }
+        if self.output.collects_items() {
+            if collected.add(value, "${items_property}", &self.output) {
+                page_token = None;
+            }
+        } else if !self.output.count {
+            if let Err(io_err) = write_json_response(&mut ostream, value, Some("${items_property}"), ${project_p and 'project' or 'None'}, &self.output) {
+                return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+            }
+        }
         % else:
-        Ok(mut response) => {
-        % endif # handle output structure
-            ## We are not generating optimal code, but hope it will still be logically correct.
-            ## If not, we might build the code in python
-            ## TODO: Fix this
-            % if track_download_flag:
-            if !download_mode {
-            % endif
-            % if mc.response_schema:
-            let mut value = json::value::to_value(&output_schema).expect("serde to work");
-            remove_json_null_values(&mut value);
-            json::to_writer_pretty(&mut ostream, &value).unwrap();
-            ostream.flush().unwrap();
-            % endif
-            % if track_download_flag:
-            } else {
-            % endif
-            % if supports_media_download:
-            ## Download is the only option - nothing else matters
-            let bytes = hyper::body::to_bytes(response.into_body()).await.expect("a string as API currently is inefficient").to_vec();
-            ostream.write_all(&bytes).expect("write to be complete");
-            ostream.flush().expect("io to never fail which should really be fixed one day");
-            % endif
-            % if track_download_flag:
+        let value = json::value::to_value(&output_schema).expect("serde to work");
+        if let Err(io_err) = write_json_response(&mut ostream, value, ${items_property and 'Some("%s")' % items_property or 'None'}, ${project_p and 'project' or 'None'}, &self.output) {
+            return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+        }
+        % endif
+        % endif
+        % if track_download_flag:
+        } else {
+        % endif
+        % if supports_media_download:
+        ## Download is the only option - nothing else matters
+        // media is written as it arrives, instead of holding all of it in memory
+        let mut body = response.into_body();
+        let progress = TransferProgress::new("downloading", hyper::body::HttpBody::size_hint(&body).exact(),
+                                             self.show_transfers${project_p and ' && project.is_none()' or ''});
+        while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+            let chunk = chunk.map_err(|err| DoitError::ApiError(Error::from(err)))?;
+            if let Err(io_err) = ostream.write_all(&chunk) {
+                return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
             }
-            % endif
-            Ok(())
+            progress.inc(chunk.len() as u64);
         }
+        if let Err(io_err) = ostream.flush() {
+            return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+        }
+        % endif
+        % if track_download_flag:
+        }
+        % endif
+        % if not handle_output:
+        // there is no response to write, like the empty one of delete methods, so only its success is reported
+        writeln!(io::stderr(), "{}", success_message("${mangle_subcommand(resource)} ${mangle_subcommand(method)}", response.status(), ${project_p and 'project' or 'None'})).ok();
+        % endif
+    }
+}
+% if page_token_p:
+if let (None, Some(interval)) = (page_token.as_ref(), self.output.watch) {
+    // poll all pages again once the interval passed
+    tokio::select! {
+        _ = tokio::time::sleep(interval) => {},
+        _ = self.cancellation_token.cancelled() => return Ok(()),
+    }
+    pages = 0;
+    items = 0;
+    continue;
+}
+if !self.output.all_pages || page_token.is_none() {
+    // sorted or limited items, and their amount, are written once all of them were received
+    if self.output.count {
+        if let Err(io_err) = write_item_count(&mut ostream, items, ${project_p and 'project' or 'None'}, &self.output) {
+            return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+        }
+    } else if let Some(value) = collected.into_response("${items_property}") {
+        if let Err(io_err) = write_json_response(&mut ostream, value, Some("${items_property}"), ${project_p and 'project' or 'None'}, &self.output) {
+            return Err(DoitError::IoError(${out_arg_value}.to_string(), io_err));
+        }
+    }
+    if self.output.verbose {
+        writeln!(io::stderr(), "{}", items_summary(items, total_items, ${project_p and 'project' or 'None'})).ok();
+    }
+    if self.output.fail_on_empty && items == 0 {
+        return Err(DoitError::NoItems);
     }
+    return Ok(());
+}
 }\
+% else:
+Ok(())\
+% endif
 </%def>
 
-<%def name="_request_value_impl(c, request_cli_schema, request_prop_name, request_prop_type)">
+<%def name="_report_impl(c)" buffered="True">\
 <%
-    allow_optionals_fn = lambda s: is_schema_with_optionals(schema_markers(s, c, transitive=False))
-
-    def flatten_schema_fields(schema, res, fields, cur=list()):
-        if len(cur) == 0:
-            cur = list()
-
-        opt_access = '.as_mut().unwrap()'
-        allow_optionals = allow_optionals_fn(schema)
-        if not allow_optionals:
-            opt_access = ''
-        for fn, f in schema.fields.items():
-            cur.append(['%s%s' % (mangle_ident(fn), opt_access), fn])
-            fields.add(fn)
-            if isinstance(f, SchemaEntry):
-                cur[-1][0] = mangle_ident(fn)
-                res.append((schema, f, list(cur)))
-            else:
-                flatten_schema_fields(f, res, fields, cur)
-            cur.pop()
-        # endfor
-    # end utility
-
-    schema_fields = list()
-    fields = set()
-    flatten_schema_fields(request_cli_schema, schema_fields, fields)
+    resource, method, mc = vulnerability_report_method(c, api)
+    _, project_format = project_param(mc)
+    filter_p = [p for p in mc.optional_props if p.name == 'filter'][0]
+    items_property = list_items_property(mc.response_schema)
 %>\
+// Returns the parent of the occurrences of the image at `image`, which is in the given project, or
+// the one hosting the image
+fn _occurrences_parent(&self, opt: &ArgMatches<'n>, image: &str, err: &mut InvalidOptionsError) -> Option<String> {
+    match opt.value_of("${REPORT_PROJECT_FLAG}").or_else(|| project_from_resource_url(image)) {
+        Some(id) => Some(format!("${project_format}", id)),
+        None => {
+            err.issues.push(CLIError::UnknownImageProject(image.to_string()));
+            None
+        }
+    }
+}
+
+async fn _vulnerability_findings(&self, parent: &str, image: &str)
+                                 -> Result<Vec<vulnerabilities::VulnerabilityFinding>, DoitError> {
+    let mut occurrences = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut call = self.hub.${mangle_ident(resource)}().${mangle_ident(method)}(parent)
+                           .${mangle_ident(setter_fn_name(filter_p))}(&vulnerability_filter(image));
+        if let Some(ref token) = page_token {
+            call = call.${mangle_ident(setter_fn_name(page_token_param(mc)))}(token);
+        }
+% if method_default_scope(mc.m, c):
+        for scope in ${opt_values(SCOPE_ARG, opt=SOPT)} {
+            call = call.${ADD_SCOPE_FN}(scope);
+        }
+% endif
+        let output_schema = match call.${api.terms.action}().await {
+            Ok((_, output_schema)) => output_schema,
+            Err(api_err) => return Err(DoitError::ApiError(api_err)),
+        };
+        page_token = output_schema.next_page_token.filter(|t| !t.is_empty());
+        occurrences.extend(output_schema.${mangle_ident(items_property)}.unwrap_or_default());
+        if page_token.is_none() {
+            break;
+        }
+    }
+    Ok(vulnerabilities::findings_from_occurrences(&occurrences))
+}
+
+async fn _report(&self, opt: &ArgMatches<'n>, dry_run: bool, err: &mut InvalidOptionsError)
+                                                -> Result<(), DoitError> {
+    let opt = match opt.subcommand() {
+        ("${VULNERABILITIES_CMD}", Some(opt)) => opt,
+        _ => {
+            err.issues.push(CLIError::MissingMethodError("${REPORT_CMD}".to_string()));
+            writeln!(io::stderr(), "{}\n", opt.usage()).ok();
+            return Ok(());
+        }
+    };
+    let image = opt.value_of("${IMAGE_FLAG}").unwrap_or("");
+    let parent = self._occurrences_parent(opt, image, err);
+    if dry_run {
+        return Ok(());
+    }
+    let findings = self._vulnerability_findings(&parent.unwrap(), image).await?;
+    let mut ostream = match writer_from_opts(opt.value_of("${OUT_ARG}")) {
+        Ok(f) => f,
+        Err(io_err) => return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+    };
+    let document = match opt.value_of("${REPORT_FORMAT_FLAG}") {
+        Some("sarif") => vulnerabilities::to_sarif(&findings, image, "${util.program_name()}"),
+        Some("vex") => vulnerabilities::to_cyclonedx_vex(&findings, image, "${util.program_name()}"),
+        _ => json::Value::Null,
+    };
+    let res = if let Some(ref template) = self.output.template {
+        template.render(&json::json!({ "image": image, "findings": findings }))
+                .and_then(|report| ostream.write_all(report.as_bytes()))
+    } else if document.is_null() {
+        vulnerabilities::write_table(&mut ostream, &findings)
+    } else {
+        json::to_writer_pretty(&mut ostream, &document).map_err(io::Error::from)
+            .and_then(|_| writeln!(ostream))
+    };
+    res.map_err(|io_err| DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err))
+}
+
+async fn _diff(&self, opt: &ArgMatches<'n>, dry_run: bool, err: &mut InvalidOptionsError)
+                                                -> Result<(), DoitError> {
+    let from_image = opt.value_of("${FROM_FLAG}").unwrap_or("");
+    let to_image = opt.value_of("${TO_FLAG}").unwrap_or("");
+    let from_parent = self._occurrences_parent(opt, from_image, err);
+    let to_parent = self._occurrences_parent(opt, to_image, err);
+    if dry_run {
+        return Ok(());
+    }
+    let from = self._vulnerability_findings(&from_parent.unwrap(), from_image).await?;
+    let to = self._vulnerability_findings(&to_parent.unwrap(), to_image).await?;
+    let diff = vulnerabilities::diff(&from, &to);
+    let mut ostream = match writer_from_opts(opt.value_of("${OUT_ARG}")) {
+        Ok(f) => f,
+        Err(io_err) => return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+    };
+    let res = match opt.value_of("${REPORT_FORMAT_FLAG}") {
+        Some("json") => json::to_writer_pretty(&mut ostream, &diff).map_err(io::Error::from)
+                            .and_then(|_| writeln!(ostream)),
+        _ => vulnerabilities::write_diff(&mut ostream, &diff),
+    };
+    res.map_err(|io_err| DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err))
+}
+</%def>
+
+<%def name="_project_fan_out(resource, method, project_p, args='')" buffered="True">\
+match opt.value_of("${PROJECTS_FROM_ARG}").map(|path| projects_from_file(path, &mut err)) {
+    None => {
+        if opt.value_of("${mangle_subcommand(project_p.name)}").is_none() {
+            err.issues.push(CLIError::MissingProjectError("${mangle_subcommand(project_p.name)}".to_string()));
+        }
+        call_result = self.${call_method_ident(resource, method)}(opt, dry_run, &mut err${args}, None).await;
+    },
+    Some(projects) => if dry_run {
+        let project = projects.first().map(|p| &p[..]).unwrap_or("");
+        call_result = self.${call_method_ident(resource, method)}(opt, dry_run, &mut err${args}, Some(project)).await;
+    } else {
+        // truncate the output file once, all calls append to it
+        if let Err(io_err) = writer_from_opts(opt.value_of("${OUT_ARG}")) {
+            return Ok(Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)));
+        }
+        let failures = for_each_project(&projects, PROJECT_FAN_OUT_CONCURRENCY, |project| async move {
+            let mut err = InvalidOptionsError::new();
+            self.${call_method_ident(resource, method)}(opt, false, &mut err${args}, Some(project)).await
+        }).await;
+        if !failures.is_empty() {
+            call_result = Err(DoitError::ProjectErrors(failures));
+        }
+    },
+}
+</%def>
+
+<%def name="_request_value_impl(c, request_value, request_prop_name, request_prop_type)">
 let mut field_cursor = FieldCursor::default();
 let mut object = json::value::Value::Object(Default::default());
 
 for kvarg in ${opt_values(KEY_VALUE_ARG)} {
     let last_errc = err.issues.len();
     let (key, value) = parse_kv_arg(&*kvarg, err, false);
+    let value = match value {
+        Some(value) if opt.is_present("${EXPAND_ENV_FLAG}") => Some(expand_env(value, err)),
+        value => value.map(Cow::Borrowed),
+    };
+    let value = value.as_deref();
+    // `field+=value` appends to a list, and `field:=json` sets raw JSON
+    let (key, append) = match (value, key.strip_suffix('+')) {
+        (Some(_), Some(key)) => (key, true),
+        _ => (&*key, false),
+    };
+    let (key, raw_json) = match (value, key.strip_suffix(':')) {
+        (Some(_), Some(key)) => (key, true),
+        _ => (key, false),
+    };
     let mut temp_cursor = field_cursor.clone();
     if let Err(field_err) = temp_cursor.set(&*key) {
         err.issues.push(field_err);
@@ -378,29 +1067,29 @@ for kvarg in ${opt_values(KEY_VALUE_ARG)} {
         continue;
     }
 
-    ## This type-annotation is not required in nightly (or newer rustc)
-    ## TODO(ST): try to remove it once there is a newer stable
-    let type_info: Option<(&'static str, JsonTypeInfo)> =
-        match &temp_cursor.to_string()[..] {
-    % for schema, fe, f in schema_fields:
-<%
-    pname = FIELD_SEP.join(mangle_subcommand(t[1]) for t in f)
-    sname = FIELD_SEP.join(t[1] for t in f)
-    ptype = actual_json_type(f[-1][1], fe.actual_property.type)
-    jtype = 'JsonType::' + JSON_TYPE_TO_ENUM_MAP[ptype]
-    ctype = 'ComplexType::' + CTYPE_TO_ENUM_MAP[fe.container_type]
-%>\
-            "${pname}" => Some(("${sname}", JsonTypeInfo { jtype: ${jtype}, ctype: ${ctype} })),
-            % endfor # each nested field
-            _ => {
-                let suggestion = FieldCursor::did_you_mean(key, &${field_vec(sorted(fields))});
-                err.issues.push(CLIError::Field(FieldError::Unknown(temp_cursor.to_string(), suggestion, value.map(|v| v.to_string()))));
-                None
-            }
-        };
-    if let Some((field_cursor_str, type_info)) = type_info {
-        FieldCursor::from(field_cursor_str).set_json_value(&mut object, value.unwrap(), type_info, err, &temp_cursor);
+    let field = find_cursor_field(${field_table_ident(request_value)}, &temp_cursor);
+    if let Some(field) = field.filter(|field| field.deprecated && !dry_run) {
+        self.deprecations.warn(&format!("field '{}'", field.cli_path));
+    }
+    match field {
+        Some(field) if raw_json => FieldCursor::from(field.path).set_json_raw(&mut object, value.unwrap(), field.type_info, append, err, &temp_cursor),
+        Some(field) => FieldCursor::from(field.path).set_json_value(&mut object, value.unwrap(), field.type_info, append, err, &temp_cursor),
+        None => {
+            let suggestions = temp_cursor.did_you_mean(${field_table_ident(request_value)});
+            err.issues.push(CLIError::Field(FieldError::Unknown(temp_cursor.to_string(), suggestions, value.map(|v| v.to_string()))));
+        }
     }
 }
-let mut ${request_prop_name}: api::${request_prop_type} = json::value::from_value(object).unwrap();
+if let Some(request_path) = opt.value_of("${REQUEST_FILE_ARG}") {
+    let mut request = request_from_file(request_path, opt.is_present("${EXPAND_ENV_FLAG}"), err);
+    merge_json(&mut request, object);
+    object = request;
+}
+let mut ${request_prop_name}: api::${request_prop_type} = match json::value::from_value(object) {
+    Ok(value) => value,
+    Err(json_err) => {
+        err.issues.push(CLIError::Input(InputError::Request((${opt_value(REQUEST_FILE_ARG, default='-')}.to_string(), json_err.to_string()))));
+        Default::default()
+    }
+};
 </%def>
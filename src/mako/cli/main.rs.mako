@@ -2,17 +2,22 @@
 <%namespace name="engine" file="lib/engine.mako"/>\
 <%namespace name="util" file="../lib/util.mako"/>\
 <%  
+    import json
+
     from util import (new_context, rust_comment, to_extern_crate_name, library_to_crate_name, library_name,
-                      indent_all_but_first_by)
-    from cli import OUT_ARG, DEBUG_FLAG, opt_value
+                      indent_all_but_first_by, rust_raw_string)
+    from cli import (OUT_ARG, DEBUG_FLAG, CONFIG_DIR, CONFIG_DIR_FLAG, opt_value, vulnerability_report_method, ema_avail_methods,
+                     alias_file_basename, has_describe_command, describe_commands, DESCRIBE_CMD, DESCRIBE_RESOURCE_ARG,
+                     DESCRIBE_METHOD_ARG, ERROR_FORMAT_FLAG)
 
     c = new_context(schemas, resources, context.get('methods'))
+    api_crate = to_extern_crate_name(library_to_crate_name(library_name(name, version), make.depends_on_suffix))
     default_user_agent = "google-cli-rust-client/" + cargo.build_version
 %>\
 <%block filter="rust_comment">\
 <%util:gen_info source="${self.uri}" />\
 </%block>
-#![allow(unused_variables, unused_imports, dead_code, unused_mut)]
+#![allow(unused_variables, unused_imports, dead_code, unused_mut, deprecated)]
 
 extern crate tokio;
 
@@ -23,39 +28,139 @@ use std::env;
 use std::io::{self, Write};
 use clap::{App, SubCommand, Arg};
 
-use ${to_extern_crate_name(library_to_crate_name(library_name(name, version), make.depends_on_suffix))}::{api, Error, oauth2};
+use ${api_crate}::{api, Error, oauth2};
+use ${api_crate}::client::{cassette, AdaptivePageSize, CancellationToken, ConnectionOptions, ConnectionRebuild,
+                           ContentRange, Delegate, ExecutableCredentials, MediaStream, ServiceAccountKeys, StaticToken, sniff_mime_type};
+use client::{ErrorFormat, ErrorReport};
+% if vulnerability_report_method(c, api):
+use ${api_crate}::vulnerabilities;
+% endif
+% if ema_avail_methods(c, api):
+use ${api_crate}::avails;
+% endif
 
 mod client;
 
 ${engine.new(c)}\
+% if has_describe_command(c):
+
+/// The description of all commands, generated from the discovery document, which the `${DESCRIBE_CMD}` command writes.
+const COMMANDS_DESCRIPTION: &str = ${rust_raw_string(json.dumps(describe_commands(c, parameters is not UNDEFINED and parameters or None), sort_keys=True))};
+% endif
+
+/// Returns the reports of `--${ERROR_FORMAT_FLAG} json` of the given error, one for each project it occurred for.
+fn doit_error_reports(doit_err: DoitError) -> Vec<ErrorReport> {
+    let report = match doit_err {
+        DoitError::IoError(path, err) => ErrorReport::new(1, format!("Failed to open output file '{}': {}", path, err)),
+        DoitError::ApiError(Error::BadRequest(response)) => ErrorReport::new(1, "Bad Request").with_response(&response),
+        DoitError::ApiError(Error::Failure(res)) => ErrorReport {
+            http_status: Some(res.status().as_u16()),
+            ..ErrorReport::new(1, Error::Failure(res))
+        },
+        DoitError::ApiError(err) => ErrorReport::new(1, err),
+        DoitError::ProjectErrors(errors) => {
+            return errors.into_iter()
+                .flat_map(|(project, err)| doit_error_reports(err).into_iter()
+                    .map(move |report| ErrorReport { project: Some(project.clone()), ..report }))
+                .collect();
+        },
+        DoitError::TokenError(msg) => ErrorReport::new(1, msg),
+        DoitError::NoItems => ErrorReport::new(1, "The response contains no items"),
+    };
+    vec![report]
+}
+
+fn print_error_report(report: &ErrorReport) {
+    writeln!(io::stderr(), "{}", report.to_json()).ok();
+}
+
+fn print_doit_error(doit_err: DoitError, prefix: &str, debug: bool, error_format: ErrorFormat) {
+    if error_format == ErrorFormat::Json {
+        doit_error_reports(doit_err).iter().for_each(print_error_report);
+        return;
+    }
+    match doit_err {
+        DoitError::IoError(path, err) => {
+            writeln!(io::stderr(), "{}Failed to open output file '{}': {}", prefix, path, err).ok();
+        },
+        DoitError::ApiError(err) => {
+            if debug {
+                writeln!(io::stderr(), "{}{:#?}", prefix, err).ok();
+            } else {
+                writeln!(io::stderr(), "{}{}", prefix, err).ok();
+            }
+        },
+        DoitError::ProjectErrors(errors) => {
+            for (project, err) in errors {
+                print_doit_error(err, &format!("{}[{}] ", prefix, project), debug, error_format);
+            }
+        },
+        DoitError::TokenError(msg) => {
+            writeln!(io::stderr(), "{}{}", prefix, msg).ok();
+        },
+        DoitError::NoItems => {
+            writeln!(io::stderr(), "{}The response contains no items", prefix).ok();
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
     let mut exit_status = 0i32;
     ${argparse.new(c) | indent_all_but_first_by(1)}\
-    let matches = app.get_matches();
+    let args: Vec<String> = env::args().collect();
+    let aliases = client::expand_config_dir(client::flag_value(&args, "${CONFIG_DIR_FLAG}").unwrap_or("${CONFIG_DIR}"))
+        .and_then(|config_dir| client::Aliases::from_config_dir(&config_dir, "${alias_file_basename(util.program_name())}"));
+    let args = match aliases {
+        Ok(aliases) => aliases.expand(args, &global_value_flags, &commands),
+        Err(err) => {
+            writeln!(io::stderr(), "{}", err).ok();
+            std::process::exit(1);
+        }
+    };
+    let matches = app.get_matches_from(args);
 
     let debug = matches.is_present("${DEBUG_FLAG}");
+    let error_format = match ErrorFormat::from_opts(&matches) {
+        Ok(error_format) => error_format,
+        Err(err) => {
+            writeln!(io::stderr(), "{}", err).ok();
+            std::process::exit(1);
+        }
+    };
+% if has_describe_command(c):
+    // describing commands needs neither credentials nor the configuration directory
+    if let ("${DESCRIBE_CMD}", Some(opt)) = matches.subcommand() {
+        match client::describe_commands(COMMANDS_DESCRIPTION, opt.value_of("${DESCRIBE_RESOURCE_ARG}"),
+                                        opt.value_of("${DESCRIBE_METHOD_ARG}")) {
+            Ok(described) => {
+                let mut ostream = io::stdout();
+                json::to_writer_pretty(&mut ostream, &described).expect("serde to work");
+                writeln!(ostream).ok();
+            },
+            Err(err) => {
+                exit_status = 1;
+                match error_format {
+                    ErrorFormat::Text => { write!(io::stderr(), "{}", err).ok(); },
+                    ErrorFormat::Json => print_error_report(&ErrorReport::new(exit_status, err)),
+                }
+            }
+        }
+        std::process::exit(exit_status);
+    }
+% endif
     match Engine::new(matches).await {
         Err(err) => {
             exit_status = err.exit_code;
-            writeln!(io::stderr(), "{}", err).ok();
+            match error_format {
+                ErrorFormat::Text => { writeln!(io::stderr(), "{}", err).ok(); },
+                ErrorFormat::Json => print_error_report(&ErrorReport::new(err.exit_code, err)),
+            }
         },
         Ok(engine) => {
             if let Err(doit_err) = engine.doit().await {
                 exit_status = 1;
-                match doit_err {
-                    DoitError::IoError(path, err) => {
-                        writeln!(io::stderr(), "Failed to open output file '{}': {}", path, err).ok();
-                    },
-                    DoitError::ApiError(err) => {
-                        if debug {
-                            writeln!(io::stderr(), "{:#?}", err).ok();
-                        } else {
-                            writeln!(io::stderr(), "{}", err).ok();
-                        }
-                    }
-                }
+                print_doit_error(doit_err, "", debug, error_format);
             }
         }
     }
@@ -61,21 +61,25 @@
 	import util
 	import os
 	import json
+	import yaml
 
 	def gen_type_cfg_path(id):
 		return '$(API_DIR)/type-' + id + '.yaml'
 
 	CMN_SRC = '/src/client.rs'
+	STAMP = '/.timestamp'
 
 	api_name = util.library_name(an, version)
 	api_target = util.target_directory_name(an, version, suffix)
 	depends_on_target = ''
 	if make.depends_on_suffix is not None:
-		depends_on_target = directories.output + '/' + util.target_directory_name(an, version, make.depends_on_suffix) + CMN_SRC
+		depends_on_target = directories.output + '/' + util.target_directory_name(an, version, make.depends_on_suffix) + STAMP
 	crate_name = util.library_to_crate_name(api_name, suffix)
 	gen_root = directories.output + '/' + api_target
-	gen_root_stamp = gen_root + '/.timestamp'
-	api_common = gen_root + CMN_SRC
+	gen_root_stamp = gen_root + STAMP
+	# crates using the common crate don't get their own copy of the client code
+	uses_common_crate = bool(cargo.get('common_crate'))
+	api_common = uses_common_crate and gen_root_stamp or gen_root + CMN_SRC
 	api_clean = api_target + '-clean'
 	api_cargo = api_target + '-cargo'
 	api_doc = api_target + '-doc'
@@ -96,8 +100,25 @@
 			api_json_overrides = api_meta_dir + '/' + an + '-api_overrides.yaml'
 			type_specific_cfg = gen_type_cfg_path(make.id)
 			api_json_inputs = api_json + ' $(API_SHARED_INFO) ' + type_specific_cfg
+			api_extensions = list()
+			api_conversions = list()
 			if os.path.isfile(api_json_overrides):
 				api_json_inputs += ' ' + api_json_overrides
+				with open(api_json_overrides, 'r') as ofh:
+					api_overrides = yaml.safe_load(ofh).get('api') or dict()
+					api_extensions = api_overrides.get('extensions', list())
+					api_conversions = api_overrides.get('conversions', list())
+			# extensions and conversions are part of the API crate, programs just use them through it
+			if make.id != 'api':
+				api_extensions = list()
+				api_conversions = list()
+			# the data of the other versions is available in its own namespace, to generate the conversions
+			api_conversion_inputs = ''
+			api_conversion_data_files = ''
+			for other_version in api_conversions:
+				other_json = util.api_json_path(directories.api_base, an, other_version)
+				api_conversion_inputs += ' ' + other_json
+				api_conversion_data_files += ' %s=%s' % (other_json, util.conversion_data_namespace(other_version))
 			api_info.append((api_target, api_clean, api_cargo, api_doc, api_crate_publish_file, gen_root))
 
 			space_join = lambda i: ' '.join(a[i] for a in api_info)
@@ -105,17 +126,26 @@
 		print('Could not open JSON file at {}'.format(api_json))
 		print(e)
 %>\
+% if not uses_common_crate:
 ${api_common}: $(RUST_SRC)/${make.id}/client.rs $(lastword $(MAKEFILE_LIST)) ${gen_root_stamp}
 	@ echo "// COPY OF '$<'"  > $@
 	@ echo "// DO NOT EDIT"  >> $@
 	@cat $< >> $@
+% endif
 
-${gen_root_stamp}: $(MAKO_RENDER) ${' '.join(i[0] for i in sds)} ${api_json_inputs} $(MAKO_STANDARD_DEPENDENCIES) ${depends_on_target}
+${gen_root_stamp}: $(MAKO_RENDER) ${' '.join(i[0] for i in sds)} ${api_json_inputs}${api_conversion_inputs} $(MAKO_STANDARD_DEPENDENCIES) ${depends_on_target}
 	@echo Generating ${api_target}
-	$(MAKO) -io ${' '.join("%s=%s" % (s, d) for s, d in sds)} ${post_processor_arg} --data-files ${api_json_inputs}
+	$(MAKO) -io ${' '.join("%s=%s" % (s, d) for s, d in sds)} ${post_processor_arg} --data-files ${api_json_inputs}${api_conversion_data_files}
 	@touch $@
 
-${api_target}: ${api_common}
+% for ext in api_extensions:
+${gen_root}/src/${ext}.rs: $(RUST_SRC)/${make.id}/extensions/${ext}.rs $(lastword $(MAKEFILE_LIST)) ${gen_root_stamp}
+	@ echo "// COPY OF '$<'"  > $@
+	@ echo "// DO NOT EDIT"  >> $@
+	@cat $< >> $@
+
+% endfor
+${api_target}: ${api_common} ${' '.join('%s/src/%s.rs' % (gen_root, ext) for ext in api_extensions)}
 
 ${api_crate_publish_file}: ${api_target}
 	cd ${gen_root} && cargo smart-release --execute --no-changelog -b keep
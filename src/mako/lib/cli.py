@@ -18,7 +18,42 @@ KEY_VALUE_ARG = 'kv'
 SCOPE_FLAG = 'scope'
 CONFIG_DIR_FLAG = 'config-dir'
 DEBUG_FLAG = 'debug'
+STREAM_FLAG = 'stream'
+ALL_PAGES_FLAG = 'all-pages'
+ADAPTIVE_PAGE_SIZE_FLAG = 'adaptive-page-size'
+SORT_BY_FLAG = 'sort-by'
+LIMIT_FLAG = 'limit'
+COUNT_FLAG = 'count'
+FAIL_ON_EMPTY_FLAG = 'fail-on-empty'
+VERBOSE_FLAG = 'verbose'
+CANONICAL_JSON_FLAG = 'canonical-json'
+OUTPUT_FORMAT_FLAG = 'format'
+COLUMNS_FLAG = 'columns'
+OUTPUT_TEMPLATE_FLAG = 'output-template'
+MAX_WIDTH_FLAG = 'max-width'
+NO_TRUNCATE_FLAG = 'no-truncate'
+ERROR_FORMAT_FLAG = 'error-format'
+AUTH_FLOW_FLAG = 'auth-flow'
+WATCH_FLAG = 'watch'
+INTERVAL_FLAG = 'interval'
+PROJECTS_FROM_FLAG = 'projects-from'
+BILLING_PROJECT_FLAG = 'billing-project'
+EXTERNAL_ACCOUNT_FLAG = 'external-account'
+SA_KEY_DIR_FLAG = 'sa-key-dir'
+PROGRESS_JSON_FLAG = 'progress-json'
+PARAM_FILE_FLAG = 'param-file'
+NO_VERIFY_FLAG = 'no-verify'
+EXEC_FLAG = 'exec'
+REPLAY_FILE_FLAG = 'replay-file'
+RECORD_FILE_FLAG = 'record-file'
+REQUEST_FILE_FLAG = 'request-file'
+SKELETON_FLAG = 'generate-request-skeleton'
+EXPAND_ENV_FLAG = 'expand-env'
 DEFAULT_MIME = 'application/octet-stream'
+# The suffixes of the subcommands dedicated to uploading the media of a method, like `insert-upload`, and to
+# downloading it, like `get-download`
+UPLOAD_SUBCOMMAND_SUFFIX = '-upload'
+DOWNLOAD_SUBCOMMAND_SUFFIX = '-download'
 
 MODE_ARG = 'mode'
 FILE_ARG = 'file'
@@ -28,12 +63,92 @@ MIME_FLAG = 'm'
 OUT_ARG = 'out'
 
 SCOPE_ARG = 'url'
+PROJECTS_FROM_ARG = 'projects-file'
+BILLING_PROJECT_ARG = 'project-id'
+EXTERNAL_ACCOUNT_ARG = 'credential-config'
+SA_KEY_DIR_ARG = 'key-dir'
 CONFIG_DIR_ARG = 'folder'
+PAGE_SIZE_ARG = 'page-size'
+SORT_BY_ARG = 'sort-field'
+LIMIT_ARG = 'max-items'
+PROGRESS_JSON_ARG = 'progress-file'
+PARAM_FILE_ARG = 'params-path'
+EXEC_ARG = 'command-line'
+REPLAY_FILE_ARG = 'replay-cassette'
+RECORD_FILE_ARG = 'record-cassette'
+REQUEST_FILE_ARG = 'request-path'
+OUTPUT_FORMAT_ARG = 'output-format'
+COLUMNS_ARG = 'column-paths'
+OUTPUT_TEMPLATE_ARG = 'template-path'
+MAX_WIDTH_ARG = 'width'
+OUTPUT_FORMATS = ('json', 'table', 'csv')
+ERROR_FORMAT_ARG = 'error-format'
+ERROR_FORMATS = ('text', 'json')
+AUTH_FLOW_ARG = 'auth-flow'
+AUTH_FLOWS = ('redirect', 'device')
+INTERVAL_ARG = 'poll-interval'
+# The interval --watch polls list responses with, unless --interval sets another one
+DEFAULT_WATCH_INTERVAL = '30s'
+# The width cells of tables are truncated to, unless --max-width sets another one
+DEFAULT_MAX_COLUMN_WIDTH = 40
 
 FIELD_SEP = '.'
+# Marks the field of a list of structures on the command-line, like `bindings[]`
+LIST_INDEX = '[]'
+# Stands for the key of a map on the command-line, like `build-options.*`, which is set like `build-options.debug=true`
+MAP_KEY = '*'
+
+PROJECT_PATTERN = '^projects/[^/]+$'
+# The kinds of resources owning the resources of many APIs, which appear as first segment of their names
+PARENT_KINDS = ('projects', 'folders', 'organizations', 'billingAccounts')
 
 CONFIG_DIR = '~/.google-service-cli'
 
+DEPRECATION_WARNING = "warning: '%s' is deprecated by the API, which might remove it"
+
+AUTH_CMD = 'auth'
+# (name, alias or None, description) of each subcommand of the auth command
+AUTH_SUBCOMMANDS = (
+    ('login', None, "Obtain a token for the given scopes, asking for consent if there is none yet"),
+    ('print-access-token', None, "Print a valid access token for the given scopes, e.g. to use it with curl"),
+    ('print-identity-token', None, "Print an identity token of the authenticated user, e.g. to call services checking it"),
+    ('revoke', 'logout', "Revoke and forget the persisted tokens for exactly the given scopes, or all of them"),
+    ('list', None, "List the scopes of all persisted tokens, along with their expiry date"),
+)
+AUTH_SCOPES_FLAG = 'scopes'
+# only checks the audience, as the identity tokens of users are always issued for the client id
+AUDIENCE_FLAG = 'expect-audience'
+# (flag, value name, description, multiple) of the arguments of the auth subcommands which have some
+AUTH_SUBCOMMAND_ARGS = {
+    'print-access-token': ((AUTH_SCOPES_FLAG, SCOPE_ARG,
+                            "The scopes of the token, in addition to the ones given with --%s" % SCOPE_FLAG, True),),
+    'print-identity-token': ((AUDIENCE_FLAG, 'aud',
+                              "Fail unless the token was issued for this audience. It can't be chosen, as the tokens "
+                              "of users are always issued for the client id of the application secret", False),),
+}
+
+DESCRIBE_CMD = 'describe'
+DESCRIBE_RESOURCE_ARG = 'resource'
+DESCRIBE_METHOD_ARG = 'method'
+# The keys of parameters and fields in the discovery document which are part of their description
+DESCRIBE_KEYS = ('type', 'format', 'description', 'location', 'repeated', 'default', 'minimum', 'maximum', 'pattern',
+                 'enum', 'enumDescriptions', 'deprecated')
+
+REPORT_CMD = 'report'
+VULNERABILITIES_CMD = 'vulnerabilities'
+IMAGE_FLAG = 'image'
+REPORT_PROJECT_FLAG = 'project'
+REPORT_FORMAT_FLAG = 'format'
+REPORT_FORMATS = ('table', 'sarif', 'vex')
+DIFF_CMD = 'diff'
+FROM_FLAG = 'from'
+TO_FLAG = 'to'
+DIFF_FORMATS = ('table', 'json')
+# The module of the API crate which groups and converts the vulnerabilities of an image
+VULNERABILITIES_EXTENSION = 'vulnerabilities'
+# The module of the API crate which exports Avails in the columns of the EMA spreadsheets
+AVAILS_EXTENSION = 'avails'
+
 POD_TYPES = set(('boolean', 'integer', 'number', 'uint32', 'double', 'float', 'int32', 'int64', 'uint64', 'string'))
 
 re_splitters = re.compile(r"%s ([\w\-\.]+)\n(.*?)\n%s" % (SPLIT_START, SPLIT_END), re.MULTILINE|re.DOTALL)
@@ -46,6 +161,10 @@ CTYPE_POD = 'pod'
 CTYPE_ARRAY = 'list'
 CTYPE_MAP = 'map'
 SchemaEntry = collections.namedtuple('SchemaEntry', ['container_type', 'actual_property', 'property'])
+# Key of the cli-schemas (see to_cli_schema()) of the structures in lists
+CLI_LIST_KEY = 'cli_list'
+# Key of the cli-schemas of the structures which are the values of maps
+CLI_MAP_KEY = 'cli_map'
 
 JSON_TYPE_RND_MAP = {'boolean': lambda: str(bool(randint(0, 1))).lower(),
                      'integer' : lambda: randint(0, 100),
@@ -98,6 +217,225 @@ def new_method_context(resource, method, c):
     return MethodContext(m, response_schema, params, request_value, media_params,
                          required_props, optional_props, part_prop)
 
+list_items_property = util.list_items_property
+
+# Returns the page-token parameter of the method if it returns pages of items, or None
+def page_token_param(mc):
+    if list_items_property(mc.response_schema) is None:
+        return None
+    for p in mc.optional_props:
+        if p.name == 'pageToken':
+            return p
+    return None
+
+# Returns (property, maximum) of the parameter setting the amount of items per page, if the method returns pages of
+# items, or (None, None). `maximum` is the largest page size the API allows.
+def page_size_param(mc):
+    if page_token_param(mc) is None:
+        return None, None
+    return util.page_size_param(mc.optional_props)
+
+# Returns True if the method has a subcommand downloading its media, which it only returns if the `alt` parameter asks
+# for it, instead of the decoded response. `global_params` are the names of the parameters of all methods of the API.
+def has_download_subcommand(mc, global_params):
+    if mc.media_params or not mc.m.get('supportsMediaDownload', False) or not mc.response_schema:
+        return False
+    return 'alt' in global_params or any(p.name == 'alt' for p in mc.optional_props)
+
+# Returns (property, format) if the method operates on a single project which is identified by its only required
+# scalar argument, or (None, None). `format` turns a project id into a value for the property.
+def project_param(mc):
+    if mc.media_params:
+        return None, None
+    props = [p for p in mc.required_props if not is_request_value_property(mc, p)]
+    if len(props) != 1:
+        return None, None
+    p = props[0]
+    if p.get('type') != 'string' or p.get('repeated', False):
+        return None, None
+    if p.get('pattern') == PROJECT_PATTERN:
+        return p, 'projects/{}'
+    if p.name in ('project', 'projectId'):
+        return p, '{}'
+    return None, None
+
+# Returns the template of the resource names matched by the discovery `pattern`, like `projects/*/locations/*`, where
+# `*` stands for a single segment and a trailing `**` for all remaining ones, or None if it can't be expressed as one.
+def resource_template(pattern):
+    if not (pattern.startswith('^') and pattern.endswith('$')):
+        return None
+    segments = pattern[1:-1].replace('[^/]+', '*').split('/')
+    template = list()
+    for i, segment in enumerate(segments):
+        if segment == '*':
+            template.append('*')
+        elif segment in ('.*', '.+') and i == len(segments) - 1:
+            template.append('**')
+        elif re.match(r'^[A-Za-z][A-Za-z0-9_-]*$', segment):
+            template.append(segment)
+        else:
+            return None
+    return '/'.join(template)
+
+# Returns the templates of the resource names accepted by the required path parameter `p` of the method, or an empty
+# list if its values can't be validated. If the method belongs to a kind of parent, like `projects`, the same method
+# of all other kinds of parents, like `folders` or `organizations`, shares its path, which makes it accept their
+# resource names as well.
+def resource_name_templates(c, mc, p):
+    if p.get('location') != 'path' or p.get('type') != 'string' or p.get('repeated', False) or not p.get('pattern'):
+        return []
+    patterns = [p.pattern]
+    parts = mc.m.id.split('.')
+    if len(parts) > 3 and parts[1] in PARENT_KINDS:
+        for kind in PARENT_KINDS:
+            m = c.fqan_map.get('.'.join([parts[0], kind] + parts[2:]))
+            if m is None or m is mc.m or m.get('path') != mc.m.get('path'):
+                continue
+            pattern = m.get('parameters', dict()).get(p.name, dict()).get('pattern')
+            if pattern and pattern not in patterns:
+                patterns.append(pattern)
+        # end for each kind of parent
+    templates = [resource_template(pattern) for pattern in patterns]
+    if None in templates:
+        return []
+    return templates
+
+# Returns a sentence describing the resource names matching the given templates
+def resource_templates_doc(templates):
+    return 'It must look like %s.' % ' or '.join('`%s`' % t for t in templates)
+
+# Returns True if the CLI should get the command to manage its persisted tokens
+def has_auth_command(c, auth):
+    return util.supports_scopes(auth) and AUTH_CMD not in (mangle_subcommand(r) for r in c.rta_map.keys())
+
+# Returns (resource, method, method_context) of the method listing the occurrences of a project if the CLI
+# should get the commands reporting the vulnerabilities of an image, and comparing them between two, or None.
+# Only the Container Analysis API has occurrences of vulnerabilities, which can be filtered by resource, and
+# the report is rendered by its crate, if it has the respective extension.
+def vulnerability_report_method(c, api):
+    if VULNERABILITIES_EXTENSION not in api.get('extensions', list()):
+        return None
+    if set((REPORT_CMD, DIFF_CMD)) & set(mangle_subcommand(r) for r in c.rta_map.keys()):
+        return None
+    for resource in sorted(c.rta_map.keys()):
+        if 'occurrences.list' not in c.rta_map[resource]:
+            continue
+        mc = new_method_context(resource, 'occurrences.list', c)
+        items_property = list_items_property(mc.response_schema)
+        project_p, _ = project_param(mc)
+        if items_property is None or project_p is None or page_token_param(mc) is None:
+            continue
+        if 'filter' not in (p.name for p in mc.optional_props):
+            continue
+        item_schema = c.schemas.get(mc.response_schema['properties'][items_property]['items'][util.TREF], dict())
+        if 'vulnerability' in item_schema.get('properties', dict()):
+            return resource, 'occurrences.list', mc
+    return None
+
+# Returns the (resource, method) of the methods whose responses are Avails, or lists of them, which the CLI writes
+# as CSV in the columns of the EMA spreadsheets, if the API crate has the respective extension.
+def ema_avail_methods(c, api):
+    if AVAILS_EXTENSION not in api.get('extensions', list()):
+        return []
+    res = list()
+    for resource in sorted(c.rta_map.keys()):
+        for method in sorted(c.rta_map[resource]):
+            mc = new_method_context(resource, method, c)
+            if not mc.response_schema:
+                continue
+            schema = mc.response_schema
+            items_property = list_items_property(schema)
+            if items_property is not None:
+                schema = c.schemas.get(schema['properties'][items_property]['items'].get(util.TREF), dict())
+            if schema.get('id') == 'Avail':
+                res.append((resource, method))
+    return res
+
+# Returns True if the CLI should get the command describing its commands as JSON
+def has_describe_command(c):
+    return DESCRIBE_CMD not in (mangle_subcommand(r) for r in c.rta_map.keys())
+
+def _describe_property(p, name=None):
+    res = dict((k, p[k]) for k in DESCRIBE_KEYS if k in p)
+    if name is not None:
+        res['name'] = mangle_subcommand(name)
+    return res
+
+# Returns the fields of the given cli-schema (result from to_cli_schema(schema)) by their name on the command-line,
+# along with the key setting them with -r
+def _describe_fields(schema, prefix=''):
+    fields = dict()
+    for fn in sorted(schema.fields.keys()):
+        f = schema.fields[fn]
+        key = prefix + cli_field_name(fn, f)
+        if isinstance(f, SchemaEntry):
+            # the type and values of lists and maps are the ones of their elements
+            field = _describe_property(f.actual_property)
+            field.update((k, f.property[k]) for k in ('description', 'deprecated') if k in f.property)
+            field['container'] = f.container_type
+            field['key'] = key
+        else:
+            field = {'type': 'object', 'schema': f.id, 'key': key, 'fields': _describe_fields(f, key + FIELD_SEP)}
+            if f.get(CLI_LIST_KEY, False):
+                field['container'] = CTYPE_ARRAY
+            elif f.get(CLI_MAP_KEY, False):
+                field['container'] = CTYPE_MAP
+            if 'description' in f:
+                field['description'] = f.description
+        fields[mangle_subcommand(fn)] = field
+    return fields
+
+# Returns the description of the command calling the method of the given method context, as JSON value
+def describe_method(c, mc):
+    project_p, _ = project_param(mc)
+    arguments = list()
+    for p in mc.required_props:
+        if is_request_value_property(mc, p):
+            continue
+        arg = _describe_property(p, p.name)
+        templates = p is not project_p and resource_name_templates(c, mc, p)
+        if templates:
+            arg['templates'] = templates
+        arguments.append(arg)
+    # end for each required property
+
+    request = None
+    if mc.request_value:
+        request = {'schema': mc.request_value.id,
+                   'fields': _describe_fields(to_cli_schema(c, mc.request_value))}
+    return {
+        'id': mc.m.id,
+        'description': mc.m.get('description'),
+        'httpMethod': mc.m.httpMethod,
+        'path': mc.m.path,
+        'deprecated': util.is_deprecated(mc.m),
+        'scopes': sorted(mc.m.get('scopes', list())),
+        'defaultScope': util.method_default_scope(mc.m, c),
+        'arguments': arguments,
+        'parameters': [_describe_property(p, p.name) for p in mc.optional_props if not p.get('skip_example', False)],
+        'request': request,
+        'upload': [mp.protocol for mp in mc.media_params],
+        'response': mc.response_schema and mc.response_schema.id or None,
+        'listItems': list_items_property(mc.response_schema),
+    }
+
+# Returns the description of all commands calling methods, and of the parameters all of them accept, as JSON value
+def describe_commands(c, parameters):
+    commands = dict()
+    for resource in sorted(c.rta_map.keys()):
+        methods = commands[mangle_subcommand(resource)] = dict()
+        for method in sorted(c.rta_map[resource]):
+            methods[mangle_subcommand(method)] = describe_method(c, new_method_context(resource, method, c))
+    # end for each resource
+    return {
+        'parameters': [_describe_property(p, pn) for pn, p in sorted((parameters or dict()).items())],
+        'commands': commands,
+    }
+
+# Returns the scope used by the auth command if none is specified
+def default_auth_scope(auth):
+    return sorted(auth.oauth2.scopes.keys())[0]
+
 def comma_sep_fields(fields):
     return ', '.join('"%s"' % mangle_subcommand(f) for f in sorted(fields))
 
@@ -134,6 +472,14 @@ def opt_values(name, opt='opt'):
 def application_secret_path(program_name):
     return program_name + '-secret.json'
 
+# The name of the file in the config directory holding the default `-v` parameters, without extension
+def param_file_basename(program_name):
+    return program_name + '-params'
+
+# The name of the file in the config directory holding the aliases of command-lines, without extension
+def alias_file_basename(program_name):
+    return program_name + '-aliases'
+
 # Returns identifier for method dealing with options for the given resource-method pair
 def call_method_ident(resource, method):
     return '_%s_%s' % (ident(resource), ident(method))
@@ -159,7 +505,8 @@ def to_docopt_arg(p):
 
 
 # Return schema' with fields dict: { 'field1' : SchemaField(...), 'SubSchema': schema' }
-def to_cli_schema(c, schema):
+# The schemas' of lists of structures are marked with CLI_LIST_KEY, and the ones of the values of maps with CLI_MAP_KEY.
+def to_cli_schema(c, schema, parents=()):
     res = deepcopy(schema)
     fd = dict()
     res['fields'] = fd
@@ -185,19 +532,36 @@ def to_cli_schema(c, schema):
             return pc
         # end
 
+        # prevent recursion (in case of self-referential schemas)
+        parents_and_self = parents + (schema.id,)
         if util.TREF in p:
-            if p[util.TREF] != schema.id: # prevent recursion (in case of self-referential schemas)
-                set_nested_schema(to_cli_schema(c, c.schemas[p[util.TREF]]))
+            if p[util.TREF] not in parents_and_self:
+                set_nested_schema(to_cli_schema(c, c.schemas[p[util.TREF]], parents_and_self))
+        elif p.type == 'array' and util.TREF in p.get('items', dict()):
+            if p['items'][util.TREF] not in parents_and_self:
+                ns = to_cli_schema(c, c.schemas[p['items'][util.TREF]], parents_and_self)
+                ns[CLI_LIST_KEY] = True
+                set_nested_schema(ns)
         elif p.type == 'array' and 'items' in p and 'type' in p.get('items') and p.get('items').type in POD_TYPES:
             pc = dup_property()
             fd[pn] = SchemaEntry(CTYPE_ARRAY, pc.get('items'), pc)
         elif p.type == 'object':
             if util.is_map_prop(p):
-                if 'type' in p.additionalProperties and p.additionalProperties.type in POD_TYPES:
+                ap = p.additionalProperties
+                ns = None
+                if 'type' in ap and ap.type in POD_TYPES:
                     pc = dup_property()
                     fd[pn] = SchemaEntry(CTYPE_MAP, pc.additionalProperties, pc)
+                elif util.TREF in ap:
+                    if ap[util.TREF] not in parents_and_self:
+                        ns = to_cli_schema(c, c.schemas[ap[util.TREF]], parents_and_self)
+                elif util.is_nested_type_property(ap):
+                    ns = to_cli_schema(c, c.schemas[util.nested_type_name(schema.id, pn)], parents_and_self)
+                if ns is not None:
+                    ns[CLI_MAP_KEY] = True
+                    set_nested_schema(ns)
             else:
-                set_nested_schema(to_cli_schema(c, c.schemas[util.nested_type_name(schema.id, pn)]))
+                set_nested_schema(to_cli_schema(c, c.schemas[util.nested_type_name(schema.id, pn)], parents_and_self))
         elif p.type in POD_TYPES:
             pc = dup_property()
             fd[pn] = SchemaEntry(CTYPE_POD, pc, pc)
@@ -206,6 +570,49 @@ def to_cli_schema(c, schema):
 
     return res
 
+# Returns the name of the given field of a cli-schema on the command-line, with LIST_INDEX if it is a list of
+# structures, like `bindings[]`, or MAP_KEY if it is a map of them, like `file-hashes.*`
+def cli_field_name(fn, f):
+    name = mangle_subcommand(fn)
+    if not isinstance(f, SchemaEntry) and f.get(CLI_LIST_KEY, False):
+        name += LIST_INDEX
+    elif not isinstance(f, SchemaEntry) and f.get(CLI_MAP_KEY, False):
+        name += FIELD_SEP + MAP_KEY
+    return name
+
+# Returns the rows of the static table of the fields of the given cli-schema which can be set on the command-line, as
+# (cli_path, path, json_type, complex_type, enum_values) tuples sorted by cli_path. The entries of maps are rows with
+# MAP_KEY as last segment, like `labels.*`, and the fields of structures in maps have it in their path, like
+# `file-hashes.*.file-hash[].type`.
+def cli_schema_field_table(schema):
+    rows = list()
+
+    def flatten(schema, cli_path, path):
+        for fn, f in schema.fields.items():
+            index = (not isinstance(f, SchemaEntry) and f.get(CLI_LIST_KEY, False)) and LIST_INDEX or ''
+            key = (not isinstance(f, SchemaEntry) and f.get(CLI_MAP_KEY, False)) and [MAP_KEY] or []
+            fcli_path = cli_path + [mangle_subcommand(fn) + index] + key
+            fpath = path + [fn + index] + key
+            if isinstance(f, SchemaEntry):
+                ptype = JSON_TYPE_TO_ENUM_MAP[actual_json_type(fn, f.actual_property.type)]
+                enum = f.actual_property.get('enum', list())
+                deprecated = util.is_deprecated(f.property)
+                rows.append((FIELD_SEP.join(fcli_path), FIELD_SEP.join(fpath), ptype,
+                             CTYPE_TO_ENUM_MAP[f.container_type], enum, deprecated))
+                if f.container_type == CTYPE_MAP:
+                    rows.append((FIELD_SEP.join(fcli_path + [MAP_KEY]), FIELD_SEP.join(fpath + [MAP_KEY]), ptype,
+                                 CTYPE_TO_ENUM_MAP[CTYPE_POD], enum, deprecated))
+            else:
+                flatten(f, fcli_path, fpath)
+    # end utility
+
+    flatten(schema, [], [])
+    return sorted(rows)
+
+# Returns the identifier of a static table of the given request schema, like `CLUSTER_FIELDS`
+def field_table_ident(schema, suffix='FIELDS'):
+    return '%s_%s' % (ident(schema.id).upper(), suffix)
+
 
 # Convert the given cli-schema (result from to_cli_schema(schema)) to a yaml-like string. It's suitable for
 # documentation only
@@ -217,7 +624,7 @@ def cli_schema_to_yaml(schema, prefix=''):
     prefix += '  '
     for fn in sorted(schema.fields.keys()):
         f = schema.fields[fn]
-        o += '%s%s:' % (prefix, mangle_subcommand(fn))
+        o += '%s%s:' % (prefix, cli_field_name(fn, f))
         if not isinstance(f, SchemaEntry):
             o += '\n' + cli_schema_to_yaml(f, prefix)
         else:
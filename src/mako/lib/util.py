@@ -30,6 +30,8 @@ TYPE_MAP = {'boolean' : 'bool',
             'array'   : 'Vec',
             'string'  : 'String',
             'object'  : 'HashMap'}
+# The type of schema fields with the 'google-duration' format, which are strings like "3.5s" on the wire
+DURATION_TYPE = 'std::time::Duration'
 
 RESERVED_WORDS = set(('abstract', 'alignof', 'as', 'become', 'box', 'break', 'const', 'continue', 'crate', 'do',
                       'else', 'enum', 'extern', 'false', 'final', 'fn', 'for', 'if', 'impl', 'in', 'let', 'loop',
@@ -48,6 +50,7 @@ RUST_TYPE_RND_MAP = {'bool': lambda: str(bool(randint(0, 1))).lower(),
                      'String': lambda: '"%s"' % choice(words),
                      '&str': lambda: '"%s"' % choice(words),
                      '&Vec<String>': lambda: '&vec!["%s".into()]' % choice(words), # why a reference to Vec? Because it works. Should be slice, but who knows how typing works here.
+                     DURATION_TYPE: lambda: '%s::from_secs(%i)' % (DURATION_TYPE, randint(0, 100)),
 }
 TREF = '$ref'
 IO_RESPONSE = 'response'
@@ -56,9 +59,17 @@ IO_TYPES = (IO_REQUEST, IO_RESPONSE)
 INS_METHOD = 'insert'
 DEL_METHOD = 'delete'
 METHODS_RESOURCE = 'methods'
+# Files the runnable examples read the application secret from, and persist tokens to
+EXAMPLE_SECRET_FILE = 'clientsecret.json'
+EXAMPLE_TOKEN_FILE = 'tokencache.json'
+# Kinds of methods we generate runnable examples for, in order of preference per kind
+EXAMPLE_METHOD_KINDS = (('list',), ('get',), ('create', INS_METHOD, 'patch', 'update', DEL_METHOD))
 
 ADD_PARAM_FN = 'param'
+# The global parameter selecting the format of error responses, which calls set to version 2 unless it is given
+ERROR_FORMAT_PARAM = '$.xgafv'
 ADD_SCOPE_FN = 'add_scope'
+ADD_SCOPES_FN = 'add_scopes'
 ADD_PARAM_MEDIA_EXAMPLE = "." + ADD_PARAM_FN + '("alt", "media")'
 
 SPACES_PER_TAB = 4
@@ -75,9 +86,16 @@ PART_MARKER_TRAIT = 'client::Part'
 NESTED_MARKER_TRAIT = 'client::NestedType'
 REQUEST_VALUE_PROPERTY_NAME = 'request'
 DELEGATE_PROPERTY_NAME = 'delegate'
+QUOTA_PROJECT_PROPERTY_NAME = 'quotaProject'
+QUOTA_PROJECT_HEADER = 'X-Goog-User-Project'
 TO_PARTS_MARKER = 'client::ToParts'
+DEPRECATED_ATTRIBUTE = '#[deprecated(note = "marked as deprecated by the API, which might remove it")]'
 UNUSED_TYPE_MARKER = 'client::UnusedType'
 
+# Suffix of the upload method taking a `client::MediaStream`, appended to the one of the simple protocol
+STREAM_UPLOAD_SUFFIX = '_stream'
+FILE_UPLOAD_SUFFIX = '_file'
+
 PROTOCOL_TYPE_INFO = {
     'simple' : {
         'arg_name': 'stream',
@@ -230,6 +248,10 @@ def trailing_newline(s):
 def rust_doc_test_norun(s):
     return "```test_harness,no_run\n%s```" % trailing_newline(s)
 
+# a rust test that is run
+def rust_doc_test(s):
+    return "```\n%s```" % trailing_newline(s)
+
 # a rust code block in (github) markdown
 def markdown_rust_block(s):
     return "```Rust\n%s```" % trailing_newline(s)
@@ -238,6 +260,15 @@ def markdown_rust_block(s):
 def rust_test_fn_invisible(s):
     return "# async fn dox() {\n%s# }" % trailing_newline(s)
 
+# wraps s into the invisible main function of a doc test, which runs it.
+def rust_test_main_invisible(s):
+    return "# #[tokio::main]\n# async fn main() {\n%s# }" % trailing_newline(s)
+
+# wraps s into the main function of a runnable example program.
+def rust_example_main_fn(s):
+    body = re.sub('^(?=.)', ' ' * SPACES_PER_TAB, trailing_newline(s), flags=re.MULTILINE)
+    return "#[tokio::main]\nasync fn main() {\n%s}\n" % body
+
 # markdown comments
 def markdown_comment(s):
     return "<!---\n%s-->" % trailing_newline(s)
@@ -383,6 +414,9 @@ def to_rust_type(schemas, sn, pn, t, allow_optionals=True, _is_recursive=False):
                 return wrap_type(nested_type(t))
         elif rust_type == USE_FORMAT:
             rust_type = TYPE_MAP[t['format']]
+        elif sn is not None and not _is_recursive and is_duration_property(t):
+            # only schema fields, which are serialized using client::serde_duration
+            rust_type = DURATION_TYPE
 
         if t.get('repeated', False):
             rust_type = 'Vec<%s>' % rust_type
@@ -394,6 +428,10 @@ def to_rust_type(schemas, sn, pn, t, allow_optionals=True, _is_recursive=False):
     except AttributeError as err:
         raise AssertionError("%s: unknown dict layout: %s" % (str(err), t))
 
+# return True if this property is a single duration
+def is_duration_property(t):
+    return t.get('type') == 'string' and t.get('format') == 'google-duration' and not t.get('repeated', False)
+
 # return True if this property is actually a nested type
 def is_nested_type_property(t):
     return 'type' in t and t['type'] == 'object' and 'properties' in t or ('items' in t and 'properties' in t['items'])
@@ -556,9 +594,108 @@ def _method_io(type_name, c, m, marker=None):
 def method_request(c, m, marker=None):
     return _method_io('request', c, m, marker)
 
-# As method request, but returns response instead
+# Returns True if the given schema is an object without any fields, like the `Empty` response of delete methods
+def is_empty_schema(s):
+    return (s.get('type', 'object') == 'object' and not s.get('properties') and
+            'additionalProperties' not in s and 'variant' not in s)
+
+# As method request, but returns response instead. Empty responses are treated like none at all, as there is
+# nothing to decode, and their body may be missing, like the one of a `204 No Content` response.
 def method_response(c, m, marker=None):
-    return _method_io('response', c, m, marker)
+    s = _method_io('response', c, m, marker)
+    if s is not None and is_empty_schema(s):
+        return None
+    return s
+
+# Returns True if calls of the given method can be polled until their response is as expected, which reads a
+# resource without changing it, and has a decoded response.
+def method_supports_polling(c, m):
+    return (m.get('httpMethod') == 'GET' and method_response(c, m) is not None and not method_media_params(m)
+            and not method_supports_streaming(m))
+
+# Returns the name of the property holding the items of a paged list response, or None if the given
+# response schema doesn't look like one.
+def list_items_property(response_schema):
+    if not response_schema:
+        return None
+    properties = response_schema.get('properties', dict())
+    if 'nextPageToken' not in properties:
+        return None
+    for pn, p in items(properties):
+        if p.get('type') == 'array' and TREF in p.get('items', dict()):
+            return pn
+    return None
+
+# Returns the optional page-token parameter among the given ones if the method returns pages of items, or None
+def page_token_param(params, response_schema):
+    if list_items_property(response_schema) is None:
+        return None
+    for p in params:
+        if p.name == 'pageToken' and p.get('type') == 'string' and not is_required_property(p):
+            return p
+    return None
+
+# Maximum of items per page if the parameter setting the page size doesn't state it
+DEFAULT_MAX_PAGE_SIZE = 1000
+
+# Returns (property, maximum) of the optional parameter among `params` setting the amount of items per page, or
+# (None, None). `maximum` is the largest page size the API allows.
+def page_size_param(params):
+    for p in params:
+        if (p.name in ('pageSize', 'maxResults') and p.get('type') == 'integer' and not p.get('repeated', False)
+                and not is_required_property(p)):
+            return p, int(p.get('maximum', DEFAULT_MAX_PAGE_SIZE))
+    return None, None
+
+# Returns True if the given parameter of method m is the id making the server ignore retries of the call, which
+# is set to a new UUID if it isn't set and the call may be retried
+def is_request_id_param(m, p):
+    return (m.get('httpMethod') == 'POST' and p.name == 'requestId' and p.get('type') == 'string'
+            and p.get('location') == 'query' and not is_required_property(p))
+
+# Returns (get activity, batchGet activity|None, items property|None) of the get methods of `resource` which take only
+# the name of the resource they return, which `get_many()` is generated for. Their batchGet sibling is used if it takes
+# the parent and the repeated names, and returns the items in a list, like described in https://google.aip.dev/231.
+def get_many_activities(c, resource):
+    res = list()
+    activities = c.rta_map.get(resource, list())
+    for a in activities:
+        prefix, _, verb = a.rpartition('.')
+        prefix = prefix and prefix + '.'
+        m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, a)]
+        params = m.get('parameters', dict())
+        required = [pn for pn, p in params.items() if p.get('required', False)]
+        response = method_response(c, m)
+        if verb != 'get' or required != ['name'] or params['name'].get('type') != 'string' or response is None \
+           or m.get('request') or method_media_params(m) or prefix + 'getMany' in activities:
+            continue
+        batch_a = prefix + 'batchGet'
+        items_property = None
+        if batch_a in activities:
+            items_property = _batch_get_items_property(c, c.fqan_map[to_fqan(c.rtc_map[resource], resource, batch_a)],
+                                                       response)
+        res.append((a, items_property and batch_a or None, items_property))
+    return res
+
+def _batch_get_items_property(c, m, item_schema):
+    params = m.get('parameters', dict())
+    required = [pn for pn, p in params.items() if p.get('required', False)]
+    names = params.get('names', dict())
+    response = method_response(c, m)
+    if m.get('httpMethod') != 'GET' or required != ['parent'] or params['parent'].get('location') != 'path' \
+       or names.get('location') != 'query' or not names.get('repeated', False) or response is None:
+        return None
+    for pn, p in items(response.get('properties', dict())):
+        if p.get('type') == 'array' and p.get('items', dict()).get(TREF) == item_schema.id:
+            return pn
+    return None
+
+# return the path of a field of the given response schema, to show how read masks are given
+def read_mask_example(schema):
+    names = list(schema.get('properties', dict()).keys())
+    if not names or 'name' in names:
+        return 'name'
+    return names[0]
 
 # return string like 'n.clone()', but depending on the type name of tn (e.g. &str -> n.to_string())
 def rust_copy_value_s(n, tn, p):
@@ -576,6 +713,10 @@ def rust_copy_value_s(n, tn, p):
 def schema_to_required_property(s, n):
     return type(s)({'name': n, TREF: s.id, 'priority': REQUEST_PRIORITY, 'is_query_param': False})
 
+# Returns True if the given method, parameter or property is marked as deprecated by the API
+def is_deprecated(o):
+    return bool(o.get('deprecated', False))
+
 def is_required_property(p):
     return p.get('required', False) or p.get('priority', 0) > 0
 
@@ -605,6 +746,10 @@ def organize_params(params, request_value):
     # end for each property
     return required_props, optional_props, part_prop
 
+# Returns True if the method streams its response, which is then received as server-sent events
+def method_supports_streaming(m):
+    return m.get('streamingType', 'NONE') != 'NONE'
+
 # returns method parameters based on whether we can make uploads, and which protocols are supported
 # or empty list if there is no media upload
 def method_media_params(m):
@@ -657,6 +802,19 @@ while executing the actual API request.
 
 It should be used to handle progress information, and to implement a certain level of resilience."""})
     params.append(dp)
+    # the project to bill, sent as header. Overrides the default set on the hub.
+    qp = type(m)({ 'name': QUOTA_PROJECT_PROPERTY_NAME,
+          'type': 'string',
+          'location': 'header',
+          'skip_example' : True,
+          'priority': 0,
+          'is_query_param': False,
+          'description':
+"""The project to be billed for this call, and whose quota it counts against, instead of the one the
+credentials belong to. This is required by some APIs when using user credentials.
+
+It overrides the project set on the hub using its `quota_project()` method."""})
+    params.append(qp)
     return params, request_value
 
 
@@ -903,20 +1061,54 @@ def hub_type_params_s():
 def supports_scopes(auth):
     return bool(auth) and bool(auth.oauth2)
 
-# Returns th desired scope for the given method. It will use read-only scopes for read-only methods
-# May be None no scope-based authentication is required
-def method_default_scope(m):
+# True if any method of the API can download media, whose checksums are verified
+def supports_media_download(c):
+    return any(m.get('supportsMediaDownload', False) for m in c.fqan_map.values())
+
+# Returns the signature of method `m`, which is compared to the one of the live discovery document at runtime.
+# It must match the one computed by `method_signature()` of the `discovery` module of the common crate.
+def method_signature(m):
+    params = list()
+    for pn, p in sorted(m.get('parameters', dict()).items()):
+        sig = '%s:%s:%s' % (pn, p.get('location', ''), p.get('type', ''))
+        if p.get('repeated', False):
+            sig += '[]'
+        if p.get('required', False):
+            sig += '!'
+        params.append(sig)
+    return '%s %s %s %s %s' % (m.get('httpMethod', ''), m.get('path', ''), ','.join(params),
+                               m.get('request', dict()).get(TREF, '-'), m.get('response', dict()).get(TREF, '-'))
+
+READ_ONLY_SCOPE_MARKERS = ('readonly', 'read-only', 'read_only')
+
+def is_read_only_scope(url):
+    return any(marker in url for marker in READ_ONLY_SCOPE_MARKERS)
+
+# Returns {scope url -> set(ids of all methods accepting it)}
+def _methods_by_scope(c):
+    res = collections.defaultdict(set)
+    for m in c.fqan_map.values():
+        for scope in m.get('scopes', ()):
+            res[scope].add(m.id)
+    return res
+
+# Returns the minimal scope for the given method, which is None if no scope-based authentication is required.
+# Read-only methods use a read-only scope. Of these, scopes granting more than another one are skipped, which is
+# the case if all methods accepting the other scope accept them, too. Other methods use the first scope.
+def method_default_scope(m, c):
     if 'scopes' not in m:
         return None
     default_scope = sorted(m.scopes)[0]
-    if m.httpMethod in ('HEAD', 'GET', 'OPTIONS', 'TRACE'):
-        for scope in m.scopes:
-            if 'readonly' in scope:
-                default_scope = scope
-                break
-        # end for each scope
-    # end try to find read-only default scope
-    return default_scope
+    if m.httpMethod not in ('HEAD', 'GET', 'OPTIONS', 'TRACE'):
+        return default_scope
+    candidates = [scope for scope in m.scopes if is_read_only_scope(scope)]
+    if not candidates:
+        return default_scope
+    methods_by_scope = _methods_by_scope(c)
+    minimal = [scope for scope in candidates
+               if not any(methods_by_scope[other] < methods_by_scope[scope] for other in candidates)]
+    # API specific read-only scopes are named 'readonly', and preferred over generic ones
+    return sorted(minimal, key=lambda scope: 'readonly' not in scope)[0]
 
 _rb_type_params = ("'a", ) + HUB_TYPE_PARAMETERS
 
@@ -981,6 +1173,41 @@ def find_fattest_resource(c):
     # end if there are schemas
     return fr
 
+# Returns a list of (example_name, resource, method) tuples, with at most one listing, one getter and one
+# mutating method per (sub-)resource. Methods uploading media are skipped, as they need local files to work,
+# as well as deprecated ones.
+# The example name is unique and usable as cargo example target, e.g. 'notes_list'.
+def example_activities(c):
+    res = list()
+    names = set()
+    for resource in sorted(c.rta_map.keys()):
+        by_sub_resource = dict()
+        for method in sorted(c.rta_map[resource]):
+            sub_resource, _, verb = method.rpartition('.')
+            by_sub_resource.setdefault(sub_resource, dict())[verb] = method
+        # end for each method
+        for sub_resource in sorted(by_sub_resource.keys()):
+            verbs = by_sub_resource[sub_resource]
+            for kind in EXAMPLE_METHOD_KINDS:
+                for verb in kind:
+                    method = verbs.get(verb)
+                    if method is None:
+                        continue
+                    m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, method)]
+                    if method_media_params(m) or is_deprecated(m):
+                        continue
+                    name = mangle_ident(method)
+                    if not sub_resource and resource != METHODS_RESOURCE or name in names:
+                        name = mangle_ident(resource) + '_' + name
+                    names.add(name)
+                    res.append((name, resource, method))
+                    break
+                # end for each verb of kind
+            # end for each kind
+        # end for each sub-resource
+    # end for each resource
+    return res
+
 # Extract valid parts from the description of the parts prop contained within the given parameter list
 # can be an empty list.
 def parts_from_params(params):
@@ -1047,5 +1274,343 @@ def size_to_bytes(size):
     # end handle errors gracefully
 
 
+## -- Conversions between API versions -- @{
+
+CONVERSION_FROM = 'From'
+CONVERSION_TRY_FROM = 'TryFrom'
+
+# Returns the namespace the data of another version of the API is available under, if the crate converts its types
+def conversion_data_namespace(version):
+    return 'conversion_' + re.sub('[^a-zA-Z0-9_]', '_', version)
+
+# Parse a rust type like 'Option<HashMap<String, Foo>>' into ('Option', [('HashMap', [('String', []), ...])])
+def _parse_rust_type(tn):
+    def parse(i):
+        start = i
+        while i < len(tn) and tn[i] not in '<>,':
+            i += 1
+        name, args = tn[start:i].strip(), list()
+        if i < len(tn) and tn[i] == '<':
+            i += 1
+            while True:
+                arg, i = parse(i)
+                args.append(arg)
+                if tn[i] == '>':
+                    i += 1
+                    break
+                i += 1  # skip ','
+        return (name, args), i
+    t, _ = parse(0)
+    return t
+
+# Returns {schema id -> {property name -> (field ident, parsed rust type)}} of all generated structs of the context,
+# whose fields are all optional, and can thus be converted member by member.
+def _convertible_structs(schemas, c):
+    res = dict()
+    for s in c.schemas.values():
+        if s.get('type') != 'object' or 'additionalProperties' in s or 'variant' in s:
+            continue
+        if UNUSED_TYPE_MARKER in schema_markers(s, c, transitive=True):
+            continue
+        fields = dict()
+        for pn, p in items(s.get('properties', dict())):
+            fields[pn] = (mangle_ident(pn), _parse_rust_type(to_rust_type(schemas, s.id, pn, p)))
+        res[s.id] = fields
+    return res
+
+# Returns True if the type contains one of the given structs
+def _contains_struct(t, structs):
+    name, args = t
+    return name in structs or any(_contains_struct(a, structs) for a in args)
+
+# Returns the kind of conversion needed to convert the type `t` of the source into the one of the destination, which
+# is the same but for the crate its structs are defined in, given the `kinds` of the structs, or None if there is none.
+def _type_conversion_kind(t, kinds):
+    name, args = t
+    if not args:
+        if name in kinds:
+            return kinds[name]
+        # structs without conversion, or types defined by the crate, like the ones of maps, can't be converted
+        if name[0].isupper() and '::' not in name and name != 'String':
+            return None
+        return CONVERSION_FROM
+    res = CONVERSION_FROM
+    for a in args:
+        k = _type_conversion_kind(a, kinds)
+        if k is None:
+            return None
+        if k == CONVERSION_TRY_FROM:
+            res = k
+    return res
+
+# Returns a rust expression converting `v` of type `t`, which is a `Result` if it is `fallible`.
+# `dst_path` is the path to the module of the destination structs, like 'crate::api' or 'other_crate::api'.
+def _conversion_expr(t, v, kinds, dst_path):
+    name, args = t
+    if not _contains_struct(t, kinds):
+        return v, False
+    if not args:
+        if kinds[name] == CONVERSION_TRY_FROM:
+            return '%s::%s::try_from(%s)' % (dst_path, name, v), True
+        return '%s.into()' % (v.startswith('*') and '(%s)' % v or v), False
+    if name == 'Option':
+        inner, fallible = _conversion_expr(args[0], 'v', kinds, dst_path)
+        return '%s.map(|v| %s)%s' % (v, inner, fallible and '.transpose()' or ''), fallible
+    if name == 'Box':
+        inner, fallible = _conversion_expr(args[0], '*' + v, kinds, dst_path)
+        if fallible:
+            return '%s.map(Box::new)' % inner, True
+        return 'Box::new(%s)' % inner, False
+    if name == 'Vec':
+        inner, fallible = _conversion_expr(args[0], 'v', kinds, dst_path)
+        if fallible:
+            return '%s.into_iter().map(|v| %s).collect::<Result<Vec<_>, _>>()' % (v, inner), True
+        return '%s.into_iter().map(|v| %s).collect()' % (v, inner), False
+    assert name == 'HashMap', "unexpected type: %s" % name
+    inner, fallible = _conversion_expr(args[1], 'v', kinds, dst_path)
+    if fallible:
+        return '%s.into_iter().map(|(k, v)| %s.map(|v| (k, v))).collect::<Result<HashMap<_, _>, _>>()' % (v, inner), True
+    return '%s.into_iter().map(|(k, v)| (k, %s)).collect()' % (v, inner), False
+
+# Returns [(schema id, kind, [(field ident, expression, fallible)], [(property name, expression checking it is set)])],
+# sorted by schema id, describing the conversions of the structs of the source API version
+# into the ones of the same name of the destination version. A struct converts if all the fields it has in common
+# with the destination have the same type, except for the crate its structs are in. Fields the destination lacks
+# make the conversion fallible, as it fails if they are set.
+# `dst_path` is the path to the module of the destination structs.
+def schema_conversions(src_schemas, src_c, dst_schemas, dst_c, dst_path):
+    src = _convertible_structs(src_schemas, src_c)
+    dst = _convertible_structs(dst_schemas, dst_c)
+    kinds = dict((sid, CONVERSION_FROM) for sid in src if sid in dst)
+
+    # Downgrade the kinds until they are consistent with the ones of the structs their fields refer to
+    changed = True
+    while changed:
+        changed = False
+        for sid, kind in list(kinds.items()):
+            if kind is None:
+                continue
+            new_kind = CONVERSION_FROM
+            for pn, (_, t) in src[sid].items():
+                if pn not in dst[sid]:
+                    new_kind = CONVERSION_TRY_FROM
+                    continue
+                if t != dst[sid][pn][1]:
+                    new_kind = None
+                    break
+                k = _type_conversion_kind(t, kinds)
+                if k is None:
+                    new_kind = None
+                    break
+                if k == CONVERSION_TRY_FROM:
+                    new_kind = k
+            if new_kind != kind:
+                kinds[sid] = new_kind
+                changed = True
+    kinds = dict((k, v) for k, v in kinds.items() if v is not None)
+
+    res = list()
+    for sid in sorted(kinds):
+        fields, dropped = list(), list()
+        for pn, (ident, t) in items(src[sid]):
+            if pn not in dst[sid]:
+                check = t[0] == 'Option' and 'value.%s.is_some()' or '!value.%s.is_empty()'
+                dropped.append((pn, check % ident))
+                continue
+            expr, fallible = _conversion_expr(t, 'value.' + ident, kinds, dst_path)
+            fields.append((ident, expr, fallible))
+        res.append((sid, kinds[sid], fields, dropped))
+    return res
+
+## -- End Conversions between API versions -- @}
+
+
+## -- Enumerations of the values of string fields -- @{
+
+# The suffix of the types enumerating the values of a field
+ENUM_TYPE_SUFFIX = 'Enum'
+
+def enum_type_name(schema_id, pn):
+    return schema_id + canonical_type_name(pn) + ENUM_TYPE_SUFFIX
+
+# Returns (pn, values, descriptions) for each property of the given schema whose values, or the ones of its items,
+# are enumerated by the API.
+def enum_properties(s):
+    res = list()
+    for pn, p in sorted(s.get('properties', dict()).items()):
+        ep = p.get('type') == 'array' and p.get('items', dict()) or p
+        if ep.get('type') == 'string' and ep.get('enum'):
+            res.append((pn, ep.enum, ep.get('enumDescriptions', list())))
+    return res
+
+# Returns the name of a variant for each of the given values, like 'NoteKindUnspecified' for 'NOTE_KIND_UNSPECIFIED'.
+# Values which aren't valid identifiers are made ones, and are numbered if they would clash otherwise.
+def enum_variants(values):
+    res = list()
+    for v in values:
+        words = re.findall('[a-zA-Z0-9]+', v)
+        name = ''.join(w.isupper() and w.capitalize() or w[0].upper() + w[1:] for w in words) or 'Empty'
+        if name[0].isdigit():
+            name = 'V' + name
+        if name in res:
+            name += str(len(res))
+        res.append(name)
+    return res
+
+## -- End Enumerations of the values of string fields -- @}
+
+## -- Fake server for contract testing -- @{
+
+# The feature providing the fake server of an API
+FAKE_FEATURE = 'fake'
+# The feature providing the recording and replay of the exchanges of hubs with the server, which CLIs use
+CASSETTE_FEATURE = 'cassette'
+# The features choosing the TLS implementation of the common crate, of which the first one is the default
+TLS_FEATURES = ('rustls', 'rustls-native-roots', 'native-tls')
+# The features of the common crate adding runtimes to run hubs on instead of tokio
+RUNTIME_FEATURES = ('async-std', 'smol')
+# The client::fake::Action performed by methods of the given name
+FAKE_ACTIONS = {'get': 'Get', 'list': 'List', 'insert': 'Create', 'create': 'Create',
+                'update': 'Update', 'patch': 'Patch', 'delete': 'Delete'}
+
+# Returns the routes of all methods, as (http_method, path, action, list_field) tuples, sorted by path.
+# Paths are relative to the root url, which is why they start with the `service_path`.
+# `list_field` is the field of the response of list methods holding the listed resources, if any.
+def fake_routes(c, service_path):
+    res = list()
+    for m in c.fqan_map.values():
+        action = FAKE_ACTIONS.get(m.id.split('.')[-1], 'Other')
+        list_field = None
+        if action == 'List':
+            rs = c.schemas.get(m.get('response', dict()).get(TREF), dict())
+            for pn, p in sorted(rs.get('properties', dict()).items()):
+                if p.get('type') == 'array' and TREF in p.get('items', dict()):
+                    list_field = pn
+                    break
+        res.append((m.httpMethod, service_path + m.path, action, list_field))
+    return sorted(res, key=lambda r: (r[1], r[0]))
+
+## -- End Fake server for contract testing -- @}
+
+## -- Round-trip tests of schemas -- @{
+
+# The sample of string fields of the given format, which is also how they are serialized again
+STRING_FORMAT_SAMPLES = {'google-duration': '3.500s', 'google-datetime': '2014-10-02T15:01:23Z', 'date': '2014-10-02',
+                         'byte': 'Zm9v', 'int64': '-1', 'uint64': '1', 'google-fieldmask': 'name'}
+
+# Returns the schemas to test the serde round-trip of, which are the used objects with fields, sorted by id.
+def round_trip_schemas(c):
+    return sorted((s for s in c.schemas.values()
+                  if s.type == 'object' and s.get('properties') and 'variant' not in s
+                  and UNUSED_TYPE_MARKER not in schema_markers(s, c, transitive=True)), key=lambda s: s.id)
+
+# Returns a JSON value of the schema `s` with a sample for each of its fields, like the first of their enumerated values.
+# Fields referring to a schema they are part of are left out, as they would be infinitely nested.
+def schema_sample(schemas, s):
+    return _property_sample(schemas, s, (s.id,))
+
+def _property_sample(schemas, p, path):
+    if TREF in p:
+        if p[TREF] in path or p[TREF] not in schemas:
+            return None
+        return _property_sample(schemas, schemas[p[TREF]], path + (p[TREF],))
+    t = p.get('type')
+    if t == 'array':
+        v = _property_sample(schemas, p.get('items', dict()), path)
+        return v is not None and [v] or []
+    if t == 'object':
+        if 'additionalProperties' in p:
+            v = _property_sample(schemas, p.additionalProperties, path)
+            return v is not None and {'key': v} or {}
+        res = dict()
+        for pn, pp in p.get('properties', dict()).items():
+            v = _property_sample(schemas, pp, path)
+            if v is not None:
+                res[pn] = v
+        return res
+    if t == 'string':
+        if p.get('enum'):
+            return p.enum[0]
+        return STRING_FORMAT_SAMPLES.get(p.get('format'), 'value')
+    if t == 'integer':
+        return 1
+    if t == 'number':
+        return 1.5
+    if t == 'boolean':
+        return True
+    if t == 'any':
+        return {'key': 'value'}
+    return None
+
+# Returns `s` as raw string literal of rust, with as many hashes as needed to enclose it.
+def rust_raw_string(s):
+    hashes = '#'
+    while '"' + hashes in s:
+        hashes += '#'
+    return 'r%s"%s"%s' % (hashes, s, hashes)
+
+## -- End Round-trip tests of schemas -- @}
+
+## -- Long running operations -- @{
+
+# The fields of operations holding packed messages, which are decoded by a method of the same name prefixed with `decode_`
+OPERATION_ANY_FIELDS = ('response', 'metadata')
+# The fully qualified protobuf names of the schemas most APIs share, which may be packed into operations
+WELL_KNOWN_ANY_TYPES = {'Empty': 'google.protobuf.Empty'}
+
+def _is_any_prop(p):
+    return p.get('type') == 'object' and p.get('additionalProperties', dict()).get('type') == 'any'
+
+# Returns [(schema, [field, ...])] of the schemas of long running operations, whose fields hold packed messages.
+def operation_schemas(c):
+    res = list()
+    for s in sorted(c.schemas.values(), key=lambda s: s.id):
+        props = s.get('properties', dict())
+        if s.get('parents') or props.get('done', dict()).get('type') != 'boolean':
+            continue
+        fields = [f for f in OPERATION_ANY_FIELDS if _is_any_prop(props.get(f, dict()))]
+        if fields:
+            res.append((s, fields))
+    return res
+
+# Returns [(schema_id, type_name)] of the schemas which may be packed into operations, which are the well-known ones and
+# those listed in the `any_types` of the overrides of the API, as discovery documents don't name their protobuf messages.
+def any_types(c, api):
+    types = dict(WELL_KNOWN_ANY_TYPES)
+    types.update(api.get('any_types', dict()))
+    return sorted((sid, tn) for sid, tn in types.items()
+                  if sid in c.schemas and UNUSED_TYPE_MARKER not in schema_markers(c.schemas[sid], c, transitive=True))
+
+## -- End Long running operations -- @}
+
+## -- JSON Schema of the schemas -- @{
+
+JSON_SCHEMA_DIALECT = 'http://json-schema.org/draft-07/schema#'
+# The keys of schemas and properties of the discovery document, which mean the same in JSON Schema
+JSON_SCHEMA_KEYS = ('type', 'format', 'description', 'enum', 'pattern', 'readOnly', 'deprecated')
+
+# Returns the JSON Schema document of the schemas of the discovery document, keyed by their name in its `definitions`.
+# It has to remain readable by the `json_schema` module of the common crate.
+def json_schema(schemas, api_id, description):
+    return {'$schema': JSON_SCHEMA_DIALECT, 'title': api_id, 'description': description,
+            'definitions': dict((sn, _json_schema_property(s)) for sn, s in (schemas or dict()).items())}
+
+def _json_schema_property(p):
+    if TREF in p:
+        return {TREF: '#/definitions/' + p[TREF]}
+    res = dict((k, p[k]) for k in JSON_SCHEMA_KEYS if k in p)
+    # any value is valid
+    if res.get('type') == 'any':
+        del res['type']
+    if 'items' in p:
+        res['items'] = _json_schema_property(p['items'])
+    if 'additionalProperties' in p:
+        res['additionalProperties'] = _json_schema_property(p['additionalProperties'])
+    if 'properties' in p:
+        res['properties'] = dict((pn, _json_schema_property(pp)) for pn, pp in p['properties'].items())
+    return res
+
+## -- End JSON Schema of the schemas -- @}
+
 if __name__ == '__main__':
     raise AssertionError('For import only')
@@ -4,7 +4,12 @@ import unittest
 import json
 import importlib_resources
 
-from .util import to_api_version, library_name, re_find_replacements, to_rust_type, new_context
+from .util import (to_api_version, library_name, re_find_replacements, to_rust_type, new_context,
+                   example_activities, Context, _parse_rust_type, _type_conversion_kind, _conversion_expr,
+                   CONVERSION_FROM, CONVERSION_TRY_FROM, method_default_scope, fake_routes,
+                   enum_variants, enum_properties, schema_sample, rust_raw_string, method_response,
+                   method_signature, json_schema, operation_schemas, page_token_param, page_size_param,
+                   is_request_id_param, get_many_activities, method_supports_polling)
 from . import test_data
 
 
@@ -12,6 +17,9 @@ def read_test_json_file(resource):
     data = importlib_resources.read_text(test_data, resource)
     return json.loads(data)
 
+class AttrDict(dict):
+    __getattr__ = dict.__getitem__
+
 class UtilsTest(unittest.TestCase):
 
     def test_to_version_ok(self):
@@ -111,6 +119,261 @@ class UtilsTest(unittest.TestCase):
         rust_type = to_rust_type(schemas, class_name, property_name, property_value, allow_optionals=True)
         self.assertEqual(rust_type, 'Option<Vec<HashMap<String, String>>>')
 
+    def test_to_rust_type_duration(self):
+        # durations of schema fields, but not of method parameters
+        property_value = {'type': 'string', 'format': 'google-duration'}
+        rust_type = to_rust_type({}, 'Foo', 'ttl', property_value, allow_optionals=True)
+        self.assertEqual(rust_type, 'Option<std::time::Duration>')
+        rust_type = to_rust_type({}, None, 'ttl', property_value, allow_optionals=True)
+        self.assertEqual(rust_type, 'Option<String>')
+        rust_type = to_rust_type({}, 'Foo', 'ttls', {'type': 'array', 'items': property_value}, allow_optionals=True)
+        self.assertEqual(rust_type, 'Option<Vec<String>>')
+
+    def test_to_rust_type_maps(self):
+        # the values of maps are typed like the values of other fields
+        for value, expected in ((AttrDict(type='string'), 'String'),
+                                (AttrDict({'$ref': 'FileHashes'}), 'FileHashes'),
+                                (AttrDict(type='object', properties={'a': AttrDict(type='string')}),
+                                 'ProvenanceScopes'),
+                                (AttrDict(type='object', additionalProperties=AttrDict(type='boolean')),
+                                 'HashMap<String, bool>')):
+            property_value = AttrDict(type='object', additionalProperties=value)
+            rust_type = to_rust_type({}, 'Provenance', 'scopes', property_value, allow_optionals=True)
+            self.assertEqual(rust_type, 'Option<HashMap<String, %s>>' % expected)
+
+    def test_example_activities(self):
+        rta_map = {'projects': ['get', 'notes.list', 'notes.get', 'notes.create', 'notes.delete',
+                                'locations.notes.list'],
+                   'media': ['get', 'list', 'upload'],
+                   'organizations': ['notes.list']}
+        rtc_map = dict((r, 'api') for r in rta_map)
+        fqan_map = dict(('api.%s.%s' % (r, m), {}) for r, ms in rta_map.items() for m in ms)
+        fqan_map['api.media.upload'] = {'supportsMediaUpload': True}
+        fqan_map['api.media.get'] = {'deprecated': True}
+        c = Context(dict(), fqan_map, rta_map, rtc_map, dict())
+
+        self.assertEqual(example_activities(c), [
+            ('media_list', 'media', 'list'),
+            ('notes_list', 'organizations', 'notes.list'),
+            ('projects_get', 'projects', 'get'),
+            ('locations_notes_list', 'projects', 'locations.notes.list'),
+            ('projects_notes_list', 'projects', 'notes.list'),
+            ('notes_get', 'projects', 'notes.get'),
+            ('notes_create', 'projects', 'notes.create'),
+        ])
+
+    def test_version_conversions(self):
+        self.assertEqual(_parse_rust_type('Option<HashMap<String, Foo>>'),
+                         ('Option', [('HashMap', [('String', []), ('Foo', [])])]))
+
+        kinds = {'Foo': CONVERSION_FROM, 'Bar': CONVERSION_TRY_FROM}
+        for tn, want in (('Option<String>', CONVERSION_FROM),
+                         ('Option<std::time::Duration>', CONVERSION_FROM),
+                         ('Option<Vec<Foo>>', CONVERSION_FROM),
+                         ('Option<HashMap<String, Bar>>', CONVERSION_TRY_FROM),
+                         ('Option<Baz>', None)):
+            self.assertEqual(_type_conversion_kind(_parse_rust_type(tn), kinds), want, tn)
+
+        for tn, want in (('Option<i64>', ('value.f', False)),
+                         ('Option<Vec<Foo>>', ('value.f.map(|v| v.into_iter().map(|v| v.into()).collect())', False)),
+                         ('Option<Option<Box<Foo>>>', ('value.f.map(|v| v.map(|v| Box::new((*v).into())))', False)),
+                         ('Option<Bar>', ('value.f.map(|v| api::Bar::try_from(v)).transpose()', True)),
+                         ('Option<HashMap<String, Bar>>',
+                          ('value.f.map(|v| v.into_iter().map(|(k, v)| api::Bar::try_from(v).map(|v| (k, v)))'
+                           '.collect::<Result<HashMap<_, _>, _>>()).transpose()', True))):
+            self.assertEqual(_conversion_expr(_parse_rust_type(tn), 'value.f', kinds, 'api'), want, tn)
+
+    def test_method_default_scope(self):
+        full, full_ro = 'https://www.googleapis.com/auth/cloud-platform', 'https://www.googleapis.com/auth/cloud-platform.read-only'
+        api, api_ro = 'https://www.googleapis.com/auth/api', 'https://www.googleapis.com/auth/api.readonly'
+        notes_ro = 'https://www.googleapis.com/auth/api.notes.readonly'
+        methods = [AttrDict(id='api.notes.get', httpMethod='GET', scopes=[full, full_ro, api_ro, notes_ro]),
+                   AttrDict(id='api.notes.list', httpMethod='GET', scopes=[full, full_ro, api_ro, notes_ro]),
+                   AttrDict(id='api.notes.create', httpMethod='POST', scopes=[full, api]),
+                   AttrDict(id='api.media.get', httpMethod='GET', scopes=[full, full_ro, api_ro]),
+                   AttrDict(id='api.other.get', httpMethod='GET', scopes=[full, full_ro]),
+                   AttrDict(id='api.other.list', httpMethod='GET', scopes=[api]),
+                   AttrDict(id='api.keys.get', httpMethod='GET')]
+        c = Context(dict(), dict((m.id, m) for m in methods), dict(), dict(), dict())
+
+        # read-only scopes accepted by fewer methods grant less
+        self.assertEqual(method_default_scope(methods[0], c), notes_ro)
+        self.assertEqual(method_default_scope(methods[1], c), notes_ro)
+        self.assertEqual(method_default_scope(methods[2], c), api)
+        # API specific ones are preferred if they grant as much
+        self.assertEqual(method_default_scope(methods[3], c), api_ro)
+        self.assertEqual(method_default_scope(methods[4], c), full_ro)
+        self.assertEqual(method_default_scope(methods[5], c), api)
+        self.assertEqual(method_default_scope(methods[6], c), None)
+
+    def test_fake_routes(self):
+        methods = [AttrDict(id='pubsub.projects.topics.list', httpMethod='GET', path='v1/{+project}/topics',
+                            response={'$ref': 'ListTopicsResponse'}),
+                   AttrDict(id='pubsub.projects.topics.create', httpMethod='PUT', path='v1/{+name}'),
+                   AttrDict(id='pubsub.projects.topics.publish', httpMethod='POST', path='v1/{+topic}:publish')]
+        schemas = {'ListTopicsResponse': {'properties': {'nextPageToken': {'type': 'string'},
+                                                         'topics': {'type': 'array', 'items': {'$ref': 'Topic'}}}}}
+        c = Context(dict(), dict((m.id, m) for m in methods), dict(), dict(), schemas)
+
+        self.assertEqual(fake_routes(c, 'pubsub/'),
+                         [('PUT', 'pubsub/v1/{+name}', 'Create', None),
+                          ('GET', 'pubsub/v1/{+project}/topics', 'List', 'topics'),
+                          ('POST', 'pubsub/v1/{+topic}:publish', 'Other', None)])
+
+    def test_page_token_param(self):
+        page = AttrDict(id='ListTopicsResponse', properties={'nextPageToken': {'type': 'string'},
+                                                             'topics': {'type': 'array', 'items': {'$ref': 'Topic'}}})
+        token = AttrDict(name='pageToken', type='string', location='query')
+        params = [AttrDict(name='project', type='string', required=True), token]
+
+        self.assertIs(page_token_param(params, page), token)
+        # pages need items, and the token must be optional
+        self.assertIsNone(page_token_param(params, AttrDict(id='Topic', properties={'nextPageToken': {}})))
+        self.assertIsNone(page_token_param([AttrDict(token, required=True)], page))
+        self.assertIsNone(page_token_param(params, None))
+
+    def test_page_size_param(self):
+        size = AttrDict(name='pageSize', type='integer', location='query', maximum='300')
+        token = AttrDict(name='pageToken', type='string', location='query')
+
+        self.assertEqual(page_size_param([token, size]), (size, 300))
+        max_results = AttrDict(name='maxResults', type='integer', location='query')
+        self.assertEqual(page_size_param([max_results]), (max_results, 1000))
+        self.assertEqual(page_size_param([token, AttrDict(size, type='string')]), (None, None))
+        self.assertEqual(page_size_param([AttrDict(size, required=True)]), (None, None))
+
+    def test_request_id_param(self):
+        insert = AttrDict(httpMethod='POST')
+        request_id = AttrDict(name='requestId', type='string', location='query')
+
+        self.assertTrue(is_request_id_param(insert, request_id))
+        # only calls creating resources need one, and it must be optional
+        self.assertFalse(is_request_id_param(AttrDict(httpMethod='DELETE'), request_id))
+        self.assertFalse(is_request_id_param(insert, AttrDict(request_id, required=True)))
+        self.assertFalse(is_request_id_param(insert, AttrDict(request_id, name='pageToken')))
+        self.assertFalse(is_request_id_param(insert, AttrDict(request_id, location='path')))
+
+    def test_method_response(self):
+        schemas = {'Empty': AttrDict(id='Empty', type='object'),
+                   'Topic': AttrDict(id='Topic', type='object', properties={'name': AttrDict(type='string')}),
+                   'Labels': AttrDict(id='Labels', type='object', additionalProperties=AttrDict(type='string'))}
+        methods = [AttrDict(id='pubsub.projects.topics.delete', response={'$ref': 'Empty'}),
+                   AttrDict(id='pubsub.projects.topics.get', response={'$ref': 'Topic'}),
+                   AttrDict(id='pubsub.projects.topics.labels', response={'$ref': 'Labels'}),
+                   AttrDict(id='pubsub.projects.topics.touch')]
+        c = Context(dict(), dict((m.id, m) for m in methods), dict(), dict(), schemas)
+
+        # empty responses have nothing to decode
+        self.assertEqual([method_response(c, m) for m in methods],
+                         [None, schemas['Topic'], schemas['Labels'], None])
+
+    def test_method_supports_polling(self):
+        schemas = {'Empty': AttrDict(id='Empty', type='object'),
+                   'Topic': AttrDict(id='Topic', type='object', properties={'name': AttrDict(type='string')})}
+        get = AttrDict(id='pubsub.projects.topics.get', httpMethod='GET', response={'$ref': 'Topic'})
+        methods = [get,
+                   AttrDict(get, id='pubsub.projects.topics.patch', httpMethod='PATCH'),
+                   AttrDict(get, id='pubsub.projects.topics.touch', response={'$ref': 'Empty'}),
+                   AttrDict(get, id='pubsub.projects.topics.watch', streamingType='SERVER_SIDE')]
+        c = Context(dict(), dict((m.id, m) for m in methods), dict(), dict(), schemas)
+
+        # only calls reading a resource have a response worth waiting for
+        self.assertEqual([method_supports_polling(c, m) for m in methods], [True, False, False, False])
+
+    def test_get_many_activities(self):
+        schemas = {'Service': AttrDict(id='Service', type='object', properties={'name': AttrDict(type='string')}),
+                   'BatchGetServicesResponse': AttrDict(id='BatchGetServicesResponse', type='object', properties={
+                        'services': AttrDict(type='array', items={'$ref': 'Service'})})}
+        name = AttrDict(type='string', location='path', required=True)
+        methods = [AttrDict(id='serviceusage.services.get', parameters={'name': name}, response={'$ref': 'Service'}),
+                   AttrDict(id='serviceusage.services.batchGet', httpMethod='GET', response={'$ref': 'BatchGetServicesResponse'},
+                            parameters={'parent': AttrDict(type='string', location='path', required=True),
+                                        'names': AttrDict(type='string', location='query', repeated=True)}),
+                   AttrDict(id='serviceusage.services.configs.get', parameters={'name': name}, response={'$ref': 'Service'}),
+                   AttrDict(id='serviceusage.services.delete', parameters={'name': name}),
+                   AttrDict(id='serviceusage.services.versions.get', response={'$ref': 'Service'},
+                            parameters={'name': name, 'version': name})]
+        c = Context(dict(), dict((m.id, m) for m in methods),
+                    {'services': ['get', 'batchGet', 'configs.get', 'delete', 'versions.get']},
+                    {'services': 'serviceusage'}, schemas)
+
+        # the batch method of the configs is missing, and versions take more than their name
+        self.assertEqual(get_many_activities(c, 'services'),
+                         [('get', 'batchGet', 'services'), ('configs.get', None, None)])
+
+    def test_enum_variants(self):
+        self.assertEqual(enum_variants(['NOTE_KIND_UNSPECIFIED', 'VULNERABILITY', 'BUILD']),
+                         ['NoteKindUnspecified', 'Vulnerability', 'Build'])
+        self.assertEqual(enum_variants(['application/json', 'camelCase', '2D', '', 'a-b', 'a_b']),
+                         ['ApplicationJson', 'CamelCase', 'V2d', 'Empty', 'AB', 'AB5'])
+
+        s = AttrDict(properties={'kind': AttrDict(type='string', enum=['A', 'B'], enumDescriptions=['a', 'b']),
+                                 'kinds': AttrDict(type='array', items=AttrDict(type='string', enum=['C'])),
+                                 'name': AttrDict(type='string')})
+        self.assertEqual(enum_properties(s), [('kind', ['A', 'B'], ['a', 'b']), ('kinds', ['C'], [])])
+
+    def test_schema_sample(self):
+        schemas = {'Note': AttrDict(id='Note', type='object', properties={
+                        'kind': AttrDict(type='string', enum=['NOTE_KIND_UNSPECIFIED', 'BUILD']),
+                        'expiration': AttrDict(type='string', format='google-duration'),
+                        'count': AttrDict(type='integer', format='int32'),
+                        'labels': AttrDict(type='object', additionalProperties=AttrDict(type='string')),
+                        'related': AttrDict(type='array', items={'$ref': 'Note'}),
+                        'parent': {'$ref': 'Note'},
+                        'detail': {'$ref': 'Detail'}}),
+                   'Detail': AttrDict(id='Detail', type='object', properties={
+                        'note': {'$ref': 'Note'},
+                        'score': AttrDict(type='number', format='float')})}
+
+        self.assertEqual(schema_sample(schemas, schemas['Note']),
+                         {'kind': 'NOTE_KIND_UNSPECIFIED', 'expiration': '3.500s', 'count': 1,
+                          'labels': {'key': 'value'}, 'related': [], 'detail': {'score': 1.5}})
+        self.assertEqual(rust_raw_string('{"a": 1}'), 'r#"{"a": 1}"#')
+        self.assertEqual(rust_raw_string('"#'), 'r##""#"##')
+
+    def test_method_signature(self):
+        m = AttrDict(httpMethod='GET', path='v1/{+parent}/notes', response={'$ref': 'ListNotesResponse'},
+                     parameters={'parent': AttrDict(location='path', type='string', required=True),
+                                 'pageSize': AttrDict(location='query', type='integer', format='int32'),
+                                 'filter': AttrDict(location='query', type='string', repeated=True)})
+        self.assertEqual(method_signature(m),
+                         'GET v1/{+parent}/notes filter:query:string[],pageSize:query:integer,parent:path:string! '
+                         '- ListNotesResponse')
+        self.assertEqual(method_signature(AttrDict(httpMethod='POST', path='v1/flush', request={'$ref': 'Flush'})),
+                         'POST v1/flush  Flush -')
+
+    def test_json_schema(self):
+        schemas = {'Note': AttrDict(id='Note', type='object', description='A note.', properties={
+                        'kind': AttrDict(type='string', enum=['A', 'B'], enumDescriptions=['a', 'b']),
+                        'count': AttrDict(type='string', format='int64', readOnly=True),
+                        'labels': AttrDict(type='object', additionalProperties=AttrDict(type='string')),
+                        'related': AttrDict(type='array', items={'$ref': 'Note'}),
+                        'extra': AttrDict(type='any'),
+                        'inner': AttrDict(type='object', properties={'a': AttrDict(type='boolean')})})}
+        self.assertEqual(json_schema(schemas, 'notes:v1', 'Notes'), {
+            '$schema': 'http://json-schema.org/draft-07/schema#', 'title': 'notes:v1', 'description': 'Notes',
+            'definitions': {'Note': {
+                'type': 'object', 'description': 'A note.', 'properties': {
+                    'kind': {'type': 'string', 'enum': ['A', 'B']},
+                    'count': {'type': 'string', 'format': 'int64', 'readOnly': True},
+                    'labels': {'type': 'object', 'additionalProperties': {'type': 'string'}},
+                    'related': {'type': 'array', 'items': {'$ref': '#/definitions/Note'}},
+                    'extra': {},
+                    'inner': {'type': 'object', 'properties': {'a': {'type': 'boolean'}}}}}}})
+        self.assertEqual(json_schema(None, 'empty:v1', '')['definitions'], {})
+
+    def test_operation_schemas(self):
+        any_map = AttrDict(type='object', additionalProperties=AttrDict(type='any'))
+        schemas = {'Operation': AttrDict(id='Operation', type='object', properties={
+                        'done': AttrDict(type='boolean'), 'response': any_map, 'metadata': any_map}),
+                   'Job': AttrDict(id='Job', type='object', properties={
+                        'done': AttrDict(type='boolean'), 'metadata': any_map,
+                        'response': AttrDict(type='object', additionalProperties=AttrDict(type='string'))}),
+                   'Labels': AttrDict(id='Labels', type='object', properties={'response': any_map})}
+        c = Context(dict(), dict(), dict(), dict(), schemas)
+        self.assertEqual([(s.id, fields) for s, fields in operation_schemas(c)],
+                         [('Job', ['metadata']), ('Operation', ['response', 'metadata'])])
+
 
 def main():
     unittest.main()
@@ -0,0 +1,442 @@
+//! Verification of the [DSSE](https://github.com/secure-systems-lab/dsse) envelopes of attestations,
+//! and decoding of the [in-toto](https://in-toto.io) statements they carry.
+//!
+//! The `envelope` of an `Occurrence`, as well as the one of its `dsseAttestation`, only hold base64
+//! encoded payloads and signatures. An `Envelope` decodes them, verifies its signatures against a
+//! `PublicKey`, which can be the one of a Cloud KMS key, and decodes its payload into a `Statement`.
+//!
+//! This module is only available with the `attestation` feature.
+//!
+//! ```ignore
+//! # use google_containeranalysis1::api::Occurrence;
+//! # use google_containeranalysis1::attestation::{Envelope, PublicKey};
+//! # fn attested() -> Occurrence { Default::default() }
+//! let occurrence: Occurrence = attested();
+//! let key = PublicKey::from_pem(include_str!("key.pem"), "EC_SIGN_P256_SHA256").unwrap();
+//! let envelope = Envelope::from_occurrence(&occurrence).unwrap();
+//! let statement = envelope.verified_statement(&key).unwrap();
+//! println!("{} attests {} subjects", statement.predicate_type, statement.subject.len());
+//! ```
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+use ring::digest;
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json as json;
+use serde_json::Value;
+
+/// The payload type of envelopes carrying in-toto statements.
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+/// The `_type` of the in-toto statements made by `Statement::new()`.
+pub const IN_TOTO_STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+/// An error decoding or verifying an envelope.
+#[derive(Debug)]
+pub enum Error {
+    /// The named field is missing, or isn't a string.
+    MissingField(&'static str),
+    /// The named field isn't valid base64.
+    Base64(&'static str, base64::DecodeError),
+    /// The payload isn't the expected JSON.
+    Json(json::Error),
+    /// The public key couldn't be read.
+    InvalidKey(String),
+    /// The signature algorithm isn't supported, or not known.
+    UnsupportedAlgorithm(String),
+    /// The payload is of another type than the expected one.
+    UnexpectedPayloadType(String),
+    /// None of the signatures of the envelope could be verified with the given key.
+    NoValidSignature,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::MissingField(field) => write!(f, "The envelope has no '{}'", field),
+            Error::Base64(field, ref err) => {
+                write!(
+                    f,
+                    "The '{}' of the envelope isn't valid base64: {}",
+                    field, err
+                )
+            }
+            Error::Json(ref err) => write!(f, "The payload couldn't be decoded: {}", err),
+            Error::InvalidKey(ref reason) => write!(f, "Invalid public key: {}", reason),
+            Error::UnsupportedAlgorithm(ref algorithm) => {
+                write!(
+                    f,
+                    "The signature algorithm '{}' is not supported",
+                    algorithm
+                )
+            }
+            Error::UnexpectedPayloadType(ref payload_type) => write!(
+                f,
+                "Expected a payload of type '{}', got '{}'",
+                IN_TOTO_PAYLOAD_TYPE, payload_type
+            ),
+            Error::NoValidSignature => f.write_str("No signature of the envelope is valid"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Base64(_, ref err) => Some(err),
+            Error::Json(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<json::Error> for Error {
+    fn from(err: json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+/// Something which can verify the signatures of envelopes.
+pub trait Verifier {
+    /// The id of the key, which is compared to the `keyid` of signatures to pick the ones to verify.
+    fn key_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns true if `signature` is valid for `message`.
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A public key, along with the algorithm of the signatures it verifies.
+pub struct PublicKey {
+    algorithm: &'static dyn VerificationAlgorithm,
+    key: Vec<u8>,
+    key_id: Option<String>,
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PublicKey")
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+// Maps the names of the Cloud KMS signing algorithms to the ones of ring
+fn verification_algorithm(name: &str) -> Option<&'static dyn VerificationAlgorithm> {
+    Some(match name {
+        "EC_SIGN_P256_SHA256" => &signature::ECDSA_P256_SHA256_ASN1,
+        "EC_SIGN_P384_SHA384" => &signature::ECDSA_P384_SHA384_ASN1,
+        "EC_SIGN_ED25519" => &signature::ED25519,
+        "RSA_SIGN_PKCS1_2048_SHA256"
+        | "RSA_SIGN_PKCS1_3072_SHA256"
+        | "RSA_SIGN_PKCS1_4096_SHA256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+        "RSA_SIGN_PKCS1_4096_SHA512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+        "RSA_SIGN_PSS_2048_SHA256" | "RSA_SIGN_PSS_3072_SHA256" | "RSA_SIGN_PSS_4096_SHA256" => {
+            &signature::RSA_PSS_2048_8192_SHA256
+        }
+        "RSA_SIGN_PSS_4096_SHA512" => &signature::RSA_PSS_2048_8192_SHA512,
+        _ => return None,
+    })
+}
+
+/// Returns the digest of `message` which Cloud KMS signs with keys of `algorithm`, along with the
+/// field of the `Digest` of an `AsymmetricSignRequest` it goes into, like `sha256`, or `None` if
+/// the algorithm signs the message itself, like `EC_SIGN_ED25519`.
+pub fn kms_digest(
+    algorithm: &str,
+    message: &[u8],
+) -> Result<Option<(&'static str, Vec<u8>)>, Error> {
+    if verification_algorithm(algorithm).is_none() {
+        return Err(Error::UnsupportedAlgorithm(algorithm.to_string()));
+    }
+    let (field, hash) = if algorithm == "EC_SIGN_ED25519" {
+        return Ok(None);
+    } else if algorithm.ends_with("SHA256") {
+        ("sha256", &digest::SHA256)
+    } else if algorithm.ends_with("SHA384") {
+        ("sha384", &digest::SHA384)
+    } else {
+        ("sha512", &digest::SHA512)
+    };
+    Ok(Some((
+        field,
+        digest::digest(hash, message).as_ref().to_vec(),
+    )))
+}
+
+// Returns the tag and the content of the DER element at the start of `input`, along with the rest.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, mut input) = input.split_first()?;
+    let len = if len < 0x80 {
+        len as usize
+    } else {
+        let num_bytes = (len & 0x7f) as usize;
+        if num_bytes == 0 || num_bytes > 4 || input.len() < num_bytes {
+            return None;
+        }
+        let (len_bytes, rest) = input.split_at(num_bytes);
+        input = rest;
+        len_bytes.iter().fold(0, |len, &b| (len << 8) | b as usize)
+    };
+    if input.len() < len {
+        return None;
+    }
+    let (content, rest) = input.split_at(len);
+    Some((tag, content, rest))
+}
+
+// Returns the key of a DER encoded `SubjectPublicKeyInfo`, in the form ring expects it.
+fn subject_public_key(spki: &[u8]) -> Option<&[u8]> {
+    let info = match der_element(spki)? {
+        (0x30, info, _) => info,
+        _ => return None,
+    };
+    let rest = match der_element(info)? {
+        (0x30, _algorithm, rest) => rest,
+        _ => return None,
+    };
+    match der_element(rest)? {
+        (0x03, [0, key @ ..], _) => Some(key),
+        _ => None,
+    }
+}
+
+fn decode_base64(field: &'static str, value: &str) -> Result<Vec<u8>, Error> {
+    base64::decode_config(value, base64::STANDARD)
+        .or_else(|_| base64::decode_config(value, base64::URL_SAFE))
+        .map_err(|err| Error::Base64(field, err))
+}
+
+impl PublicKey {
+    /// Read a PEM encoded public key, like `-----BEGIN PUBLIC KEY-----\n...`, verifying signatures
+    /// made with `algorithm`, named like the Cloud KMS algorithms, e.g. `EC_SIGN_P256_SHA256` or
+    /// `RSA_SIGN_PKCS1_2048_SHA256`.
+    pub fn from_pem(pem: &str, algorithm: &str) -> Result<PublicKey, Error> {
+        let algorithm = verification_algorithm(algorithm)
+            .ok_or_else(|| Error::UnsupportedAlgorithm(algorithm.to_string()))?;
+        let encoded: String = pem
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+            .collect();
+        let der = base64::decode(&encoded).map_err(|err| Error::InvalidKey(err.to_string()))?;
+        let key = subject_public_key(&der)
+            .ok_or_else(|| Error::InvalidKey("not a DER encoded SubjectPublicKeyInfo".into()))?;
+        Ok(PublicKey {
+            algorithm,
+            key: key.to_vec(),
+            key_id: None,
+        })
+    }
+
+    /// Use the public key of a Cloud KMS key version, as returned by its `getPublicKey` method,
+    /// like the `PublicKey` of the `cloudkms1` crate.
+    ///
+    /// Its `name` becomes the id of the key.
+    pub fn from_kms<K: Serialize>(public_key: &K) -> Result<PublicKey, Error> {
+        let public_key = json::to_value(public_key)?;
+        let field = |name: &'static str| public_key[name].as_str().ok_or(Error::MissingField(name));
+        let key = PublicKey::from_pem(field("pem")?, field("algorithm")?)?;
+        Ok(match public_key["name"].as_str() {
+            Some(name) => key.with_key_id(name),
+            None => key,
+        })
+    }
+
+    /// Only verify the signatures without `keyid`, or with the given one.
+    pub fn with_key_id(mut self, key_id: &str) -> PublicKey {
+        self.key_id = Some(key_id.to_string());
+        self
+    }
+}
+
+impl Verifier for PublicKey {
+    fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        UnparsedPublicKey::new(self.algorithm, &self.key)
+            .verify(message, signature)
+            .is_ok()
+    }
+}
+
+/// A signature of an envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// The id of the key which made the signature, if known.
+    pub keyid: Option<String>,
+    pub sig: Vec<u8>,
+}
+
+/// A decoded DSSE envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    pub payload_type: String,
+    pub payload: Vec<u8>,
+    pub signatures: Vec<Signature>,
+}
+
+impl Envelope {
+    /// Decode an envelope as returned by the API, like its `Envelope`, or any value serializing
+    /// to the same JSON, which is the one used by DSSE.
+    pub fn from_api<E: Serialize>(envelope: &E) -> Result<Envelope, Error> {
+        let envelope = json::to_value(envelope)?;
+        let field = |name: &'static str| envelope[name].as_str().ok_or(Error::MissingField(name));
+        let signatures = envelope["signatures"]
+            .as_array()
+            .map(|a| &a[..])
+            .unwrap_or(&[])
+            .iter()
+            .map(|signature| {
+                let sig = signature["sig"]
+                    .as_str()
+                    .ok_or(Error::MissingField("sig"))?;
+                Ok(Signature {
+                    keyid: signature["keyid"]
+                        .as_str()
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string),
+                    sig: decode_base64("sig", sig)?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Envelope {
+            payload_type: field("payloadType")?.to_string(),
+            payload: decode_base64("payload", field("payload")?)?,
+            signatures,
+        })
+    }
+
+    /// Decode the envelope of an `Occurrence`, which is its own, or the one of its `dsseAttestation`.
+    pub fn from_occurrence<O: Serialize>(occurrence: &O) -> Result<Envelope, Error> {
+        let occurrence = json::to_value(occurrence)?;
+        match occurrence.get("envelope").filter(|e| e.is_object()) {
+            Some(envelope) => Envelope::from_api(envelope),
+            None => match occurrence["dsseAttestation"].get("envelope") {
+                Some(envelope) if envelope.is_object() => Envelope::from_api(envelope),
+                _ => Err(Error::MissingField("envelope")),
+            },
+        }
+    }
+
+    /// An envelope carrying the given in-toto `statement`, which has no signatures yet.
+    pub fn from_statement(statement: &Statement) -> Result<Envelope, Error> {
+        Ok(Envelope {
+            payload_type: IN_TOTO_PAYLOAD_TYPE.to_string(),
+            payload: json::to_vec(statement)?,
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Returns the JSON of the envelope with base64 encoded fields, as the API expects it, which
+    /// deserializes into its `Envelope`.
+    pub fn to_api(&self) -> Value {
+        let signatures: Vec<Value> = self
+            .signatures
+            .iter()
+            .map(|s| match s.keyid {
+                Some(ref keyid) => json::json!({"keyid": keyid, "sig": base64::encode(&s.sig)}),
+                None => json::json!({"sig": base64::encode(&s.sig)}),
+            })
+            .collect();
+        json::json!({
+            "payloadType": self.payload_type,
+            "payload": base64::encode(&self.payload),
+            "signatures": signatures,
+        })
+    }
+
+    /// Returns the pre-authentication encoding of the payload, which is what the signatures sign.
+    pub fn pre_authentication_encoding(&self) -> Vec<u8> {
+        let mut pae = format!(
+            "DSSEv1 {} {} {} ",
+            self.payload_type.len(),
+            self.payload_type,
+            self.payload.len()
+        )
+        .into_bytes();
+        pae.extend_from_slice(&self.payload);
+        pae
+    }
+
+    /// Returns the first signature which is valid according to `verifier`. If the verifier has a
+    /// key id, only the signatures without one, or with a matching one, are considered.
+    pub fn verify(&self, verifier: &dyn Verifier) -> Result<&Signature, Error> {
+        let pae = self.pre_authentication_encoding();
+        let key_id_matches = |keyid: &Option<String>| match (keyid, verifier.key_id()) {
+            (Some(keyid), Some(key_id)) => {
+                keyid == key_id || keyid.ends_with(&format!("/{}", key_id))
+            }
+            _ => true,
+        };
+        self.signatures
+            .iter()
+            .filter(|s| key_id_matches(&s.keyid))
+            .find(|s| verifier.verify(&pae, &s.sig))
+            .ok_or(Error::NoValidSignature)
+    }
+
+    /// Decode the payload as in-toto statement, without verifying it.
+    pub fn statement(&self) -> Result<Statement, Error> {
+        if self.payload_type != IN_TOTO_PAYLOAD_TYPE {
+            return Err(Error::UnexpectedPayloadType(self.payload_type.clone()));
+        }
+        Ok(json::from_slice(&self.payload)?)
+    }
+
+    /// Decode the payload as in-toto statement, if the envelope has a valid signature.
+    pub fn verified_statement(&self, verifier: &dyn Verifier) -> Result<Statement, Error> {
+        self.verify(verifier)?;
+        self.statement()
+    }
+}
+
+/// An in-toto statement, which attests the `predicate` about its `subject`s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub type_: String,
+    pub subject: Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    #[serde(default)]
+    pub predicate: Value,
+}
+
+impl Statement {
+    /// A statement attesting the `predicate` of the given type about the `subject`s.
+    pub fn new(subject: Vec<Subject>, predicate_type: &str, predicate: Value) -> Statement {
+        Statement {
+            type_: IN_TOTO_STATEMENT_TYPE.to_string(),
+            subject,
+            predicate_type: predicate_type.to_string(),
+            predicate,
+        }
+    }
+
+    /// Decode the predicate into the type matching the `predicate_type`.
+    pub fn predicate_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(json::from_value(self.predicate.clone())?)
+    }
+
+    /// Returns the subject with the given digest, like `("sha256", "abc...")`.
+    pub fn subject_with_digest(&self, algorithm: &str, digest: &str) -> Option<&Subject> {
+        self.subject
+            .iter()
+            .find(|s| s.digest.get(algorithm).map(String::as_str) == Some(digest))
+    }
+}
+
+/// A software artifact the statement is about.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subject {
+    pub name: String,
+    /// The digests of the artifact by algorithm, like `sha256`.
+    pub digest: BTreeMap<String, String>,
+}
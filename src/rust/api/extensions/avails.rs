@@ -0,0 +1,106 @@
+//! Exporters of the Avails of the Play Movies Partner API to the spreadsheets of the
+//! [EMA Avails](http://www.movielabs.com/md/avails/) format, version 1.6b, which studios and
+//! retailers exchange availability windows with.
+//!
+//! All exporters accept the `Avail` type of the API, or anything else which serializes to the same
+//! JSON, like the responses of the CLI.
+//!
+//! ```ignore
+//! # use google_playmoviespartner1::api::Avail;
+//! # use google_playmoviespartner1::avails;
+//! # fn listed() -> Vec<Avail> { Vec::new() }
+//! let avails: Vec<Avail> = listed();
+//! std::fs::write("avails.csv", avails::to_ema_csv(&avails))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+use std::borrow::Cow;
+
+use serde::Serialize;
+use serde_json as json;
+use serde_json::Value;
+
+/// The columns of the EMA spreadsheets which Avails have, in the order of the spec, as their
+/// header and the field of the Avail they are taken from. The fields which aren't part of the
+/// spec, like `videoId` and `pphNames`, are left out.
+pub const EMA_COLUMNS: &[(&str, &str)] = &[
+    ("DisplayName", "displayName"),
+    ("StoreLanguage", "storeLanguage"),
+    ("Territory", "territory"),
+    ("WorkType", "workType"),
+    ("TitleInternalAlias", "titleInternalAlias"),
+    ("SeriesTitleInternalAlias", "seriesTitleInternalAlias"),
+    ("SeasonTitleInternalAlias", "seasonTitleInternalAlias"),
+    ("EpisodeTitleInternalAlias", "episodeTitleInternalAlias"),
+    ("SeasonNumber", "seasonNumber"),
+    ("EpisodeNumber", "episodeNumber"),
+    ("LicenseType", "licenseType"),
+    ("FormatProfile", "formatProfile"),
+    ("Start", "start"),
+    ("End", "end"),
+    ("PriceType", "priceType"),
+    ("PriceValue", "priceValue"),
+    ("ContentID", "contentId"),
+    ("ProductID", "productId"),
+    ("EncodeID", "encodeId"),
+    ("AvailID", "availId"),
+    ("AltID", "altId"),
+    ("SeriesAltID", "seriesAltId"),
+    ("SeasonAltID", "seasonAltId"),
+    ("EpisodeAltID", "episodeAltId"),
+    ("SuppressionLiftDate", "suppressionLiftDate"),
+    ("ReleaseYear", "releaseDate"),
+    ("RatingSystem", "ratingSystem"),
+    ("RatingValue", "ratingValue"),
+    ("RatingReason", "ratingReason"),
+    ("CaptionIncluded", "captionIncluded"),
+    ("CaptionExemption", "captionExemption"),
+];
+
+/// Returns the cell of the EMA spreadsheet holding `value`, the one of the `field` of an Avail.
+/// Unspecified enum values and missing fields are empty, work types are capitalized like `Movie`,
+/// and booleans are `Yes` or `No`, as the spec has them.
+pub fn ema_cell(field: &str, value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::Bool(b)) => if *b { "Yes" } else { "No" }.to_string(),
+        Some(Value::String(s)) if s.ends_with("_UNSPECIFIED") => String::new(),
+        Some(Value::String(s)) if field == "workType" => {
+            let mut chars = s.chars();
+            chars
+                .next()
+                .map(|first| first.to_string() + &chars.as_str().to_lowercase())
+                .unwrap_or_default()
+        }
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Returns the `avails` as CSV, with a header row naming the `EMA_COLUMNS`, and a row per Avail.
+/// Rows end with CRLF, and cells are quoted as needed, as RFC 4180 has it.
+pub fn to_ema_csv<A: Serialize>(avails: &[A]) -> String {
+    let mut csv = csv_record(EMA_COLUMNS.iter().map(|&(header, _)| Cow::Borrowed(header)));
+    for avail in avails {
+        let avail = json::to_value(avail).expect("serde to work");
+        csv += &csv_record(
+            EMA_COLUMNS
+                .iter()
+                .map(|&(_, field)| Cow::Owned(ema_cell(field, avail.get(field)))),
+        );
+    }
+    csv
+}
+
+// Returns the `cells` as a line of CSV, quoting the ones with separators, quotes or line breaks.
+fn csv_record<'a>(cells: impl Iterator<Item = Cow<'a, str>>) -> String {
+    let cells: Vec<Cow<str>> = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\r', '\n']) {
+                Cow::Owned(format!("\"{}\"", cell.replace('"', "\"\"")))
+            } else {
+                cell
+            }
+        })
+        .collect();
+    cells.join(",") + "\r\n"
+}
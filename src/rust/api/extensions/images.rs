@@ -0,0 +1,213 @@
+//! Construction and parsing of the resource URIs of container images, like
+//! `https://gcr.io/my-project/app@sha256:<digest>`, which occurrences refer to their image by.
+//!
+//! Occurrences are only found if the URI of the image matches theirs exactly. It starts with
+//! `https://`, and names the image by its digest rather than a tag. An `ImageUri` validates these
+//! parts, and is parsed from the references people usually have at hand, like the ones of
+//! `docker pull`, while it always formats as the URI of the occurrences.
+//!
+//! ```ignore
+//! # use google_containeranalysis1::images::ImageUri;
+//! let image: ImageUri = "gcr.io/my-project/app:1.0@sha256:4b0e...".parse().unwrap();
+//! assert_eq!(image.project(), Some("my-project"));
+//! let occurrences = hub.projects().occurrences_list("projects/my-project")
+//!                      .filter(&image.occurrences_filter())
+//!                      .doit().await;
+//! ```
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The scheme of the resource URIs of images.
+pub const SCHEME: &str = "https";
+/// The algorithm of the digests of images.
+pub const DIGEST_ALGORITHM: &str = "sha256";
+
+const DIGEST_LEN: usize = 64;
+
+/// An error constructing or parsing the resource URI of an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The URI has another scheme than `https`, like `http`.
+    UnsupportedScheme(String),
+    /// The URI lacks the registry, or the path of the image in it.
+    MissingImage,
+    /// The registry isn't a host name, with an optional port.
+    InvalidRegistry(String),
+    /// The path has empty segments, or characters which aren't allowed in repository names.
+    InvalidPath(String),
+    /// The image is named by the given tag, if any, instead of its digest.
+    MissingDigest(Option<String>),
+    /// The digest has another algorithm than `sha256`.
+    UnsupportedDigestAlgorithm(String),
+    /// The digest isn't 64 hexadecimal characters.
+    InvalidDigest(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnsupportedScheme(ref scheme) => write!(
+                f,
+                "The scheme '{}' is not supported, image URIs start with '{}://'",
+                scheme, SCHEME
+            ),
+            Error::MissingImage => f.write_str(
+                "The URI doesn't name an image, like 'https://gcr.io/<project>/<image>@sha256:<digest>'",
+            ),
+            Error::InvalidRegistry(ref registry) => {
+                write!(f, "The registry '{}' is not a valid host name", registry)
+            }
+            Error::InvalidPath(ref path) => write!(
+                f,
+                "The image path '{}' must consist of non-empty segments of lowercase letters, digits, '.', '_' and '-'",
+                path
+            ),
+            Error::MissingDigest(Some(ref tag)) => write!(
+                f,
+                "The image is named by its tag '{}', but occurrences refer to images by their digest, like '<image>@sha256:<digest>'",
+                tag
+            ),
+            Error::MissingDigest(None) => f.write_str(
+                "The image has no digest, but occurrences refer to images by their digest, like '<image>@sha256:<digest>'",
+            ),
+            Error::UnsupportedDigestAlgorithm(ref algorithm) => write!(
+                f,
+                "The digest algorithm '{}' is not supported, expected '{}'",
+                algorithm, DIGEST_ALGORITHM
+            ),
+            Error::InvalidDigest(ref digest) => write!(
+                f,
+                "The digest '{}' is not {} hexadecimal characters",
+                digest, DIGEST_LEN
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// The resource URI of a container image, like `https://gcr.io/my-project/app@sha256:<digest>` or
+/// `https://us-docker.pkg.dev/my-project/my-repository/app@sha256:<digest>`.
+///
+/// It is parsed from URIs and `docker pull` references, with or without scheme and with an
+/// optional tag, which are all formatted as the URI occurrences use.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageUri {
+    registry: String,
+    path: String,
+    digest: String,
+}
+
+impl ImageUri {
+    /// Returns the URI of the image at `path` in `registry`, like `gcr.io`, with the given digest,
+    /// which may be prefixed with its algorithm, like `sha256:<digest>`.
+    pub fn new(registry: &str, path: &str, digest: &str) -> Result<ImageUri, Error> {
+        let registry = registry.to_ascii_lowercase();
+        if registry.is_empty()
+            || !registry
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ".-:".contains(c))
+        {
+            return Err(Error::InvalidRegistry(registry));
+        }
+        let valid_segment = |segment: &str| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c))
+        };
+        if !path.split('/').all(valid_segment) {
+            return Err(Error::InvalidPath(path.to_string()));
+        }
+        let hex = match digest.split_once(':') {
+            Some((algorithm, hex)) if algorithm.eq_ignore_ascii_case(DIGEST_ALGORITHM) => hex,
+            Some((algorithm, _)) => {
+                return Err(Error::UnsupportedDigestAlgorithm(algorithm.to_string()))
+            }
+            None => digest,
+        };
+        if hex.len() != DIGEST_LEN || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::InvalidDigest(digest.to_string()));
+        }
+        Ok(ImageUri {
+            registry,
+            path: path.to_string(),
+            digest: hex.to_ascii_lowercase(),
+        })
+    }
+
+    /// The host of the registry, like `gcr.io` or `us-docker.pkg.dev`.
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    /// The path of the image in its registry, like `my-project/app`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The digest of the image, along with its algorithm, like `sha256:<digest>`.
+    pub fn digest(&self) -> String {
+        format!("{}:{}", DIGEST_ALGORITHM, self.digest)
+    }
+
+    /// The id of the project hosting the image, which is the first segment of its path in the
+    /// registries of Google, like `my-project` of `gcr.io/my-project/app`.
+    pub fn project(&self) -> Option<&str> {
+        self.path.split('/').next()
+    }
+
+    /// Returns the filter selecting the occurrences of this image.
+    pub fn occurrences_filter(&self) -> String {
+        format!("resourceUrl=\"{}\"", self)
+    }
+}
+
+impl fmt::Display for ImageUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}://{}/{}@{}:{}",
+            SCHEME, self.registry, self.path, DIGEST_ALGORITHM, self.digest
+        )
+    }
+}
+
+impl FromStr for ImageUri {
+    type Err = Error;
+
+    /// Parses URIs like `https://gcr.io/my-project/app@sha256:<digest>`, as well as references
+    /// like `gcr.io/my-project/app:1.0@sha256:<digest>`, whose tag is dropped.
+    fn from_str(s: &str) -> Result<ImageUri, Error> {
+        let s = s.trim();
+        let location = match s.split_once("://") {
+            Some((scheme, location)) if scheme.eq_ignore_ascii_case(SCHEME) => location,
+            Some((scheme, _)) => return Err(Error::UnsupportedScheme(scheme.to_string())),
+            None => s,
+        };
+        let (registry, name) = match location.split_once('/') {
+            Some((registry, name)) if !registry.is_empty() && !name.is_empty() => (registry, name),
+            _ => return Err(Error::MissingImage),
+        };
+        let (name, digest) = match name.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (name, None),
+        };
+        // Only the last segment can have a tag, as the registry may have a port
+        let (path, tag) = match name.rsplit_once('/') {
+            Some((parent, last)) => match last.split_once(':') {
+                Some((image, tag)) => (&name[..parent.len() + 1 + image.len()], Some(tag)),
+                None => (name, None),
+            },
+            None => match name.split_once(':') {
+                Some((image, tag)) => (image, Some(tag)),
+                None => (name, None),
+            },
+        };
+        match digest {
+            Some(digest) => ImageUri::new(registry, path, digest),
+            None => Err(Error::MissingDigest(tag.map(str::to_string))),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+// Modules added to the crates of the APIs listing them as `extensions` in their overrides.
+pub mod attestation;
+pub mod avails;
+pub mod images;
+pub mod vulnerabilities;
+// `signing` isn't built here, as it uses the hubs of the `containeranalysis1` and `cloudkms1` crates.
+// CI checks it with all features of the generated `containeranalysis1` crate instead.
@@ -0,0 +1,259 @@
+//! Signing of attestations with the asymmetric keys of Cloud KMS, through the hub of the
+//! `cloudkms1` crate, and creation of the occurrences carrying them in a single call.
+//!
+//! A `KmsSigner` signs with a key version, which never leaves Cloud KMS. The signed attestations
+//! are verified by Binary Authorization, or with a `PublicKey` of the `attestation` module.
+//!
+//! This module is only available with the `signing` feature, which implies `attestation`.
+//!
+//! ```ignore
+//! # let payload = r#"{"critical": {"type": "atomic container signature"}}"#;
+//! # use google_containeranalysis1::signing::{self, KmsSigner};
+//! let key = "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1";
+//! let signer = KmsSigner::from_key_version(&kms_hub, key).await.unwrap();
+//! let occurrence = signing::create_attestation_occurrence(
+//!     &hub,
+//!     &signer,
+//!     "projects/p",
+//!     "projects/p/notes/built-by-ci",
+//!     "https://gcr.io/p/app@sha256:4b0e...",
+//!     payload.as_bytes(),
+//! )
+//! .await
+//! .unwrap();
+//! ```
+use std::error;
+use std::fmt;
+
+use google_cloudkms1::api::{AsymmetricSignRequest, Digest};
+use google_cloudkms1::CloudKMS;
+use serde_json as json;
+
+use crate::api::Occurrence;
+use crate::attestation::{self, Envelope, Signature, Statement};
+use crate::ContainerAnalysis;
+
+/// The kind of the occurrences created by this module.
+pub const ATTESTATION_KIND: &str = "ATTESTATION";
+
+/// An error signing an attestation, or creating its occurrence.
+#[derive(Debug)]
+pub enum Error {
+    /// A call to Cloud KMS, or the creation of the occurrence, failed.
+    Api(crate::Error),
+    /// The payload couldn't be encoded, or the key has an unsupported algorithm.
+    Attestation(attestation::Error),
+    /// The response of Cloud KMS lacks the named field.
+    MissingField(&'static str),
+    /// Cloud KMS signed with another key version than the requested one.
+    UnexpectedKeyVersion(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Api(ref err) => fmt::Display::fmt(err, f),
+            Error::Attestation(ref err) => fmt::Display::fmt(err, f),
+            Error::MissingField(field) => write!(f, "The response of Cloud KMS has no '{}'", field),
+            Error::UnexpectedKeyVersion(ref name) => {
+                write!(
+                    f,
+                    "Cloud KMS signed with the unexpected key version '{}'",
+                    name
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Api(ref err) => Some(err),
+            Error::Attestation(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(err: crate::Error) -> Self {
+        Error::Api(err)
+    }
+}
+
+impl From<attestation::Error> for Error {
+    fn from(err: attestation::Error) -> Self {
+        Error::Attestation(err)
+    }
+}
+
+impl From<json::Error> for Error {
+    fn from(err: json::Error) -> Self {
+        Error::Attestation(err.into())
+    }
+}
+
+/// Signs messages with an asymmetric key version of Cloud KMS.
+pub struct KmsSigner<'a> {
+    hub: &'a CloudKMS,
+    key_version: String,
+    algorithm: String,
+}
+
+impl<'a> KmsSigner<'a> {
+    /// Sign with the key version of the given name, like
+    /// `projects/p/locations/l/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1`, whose algorithm is
+    /// named like `EC_SIGN_P256_SHA256`.
+    pub fn new(hub: &'a CloudKMS, key_version: &str, algorithm: &str) -> KmsSigner<'a> {
+        KmsSigner {
+            hub,
+            key_version: key_version.to_string(),
+            algorithm: algorithm.to_string(),
+        }
+    }
+
+    /// Sign with the key version of the given name, whose algorithm is read from its public key.
+    pub async fn from_key_version(
+        hub: &'a CloudKMS,
+        key_version: &str,
+    ) -> Result<KmsSigner<'a>, Error> {
+        let (_, public_key) = hub
+            .projects()
+            .locations_key_rings_crypto_keys_crypto_key_versions_get_public_key(key_version)
+            .doit()
+            .await?;
+        let algorithm = public_key
+            .algorithm
+            .ok_or(Error::MissingField("algorithm"))?;
+        Ok(KmsSigner::new(hub, key_version, &algorithm))
+    }
+
+    /// The name of the key version signing messages.
+    pub fn key_version(&self) -> &str {
+        &self.key_version
+    }
+
+    /// The id of the public key verifying the signatures, as Binary Authorization expects it, like
+    /// `//cloudkms.googleapis.com/v1/projects/p/.../cryptoKeyVersions/1`.
+    pub fn public_key_id(&self) -> String {
+        format!("//cloudkms.googleapis.com/v1/{}", self.key_version)
+    }
+
+    /// Returns the signature of `message`.
+    pub async fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut request = AsymmetricSignRequest::default();
+        match attestation::kms_digest(&self.algorithm, message)? {
+            Some((field, digest)) => {
+                let digest = Some(base64::encode(&digest));
+                // the schemas of other crates are non-exhaustive
+                request.digest = Some(Digest::with(|d| match field {
+                    "sha256" => d.sha256 = digest,
+                    "sha384" => d.sha384 = digest,
+                    _ => d.sha512 = digest,
+                }));
+            }
+            None => request.data = Some(base64::encode(message)),
+        }
+        let (_, response) = self
+            .hub
+            .projects()
+            .locations_key_rings_crypto_keys_crypto_key_versions_asymmetric_sign(
+                request,
+                &self.key_version,
+            )
+            .doit()
+            .await?;
+        match response.name {
+            Some(ref name) if *name != self.key_version => {
+                return Err(Error::UnexpectedKeyVersion(name.clone()))
+            }
+            _ => {}
+        }
+        let signature = response.signature.ok_or(Error::MissingField("signature"))?;
+        base64::decode(&signature)
+            .map_err(|err| attestation::Error::Base64("signature", err).into())
+    }
+}
+
+// Create the attestation occurrence of the resource in `parent`, which has the given `field`
+async fn create_occurrence(
+    hub: &ContainerAnalysis,
+    parent: &str,
+    note_name: &str,
+    resource_uri: &str,
+    field: &str,
+    value: json::Value,
+) -> Result<Occurrence, Error> {
+    let mut occurrence = json::json!({
+        "kind": ATTESTATION_KIND,
+        "noteName": note_name,
+        "resourceUri": resource_uri,
+    });
+    occurrence[field] = value;
+    let (_, occurrence) = hub
+        .projects()
+        .occurrences_create(json::from_value(occurrence)?, parent)
+        .doit()
+        .await?;
+    Ok(occurrence)
+}
+
+/// Sign `payload`, like a Binary Authorization
+/// [simple signing](https://cloud.google.com/binary-authorization/docs/making-attestations)
+/// payload, and create the occurrence attesting it for the image at `resource_uri`, which belongs
+/// to the attestation note `note_name`, in `parent`, like `projects/p`.
+pub async fn create_attestation_occurrence(
+    hub: &ContainerAnalysis,
+    signer: &KmsSigner<'_>,
+    parent: &str,
+    note_name: &str,
+    resource_uri: &str,
+    payload: &[u8],
+) -> Result<Occurrence, Error> {
+    let signature = signer.sign(payload).await?;
+    let attestation = json::json!({
+        "serializedPayload": base64::encode(payload),
+        "signatures": [{
+            "publicKeyId": signer.public_key_id(),
+            "signature": base64::encode(&signature),
+        }],
+    });
+    create_occurrence(
+        hub,
+        parent,
+        note_name,
+        resource_uri,
+        "attestation",
+        attestation,
+    )
+    .await
+}
+
+/// Sign the DSSE envelope of the in-toto `statement`, and create the occurrence carrying it for
+/// the image at `resource_uri`, like `create_attestation_occurrence()`. The signature has the name
+/// of the key version as `keyid`.
+pub async fn create_statement_occurrence(
+    hub: &ContainerAnalysis,
+    signer: &KmsSigner<'_>,
+    parent: &str,
+    note_name: &str,
+    resource_uri: &str,
+    statement: &Statement,
+) -> Result<Occurrence, Error> {
+    let mut envelope = Envelope::from_statement(statement)?;
+    let sig = signer.sign(&envelope.pre_authentication_encoding()).await?;
+    envelope.signatures.push(Signature {
+        keyid: Some(signer.key_version().to_string()),
+        sig,
+    });
+    create_occurrence(
+        hub,
+        parent,
+        note_name,
+        resource_uri,
+        "envelope",
+        envelope.to_api(),
+    )
+    .await
+}
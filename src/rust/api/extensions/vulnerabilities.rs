@@ -0,0 +1,463 @@
+//! Converters from the vulnerability occurrences found by the Container Analysis API to formats
+//! understood by other tools, like [SARIF](https://sarifweb.azurewebsites.net) 2.1.0 logs for code
+//! scanning dashboards, and [CycloneDX](https://cyclonedx.org/capabilities/vex/) VEX documents.
+//!
+//! All converters accept the `Occurrence` and `VulnerabilityOccurrence` types of the API, or anything
+//! else which serializes to the same JSON, like the responses of the CLI.
+//!
+//! ```ignore
+//! # use google_containeranalysis1::api::Occurrence;
+//! # use google_containeranalysis1::vulnerabilities;
+//! # fn scanned() -> Vec<Occurrence> { Vec::new() }
+//! let occurrences: Vec<Occurrence> = scanned();
+//! let findings = vulnerabilities::findings_from_occurrences(&occurrences);
+//! let image = "https://gcr.io/my-project/app@sha256:abc";
+//! let sarif = vulnerabilities::to_sarif(&findings, image, "my-scanner");
+//! let vex = vulnerabilities::to_cyclonedx_vex(&findings, image, "my-scanner");
+//! ```
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json as json;
+use serde_json::Value;
+
+/// The severities of vulnerabilities, from the least to the most severe.
+pub const SEVERITIES: &[&str] = &[
+    "SEVERITY_UNSPECIFIED",
+    "MINIMAL",
+    "LOW",
+    "MEDIUM",
+    "HIGH",
+    "CRITICAL",
+];
+
+/// A package affected by a vulnerability.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct AffectedPackage {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl AffectedPackage {
+    /// A reference to the package which is unique within a report, like `zlib@1.2.11`.
+    pub fn bom_ref(&self) -> String {
+        match self.version {
+            Some(ref version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for AffectedPackage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.version {
+            Some(ref version) => write!(f, "{} {}", self.name, version),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+/// A vulnerability, along with all packages affected by it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VulnerabilityFinding {
+    /// The id of the vulnerability, usually a CVE, like `CVE-2021-44228`.
+    pub cve: String,
+    /// The effective severity, one of `SEVERITIES`.
+    pub severity: String,
+    pub cvss_score: Option<f64>,
+    /// True if a fix is available for at least one of the affected packages.
+    pub fix_available: bool,
+    /// The affected packages, sorted and without duplicates.
+    pub packages: Vec<AffectedPackage>,
+    pub description: String,
+}
+
+fn severity_rank(severity: &str) -> usize {
+    SEVERITIES.iter().position(|s| *s == severity).unwrap_or(0)
+}
+
+fn merge_vulnerability(findings: &mut Vec<VulnerabilityFinding>, cve: &str, vulnerability: &Value) {
+    let severity = vulnerability["effectiveSeverity"]
+        .as_str()
+        .or_else(|| vulnerability["severity"].as_str())
+        .unwrap_or(SEVERITIES[0]);
+    let issues = vulnerability["packageIssue"]
+        .as_array()
+        .map(|a| &a[..])
+        .unwrap_or(&[]);
+    let packages = issues.iter().filter_map(|issue| {
+        Some(AffectedPackage {
+            name: issue["affectedPackage"].as_str()?.to_string(),
+            version: issue["affectedVersion"]["fullName"]
+                .as_str()
+                .map(str::to_string),
+        })
+    });
+    let fix_available = vulnerability["fixAvailable"].as_bool().unwrap_or(false)
+        || issues
+            .iter()
+            .any(|issue| issue["fixAvailable"].as_bool().unwrap_or(false));
+
+    let finding = match findings.iter().position(|f| f.cve == cve) {
+        Some(index) => &mut findings[index],
+        None => {
+            findings.push(VulnerabilityFinding {
+                cve: cve.to_string(),
+                severity: SEVERITIES[0].to_string(),
+                cvss_score: None,
+                fix_available: false,
+                packages: Vec::new(),
+                description: String::new(),
+            });
+            findings.last_mut().unwrap()
+        }
+    };
+    if severity_rank(severity) > severity_rank(&finding.severity) {
+        finding.severity = severity.to_string();
+    }
+    if let Some(score) = vulnerability["cvssScore"].as_f64() {
+        finding.cvss_score = Some(finding.cvss_score.map_or(score, |s| s.max(score)));
+    }
+    finding.fix_available |= fix_available;
+    finding.packages.extend(packages);
+    if finding.description.is_empty() {
+        finding.description = vulnerability["shortDescription"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+    }
+}
+
+fn sorted(mut findings: Vec<VulnerabilityFinding>) -> Vec<VulnerabilityFinding> {
+    for finding in &mut findings {
+        finding.packages.sort();
+        finding.packages.dedup();
+    }
+    findings.sort_by(|a, b| {
+        severity_rank(&b.severity)
+            .cmp(&severity_rank(&a.severity))
+            .then_with(|| a.cve.cmp(&b.cve))
+    });
+    findings
+}
+
+/// Group the given occurrences by the vulnerability they refer to, which is identified by the last
+/// segment of their note name. Occurrences of another kind are ignored.
+///
+/// The findings are sorted by severity, the most severe one first, and then by their id.
+pub fn findings_from_occurrences<O: Serialize>(occurrences: &[O]) -> Vec<VulnerabilityFinding> {
+    let mut findings = Vec::new();
+    for occurrence in occurrences {
+        let occurrence = json::to_value(occurrence).expect("serde to work");
+        let vulnerability = &occurrence["vulnerability"];
+        if !vulnerability.is_object() {
+            continue;
+        }
+        let cve = occurrence["noteName"]
+            .as_str()
+            .and_then(|name| name.rsplit('/').next())
+            .filter(|id| !id.is_empty())
+            .unwrap_or("UNKNOWN");
+        merge_vulnerability(&mut findings, cve, vulnerability);
+    }
+    sorted(findings)
+}
+
+/// Like `findings_from_occurrences()`, but for vulnerabilities along with their id.
+pub fn findings_from_vulnerabilities<'a, V: Serialize + 'a>(
+    vulnerabilities: impl IntoIterator<Item = (&'a str, &'a V)>,
+) -> Vec<VulnerabilityFinding> {
+    let mut findings = Vec::new();
+    for (cve, vulnerability) in vulnerabilities {
+        let vulnerability = json::to_value(vulnerability).expect("serde to work");
+        merge_vulnerability(&mut findings, cve, &vulnerability);
+    }
+    sorted(findings)
+}
+
+const TABLE_HEADER: [&str; 5] = ["SEVERITY", "CVE", "CVSS", "FIX", "PACKAGES"];
+
+fn table_cells(f: &VulnerabilityFinding) -> Vec<String> {
+    vec![
+        f.severity.clone(),
+        f.cve.clone(),
+        f.cvss_score
+            .map(|s| format!("{:.1}", s))
+            .unwrap_or_default(),
+        if f.fix_available { "yes" } else { "no" }.to_string(),
+        f.packages
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+    ]
+}
+
+// Write the rows with all cells of a column padded to the same width
+fn write_columns(ostream: &mut dyn Write, rows: &[Vec<String>]) -> io::Result<()> {
+    let mut widths = Vec::new();
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(ostream, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+/// Write a summary of the number of findings per severity, followed by a table of all findings.
+pub fn write_table(ostream: &mut dyn Write, findings: &[VulnerabilityFinding]) -> io::Result<()> {
+    for severity in SEVERITIES.iter().rev() {
+        let count = findings.iter().filter(|f| f.severity == *severity).count();
+        if count > 0 {
+            writeln!(ostream, "{}: {}", severity, count)?;
+        }
+    }
+    writeln!(ostream, "TOTAL: {}", findings.len())?;
+    if !findings.is_empty() {
+        let mut rows = vec![TABLE_HEADER.iter().map(|h| h.to_string()).collect()];
+        rows.extend(findings.iter().map(table_cells));
+        writeln!(ostream)?;
+        write_columns(ostream, &rows)?;
+    }
+    ostream.flush()
+}
+
+/// A vulnerability found in both resources, which differs between them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangedFinding {
+    pub from: VulnerabilityFinding,
+    pub to: VulnerabilityFinding,
+}
+
+/// The difference between the vulnerabilities of two resources, like two digests of an image.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct FindingsDiff {
+    /// The vulnerabilities only found in the new resource.
+    pub added: Vec<VulnerabilityFinding>,
+    /// The vulnerabilities only found in the old resource.
+    pub removed: Vec<VulnerabilityFinding>,
+    /// The vulnerabilities found in both, but with another severity, score, fix or set of packages.
+    pub changed: Vec<ChangedFinding>,
+}
+
+impl FindingsDiff {
+    /// Returns true if both resources have the same vulnerabilities.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare the findings of the `from` resource with the ones of the `to` resource, identifying
+/// vulnerabilities by their id. All parts of the diff keep the order of the findings.
+pub fn diff(from: &[VulnerabilityFinding], to: &[VulnerabilityFinding]) -> FindingsDiff {
+    let find = |findings: &[VulnerabilityFinding], cve: &str| {
+        findings.iter().find(|f| f.cve == cve).cloned()
+    };
+    let mut diff = FindingsDiff::default();
+    for new in to {
+        match find(from, &new.cve) {
+            None => diff.added.push(new.clone()),
+            Some(old) => {
+                // the description is informational, and may be updated at any time
+                if old.severity != new.severity
+                    || old.cvss_score != new.cvss_score
+                    || old.fix_available != new.fix_available
+                    || old.packages != new.packages
+                {
+                    diff.changed.push(ChangedFinding {
+                        from: old,
+                        to: new.clone(),
+                    });
+                }
+            }
+        }
+    }
+    diff.removed = from
+        .iter()
+        .filter(|old| find(to, &old.cve).is_none())
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Write the number of added, removed and changed vulnerabilities, followed by a table of them,
+/// marked with `+`, `-` and `~` respectively. Changed ones are shown as they are now, along with
+/// what they were before.
+pub fn write_diff(ostream: &mut dyn Write, diff: &FindingsDiff) -> io::Result<()> {
+    writeln!(ostream, "ADDED: {}", diff.added.len())?;
+    writeln!(ostream, "REMOVED: {}", diff.removed.len())?;
+    writeln!(ostream, "CHANGED: {}", diff.changed.len())?;
+    if !diff.is_empty() {
+        let marked = |marker: &str, cells: Vec<String>| {
+            std::iter::once(marker.to_string())
+                .chain(cells)
+                .collect::<Vec<_>>()
+        };
+        let mut rows = vec![marked(
+            "",
+            TABLE_HEADER.iter().map(|h| h.to_string()).collect(),
+        )];
+        rows.extend(diff.added.iter().map(|f| marked("+", table_cells(f))));
+        rows.extend(diff.removed.iter().map(|f| marked("-", table_cells(f))));
+        for change in &diff.changed {
+            let mut row = marked("~", table_cells(&change.to));
+            let was = table_cells(&change.from);
+            for (cell, old) in row.iter_mut().skip(1).zip(was) {
+                if *cell != old {
+                    *cell = format!("{} (was {})", cell, if old.is_empty() { "-" } else { &old });
+                }
+            }
+            rows.push(row);
+        }
+        writeln!(ostream)?;
+        write_columns(ostream, &rows)?;
+    }
+    ostream.flush()
+}
+
+/// Returns the findings for the resource at `resource_url` as SARIF 2.1.0 log, with one rule per
+/// vulnerability and one result per finding, as reported by the tool called `tool_name`.
+pub fn to_sarif(findings: &[VulnerabilityFinding], resource_url: &str, tool_name: &str) -> Value {
+    let level = |severity: &str| match severity {
+        "CRITICAL" | "HIGH" => "error",
+        "MEDIUM" => "warning",
+        _ => "note",
+    };
+    let rules: Vec<Value> = findings
+        .iter()
+        .map(|f| {
+            let text = if f.description.is_empty() {
+                &f.cve
+            } else {
+                &f.description
+            };
+            let mut rule = json::json!({
+                "id": f.cve,
+                "shortDescription": { "text": text },
+                "properties": { "tags": ["security", "vulnerability"] },
+            });
+            if let Some(score) = f.cvss_score {
+                rule["properties"]["security-severity"] = Value::String(format!("{:.1}", score));
+            }
+            rule
+        })
+        .collect();
+    let results: Vec<Value> = findings
+        .iter()
+        .enumerate()
+        .map(|(index, f)| {
+            let packages: Vec<String> = f.packages.iter().map(ToString::to_string).collect();
+            let fix = if f.fix_available {
+                ", a fix is available"
+            } else {
+                ""
+            };
+            json::json!({
+                "ruleId": f.cve,
+                "ruleIndex": index,
+                "level": level(&f.severity),
+                "message": {
+                    "text": format!("{} ({}) affects {}{}", f.cve, f.severity, packages.join(", "), fix),
+                },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": resource_url } },
+                }],
+            })
+        })
+        .collect();
+    json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": { "name": tool_name, "rules": rules } },
+            "results": results,
+        }],
+    })
+}
+
+/// Returns the findings for the image at `resource_url` as CycloneDX 1.4 VEX document, created by
+/// the tool called `tool_name`.
+///
+/// The image is the component the document describes, and each affected package one of its
+/// components. As the occurrences only tell that a vulnerability is present, but not whether it
+/// is exploitable, the analysis of each vulnerability is left `in_triage`.
+pub fn to_cyclonedx_vex(
+    findings: &[VulnerabilityFinding],
+    resource_url: &str,
+    tool_name: &str,
+) -> Value {
+    let severity = |severity: &str| match severity {
+        "CRITICAL" | "HIGH" | "MEDIUM" | "LOW" => severity.to_lowercase(),
+        "MINIMAL" => "info".to_string(),
+        _ => "unknown".to_string(),
+    };
+    let mut packages: Vec<&AffectedPackage> = findings.iter().flat_map(|f| &f.packages).collect();
+    packages.sort();
+    packages.dedup();
+    let components: Vec<Value> = packages
+        .iter()
+        .map(|p| {
+            let mut component = json::json!({
+                "type": "library",
+                "bom-ref": p.bom_ref(),
+                "name": p.name,
+            });
+            if let Some(ref version) = p.version {
+                component["version"] = Value::String(version.clone());
+            }
+            component
+        })
+        .collect();
+    let vulnerabilities: Vec<Value> = findings
+        .iter()
+        .map(|f| {
+            let mut rating = json::json!({ "severity": severity(&f.severity) });
+            if let Some(score) = f.cvss_score {
+                rating["score"] = json::json!(score);
+            }
+            let mut affects: Vec<Value> = f
+                .packages
+                .iter()
+                .map(|p| json::json!({ "ref": p.bom_ref() }))
+                .collect();
+            if affects.is_empty() {
+                affects.push(json::json!({ "ref": resource_url }));
+            }
+            let mut vulnerability = json::json!({
+                "bom-ref": f.cve,
+                "id": f.cve,
+                "ratings": [rating],
+                "affects": affects,
+                "analysis": { "state": "in_triage" },
+            });
+            if !f.description.is_empty() {
+                vulnerability["description"] = Value::String(f.description.clone());
+            }
+            if f.fix_available {
+                vulnerability["analysis"]["response"] = json::json!(["update"]);
+            }
+            vulnerability
+        })
+        .collect();
+    json::json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.4",
+        "version": 1,
+        "metadata": {
+            "tools": [{ "name": tool_name }],
+            "component": { "type": "container", "bom-ref": resource_url, "name": resource_url },
+        },
+        "components": components,
+        "vulnerabilities": vulnerabilities,
+    })
+}
@@ -1 +1,2 @@
-pub mod client;
+pub use google_apis_common as client;
+pub mod extensions;
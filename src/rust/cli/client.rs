@@ -1,9 +1,18 @@
-use clap::{App, SubCommand};
-use mime::Mime;
+use crate::oauth2::authenticator_delegate::{DeviceAuthResponse, DeviceFlowDelegate};
 use crate::oauth2::{ApplicationSecret, ConsoleApplicationSecret};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use futures::future::Future;
+use futures::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressStyle};
+use mime::Mime;
+use serde_derive::Deserialize;
 use serde_json as json;
 use serde_json::value::Value;
+use tokio::io::{AsyncRead, ReadBuf};
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::env;
 use std::error::Error as StdError;
 use std::fmt;
@@ -11,13 +20,21 @@ use std::fs;
 use std::io;
 use std::io::{stdout, Read, Write};
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::str::FromStr;
 use std::string::ToString;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use std::default::Default;
 
 const FIELD_SEP: char = '.';
+const LIST_INDEX: &str = "[]";
+// Stands for the key of a map in the paths of the static field tables, like `build-options.*`
+const MAP_KEY: &str = "*";
 
+#[derive(Clone, Copy)]
 pub enum ComplexType {
     Pod,
     Vec,
@@ -31,6 +48,7 @@ pub enum ComplexType {
 // F64(f64),
 // String(String),
 
+#[derive(Clone, Copy)]
 pub enum JsonType {
     Boolean,
     Int,
@@ -39,9 +57,132 @@ pub enum JsonType {
     String,
 }
 
+impl JsonType {
+    /// The name of the type, as used in error messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            JsonType::Boolean => "boolean",
+            JsonType::Int => "int",
+            JsonType::Uint => "uint",
+            JsonType::Float => "float",
+            JsonType::String => "string",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct JsonTypeInfo {
     pub jtype: JsonType,
     pub ctype: ComplexType,
+    /// The values a string may have, or none if it may have any.
+    pub values: &'static [&'static str],
+}
+
+impl JsonTypeInfo {
+    /// The name of the type, like `int`, `list of string` or `map of boolean`.
+    pub fn type_name(&self) -> String {
+        match self.ctype {
+            ComplexType::Pod => self.jtype.name().to_string(),
+            ComplexType::Vec => format!("list of {}", self.jtype.name()),
+            ComplexType::Map => format!("map of {}", self.jtype.name()),
+        }
+    }
+}
+
+/// A field of a request structure which may be set with `-r`, as generated once per structure
+/// into a static table, which is sorted by `cli_path`.
+pub struct FieldInfo {
+    /// The path of the field as typed on the command line, like `spec.node-count`.
+    pub cli_path: &'static str,
+    /// The path of the field in the JSON of the structure, like `spec.nodeCount`.
+    pub path: &'static str,
+    pub type_info: JsonTypeInfo,
+    /// Whether the API deprecated the field, which is warned about when it is set.
+    pub deprecated: bool,
+}
+
+/// Warns on stderr about deprecated fields and parameters which are set, once per each of them,
+/// as a call may be made many times, like for each page or project.
+#[derive(Debug, Default)]
+pub struct DeprecationWarnings {
+    warned: Mutex<Vec<String>>,
+}
+
+impl DeprecationWarnings {
+    /// Warns that the deprecated `what`, like `field 'spec.node-count'`, is used, unless that was
+    /// warned about before. Returns true if it warned.
+    pub fn warn(&self, what: &str) -> bool {
+        let mut warned = self.warned.lock().unwrap();
+        if warned.iter().any(|w| w == what) {
+            return false;
+        }
+        warned.push(what.to_string());
+        writeln!(
+            io::stderr(),
+            "warning: the {} is deprecated by the API, which might remove it",
+            what
+        )
+        .ok();
+        true
+    }
+}
+
+/// A field suggested in place of an unknown one, see `FieldCursor::did_you_mean()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSuggestion {
+    /// The path of the field, with the indices of the unknown one, like `bindings[1].role`.
+    pub path: String,
+    /// The type of the field, like `int` or `list of string`.
+    pub type_name: String,
+}
+
+/// The amount of fields suggested for an unknown one, at most.
+pub const MAX_FIELD_SUGGESTIONS: usize = 3;
+
+/// Find the field with the given `cli_path` in the sorted table `fields`.
+pub fn find_field(fields: &'static [FieldInfo], cli_path: &str) -> Option<&'static FieldInfo> {
+    fields
+        .binary_search_by(|f| f.cli_path.cmp(cli_path))
+        .ok()
+        .map(|i| &fields[i])
+}
+
+/// Find the field at `cursor` in the sorted table `fields`, taking the segments which aren't fields
+/// as the keys of maps, like `debug` of `build-options.debug` for the field `build-options.*`.
+pub fn find_cursor_field(
+    fields: &'static [FieldInfo],
+    cursor: &FieldCursor,
+) -> Option<&'static FieldInfo> {
+    // true if the path is the one of a field, or of a structure containing fields
+    let is_prefix = |path: &str| {
+        let start = fields.partition_point(|f| f.cli_path < path);
+        fields[start..]
+            .iter()
+            .take_while(|f| f.cli_path.starts_with(path))
+            .any(|f| {
+                f.cli_path.len() == path.len() || f.cli_path[path.len()..].starts_with(FIELD_SEP)
+            })
+    };
+    let mut cli_path = String::new();
+    for field in &cursor.0 {
+        let name = match split_index(field) {
+            (name, Some(_)) => format!("{}{}", name, LIST_INDEX),
+            (name, None) => name.to_string(),
+        };
+        let prefix = if cli_path.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", cli_path, FIELD_SEP)
+        };
+        cli_path = if is_prefix(&format!("{}{}", prefix, name)) {
+            format!("{}{}", prefix, name)
+        } else if is_prefix(&format!("{}{}", prefix, MAP_KEY)) {
+            format!("{}{}", prefix, MAP_KEY)
+        } else {
+            return None;
+        };
+    }
+    find_field(fields, &cli_path)
 }
 
 // Based on @erickt user comment. Thanks for the idea !
@@ -51,11 +192,11 @@ pub fn remove_json_null_values(value: &mut Value) {
         Value::Object(ref mut map) => {
             let mut for_removal = Vec::new();
 
-            for (key, mut value) in map.iter_mut() {
+            for (key, value) in map.iter_mut() {
                 if value.is_null() {
                     for_removal.push(key.clone());
                 } else {
-                    remove_json_null_values(&mut value);
+                    remove_json_null_values(value);
                 }
             }
 
@@ -78,6 +219,33 @@ pub fn remove_json_null_values(value: &mut Value) {
     }
 }
 
+/// Bring `value` into its canonical form (changed in place), whose serialization doesn't depend on
+/// the order the server sent the fields in, or on how it formatted numbers.
+///
+/// The keys of all objects are sorted, and floating point numbers without fraction are turned into
+/// integers, which writes `1.0` and `-0.0` as `1` and `0`.
+pub fn canonicalize_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<_> = std::mem::take(map).into_iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut value) in fields {
+                canonicalize_json(&mut value);
+                map.insert(key, value);
+            }
+        }
+        Value::Array(arr) => arr.iter_mut().for_each(canonicalize_json),
+        Value::Number(n) if n.is_f64() => {
+            // Integers beyond 2^53 can't be represented exactly by a float anyway
+            let f = n.as_f64().unwrap_or_default();
+            if f.fract() == 0.0 && f.abs() <= 9_007_199_254_740_992.0 {
+                *value = Value::from(f as i64);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn did_you_mean<'a>(v: &str, possible_values: &[&'a str]) -> Option<&'a str> {
     let mut candidate: Option<(f64, &str)> = None;
     for pv in possible_values {
@@ -93,6 +261,29 @@ fn did_you_mean<'a>(v: &str, possible_values: &[&'a str]) -> Option<&'a str> {
     }
 }
 
+// Reports a value which isn't one of the given ones, if there are any, suggesting the closest one.
+fn check_value(
+    value: &str,
+    values: &'static [&'static str],
+    cursor: &FieldCursor,
+    err: &mut InvalidOptionsError,
+) {
+    if values.is_empty() || values.contains(&value) {
+        return;
+    }
+    let suggestion = values
+        .iter()
+        .find(|v| v.eq_ignore_ascii_case(value))
+        .copied()
+        .or_else(|| did_you_mean(value, values));
+    err.issues.push(CLIError::Field(FieldError::InvalidValue(
+        cursor.to_string(),
+        value.to_owned(),
+        suggestion.map(str::to_owned),
+        values,
+    )));
+}
+
 pub enum CallType {
     Upload(UploadProtocol),
     Standard,
@@ -126,9 +317,9 @@ impl AsRef<str> for CallType {
 #[derive(Clone, Default)]
 pub struct FieldCursor(Vec<String>);
 
-impl ToString for FieldCursor {
-    fn to_string(&self) -> String {
-        self.0.join(".")
+impl fmt::Display for FieldCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(&self.0.join("."))
     }
 }
 
@@ -140,14 +331,33 @@ impl From<&'static str> for FieldCursor {
     }
 }
 
-fn assure_entry<'a>(m: &'a mut json::Map<String, Value>, k: &str) -> &'a mut Value {
+fn assure_entry<'a>(m: &'a mut json::Map<String, Value>, k: &str, default: Value) -> &'a mut Value {
     if m.contains_key(k) {
         return m.get_mut(k).expect("value to exist");
     }
-    m.insert(k.to_owned(), Value::Object(Default::default()));
+    m.insert(k.to_owned(), default);
     m.get_mut(k).expect("value to exist")
 }
 
+// Splits a field like `bindings[0]` into its name and its index, which is empty for `bindings[]`.
+fn split_index(field: &str) -> (&str, Option<&str>) {
+    match field.find('[') {
+        Some(pos) if field.ends_with(']') => {
+            (&field[..pos], Some(&field[pos + 1..field.len() - 1]))
+        }
+        _ => (field, None),
+    }
+}
+
+fn is_valid_field(field: &str) -> bool {
+    match split_index(field) {
+        (name, Some(index)) => {
+            !name.is_empty() && !name.contains('[') && index.chars().all(|c| c.is_ascii_digit())
+        }
+        (name, None) => !name.contains('[') && !name.contains(']'),
+    }
+}
+
 impl FieldCursor {
     pub fn set(&mut self, value: &str) -> Result<(), CLIError> {
         if value.is_empty() {
@@ -205,219 +415,1965 @@ impl FieldCursor {
                 value.to_string(),
             )));
         }
+        if !fields.iter().all(|f| is_valid_field(f)) {
+            return Err(CLIError::Field(FieldError::InvalidIndex(value.to_string())));
+        }
 
         self.0 = fields;
         Ok(())
     }
 
-    pub fn did_you_mean(value: &str, possible_values: &[&str]) -> Option<String> {
-        if value.is_empty() {
-            return None;
+    /// Suggest the fields of the table `fields` whose paths are most similar to the one at the
+    /// cursor, most similar first. Paths are compared as a whole, which finds `spec.node-count` for
+    /// `spec.nde-count`, and with as many of their trailing segments as the cursor has, which finds
+    /// it for `node-count`, too.
+    pub fn did_you_mean(&self, fields: &[FieldInfo]) -> Vec<FieldSuggestion> {
+        let path = self.schema_path();
+        if path.is_empty() {
+            return Vec::new();
         }
+        // (confidence, score of the whole path, depth, field)
+        let mut candidates: Vec<(f64, f64, usize, &FieldInfo)> = fields
+            .iter()
+            // the keys of maps are suggested as the map itself
+            .filter(|f| !f.cli_path.ends_with(MAP_KEY))
+            .filter_map(|f| {
+                let segments: Vec<&str> = f.cli_path.split(FIELD_SEP).collect();
+                let tail = segments[segments.len().saturating_sub(self.0.len())..].join(".");
+                let whole = strsim::jaro_winkler(&path, f.cli_path);
+                let confidence = whole.max(strsim::jaro_winkler(&path, &tail));
+                Some((confidence, whole, segments.len(), f)).filter(|_| confidence > 0.8)
+            })
+            .collect();
+        // tails of the same name are told apart by how similar their whole path is, and how
+        // shallow it is, which prefers `kind` over `build.kind` for `kindd`
+        candidates.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| b.1.total_cmp(&a.1))
+                .then_with(|| a.2.cmp(&b.2))
+                .then_with(|| a.3.cli_path.cmp(b.3.cli_path))
+        });
+        candidates
+            .into_iter()
+            .take(MAX_FIELD_SUGGESTIONS)
+            .map(|(_, _, _, f)| FieldSuggestion {
+                path: self.indexed_like(f.cli_path),
+                type_name: f.type_info.type_name(),
+            })
+            .collect()
+    }
 
-        let mut last_c = FIELD_SEP;
+    // Replaces the `[]` of the segments of `cli_path` with the indices of the ones of the cursor,
+    // matching them from the end
+    fn indexed_like(&self, cli_path: &str) -> String {
+        let mut segments: Vec<String> = cli_path.split(FIELD_SEP).map(str::to_string).collect();
+        for (segment, field) in segments.iter_mut().rev().zip(self.0.iter().rev()) {
+            if let (Some(name), (_, Some(index))) =
+                (segment.strip_suffix(LIST_INDEX), split_index(field))
+            {
+                *segment = format!("{}[{}]", name, index);
+            }
+        }
+        segments.join(".")
+    }
 
-        let mut field = String::new();
-        let mut output = String::new();
+    /// Returns the path of the field at the cursor, with the indices of lists replaced by `[]`,
+    /// like `bindings[].role` for `bindings[2].role`.
+    pub fn schema_path(&self) -> String {
+        self.0
+            .iter()
+            .map(|f| match split_index(f) {
+                (name, Some(_)) => format!("{}{}", name, LIST_INDEX),
+                (name, None) => name.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
 
-        let push_field = |fs: &mut String, f: &mut String| {
-            if !f.is_empty() {
-                fs.push_str(match did_you_mean(&f, possible_values) {
-                    Some(candidate) => candidate,
-                    None => &f,
-                });
-                f.truncate(0);
+    /// Sets the field at this cursor, which must be a schema path like `bindings[].role`, to
+    /// `value`. The lists on the way are indexed like the fields of `orig_cursor`, where `[]` is
+    /// the last item of a list, and the index of the length of a list adds an item to it.
+    /// Values are added to lists of values if `append` is set, which only they allow.
+    ///
+    /// The value is resolved by `kv_value()`, and the values of lists are split at commas.
+    pub fn set_json_value(
+        &self,
+        object: &mut Value,
+        value: &str,
+        type_info: JsonTypeInfo,
+        append: bool,
+        err: &mut InvalidOptionsError,
+        orig_cursor: &FieldCursor,
+    ) {
+        let (mapping, field) = match self.target(object, type_info, append, err, orig_cursor) {
+            Some(target) => target,
+            None => return,
+        };
+        let values = type_info.values;
+        let to_jval = |value: &str, jtype: JsonType, err: &mut InvalidOptionsError| -> Value {
+            let value = kv_value(value, err);
+            match jtype {
+                JsonType::Boolean => Value::Bool(arg_from_str(&value, err, &field, "boolean")),
+                JsonType::Int => Value::Number(
+                    json::Number::from_f64(arg_from_str(&value, err, &field, "int"))
+                        .expect("valid f64"),
+                ),
+                JsonType::Uint => Value::Number(
+                    json::Number::from_f64(arg_from_str(&value, err, &field, "uint"))
+                        .expect("valid f64"),
+                ),
+                JsonType::Float => Value::Number(
+                    json::Number::from_f64(arg_from_str(&value, err, &field, "float"))
+                        .expect("valid f64"),
+                ),
+                JsonType::String => {
+                    check_value(&value, values, orig_cursor, err);
+                    Value::String(value.into_owned())
+                }
             }
         };
 
-        for (cid, c) in value.chars().enumerate() {
-            if c == FIELD_SEP {
-                if last_c != FIELD_SEP {
-                    push_field(&mut output, &mut field);
+        match type_info.ctype {
+            ComplexType::Pod => {
+                let jval = to_jval(value, type_info.jtype, err);
+                if mapping.insert(field.clone(), jval).is_some() {
+                    err.issues.push(CLIError::Field(FieldError::Duplicate(
+                        orig_cursor.to_string(),
+                    )));
                 }
-                output.push(c);
-            } else {
-                field.push(c);
             }
+            ComplexType::Vec => {
+                let jvals: Vec<_> = split_kv_list(value)
+                    .into_iter()
+                    .map(|item| to_jval(item, type_info.jtype, err))
+                    .collect();
+                match *assure_entry(mapping, &field, Value::Array(Vec::new())) {
+                    Value::Array(ref mut values) => values.extend(jvals),
+                    _ => unreachable!(),
+                }
+            }
+            ComplexType::Map => {
+                let (key, value) = parse_kv_arg(value, err, true);
+                let jval = to_jval(value.unwrap_or(""), type_info.jtype, err);
 
-            last_c = c;
-        }
-
-        push_field(&mut output, &mut field);
-
-        if output == value {
-            None
-        } else {
-            Some(output)
+                match *assure_entry(mapping, &field, Value::Object(Default::default())) {
+                    Value::Object(ref mut value_map) => {
+                        if value_map.insert(key.into_owned(), jval).is_some() {
+                            err.issues.push(CLIError::Field(FieldError::Duplicate(
+                                orig_cursor.to_string(),
+                            )));
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
         }
     }
 
-    pub fn set_json_value(
+    /// Like `set_json_value()`, but sets the field to `value` as raw JSON, like `true`, `3` or
+    /// `{"key": "value"}`, as given with `field:=json`. Arrays extend lists, and objects add
+    /// their entries to maps.
+    pub fn set_json_raw(
         &self,
-        mut object: &mut Value,
+        object: &mut Value,
         value: &str,
         type_info: JsonTypeInfo,
+        append: bool,
         err: &mut InvalidOptionsError,
         orig_cursor: &FieldCursor,
     ) {
+        let jval: Value = match json::from_str(&kv_value(value, err)) {
+            Ok(jval) => jval,
+            Err(json_err) => {
+                err.issues.push(CLIError::Field(FieldError::InvalidJson(
+                    orig_cursor.to_string(),
+                    json_err.to_string(),
+                )));
+                return;
+            }
+        };
+        let (mapping, field) = match self.target(object, type_info, append, err, orig_cursor) {
+            Some(target) => target,
+            None => return,
+        };
+        match (type_info.ctype, jval) {
+            (ComplexType::Pod, jval) => {
+                if mapping.insert(field, jval).is_some() {
+                    err.issues.push(CLIError::Field(FieldError::Duplicate(
+                        orig_cursor.to_string(),
+                    )));
+                }
+            }
+            (ComplexType::Vec, jval) => {
+                match *assure_entry(mapping, &field, Value::Array(Vec::new())) {
+                    Value::Array(ref mut values) => match jval {
+                        Value::Array(items) => values.extend(items),
+                        item => values.push(item),
+                    },
+                    _ => unreachable!(),
+                }
+            }
+            (ComplexType::Map, Value::Object(entries)) => {
+                match *assure_entry(mapping, &field, Value::Object(Default::default())) {
+                    Value::Object(ref mut value_map) => {
+                        for (key, jval) in entries {
+                            if value_map.insert(key, jval).is_some() {
+                                err.issues.push(CLIError::Field(FieldError::Duplicate(
+                                    orig_cursor.to_string(),
+                                )));
+                            }
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            (ComplexType::Map, _) => err.issues.push(CLIError::Field(FieldError::InvalidJson(
+                orig_cursor.to_string(),
+                "the entries of maps are set with an object".to_string(),
+            ))),
+        }
+    }
+
+    // Returns the structure holding the field at this cursor, along with the name of the field,
+    // adding the structures and list items on the way to `object`.
+    fn target<'o>(
+        &self,
+        mut object: &'o mut Value,
+        type_info: JsonTypeInfo,
+        append: bool,
+        err: &mut InvalidOptionsError,
+        orig_cursor: &FieldCursor,
+    ) -> Option<(&'o mut json::Map<String, Value>, String)> {
         assert!(!self.0.is_empty());
 
-        for field in &self.0[..self.0.len() - 1] {
+        for (fid, field) in self.0[..self.0.len() - 1].iter().enumerate() {
             let tmp = object;
-            object = match *tmp {
-                Value::Object(ref mut mapping) => assure_entry(mapping, &field),
+            let mapping = match *tmp {
+                Value::Object(ref mut mapping) => mapping,
                 _ => panic!("We don't expect non-object Values here ..."),
             };
+            // the entries of maps are at the key given on the command line
+            if field == MAP_KEY {
+                let key = &orig_cursor.0[fid];
+                object = assure_entry(mapping, key, Value::Object(Default::default()));
+                continue;
+            }
+            let name = match field.strip_suffix(LIST_INDEX) {
+                Some(name) => name,
+                None => {
+                    object = assure_entry(mapping, field, Value::Object(Default::default()));
+                    continue;
+                }
+            };
+            let items = match *assure_entry(mapping, name, Value::Array(Vec::new())) {
+                Value::Array(ref mut items) => items,
+                _ => unreachable!(),
+            };
+            let index = match orig_cursor.0.get(fid).map(|f| split_index(f)) {
+                Some((_, Some(index))) if !index.is_empty() => {
+                    index.parse().expect("indices to be validated by set()")
+                }
+                _ => items.len().saturating_sub(1),
+            };
+            if index == items.len() {
+                items.push(Value::Object(Default::default()));
+            } else if index > items.len() {
+                err.issues.push(CLIError::Field(FieldError::IndexOutOfRange(
+                    orig_cursor.to_string(),
+                    items.len(),
+                )));
+                return None;
+            }
+            object = &mut items[index];
         }
 
+        if append && !matches!(type_info.ctype, ComplexType::Vec) {
+            err.issues.push(CLIError::Field(FieldError::AppendToNonList(
+                orig_cursor.to_string(),
+            )));
+            return None;
+        }
         match *object {
             Value::Object(ref mut mapping) => {
-                let field = &self.0[self.0.len() - 1];
-                let to_jval =
-                    |value: &str, jtype: JsonType, err: &mut InvalidOptionsError| -> Value {
-                        match jtype {
-                            JsonType::Boolean => {
-                                Value::Bool(arg_from_str(value, err, &field, "boolean"))
-                            }
-                            JsonType::Int => Value::Number(
-                                json::Number::from_f64(arg_from_str(value, err, &field, "int"))
-                                    .expect("valid f64"),
-                            ),
-                            JsonType::Uint => Value::Number(
-                                json::Number::from_f64(arg_from_str(value, err, &field, "uint"))
-                                    .expect("valid f64"),
-                            ),
-                            JsonType::Float => Value::Number(
-                                json::Number::from_f64(arg_from_str(value, err, &field, "float"))
-                                    .expect("valid f64"),
-                            ),
-                            JsonType::String => Value::String(value.to_owned()),
-                        }
-                    };
+                let last = self.0.len() - 1;
+                let field = if self.0[last] == MAP_KEY {
+                    &orig_cursor.0[last]
+                } else {
+                    &self.0[last]
+                };
+                Some((mapping, field.clone()))
+            }
+            _ => unreachable!(),
+        }
+    }
 
-                match type_info.ctype {
-                    ComplexType::Pod => {
-                        if mapping
-                            .insert(field.to_owned(), to_jval(value, type_info.jtype, err))
-                            .is_some()
-                        {
-                            err.issues.push(CLIError::Field(FieldError::Duplicate(
-                                orig_cursor.to_string(),
-                            )));
-                        }
+    pub fn num_fields(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Splits `kv` at the first `=` which isn't escaped like `\=`, into its key, whose escapes are
+/// resolved, and its value, which is left as it is for `kv_value()`.
+pub fn parse_kv_arg<'a>(
+    kv: &'a str,
+    err: &mut InvalidOptionsError,
+    for_hashmap: bool,
+) -> (Cow<'a, str>, Option<&'a str>) {
+    let mut add_err = || {
+        err.issues
+            .push(CLIError::InvalidKeyValueSyntax(kv.to_string(), for_hashmap))
+    };
+    match split_unescaped(kv, '=').next() {
+        Some(key) if key.len() < kv.len() => {
+            let value = &kv[key.len() + 1..];
+            if value.is_empty() {
+                add_err();
+            }
+            (unescape_kv(key), Some(value))
+        }
+        _ => {
+            add_err();
+            (unescape_kv(kv), None)
+        }
+    }
+}
+
+/// Returns the value of a `key=value` argument: the content of the file if it is like `@path`,
+/// or the value with the escapes of `\=`, `\,`, `\@` and `\\` resolved. Other backslashes are
+/// kept. Files which can't be read are added to `err`, and have an empty value.
+pub fn kv_value<'a>(value: &'a str, err: &mut InvalidOptionsError) -> Cow<'a, str> {
+    match value.strip_prefix('@') {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(content) => Cow::Owned(content),
+            Err(io_err) => {
+                err.issues
+                    .push(CLIError::Input(InputError::Io((path.to_string(), io_err))));
+                Cow::Borrowed("")
+            }
+        },
+        None => unescape_kv(value),
+    }
+}
+
+/// Splits the value of a list at the commas which aren't escaped like `\,`.
+pub fn split_kv_list(value: &str) -> Vec<&str> {
+    split_unescaped(value, ',').collect()
+}
+
+const KV_ESCAPED: &[char] = &['=', ',', '@', KV_ESCAPE];
+const KV_ESCAPE: char = '\\';
+
+// Splits `s` at each `sep` which doesn't follow an escape.
+fn split_unescaped(s: &str, sep: char) -> impl Iterator<Item = &str> {
+    let mut escaped = false;
+    s.split(move |c| {
+        let split = c == sep && !escaped;
+        escaped = c == KV_ESCAPE && !escaped;
+        split
+    })
+}
+
+/// Returns `value` with its backslashes and a leading `@` escaped, which `kv_value()` returns as
+/// it is.
+pub fn literal_kv_value(value: &str) -> Cow<'_, str> {
+    if !value.starts_with('@') && !value.contains(KV_ESCAPE) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 1);
+    if value.starts_with('@') {
+        escaped.push(KV_ESCAPE);
+    }
+    for c in value.chars() {
+        if c == KV_ESCAPE {
+            escaped.push(KV_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+fn unescape_kv(s: &str) -> Cow<'_, str> {
+    if !s.contains(KV_ESCAPE) {
+        return Cow::Borrowed(s);
+    }
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == KV_ESCAPE && KV_ESCAPED.contains(&next) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+pub fn calltype_from_str(
+    name: &str,
+    valid_protocols: Vec<String>,
+    err: &mut InvalidOptionsError,
+) -> CallType {
+    CallType::Upload(match UploadProtocol::from_str(name) {
+        Ok(up) => up,
+        Err(msg) => {
+            err.issues.push(CLIError::InvalidUploadProtocol(
+                name.to_string(),
+                valid_protocols,
+            ));
+            UploadProtocol::Simple
+        }
+    })
+}
+
+pub fn input_file_from_opts(file_path: &str, err: &mut InvalidOptionsError) -> Option<fs::File> {
+    match fs::File::open(file_path) {
+        Ok(f) => Some(f),
+        Err(io_err) => {
+            err.issues.push(CLIError::Input(InputError::Io((
+                file_path.to_string(),
+                io_err,
+            ))));
+            None
+        }
+    }
+}
+
+/// Replaces each `${NAME}` in `s` with the value of the environment variable `NAME`, as done with
+/// `--expand-env`. `$${` stands for a literal `${`, and a `${` without closing brace is kept as is.
+/// Variables which aren't set expand to nothing, and are added to `err`.
+pub fn expand_env<'a>(s: &'a str, err: &mut InvalidOptionsError) -> Cow<'a, str> {
+    if !s.contains("${") {
+        return Cow::Borrowed(s);
+    }
+    let mut expanded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+        let name_and_rest = &rest[start + 2..];
+        match name_and_rest.find('}') {
+            Some(end) => {
+                let name = &name_and_rest[..end];
+                match env::var(name) {
+                    Ok(value) => expanded.push_str(&value),
+                    Err(_) => err
+                        .issues
+                        .push(CLIError::Input(InputError::UnsetVariable(name.to_string()))),
+                }
+                rest = &name_and_rest[end + 1..];
+            }
+            None => {
+                expanded.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Cow::Owned(expanded)
+}
+
+/// Reads the request structure from the JSON file at `file_path`, which must be an object. Returns
+/// an empty object if it can't be read, and adds the reason to `err`. With `expand`, environment
+/// variables in the content are replaced with `expand_env()` before it is parsed.
+pub fn request_from_file(file_path: &str, expand: bool, err: &mut InvalidOptionsError) -> Value {
+    let value = fs::read_to_string(file_path)
+        .map_err(|io_err| InputError::Io((file_path.to_string(), io_err)))
+        .and_then(|content| {
+            let content = if expand {
+                expand_env(&content, err)
+            } else {
+                Cow::Borrowed(content.as_str())
+            };
+            json::from_str::<Value>(&content).map_err(|json_err| {
+                InputError::Request((file_path.to_string(), json_err.to_string()))
+            })
+        });
+    match value {
+        Ok(value @ Value::Object(_)) => value,
+        Ok(_) => {
+            err.issues.push(CLIError::Input(InputError::Request((
+                file_path.to_string(),
+                "it is no JSON object".to_string(),
+            ))));
+            Value::Object(Default::default())
+        }
+        Err(input_err) => {
+            err.issues.push(CLIError::Input(input_err));
+            Value::Object(Default::default())
+        }
+    }
+}
+
+/// Sets the fields of `overlay` in `value`, merging the objects they both have a field with, like
+/// the fields set with `-r` into the request structure read from a file.
+pub fn merge_json(value: &mut Value, overlay: Value) {
+    match (value, overlay) {
+        (Value::Object(ref mut fields), Value::Object(overlay_fields)) => {
+            for (name, overlay_value) in overlay_fields {
+                match fields.get_mut(&name) {
+                    Some(field) if field.is_object() && overlay_value.is_object() => {
+                        merge_json(field, overlay_value)
                     }
-                    ComplexType::Vec => match *assure_entry(mapping, field) {
-                        Value::Array(ref mut values) => {
-                            values.push(to_jval(value, type_info.jtype, err))
-                        }
-                        _ => unreachable!(),
-                    },
-                    ComplexType::Map => {
-                        let (key, value) = parse_kv_arg(value, err, true);
-                        let jval = to_jval(value.unwrap_or(""), type_info.jtype, err);
-
-                        match *assure_entry(mapping, &field) {
-                            Value::Object(ref mut value_map) => {
-                                if value_map.insert(key.to_owned(), jval).is_some() {
-                                    err.issues.push(CLIError::Field(FieldError::Duplicate(
-                                        orig_cursor.to_string(),
-                                    )));
-                                }
-                            }
-                            _ => unreachable!(),
-                        }
+                    _ => {
+                        fields.insert(name, overlay_value);
                     }
                 }
             }
-            _ => unreachable!(),
         }
+        (value, overlay) => *value = overlay,
     }
+}
 
-    pub fn num_fields(&self) -> usize {
-        self.0.len()
+/// Returns the MIME type of the file to upload, as given by `mime`, or as detected by `sniff` from
+/// the path of the file and the bytes it starts with if unset.
+pub fn input_mime_from_opts<F>(
+    mime: Option<&str>,
+    file_path: &str,
+    file: Option<&mut fs::File>,
+    sniff: F,
+    err: &mut InvalidOptionsError,
+) -> Option<Mime>
+where
+    F: FnOnce(&Path, &mut fs::File) -> io::Result<Mime>,
+{
+    match mime {
+        Some(mime) => match mime.parse() {
+            Ok(m) => Some(m),
+            Err(_) => {
+                err.issues
+                    .push(CLIError::Input(InputError::Mime(mime.to_string())));
+                None
+            }
+        },
+        None => match sniff(Path::new(file_path), file?) {
+            Ok(m) => Some(m),
+            Err(io_err) => {
+                err.issues.push(CLIError::Input(InputError::Io((
+                    file_path.to_string(),
+                    io_err,
+                ))));
+                None
+            }
+        },
     }
 }
 
-pub fn parse_kv_arg<'a>(
-    kv: &'a str,
-    err: &mut InvalidOptionsError,
-    for_hashmap: bool,
-) -> (&'a str, Option<&'a str>) {
-    let mut add_err = || {
-        err.issues
-            .push(CLIError::InvalidKeyValueSyntax(kv.to_string(), for_hashmap))
+pub fn writer_from_opts(arg: Option<&str>) -> Result<Box<dyn Write>, io::Error> {
+    let f = arg.unwrap_or("-");
+    match f {
+        "-" => Ok(Box::new(stdout())),
+        _ => match fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(f)
+        {
+            Ok(f) => Ok(Box::new(f)),
+            Err(io_err) => Err(io_err),
+        },
+    }
+}
+
+/// Like `writer_from_opts()`, but appends to the given file instead of truncating it, and passes
+/// on everything written in between two flushes in one piece. This allows multiple concurrent
+/// calls to share the same output without interleaving their results.
+pub fn appending_writer_from_opts(arg: Option<&str>) -> Result<Box<dyn Write>, io::Error> {
+    let f = arg.unwrap_or("-");
+    let inner: Box<dyn Write> = match f {
+        "-" => Box::new(stdout()),
+        _ => Box::new(fs::OpenOptions::new().create(true).append(true).open(f)?),
+    };
+    Ok(Box::new(ChunkWriter {
+        inner,
+        buf: Vec::new(),
+    }))
+}
+
+/// Buffers everything written to it, and writes it to `inner` with a single call on flush.
+struct ChunkWriter {
+    inner: Box<dyn Write>,
+    buf: Vec<u8>,
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.buf)?;
+        self.buf.clear();
+        self.inner.flush()
+    }
+}
+
+impl Drop for ChunkWriter {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+/// The amount of calls made at the same time when running a call for each project listed in a file.
+pub const PROJECT_FAN_OUT_CONCURRENCY: usize = 8;
+
+/// Read the project ids to run a call for from the file at `file_path`, one per line.
+/// Empty lines and lines starting with `#` are ignored.
+pub fn projects_from_file(file_path: &str, err: &mut InvalidOptionsError) -> Vec<String> {
+    match fs::read_to_string(file_path) {
+        Ok(content) => content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(ToString::to_string)
+            .collect(),
+        Err(io_err) => {
+            err.issues.push(CLIError::Input(InputError::Io((
+                file_path.to_string(),
+                io_err,
+            ))));
+            Vec::new()
+        }
+    }
+}
+
+/// Run `call` for each of the given `projects`, with at most `concurrency` calls in flight at a time.
+///
+/// A failing call does not stop the others. Returns the id of each project whose call failed,
+/// along with its error.
+pub async fn for_each_project<'a, F, Fut, E>(
+    projects: &'a [String],
+    concurrency: usize,
+    call: F,
+) -> Vec<(String, E)>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    stream::iter(projects.iter().map(|project| {
+        let res = call(project);
+        async move { (project, res.await) }
+    }))
+    .buffer_unordered(concurrency.max(1))
+    .filter_map(|(project, res)| async move { res.err().map(|e| (project.clone(), e)) })
+    .collect()
+    .await
+}
+
+/// Controls how the results of a call are written to the output stream.
+#[derive(Default)]
+pub struct OutputOptions {
+    /// Write each item of a list response as a single line of JSON, instead of pretty-printing
+    /// the response as a whole.
+    pub stream: bool,
+    /// Keep fetching the pages of list responses until there is no `nextPageToken` anymore.
+    pub all_pages: bool,
+    /// The page size to start fetching all pages with, which is then adapted to how fast the server
+    /// delivers them.
+    pub adaptive_page_size: Option<u32>,
+    /// The order of the items of list responses, which are written as received if `None`.
+    pub sort_by: Option<SortKey>,
+    /// The maximum amount of items of list responses to write.
+    pub limit: Option<usize>,
+    /// Write only the amount of items of list responses, across all pages, instead of the items.
+    pub count: bool,
+    /// Fail if a list response, across all pages, contains no items.
+    pub fail_on_empty: bool,
+    /// Write JSON in its canonical form, as produced by `canonicalize_json()`.
+    pub canonical_json: bool,
+    /// Write a summary of the fetched items of list responses to stderr, see `items_summary()`.
+    pub verbose: bool,
+    /// The shell command each response, or each item when streaming, is piped to, whose output
+    /// is written in place of the JSON.
+    pub exec: Option<String>,
+    /// The format results are written in.
+    pub format: OutputFormat,
+    /// The columns and width of tables, if results are written as such.
+    pub table: TableOptions,
+    /// The columns of CSV, unless `table.columns` are given, for items with a standard layout.
+    pub csv_layout: Option<CsvLayout>,
+    /// Poll list responses again after the given interval, until interrupted, and write only the
+    /// items which are new or changed since the previous poll, see `ItemWatcher`.
+    pub watch: Option<Duration>,
+    /// The template results are rendered with, in place of writing them in `format`.
+    pub template: Option<OutputTemplate>,
+}
+
+impl OutputOptions {
+    pub fn from_opts(opt: &ArgMatches) -> Result<OutputOptions, CLIError> {
+        let watch = if opt.is_present("watch") {
+            let interval: Option<WatchInterval> =
+                parsed_opt(opt, "poll-interval", "--interval", "duration")?;
+            Some(interval.map_or(DEFAULT_WATCH_INTERVAL, |i| i.0))
+        } else {
+            None
+        };
+        let opts = OutputOptions {
+            // watched items are written as they are polled, from all pages
+            stream: opt.is_present("stream") || watch.is_some(),
+            all_pages: opt.is_present("all-pages") || watch.is_some(),
+            adaptive_page_size: parsed_opt(opt, "page-size", "--adaptive-page-size", "u32")?,
+            sort_by: parsed_opt(opt, "sort-field", "--sort-by", "field[:desc]")?,
+            limit: parsed_opt(opt, "max-items", "--limit", "usize")?,
+            count: opt.is_present("count"),
+            fail_on_empty: opt.is_present("fail-on-empty"),
+            canonical_json: opt.is_present("canonical-json"),
+            verbose: opt.is_present("verbose"),
+            exec: opt.value_of("command-line").map(|c| c.to_string()),
+            format: parsed_opt(opt, "output-format", "--format", "json|table|csv")?
+                .unwrap_or_default(),
+            table: TableOptions {
+                columns: match opt.value_of("column-paths") {
+                    Some(columns) => Some(parse_columns(columns).map_err(|perr| {
+                        CLIError::ParseError(
+                            "--columns".to_string(),
+                            "field[,field]...".to_string(),
+                            columns.to_string(),
+                            perr,
+                        )
+                    })?),
+                    None => None,
+                },
+                max_width: if opt.is_present("no-truncate") {
+                    None
+                } else {
+                    Some(
+                        parsed_opt(opt, "width", "--max-width", "usize")?
+                            .unwrap_or(DEFAULT_MAX_COLUMN_WIDTH),
+                    )
+                },
+            },
+            csv_layout: None,
+            watch,
+            template: match opt.value_of("template-path") {
+                Some(path) => Some(OutputTemplate::from_file(path)?),
+                None => None,
+            },
+        };
+        if opts.template.is_some() {
+            let conflicts = [
+                (opts.watch.is_some(), "--watch"),
+                (opt.is_present("stream"), "--stream"),
+                (opts.count, "--count"),
+                (opts.format != OutputFormat::Json, "--format"),
+            ];
+            if let Some(&(_, flag)) = conflicts.iter().find(|c| c.0) {
+                return Err(CLIError::ConflictingFlags(
+                    "--output-template".to_string(),
+                    flag.to_string(),
+                ));
+            }
+        }
+        if opts.watch.is_some() {
+            let conflicts = [
+                (opts.sort_by.is_some(), "--sort-by"),
+                (opts.limit.is_some(), "--limit"),
+                (opts.count, "--count"),
+                (opts.fail_on_empty, "--fail-on-empty"),
+                (opts.format == OutputFormat::Table, "--format table"),
+                (opts.format == OutputFormat::Csv, "--format csv"),
+            ];
+            if let Some(&(_, flag)) = conflicts.iter().find(|c| c.0) {
+                return Err(CLIError::ConflictingFlags(
+                    "--watch".to_string(),
+                    flag.to_string(),
+                ));
+            }
+        }
+        Ok(opts)
+    }
+
+    /// Returns true if the items of list responses are sorted or limited, or written as a table or
+    /// CSV, or rendered with a template, which requires collecting the items of all pages before
+    /// writing them.
+    pub fn collects_items(&self) -> bool {
+        self.sort_by.is_some()
+            || self.limit.is_some()
+            || self.format != OutputFormat::Json
+            || self.template.is_some()
+    }
+
+    /// Sort the `items` of a list response and drop the ones beyond the limit, as configured.
+    pub fn post_process_items(&self, items: &mut Vec<Value>) {
+        if let Some(ref key) = self.sort_by {
+            key.sort(items);
+        }
+        if let Some(limit) = self.limit {
+            items.truncate(limit);
+        }
+    }
+}
+
+// Returns the value of the argument `name`, parsed, or None if it isn't set.
+fn parsed_opt<T>(
+    opt: &ArgMatches,
+    name: &str,
+    flag: &str,
+    type_name: &str,
+) -> Result<Option<T>, CLIError>
+where
+    T: FromStr,
+    <T as FromStr>::Err: fmt::Display,
+{
+    match opt.value_of(name) {
+        Some(value) => value.parse().map(Some).map_err(|perr| {
+            CLIError::ParseError(
+                flag.to_string(),
+                type_name.to_string(),
+                value.to_string(),
+                format!("{}", perr),
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+/// The interval list responses are polled with by `--watch`, unless `--interval` sets another one.
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The time between the polls of `--watch`, like `30s`, `5m`, `1h` or `500ms`. Plain numbers are
+/// seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchInterval(pub Duration);
+
+impl FromStr for WatchInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<WatchInterval, String> {
+        let unit_pos = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (amount, unit) = s.split_at(unit_pos);
+        let amount: u64 = amount
+            .parse()
+            .map_err(|_| format!("'{}' doesn't start with an amount", s))?;
+        let interval = match unit {
+            "ms" => Duration::from_millis(amount),
+            "" | "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount * 60),
+            "h" => Duration::from_secs(amount * 60 * 60),
+            _ => {
+                return Err(format!(
+                    "unknown unit '{}', expected 'ms', 's', 'm' or 'h'",
+                    unit
+                ))
+            }
+        };
+        if interval.is_zero() {
+            return Err("the interval must not be zero".to_string());
+        }
+        Ok(WatchInterval(interval))
+    }
+}
+
+/// The format results are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON, or a single line of it per item when streaming.
+    #[default]
+    Json,
+    /// A table with a row per item of list responses, or a single row for other responses, whose
+    /// columns are aligned for humans to read.
+    Table,
+    /// Comma-separated values with a row per item of list responses, or a single row for other
+    /// responses, for spreadsheets to import.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "unknown format '{}', expected 'json', 'table' or 'csv'",
+                s
+            )),
+        }
+    }
+}
+
+/// A template file results are rendered with, like a Markdown or HTML report, given with
+/// `--output-template`. It uses the syntax of [TinyTemplate](https://docs.rs/tinytemplate), which
+/// inserts fields like `{response.name}`, and has conditions like `{{ if response.done }}`...
+/// `{{ endif }}` and loops like `{{ for item in response.items }}{item.name}{{ endfor }}`. If the
+/// call was made for one of many projects, its id is `{project}`.
+///
+/// Values are HTML-escaped if the file has the `.html` or `.htm` extension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    path: String,
+    source: String,
+    escape_html: bool,
+}
+
+impl OutputTemplate {
+    /// Read the template at `path`, which fails if it can't be parsed.
+    pub fn from_file(path: &str) -> Result<OutputTemplate, CLIError> {
+        let source = fs::read_to_string(path).map_err(|io_err| {
+            CLIError::Configuration(ConfigurationError::Io((path.to_string(), io_err)))
+        })?;
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        tinytemplate::TinyTemplate::new()
+            .add_template("output", &source)
+            .map_err(|err| {
+                CLIError::Configuration(ConfigurationError::Template((
+                    path.to_string(),
+                    err.to_string(),
+                )))
+            })?;
+        Ok(OutputTemplate {
+            path: path.to_string(),
+            source,
+            escape_html: matches!(extension.as_deref(), Some("html") | Some("htm")),
+        })
+    }
+
+    /// Render the template with `context`, which fails if it refers to fields `context` doesn't
+    /// have.
+    pub fn render(&self, context: &Value) -> Result<String, io::Error> {
+        let mut tt = tinytemplate::TinyTemplate::new();
+        if !self.escape_html {
+            tt.set_default_formatter(&tinytemplate::format_unescaped);
+        }
+        tt.add_template("output", &self.source)
+            .and_then(|_| tt.render("output", context))
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Template '{}' failed to render: {}", self.path, err),
+                )
+            })
+    }
+}
+
+/// The width cells of tables are truncated to by default.
+pub const DEFAULT_MAX_COLUMN_WIDTH: usize = 40;
+
+/// Controls the columns of tables, and the width of their cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableOptions {
+    /// The columns, as paths into the JSON of each row, like `metadata.name`. If `None`, each
+    /// field is a column, with the fields of nested objects flattened into paths like these.
+    pub columns: Option<Vec<String>>,
+    /// The amount of characters cells are truncated to, which ends them with '…'. If `None`, they
+    /// are never truncated.
+    pub max_width: Option<usize>,
+}
+
+impl Default for TableOptions {
+    fn default() -> TableOptions {
+        TableOptions {
+            columns: None,
+            max_width: Some(DEFAULT_MAX_COLUMN_WIDTH),
+        }
+    }
+}
+
+/// Returns the cell of CSV holding a value, given the field it is taken from.
+pub type CsvCell = fn(&str, Option<&Value>) -> String;
+
+/// The columns of CSV for items with a standard layout, like the EMA spreadsheets of Avails.
+#[derive(Debug, Clone, Copy)]
+pub struct CsvLayout {
+    /// The header of each column, and the field of the items it is taken from.
+    pub columns: &'static [(&'static str, &'static str)],
+    /// Returns the cell of a column, given its field and the value of the field.
+    pub cell: CsvCell,
+}
+
+// Parses a comma-separated list of paths, like `name,metadata.createTime`.
+fn parse_columns(s: &str) -> Result<Vec<String>, String> {
+    let columns: Vec<String> = s.split(',').map(|c| c.trim().to_string()).collect();
+    if columns
+        .iter()
+        .any(|c| c.split(FIELD_SEP).any(str::is_empty))
+    {
+        return Err("the fields must not be empty".to_string());
+    }
+    Ok(columns)
+}
+
+/// The field the items of list responses are sorted by, given like `field[:desc]`.
+///
+/// The field is a path into the JSON of each item, like `vulnerability.cvssScore`. Numbers are
+/// compared numerically, even if the API represents them as strings, and items lacking the field
+/// always come last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: Vec<String>,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SortKey, String> {
+        let (field, descending) = match s.rsplit_once(':') {
+            Some((field, "desc")) => (field, true),
+            Some((field, "asc")) => (field, false),
+            Some((_, order)) => {
+                return Err(format!(
+                    "unknown order '{}', expected 'asc' or 'desc'",
+                    order
+                ))
+            }
+            None => (s, false),
+        };
+        let field: Vec<String> = field.split(FIELD_SEP).map(|f| f.to_string()).collect();
+        if field.iter().any(|f| f.is_empty()) {
+            return Err("the field must not be empty".to_string());
+        }
+        Ok(SortKey { field, descending })
+    }
+}
+
+impl SortKey {
+    /// Sort `items` by this key. The sort is stable, which keeps the order of equal items.
+    pub fn sort(&self, items: &mut [Value]) {
+        items.sort_by(|a, b| match (self.value_of(a), self.value_of(b)) {
+            (Some(a), Some(b)) if self.descending => compare_json_values(b, a),
+            (Some(a), Some(b)) => compare_json_values(a, b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+
+    fn value_of<'a>(&self, item: &'a Value) -> Option<&'a Value> {
+        self.field
+            .iter()
+            .try_fold(item, |value, name| value.get(name))
+            .filter(|v| !v.is_null())
+    }
+}
+
+// Orders numbers, and strings holding them, numerically, other strings lexicographically, and
+// values of different types by their type.
+fn compare_json_values(a: &Value, b: &Value) -> std::cmp::Ordering {
+    fn number(v: &Value) -> Option<f64> {
+        match v {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+    fn type_rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+    if let (Some(a), Some(b)) = (number(a), number(b)) {
+        return a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal);
+    }
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Collects the pages of a list response whose items are sorted or limited, to write them as a
+/// single response once the last page was received.
+#[derive(Default)]
+pub struct CollectedPages {
+    response: Option<Value>,
+    items: Vec<Value>,
+}
+
+impl CollectedPages {
+    /// Add the page `value`, whose items are stored in `items_field`. Returns true if no more
+    /// pages are needed, as the limit was reached and the items aren't sorted.
+    pub fn add(&mut self, mut value: Value, items_field: &str, opts: &OutputOptions) -> bool {
+        if let Some(Value::Array(items)) = value.get_mut(items_field) {
+            self.items.append(items);
+        }
+        self.response = Some(value);
+        opts.sort_by.is_none() && opts.limit.is_some_and(|limit| self.items.len() >= limit)
+    }
+
+    /// Returns the last page, holding the items of all pages, or None if no page was added.
+    pub fn into_response(self, items_field: &str) -> Option<Value> {
+        let items = self.items;
+        self.response.map(|mut value| {
+            if let Value::Object(ref mut fields) = value {
+                fields.insert(items_field.to_string(), Value::Array(items));
+            }
+            value
+        })
+    }
+}
+
+/// Remembers the items of list responses polled by `--watch`, by their `name` and `updateTime`,
+/// to write only the ones which are new or changed since the previous poll. Items without a name
+/// are identified by their JSON, and the ones without an update time are versioned by it.
+#[derive(Debug, Default)]
+pub struct ItemWatcher {
+    versions: HashMap<String, String>,
+}
+
+impl ItemWatcher {
+    /// Remove the items of the page `value`, which are stored in `items_field`, which were polled
+    /// before and didn't change since.
+    pub fn retain_changed(&mut self, value: &mut Value, items_field: &str) {
+        if let Some(Value::Array(items)) = value.get_mut(items_field) {
+            items.retain(|item| self.is_changed(item));
+        }
+    }
+
+    fn is_changed(&mut self, item: &Value) -> bool {
+        let json = item.to_string();
+        let key = item["name"]
+            .as_str()
+            .map_or_else(|| json.clone(), str::to_string);
+        let version = item["updateTime"].as_str().map_or(json, str::to_string);
+        self.versions.insert(key, version.clone()) != Some(version)
+    }
+}
+
+/// Write the JSON `value` of a response to `ostream`, with all null values removed.
+///
+/// `items_field` is the name of the field holding the items of a list response, if the response
+/// is one. These are sorted and limited as configured. If streaming is enabled, each of them is
+/// written as a single line of JSON ([NDJSON](http://ndjson.org)), which allows processing them as
+/// soon as they arrive, page by page.
+///
+/// If the call was made for one of many projects, `project` is its id, and each written value is
+/// wrapped into an object like `{"project": <id>, "response": <value>}`, or `"item"` for streamed items.
+///
+/// If canonical JSON is enabled, each written value is canonicalized, which makes the output of
+/// repeated calls comparable.
+///
+/// If a template is given, the value is rendered with it instead, as `response`, along with the
+/// `project`.
+pub fn write_json_response(
+    ostream: &mut dyn Write,
+    mut value: Value,
+    items_field: Option<&str>,
+    project: Option<&str>,
+    opts: &OutputOptions,
+) -> Result<(), io::Error> {
+    remove_json_null_values(&mut value);
+    if let Some(Value::Array(items)) = items_field.and_then(|field| value.get_mut(field)) {
+        opts.post_process_items(items);
+    }
+    if let Some(ref template) = opts.template {
+        // lists without items lack the field, which templates would fail to loop over
+        if let (Some(field), Value::Object(fields)) = (items_field, &mut value) {
+            fields
+                .entry(field)
+                .or_insert_with(|| Value::Array(Vec::new()));
+        }
+        let context = match project {
+            Some(id) => json::json!({ "project": id, "response": value }),
+            None => json::json!({ "response": value }),
+        };
+        write_result(ostream, template.render(&context)?.as_bytes(), opts)?;
+        return ostream.flush();
+    }
+    if opts.format != OutputFormat::Json {
+        let rows = match items_field {
+            Some(field) => match value.get_mut(field) {
+                Some(Value::Array(items)) => std::mem::take(items),
+                _ => Vec::new(),
+            },
+            None => vec![value],
+        };
+        let mut table = Vec::new();
+        match opts.format {
+            OutputFormat::Csv => write_csv(&mut table, &rows, project, opts)?,
+            _ => write_table(&mut table, &rows, project, &opts.table)?,
+        }
+        write_result(ostream, &table, opts)?;
+        return ostream.flush();
+    }
+    let tagged = |key: &str, value: Value| {
+        let mut value = match project {
+            Some(id) => json::json!({ "project": id, key: value }),
+            None => value,
+        };
+        if opts.canonical_json {
+            canonicalize_json(&mut value);
+        }
+        value
+    };
+    match (opts.stream, items_field) {
+        (true, Some(field)) => {
+            if let Some(Value::Array(items)) = value.get_mut(field) {
+                for item in items.drain(..) {
+                    let mut line = json::to_vec(&tagged("item", item))?;
+                    line.push(b'\n');
+                    write_result(ostream, &line, opts)?;
+                }
+            }
+        }
+        _ => {
+            // pages and projects are written one document each, which need to be told apart
+            let mut pretty = json::to_vec_pretty(&tagged("response", value))?;
+            pretty.push(b'\n');
+            write_result(ostream, &pretty, opts)?;
+        }
+    }
+    ostream.flush()
+}
+
+/// Write `rows` as a table to `ostream`, with a header naming the columns, like `METADATA.NAME`,
+/// and all cells of a column padded to the same width. Strings are written as they are, while
+/// lists and objects are written as JSON.
+///
+/// If the call was made for one of many projects, `project` is its id, which is written in a
+/// leading `PROJECT` column.
+pub fn write_table(
+    ostream: &mut dyn Write,
+    rows: &[Value],
+    project: Option<&str>,
+    opts: &TableOptions,
+) -> Result<(), io::Error> {
+    let columns = match opts.columns {
+        Some(ref columns) => columns.clone(),
+        None => {
+            let mut columns = Vec::new();
+            let mut seen = std::collections::HashSet::new();
+            for row in rows {
+                flattened_paths(row, "", &mut columns, &mut seen);
+            }
+            columns
+        }
+    };
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let mut header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    if project.is_some() {
+        header.insert(0, "PROJECT".to_string());
+    }
+    let mut cells = vec![header];
+    for row in rows {
+        let mut row_cells: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let value = column
+                    .split(FIELD_SEP)
+                    .try_fold(row, |value, name| value.get(name));
+                truncated_cell(value, opts.max_width)
+            })
+            .collect();
+        if let Some(id) = project {
+            row_cells.insert(0, id.to_string());
+        }
+        cells.push(row_cells);
+    }
+
+    let mut widths = vec![0; cells[0].len()];
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &cells {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(ostream, "{}", line.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+/// Write `rows` as CSV to `ostream`, with a header naming the columns, like `metadata.name`, and
+/// rows ending with CRLF, as RFC 4180 has it. Strings are written as they are, while lists and
+/// objects are written as JSON, and cells are quoted as needed.
+///
+/// The columns are the ones of `opts.table`, or else the ones of `opts.csv_layout`, or else each
+/// field is a column, as in tables. If the call was made for one of many projects, `project` is
+/// its id, which is written in a leading `project` column.
+pub fn write_csv(
+    ostream: &mut dyn Write,
+    rows: &[Value],
+    project: Option<&str>,
+    opts: &OutputOptions,
+) -> Result<(), io::Error> {
+    // the header and the field of each column
+    let (columns, cell): (Vec<(String, String)>, CsvCell) =
+        match (opts.table.columns.as_ref(), opts.csv_layout) {
+            (Some(columns), _) => (
+                columns.iter().map(|c| (c.clone(), c.clone())).collect(),
+                csv_cell,
+            ),
+            (None, Some(layout)) => (
+                layout
+                    .columns
+                    .iter()
+                    .map(|&(header, field)| (header.to_string(), field.to_string()))
+                    .collect(),
+                layout.cell,
+            ),
+            (None, None) => {
+                let mut paths = Vec::new();
+                let mut seen = std::collections::HashSet::new();
+                for row in rows {
+                    flattened_paths(row, "", &mut paths, &mut seen);
+                }
+                (
+                    paths.into_iter().map(|p| (p.clone(), p)).collect(),
+                    csv_cell,
+                )
+            }
+        };
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let headers = columns.iter().map(|c| c.0.as_str());
+    write_csv_record(
+        ostream,
+        project.map(|_| "project").into_iter().chain(headers),
+    )?;
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|(_, field)| {
+                let value = field
+                    .split(FIELD_SEP)
+                    .try_fold(row, |value, name| value.get(name));
+                cell(field, value)
+            })
+            .collect();
+        write_csv_record(
+            ostream,
+            project.into_iter().chain(cells.iter().map(String::as_str)),
+        )?;
+    }
+    Ok(())
+}
+
+// Returns the cell of CSV holding `value`, which is empty if there is none.
+fn csv_cell(_field: &str, value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+// Writes `cells` as a line of CSV, quoting the ones with separators, quotes or line breaks.
+fn write_csv_record<'a>(
+    ostream: &mut dyn Write,
+    cells: impl Iterator<Item = &'a str>,
+) -> Result<(), io::Error> {
+    let cells: Vec<Cow<str>> = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\r', '\n']) {
+                Cow::Owned(format!("\"{}\"", cell.replace('"', "\"\"")))
+            } else {
+                Cow::Borrowed(cell)
+            }
+        })
+        .collect();
+    write!(ostream, "{}\r\n", cells.join(","))
+}
+
+// Adds the paths of the fields of `value` which aren't objects to `paths`, unless they were `seen`.
+fn flattened_paths(
+    value: &Value,
+    prefix: &str,
+    paths: &mut Vec<String>,
+    seen: &mut std::collections::HashSet<String>,
+) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() || prefix.is_empty() => {
+            for (name, value) in fields {
+                let path = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}{}{}", prefix, FIELD_SEP, name)
+                };
+                flattened_paths(value, &path, paths, seen);
+            }
+        }
+        _ if prefix.is_empty() => {}
+        _ => {
+            if seen.insert(prefix.to_string()) {
+                paths.push(prefix.to_string());
+            }
+        }
+    }
+}
+
+// Returns the text of a cell holding `value`, on a single line, and no longer than `max_width`.
+fn truncated_cell(value: Option<&Value>, max_width: Option<usize>) -> String {
+    let cell = match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    };
+    let cell = cell.replace(['\r', '\n'], " ");
+    match max_width {
+        Some(width) if cell.chars().count() > width => cell
+            .chars()
+            .take(width.saturating_sub(1))
+            .chain(std::iter::once('…'))
+            .collect(),
+        _ => cell,
+    }
+}
+
+// Writes a single result to `ostream`, or what the command of `opts.exec` outputs for it.
+fn write_result(
+    ostream: &mut dyn Write,
+    result: &[u8],
+    opts: &OutputOptions,
+) -> Result<(), io::Error> {
+    match opts.exec {
+        Some(ref command) => ostream.write_all(&exec_command(command, result)?),
+        None => ostream.write_all(result),
+    }
+}
+
+/// Run `command` in the shell of the platform, with `input` as its standard input, and return
+/// what it wrote to its standard output. Its standard error is the one of this program.
+///
+/// Fails if the command can't be started, or exits with a non-zero status.
+pub fn exec_command(command: &str, input: &[u8]) -> Result<Vec<u8>, io::Error> {
+    use std::process::{Command, Stdio};
+
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // The input is written while the output is read, as either may exceed the buffer of a pipe
+    let output = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || match stdin.write_all(input) {
+            // the command doesn't need to read all of its input
+            Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            res => res,
+        });
+        let output = child.wait_with_output();
+        writer.join().expect("writing the input doesn't panic")?;
+        output
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "the command '{}' failed with {}",
+            command, output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Write `count`, the amount of received items of a list response, to `ostream`, as a single line.
+/// The amount is capped by the configured limit.
+///
+/// If the call was made for one of many projects, `project` is its id, and the amount is wrapped
+/// into an object like `{"project": <id>, "count": <count>}`.
+pub fn write_item_count(
+    ostream: &mut dyn Write,
+    count: u64,
+    project: Option<&str>,
+    opts: &OutputOptions,
+) -> Result<(), io::Error> {
+    let count = opts.limit.map_or(count, |limit| count.min(limit as u64));
+    match project {
+        Some(id) => {
+            let mut value = json::json!({ "project": id, "count": count });
+            if opts.canonical_json {
+                canonicalize_json(&mut value);
+            }
+            json::to_writer(&mut *ostream, &value)?
+        }
+        None => write!(ostream, "{}", count)?,
+    }
+    ostream.write_all(b"\n")?;
+    ostream.flush()
+}
+
+/// An event of the progress of a long operation, written as a single line of JSON to the
+/// progress channel, which allows wrappers to display the progress without parsing the output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// The `page`th page of a list call was fetched, with `items` items, taking `elapsed`.
+    PageFetched {
+        page: u64,
+        items: usize,
+        elapsed: Duration,
+    },
+    /// `count` items were processed so far.
+    ItemsProcessed { count: u64 },
+    /// A request is sent again after `delay`, for the given `reason`.
+    Retry { reason: String, delay: Duration },
+    /// `uploaded` of `total` bytes of a resumable upload were sent.
+    UploadProgress { uploaded: u64, total: u64 },
+}
+
+impl ProgressEvent {
+    pub fn to_json(&self) -> Value {
+        match *self {
+            ProgressEvent::PageFetched {
+                page,
+                items,
+                elapsed,
+            } => json::json!({
+                "event": "page_fetched",
+                "page": page,
+                "items": items,
+                "elapsed_ms": elapsed.as_millis() as u64,
+            }),
+            ProgressEvent::ItemsProcessed { count } => json::json!({
+                "event": "items_processed",
+                "count": count,
+            }),
+            ProgressEvent::Retry { ref reason, delay } => json::json!({
+                "event": "retry",
+                "reason": reason,
+                "delay_ms": delay.as_millis() as u64,
+            }),
+            ProgressEvent::UploadProgress { uploaded, total } => json::json!({
+                "event": "upload_progress",
+                "uploaded_bytes": uploaded,
+                "total_bytes": total,
+                "percent": (uploaded * 100).checked_div(total).unwrap_or(100),
+            }),
+        }
+    }
+}
+
+/// Writes `ProgressEvent`s as newline-delimited JSON to the progress channel, if there is one.
+/// It is shared by concurrent calls, whose events never interleave.
+#[derive(Default)]
+pub struct ProgressReporter {
+    out: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl ProgressReporter {
+    /// Returns a reporter writing to the file at `arg`, or to stderr if it is `-`, as stdout
+    /// carries the output. Without `arg`, events are dropped.
+    pub fn from_opts(arg: Option<&str>) -> Result<ProgressReporter, io::Error> {
+        let out: Box<dyn Write + Send> = match arg {
+            None => return Ok(ProgressReporter::default()),
+            Some("-") => Box::new(io::stderr()),
+            Some(path) => Box::new(
+                fs::OpenOptions::new()
+                    .create(true)
+                    .truncate(true)
+                    .write(true)
+                    .open(path)?,
+            ),
+        };
+        Ok(ProgressReporter {
+            out: Some(Mutex::new(out)),
+        })
+    }
+
+    pub fn emit(&self, event: &ProgressEvent) {
+        if let Some(ref out) = self.out {
+            let mut out = out.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            writeln!(out, "{}", event.to_json()).ok();
+            out.flush().ok();
+        }
+    }
+}
+
+/// A progress bar of an upload or download on stderr, with the transferred bytes, their rate, and
+/// the time left if the size of the media is known. It is only drawn if stderr is a terminal, and
+/// removed once the transfer is done or failed, as the last of its clones is dropped.
+#[derive(Clone)]
+pub struct TransferProgress(ProgressBar);
+
+impl TransferProgress {
+    /// Returns a bar of the transfer of `total` bytes, or of an unknown amount, labelled like
+    /// `uploading`. If it isn't `visible`, like when calls run concurrently, it isn't drawn.
+    pub fn new(label: &str, total: Option<u64>, visible: bool) -> TransferProgress {
+        let target = if visible {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        let bar = ProgressBar::with_draw_target(None, target).with_finish(ProgressFinish::AndClear);
+        let progress = TransferProgress(bar);
+        progress.0.set_message(label.to_string());
+        progress.set_total(total);
+        progress
+    }
+
+    /// Returns a bar which is never drawn.
+    pub fn hidden() -> TransferProgress {
+        TransferProgress::new("", None, false)
+    }
+
+    /// Sets the size of the media, once it is known.
+    pub fn set_total(&self, total: Option<u64>) {
+        let template = match total {
+            Some(total) => {
+                self.0.set_length(total);
+                "{msg} [{bar:40}] {bytes}/{total_bytes} at {bytes_per_sec}, {eta} left"
+            }
+            None => "{msg} {spinner} {bytes} at {bytes_per_sec}",
+        };
+        let style = ProgressStyle::with_template(template).expect("valid template");
+        self.0.set_style(style.progress_chars("=> "));
+    }
+
+    pub fn set_position(&self, transferred: u64) {
+        self.0.set_position(transferred);
+    }
+
+    pub fn inc(&self, transferred: u64) {
+        self.0.inc(transferred);
+    }
+
+    /// The amount of bytes transferred so far.
+    pub fn position(&self) -> u64 {
+        self.0.position()
+    }
+}
+
+/// Reads the media of an upload from `inner`, and advances the `TransferProgress` by the bytes
+/// read, which are sent as they are read when streaming it.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: TransferProgress,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, progress: TransferProgress) -> ProgressReader<R> {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let polled = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = polled {
+            self.progress.inc((buf.filled().len() - filled) as u64);
+        }
+        polled
+    }
+}
+
+/// Returns the message reporting the success of a call of the given command, like
+/// `projects topics-delete`, which has no response to write. Calls made for one of many projects
+/// are prefixed with its id, like their errors.
+pub fn success_message(command: &str, status: hyper::StatusCode, project: Option<&str>) -> String {
+    let prefix = project.map(|p| format!("[{}] ", p)).unwrap_or_default();
+    format!("{}{} succeeded with status {}", prefix, command, status)
+}
+
+/// Returns the `totalSize` of the list response `value`, the amount of items of all its pages, for
+/// APIs which report it, as a number or as the string of an `int64`.
+pub fn total_size(value: &Value) -> Option<u64> {
+    match value.get("totalSize")? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Returns the summary `--verbose` writes once all pages of a list were fetched, like
+/// `10 of 42 items fetched`, or `10 items fetched` if the API doesn't report their total. Calls
+/// made for one of many projects are prefixed with its id.
+pub fn items_summary(items: u64, total_size: Option<u64>, project: Option<&str>) -> String {
+    let prefix = project.map(|p| format!("[{}] ", p)).unwrap_or_default();
+    match total_size {
+        Some(total) => format!("{}{} of {} items fetched", prefix, items, total),
+        None => format!("{}{} items fetched", prefix, items),
+    }
+}
+
+/// Returns the root and base url of a hub whose calls go to the local server at `address`, like
+/// the one replaying a cassette, given its `root_url` and `base_url`, which keeps the service path
+/// of the base url.
+pub fn local_urls(
+    address: std::net::SocketAddr,
+    root_url: &str,
+    base_url: &str,
+) -> (String, String) {
+    let local_root = format!("http://{}/", address);
+    let service_path = base_url.strip_prefix(root_url).unwrap_or_default();
+    let local_base = format!("{}{}", local_root, service_path);
+    (local_root, local_base)
+}
+
+/// Default values of the `-v` parameters of all calls, as loaded from a JSON or TOML file.
+/// Each top-level key names a parameter, and its value is a string, number or boolean, or a list of
+/// them for parameters taking multiple values. Explicit `-v` parameters take precedence.
+#[derive(Default, Debug, PartialEq)]
+pub struct ParamDefaults {
+    params: Vec<(String, String)>,
+}
+
+impl ParamDefaults {
+    /// Loads the file at `arg` or, without it, the one of the given basename in the config
+    /// directory, with a `.json` or `.toml` extension, if there is one.
+    pub fn from_opts(
+        arg: Option<&str>,
+        config_dir: &str,
+        basename: &str,
+    ) -> Result<ParamDefaults, CLIError> {
+        if let Some(path) = arg {
+            return ParamDefaults::from_file(Path::new(path));
+        }
+        match config_file(config_dir, basename) {
+            Some(path) => ParamDefaults::from_file(&path),
+            None => Ok(ParamDefaults::default()),
+        }
+    }
+
+    /// Loads the given file, which is parsed as TOML if its extension is `.toml`, and as JSON
+    /// otherwise.
+    pub fn from_file(path: &Path) -> Result<ParamDefaults, CLIError> {
+        parse_config_file(
+            path,
+            ParamDefaults::from_value,
+            ConfigurationError::ParamFile,
+        )
+    }
+
+    fn from_value(value: &Value) -> Result<ParamDefaults, String> {
+        let to_string = |key: &str, value: &Value| match *value {
+            Value::String(ref s) => Ok(s.clone()),
+            Value::Number(_) | Value::Bool(_) => Ok(value.to_string()),
+            _ => Err(format!(
+                "the value of '{}' must be a string, number, boolean or a list of them",
+                key
+            )),
+        };
+        let mut params = Vec::new();
+        let object = value
+            .as_object()
+            .ok_or_else(|| "the parameters must be an object".to_string())?;
+        for (key, value) in object {
+            match *value {
+                Value::Array(ref values) => {
+                    for value in values {
+                        params.push((key.clone(), to_string(key, value)?));
+                    }
+                }
+                _ => params.push((key.clone(), to_string(key, value)?)),
+            }
+        }
+        Ok(ParamDefaults { params })
+    }
+
+    /// Returns the `key=value` arguments to handle like `-v` parameters: the defaults whose key is
+    /// among the `keys` supported by the call and not in the `explicit` arguments, followed by the
+    /// explicit ones.
+    pub fn args(&self, explicit: &[&str], keys: &[&str]) -> Vec<String> {
+        let explicit_keys: Vec<_> = explicit
+            .iter()
+            .map(|arg| arg.split('=').next().unwrap_or_default())
+            .collect();
+        self.params
+            .iter()
+            .filter(|(key, _)| {
+                keys.contains(&key.as_str()) && !explicit_keys.contains(&key.as_str())
+            })
+            .map(|(key, value)| format!("{}={}", key, literal_kv_value(value)))
+            .chain(explicit.iter().map(|arg| arg.to_string()))
+            .collect()
+    }
+}
+
+/// Returns the file of the given basename in the config directory, with a `.json` or `.toml`
+/// extension, if there is one.
+fn config_file(config_dir: &str, basename: &str) -> Option<PathBuf> {
+    ["json", "toml"]
+        .iter()
+        .map(|extension| Path::new(config_dir).join(format!("{}.{}", basename, extension)))
+        .find(|path| path.exists())
+}
+
+/// Reads the given file, which is parsed as TOML if its extension is `.toml`, and as JSON
+/// otherwise, and converts its value with `parse`. Its errors are reported with `invalid`.
+fn parse_config_file<T>(
+    path: &Path,
+    parse: fn(&Value) -> Result<T, String>,
+    invalid: fn((String, String)) -> ConfigurationError,
+) -> Result<T, CLIError> {
+    let path_str = path.to_string_lossy().to_string();
+    let content = fs::read_to_string(path)
+        .map_err(|err| CLIError::Configuration(ConfigurationError::Io((path_str.clone(), err))))?;
+    let value = if path.extension().is_some_and(|e| e == "toml") {
+        toml::from_str::<Value>(&content).map_err(|err| err.to_string())
+    } else {
+        json::from_str::<Value>(&content).map_err(|err| err.to_string())
     };
-    match kv.find('=') {
-        None => {
-            add_err();
-            (kv, None)
-        }
-        Some(pos) => {
-            let key = &kv[..pos];
-            if kv.len() <= pos + 1 {
-                add_err();
-                return (key, Some(""));
+    value
+        .and_then(|value| parse(&value))
+        .map_err(|msg| CLIError::Configuration(invalid((path_str, msg))))
+}
+
+/// Named abbreviations of command-lines, as loaded from a JSON or TOML file. Each top-level key
+/// names an alias, and its value is the command-line it stands for, either as a string which is
+/// split into arguments like a shell would, or as a list of arguments.
+#[derive(Default, Debug, PartialEq)]
+pub struct Aliases {
+    aliases: Vec<(String, Vec<String>)>,
+}
+
+impl Aliases {
+    /// Loads the file of the given basename in the config directory, with a `.json` or `.toml`
+    /// extension, if there is one.
+    pub fn from_config_dir(config_dir: &str, basename: &str) -> Result<Aliases, CLIError> {
+        match config_file(config_dir, basename) {
+            Some(path) => {
+                parse_config_file(&path, Aliases::from_value, ConfigurationError::AliasFile)
             }
-            (key, Some(&kv[pos + 1..]))
+            None => Ok(Aliases::default()),
         }
     }
-}
 
-pub fn calltype_from_str(
-    name: &str,
-    valid_protocols: Vec<String>,
-    err: &mut InvalidOptionsError,
-) -> CallType {
-    CallType::Upload(match UploadProtocol::from_str(name) {
-        Ok(up) => up,
-        Err(msg) => {
-            err.issues.push(CLIError::InvalidUploadProtocol(
-                name.to_string(),
-                valid_protocols,
-            ));
-            UploadProtocol::Simple
+    fn from_value(value: &Value) -> Result<Aliases, String> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| "the aliases must be an object".to_string())?;
+        let mut aliases = Vec::new();
+        for (name, value) in object {
+            let args = match *value {
+                Value::String(ref line) => split_command_line(line)
+                    .map_err(|msg| format!("the alias '{}' {}", name, msg))?,
+                Value::Array(ref values) => values
+                    .iter()
+                    .map(|value| value.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| {
+                        format!("the arguments of the alias '{}' must be strings", name)
+                    })?,
+                _ => {
+                    return Err(format!(
+                        "the alias '{}' must be a string or a list of strings",
+                        name
+                    ))
+                }
+            };
+            if args.is_empty() {
+                return Err(format!("the alias '{}' is empty", name));
+            }
+            aliases.push((name.clone(), args));
         }
-    })
-}
+        Ok(Aliases { aliases })
+    }
 
-pub fn input_file_from_opts(file_path: &str, err: &mut InvalidOptionsError) -> Option<fs::File> {
-    match fs::File::open(file_path) {
-        Ok(f) => Some(f),
-        Err(io_err) => {
-            err.issues.push(CLIError::Input(InputError::Io((
-                file_path.to_string(),
-                io_err,
-            ))));
-            None
+    /// Replaces the command in `args`, the arguments of the program starting with its name, by
+    /// the arguments of the alias of the same name. The remaining arguments follow the leading
+    /// non-option arguments of the alias, and precede its options, as an option taking many
+    /// values, like `-p`, would take them as further values otherwise. The options preceding the
+    /// command are kept, and the argument following one of the `value_flags` is its value. Names
+    /// of `commands` are never replaced. An alias may start with another one, unless that would
+    /// expand it again.
+    pub fn expand(
+        &self,
+        mut args: Vec<String>,
+        value_flags: &[&str],
+        commands: &[&str],
+    ) -> Vec<String> {
+        let mut pos = 1;
+        while pos < args.len() && args[pos].starts_with('-') {
+            let takes_value = args[pos]
+                .strip_prefix("--")
+                .is_some_and(|flag| value_flags.contains(&flag));
+            pos += if takes_value { 2 } else { 1 };
+        }
+        let mut expanded = Vec::new();
+        while let Some(name) = args.get(pos) {
+            if commands.contains(&name.as_str()) || expanded.contains(name) {
+                break;
+            }
+            match self.aliases.iter().find(|(alias, _)| alias == name) {
+                Some((alias, alias_args)) => {
+                    expanded.push(alias.clone());
+                    let num_leading = alias_args
+                        .iter()
+                        .position(|arg| arg.starts_with('-'))
+                        .unwrap_or(alias_args.len());
+                    let mut remaining = args.split_off(pos + 1);
+                    // options after `--` would be positional arguments
+                    let end = remaining
+                        .iter()
+                        .position(|arg| arg == "--")
+                        .unwrap_or(remaining.len());
+                    remaining.splice(end..end, alias_args[num_leading..].iter().cloned());
+                    args.truncate(pos);
+                    args.extend(alias_args[..num_leading].iter().cloned());
+                    args.extend(remaining);
+                }
+                None => break,
+            }
         }
+        args
     }
 }
 
-pub fn input_mime_from_opts(mime: &str, err: &mut InvalidOptionsError) -> Option<Mime> {
-    match mime.parse() {
-        Ok(m) => Some(m),
-        Err(_) => {
-            err.issues
-                .push(CLIError::Input(InputError::Mime(mime.to_string())));
-            None
+/// Splits the given line into arguments at whitespace, like a shell would. Quotes keep the
+/// whitespace they enclose, and backslashes escape the next character outside of single quotes.
+pub fn split_command_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('\'') => arg.get_or_insert_with(String::new).push(c),
+            _ if c == '\\' => match chars.next() {
+                Some(escaped) => arg.get_or_insert_with(String::new).push(escaped),
+                None => return Err("ends with a backslash".to_string()),
+            },
+            Some(_) => arg.get_or_insert_with(String::new).push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => args.extend(arg.take()),
+            None => arg.get_or_insert_with(String::new).push(c),
         }
     }
+    if quote.is_some() {
+        return Err("has an unterminated quote".to_string());
+    }
+    args.extend(arg);
+    Ok(args)
 }
 
-pub fn writer_from_opts(arg: Option<&str>) -> Result<Box<dyn Write>, io::Error> {
-    let f = arg.unwrap_or("-");
-    match f {
-        "-" => Ok(Box::new(stdout())),
-        _ => match fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(f)
-        {
-            Ok(f) => Ok(Box::new(f)),
-            Err(io_err) => Err(io_err),
-        },
+/// Returns the value of the long `flag` among `args`, given as `--flag value` or `--flag=value`.
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--").and_then(|a| a.strip_prefix(flag)) {
+            Some("") => return args.next().map(String::as_str),
+            Some(value) if value.starts_with('=') => return Some(&value[1..]),
+            _ => {}
+        }
     }
+    None
 }
 
 pub fn arg_from_str<'a, T>(
@@ -444,6 +2400,172 @@ where
     }
 }
 
+/// Returns true if the resource name `value` matches `template`, like `projects/*/locations/*`,
+/// where `*` stands for a single segment, and a trailing `**` for all remaining ones.
+pub fn matches_resource_template(value: &str, template: &str) -> bool {
+    let mut segments = value.split('/');
+    for part in template.split('/') {
+        match (part, segments.next()) {
+            (_, None) => return false,
+            ("**", Some(_)) => return true,
+            ("*", Some(segment)) if !segment.is_empty() => {}
+            (literal, Some(segment)) if literal == segment => {}
+            _ => return false,
+        }
+    }
+    segments.next().is_none()
+}
+
+/// Verify that `value`, given for the argument `arg_name`, is a resource name matching one of the
+/// `templates` accepted by the method, which are taken from the path patterns of the API.
+pub fn check_resource_name(
+    value: &str,
+    arg_name: &str,
+    templates: &[&str],
+    err: &mut InvalidOptionsError,
+) {
+    if !templates
+        .iter()
+        .any(|template| matches_resource_template(value, template))
+    {
+        err.issues.push(CLIError::InvalidResourceName(
+            arg_name.to_string(),
+            value.to_string(),
+            templates.iter().map(|t| t.to_string()).collect(),
+        ));
+    }
+}
+
+/// The arguments of a command, as generated from the discovery document into a JSON file, which
+/// is embedded into the program and read by `load_arg_data()` when it starts. This compiles a lot
+/// faster than the equivalent literal.
+///
+/// Each structure is stored as an array of its fields, in order, which keeps the file compact.
+#[derive(Debug, Deserialize)]
+pub struct CommandArgs {
+    /// The name of the resource, like `projects`.
+    pub name: String,
+    pub about: String,
+    pub methods: Vec<MethodArgs>,
+}
+
+/// The arguments of a method of a resource, see `CommandArgs`.
+#[derive(Debug, Deserialize)]
+pub struct MethodArgs {
+    pub name: String,
+    pub about: Option<String>,
+    pub after_help: String,
+    pub args: Vec<ArgSpec>,
+}
+
+/// An argument of a method, see `CommandArgs`.
+#[derive(Debug, Deserialize)]
+pub struct ArgSpec {
+    /// The name of the argument, or of its value if it is a flag.
+    pub name: Option<String>,
+    /// The flag, like `r`, or `projects-from` if it has more than one character.
+    pub flag: Option<String>,
+    pub help: Option<String>,
+    pub required: Option<bool>,
+    pub multiple: Option<bool>,
+    /// The ids of the arguments any of which makes a required argument optional.
+    #[serde(default)]
+    pub required_unless: Vec<String>,
+}
+
+impl ArgSpec {
+    /// The name of the argument in `ArgMatches`.
+    pub fn id(&self) -> &str {
+        match (&self.name, &self.flag) {
+            (Some(name), _) => name,
+            (None, Some(flag)) => flag,
+            (None, None) => unreachable!("an argument without name and flag"),
+        }
+    }
+
+    /// The argument to add to the subcommand of the method. Flags without a name are switches.
+    pub fn to_arg(&self) -> Arg<'_, '_> {
+        let mut arg = Arg::with_name(self.id());
+        match self.flag {
+            Some(ref short_flag) if short_flag.len() == 1 => arg = arg.short(short_flag),
+            Some(ref long_flag) => arg = arg.long(long_flag),
+            None => {}
+        }
+        if let Some(ref help) = self.help {
+            arg = arg.help(help);
+        }
+        // `empty_values()` makes the argument take a value, which a switch must not
+        if self.name.is_some() {
+            arg = arg.empty_values(false);
+            if self.flag.is_some() {
+                arg = arg.takes_value(true);
+            }
+        }
+        if let Some(required) = self.required {
+            arg = arg.required(required);
+        }
+        if let Some(multiple) = self.multiple {
+            arg = arg.multiple(multiple);
+        }
+        if !self.required_unless.is_empty() {
+            let ids: Vec<&str> = self.required_unless.iter().map(String::as_str).collect();
+            arg = arg.required_unless_one(&ids);
+        }
+        arg
+    }
+}
+
+/// Read the arguments of all commands from the `data` embedded into the program.
+pub fn load_arg_data(data: &str) -> Vec<CommandArgs> {
+    json::from_str(data).expect("the argument data to be valid JSON")
+}
+
+/// Returns the part of the `description` of all commands, which is generated from the discovery
+/// document, selected by the given resource and method. The description of a single method lists
+/// the parameters all methods accept as well, marked as `global`.
+pub fn describe_commands(
+    description: &str,
+    resource: Option<&str>,
+    method: Option<&str>,
+) -> Result<Value, CLIError> {
+    let mut description: Value =
+        json::from_str(description).expect("the description of commands to be valid JSON");
+    let resource = match resource {
+        Some(resource) => resource,
+        None => return Ok(description),
+    };
+    let mut methods = take_described_command(&mut description["commands"], resource)?;
+    let method = match method {
+        Some(method) => method,
+        None => return Ok(methods),
+    };
+    let mut described = take_described_command(&mut methods, method)?;
+    if let (Some(parameters), Value::Array(global_parameters)) = (
+        described["parameters"].as_array_mut(),
+        description["parameters"].take(),
+    ) {
+        parameters.extend(global_parameters.into_iter().map(|mut parameter| {
+            parameter["global"] = Value::Bool(true);
+            parameter
+        }));
+    }
+    Ok(described)
+}
+
+fn take_described_command(commands: &mut Value, name: &str) -> Result<Value, CLIError> {
+    if let Some(described) = commands.get_mut(name) {
+        return Ok(described.take());
+    }
+    let names: Vec<&str> = commands
+        .as_object()
+        .map(|commands| commands.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+    Err(CLIError::UnknownCommand(
+        name.to_string(),
+        did_you_mean(name, &names).map(str::to_string),
+    ))
+}
+
 #[derive(Debug)]
 pub enum ApplicationSecretError {
     DecoderError((String, json::Error)),
@@ -474,6 +2596,9 @@ pub enum ConfigurationError {
     HomeExpansionFailed(String),
     Secret(ApplicationSecretError),
     Io((String, io::Error)),
+    ParamFile((String, String)),
+    AliasFile((String, String)),
+    Template((String, String)),
 }
 
 impl fmt::Display for ConfigurationError {
@@ -496,6 +2621,15 @@ impl fmt::Display for ConfigurationError {
                 "IO operation failed on path '{}' with error: {}.",
                 path, err
             ),
+            ConfigurationError::ParamFile((ref path, ref msg)) => {
+                writeln!(f, "Parameter file '{}' is invalid: {}.", path, msg)
+            }
+            ConfigurationError::AliasFile((ref path, ref msg)) => {
+                writeln!(f, "Alias file '{}' is invalid: {}.", path, msg)
+            }
+            ConfigurationError::Template((ref path, ref msg)) => {
+                writeln!(f, "Template '{}' is invalid: {}.", path, msg)
+            }
         }
     }
 }
@@ -504,6 +2638,8 @@ impl fmt::Display for ConfigurationError {
 pub enum InputError {
     Io((String, io::Error)),
     Mime(String),
+    Request((String, String)),
+    UnsetVariable(String),
 }
 
 impl fmt::Display for InputError {
@@ -515,6 +2651,16 @@ impl fmt::Display for InputError {
                 file_path, io_err
             ),
             InputError::Mime(ref mime) => writeln!(f, "'{}' is not a known mime-type.", mime),
+            InputError::Request((ref file_path, ref msg)) => writeln!(
+                f,
+                "The request structure in '{}' is invalid: {}.",
+                file_path, msg
+            ),
+            InputError::UnsetVariable(ref name) => writeln!(
+                f,
+                "The environment variable '{}' is not set, or not valid unicode.",
+                name
+            ),
         }
     }
 }
@@ -523,9 +2669,14 @@ impl fmt::Display for InputError {
 pub enum FieldError {
     PopOnEmpty(String),
     TrailingFieldSep(String),
-    Unknown(String, Option<String>, Option<String>),
+    Unknown(String, Vec<FieldSuggestion>, Option<String>),
     Duplicate(String),
+    InvalidValue(String, String, Option<String>, &'static [&'static str]),
     Empty,
+    InvalidIndex(String),
+    IndexOutOfRange(String, usize),
+    AppendToNonList(String),
+    InvalidJson(String, String),
 }
 
 impl fmt::Display for FieldError {
@@ -539,23 +2690,55 @@ impl fmt::Display for FieldError {
                 "'{}': Single field separator may not be last character.",
                 field
             ),
-            FieldError::Unknown(ref field, ref suggestion, ref value) => {
-                let suffix = match *suggestion {
-                    Some(ref s) => {
-                        let kv = match *value {
-                            Some(ref v) => format!("{}={}", s, v),
-                            None => s.clone(),
-                        };
-                        format!(" Did you mean '{}' ?", kv)
-                    }
-                    None => String::new(),
+            FieldError::Unknown(ref field, ref suggestions, ref value) => {
+                let candidates: Vec<String> = suggestions
+                    .iter()
+                    .map(|s| match *value {
+                        Some(ref v) => format!("'{}={}' ({})", s.path, v, s.type_name),
+                        None => format!("'{}' ({})", s.path, s.type_name),
+                    })
+                    .collect();
+                let suffix = match candidates.len() {
+                    0 => String::new(),
+                    1 => format!(" Did you mean {} ?", candidates[0]),
+                    _ => format!(" Did you mean one of {} ?", candidates.join(", ")),
                 };
                 writeln!(f, "Field '{}' does not exist.{}", field, suffix)
             }
             FieldError::Duplicate(ref cursor) => {
                 writeln!(f, "Value at '{}' was already set", cursor)
             }
+            FieldError::InvalidValue(ref field, ref value, ref suggestion, values) => {
+                let suffix = match *suggestion {
+                    Some(ref s) => format!(" Did you mean '{}' ?", s),
+                    None => String::new(),
+                };
+                writeln!(
+                    f,
+                    "'{}' is not a valid value of field '{}'.{} Valid values are {}.",
+                    value,
+                    field,
+                    suffix,
+                    values.join(", ")
+                )
+            }
             FieldError::Empty => writeln!(f, "Field names must not be empty."),
+            FieldError::InvalidIndex(ref field) => writeln!(
+                f,
+                "'{}': Lists must be indexed like 'field[0]', or 'field[]' for their last item.",
+                field
+            ),
+            FieldError::IndexOutOfRange(ref cursor, len) => writeln!(
+                f,
+                "'{}': Index is out of range of a list of {} items, of which the index {} adds one.",
+                cursor, len, len
+            ),
+            FieldError::AppendToNonList(ref cursor) => {
+                writeln!(f, "'{}': Only lists can be appended to with '+='.", cursor)
+            }
+            FieldError::InvalidJson(ref cursor, ref msg) => {
+                writeln!(f, "'{}': The value set with ':=' is invalid: {}.", cursor, msg)
+            }
         }
     }
 }
@@ -571,6 +2754,11 @@ pub enum CLIError {
     Field(FieldError),
     MissingCommandError,
     MissingMethodError(String),
+    MissingProjectError(String),
+    UnknownImageProject(String),
+    InvalidResourceName(String, String, Vec<String>),
+    UnknownCommand(String, Option<String>),
+    ConflictingFlags(String, String),
 }
 
 impl fmt::Display for CLIError {
@@ -591,7 +2779,7 @@ impl fmt::Display for CLIError {
                 arg_name, value, type_name, err_desc
             ),
             CLIError::UnknownParameter(ref param_name, ref possible_values) => {
-                let suffix = match did_you_mean(param_name, &possible_values) {
+                let suffix = match did_you_mean(param_name, possible_values) {
                     Some(v) => format!(" Did you mean '{}' ?", v),
                     None => String::new(),
                 };
@@ -611,6 +2799,33 @@ impl fmt::Display for CLIError {
                 "Please specify the method to call on the '{}' command.",
                 cmd
             ),
+            CLIError::MissingProjectError(ref arg_name) => writeln!(
+                f,
+                "Please specify the project using '<{}>' or '--projects-from <file>'.",
+                arg_name
+            ),
+            CLIError::UnknownImageProject(ref image) => writeln!(
+                f,
+                "Cannot determine the project of image '{}', please specify it using '--project <id>'.",
+                image
+            ),
+            CLIError::InvalidResourceName(ref arg_name, ref value, ref templates) => writeln!(
+                f,
+                "'{}' is not a valid value of argument '{}', which must look like {}.",
+                value,
+                arg_name,
+                templates.join(" or ")
+            ),
+            CLIError::UnknownCommand(ref name, ref suggestion) => {
+                let suffix = match suggestion {
+                    Some(v) => format!(" Did you mean '{}' ?", v),
+                    None => String::new(),
+                };
+                writeln!(f, "Command '{}' is unknown.{}", name, suffix)
+            }
+            CLIError::ConflictingFlags(ref flag, ref other) => {
+                writeln!(f, "'{}' can't be combined with '{}'.", flag, other)
+            }
         }
     }
 }
@@ -646,7 +2861,135 @@ impl InvalidOptionsError {
     }
 }
 
-pub fn assure_config_dir_exists(dir: &str) -> Result<String, CLIError> {
+/// The format failures are written to stderr in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Messages for humans to read.
+    #[default]
+    Text,
+    /// A single line of JSON per failure, see `ErrorReport`.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ErrorFormat, String> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "unknown error format '{}', expected 'text' or 'json'",
+                s
+            )),
+        }
+    }
+}
+
+impl ErrorFormat {
+    pub fn from_opts(opt: &ArgMatches) -> Result<ErrorFormat, CLIError> {
+        Ok(parsed_opt(opt, "error-format", "--error-format", "error format")?.unwrap_or_default())
+    }
+}
+
+const REQUEST_INFO_TYPE: &str = "type.googleapis.com/google.rpc.RequestInfo";
+const ERROR_INFO_TYPE: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+const BAD_REQUEST_TYPE: &str = "type.googleapis.com/google.rpc.BadRequest";
+const QUOTA_FAILURE_TYPE: &str = "type.googleapis.com/google.rpc.QuotaFailure";
+
+/// A failure, as written to stderr by `--error-format json` for scripts to handle it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorReport {
+    /// The status the program exits with.
+    pub exit_code: i32,
+    /// The HTTP status code of the response, if the server responded.
+    pub http_status: Option<u16>,
+    /// The reason of the error the server responded with, like `rateLimitExceeded`, or its
+    /// canonical status, like `NOT_FOUND`, if it has no reason.
+    pub reason: Option<String>,
+    pub message: String,
+    /// The id the server assigned to the request, to refer to it in support cases.
+    pub request_id: Option<String>,
+    /// The invalid fields of the request, and the exceeded quotas, like `name: must not be empty`.
+    pub violations: Vec<String>,
+    /// The project of the call which failed, when calling several projects.
+    pub project: Option<String>,
+}
+
+impl ErrorReport {
+    pub fn new<M: fmt::Display>(exit_code: i32, message: M) -> ErrorReport {
+        ErrorReport {
+            exit_code,
+            message: message.to_string().trim_end().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the status, reason, message and request id of the report to the ones of the given
+    /// error response, like `{"error": {"code": 404, "message": ..., "details": [...]}}`.
+    pub fn with_response(mut self, response: &Value) -> ErrorReport {
+        let error = &response["error"];
+        let details = error["details"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+        let detail = |t: &str, field: &str| {
+            details
+                .iter()
+                .find(|d| d["@type"] == t)
+                .and_then(|d| d[field].as_str())
+                .map(str::to_string)
+        };
+        if let Some(code) = error["code"].as_u64() {
+            self.http_status = u16::try_from(code).ok();
+        }
+        self.reason = detail(ERROR_INFO_TYPE, "reason")
+            .or_else(|| error["errors"][0]["reason"].as_str().map(str::to_string))
+            .or_else(|| error["status"].as_str().map(str::to_string));
+        if let Some(message) = error["message"].as_str() {
+            self.message = message.to_string();
+        }
+        self.request_id = detail(REQUEST_INFO_TYPE, "requestId");
+        let violations = |t: &str, field: &str, subject: &str| {
+            details
+                .iter()
+                .filter(|d| d["@type"] == t)
+                .filter_map(|d| d[field].as_array())
+                .flatten()
+                .map(|v| {
+                    let string = |name: &str| v[name].as_str().unwrap_or_default();
+                    format!("{}: {}", string(subject), string("description"))
+                })
+                .collect::<Vec<_>>()
+        };
+        self.violations = violations(BAD_REQUEST_TYPE, "fieldViolations", "field");
+        self.violations
+            .extend(violations(QUOTA_FAILURE_TYPE, "violations", "subject"));
+        self
+    }
+
+    /// Returns the report as JSON object, with all fields, which are `null` if they are unknown,
+    /// except for the project, and the violations if there are none.
+    pub fn to_json(&self) -> Value {
+        let mut report = json::json!({
+            "exitCode": self.exit_code,
+            "httpStatus": self.http_status,
+            "reason": self.reason,
+            "message": self.message,
+            "requestId": self.request_id,
+        });
+        if !self.violations.is_empty() {
+            report["violations"] = json::json!(self.violations);
+        }
+        if let Some(ref project) = self.project {
+            report["project"] = Value::String(project.clone());
+        }
+        report
+    }
+}
+
+/// Returns the given config directory, with a leading `~` replaced by the home directory.
+pub fn expand_config_dir(dir: &str) -> Result<String, CLIError> {
     let trdir = dir.trim();
     if trdir.is_empty() {
         return Err(CLIError::Configuration(ConfigurationError::DirectoryUnset));
@@ -670,7 +3013,11 @@ pub fn assure_config_dir_exists(dir: &str) -> Result<String, CLIError> {
     } else {
         trdir.to_string()
     };
+    Ok(expanded_config_dir)
+}
 
+pub fn assure_config_dir_exists(dir: &str) -> Result<String, CLIError> {
+    let expanded_config_dir = expand_config_dir(dir)?;
     if let Err(err) = fs::create_dir(&expanded_config_dir) {
         if err.kind() != io::ErrorKind::AlreadyExists {
             return Err(CLIError::Configuration(
@@ -744,3 +3091,371 @@ pub fn application_secret_from_directory(
     }
     unreachable!();
 }
+
+/// The endpoint at which refresh and access tokens are revoked.
+pub const TOKEN_REVOCATION_URL: &str = "https://oauth2.googleapis.com/revoke";
+
+/// How the user authorizes the program to obtain a token, if none was persisted for the scopes of
+/// a call yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthFlow {
+    /// Consent in a browser, which redirects to a local server receiving the authorization code.
+    #[default]
+    Redirect,
+    /// Enter a code at a verification URL on any device, while the program polls for the token,
+    /// which needs neither a browser nor a reachable port on this host.
+    Device,
+}
+
+impl FromStr for AuthFlow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AuthFlow, String> {
+        match s {
+            "redirect" => Ok(AuthFlow::Redirect),
+            "device" => Ok(AuthFlow::Device),
+            _ => Err(format!(
+                "unknown authorization flow '{}', expected 'redirect' or 'device'",
+                s
+            )),
+        }
+    }
+}
+
+impl AuthFlow {
+    pub fn from_opts(opt: &ArgMatches) -> Result<AuthFlow, CLIError> {
+        Ok(parsed_opt(opt, "auth-flow", "--auth-flow", "authorization flow")?.unwrap_or_default())
+    }
+}
+
+/// Returns the instructions of the device flow, telling the user where to enter which code.
+pub fn device_flow_instructions(device_auth: &DeviceAuthResponse) -> String {
+    format!(
+        "To authorize this program, visit {} on any device and enter the code {} before {}.",
+        device_auth.verification_uri, device_auth.user_code, device_auth.expires_at
+    )
+}
+
+/// Presents the code of the device flow on stderr, which keeps it apart from the results on
+/// stdout.
+pub struct StderrDeviceFlowDelegate;
+
+impl DeviceFlowDelegate for StderrDeviceFlowDelegate {
+    fn present_user_code<'a>(
+        &'a self,
+        device_auth: &'a DeviceAuthResponse,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        writeln!(io::stderr(), "{}", device_flow_instructions(device_auth)).ok();
+        Box::pin(futures::future::ready(()))
+    }
+}
+
+/// Read the tokens the authenticator persisted at `path`, one entry for each set of scopes.
+/// A file which doesn't exist yet holds no tokens.
+pub fn read_persisted_tokens(path: &str) -> Result<Vec<Value>, io::Error> {
+    match fs::read(path) {
+        Ok(bytes) => {
+            json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Write `tokens` back to `path`, keeping the permissions of an existing file. They are written to
+/// a temporary file first, which then replaces the one at `path` at once, so other invocations
+/// read either all of the previous tokens, or all of the new ones.
+pub fn write_persisted_tokens(path: &str, tokens: &[Value]) -> Result<(), io::Error> {
+    let temp_path = format!("{}.{}.tmp", path, std::process::id());
+    let mut options = fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    // like the authenticator, only the current user may read the tokens
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&temp_path).and_then(|f| {
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        json::to_writer(&f, tokens)?;
+        f.sync_all()?;
+        fs::rename(&temp_path, path)
+    });
+    if written.is_err() {
+        fs::remove_file(&temp_path).ok();
+    }
+    written
+}
+
+/// Lock the tokens persisted at `path` while `update` changes them, and write them back, so
+/// invocations running at the same time, like the jobs of a CI matrix, don't lose each other's
+/// changes.
+pub fn update_persisted_tokens<T>(
+    path: &str,
+    update: impl FnOnce(&mut Vec<Value>) -> T,
+) -> Result<T, io::Error> {
+    let _lock = TokenStorageLock::acquire(path, TOKEN_LOCK_TIMEOUT)?;
+    let mut tokens = read_persisted_tokens(path)?;
+    let updated = update(&mut tokens);
+    write_persisted_tokens(path, &tokens)?;
+    Ok(updated)
+}
+
+/// How long to wait for other invocations to release the lock of the token storage.
+pub const TOKEN_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The delay between attempts to lock the token storage
+const TOKEN_LOCK_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// An exclusive lock of the token storage, held as lock of the operating system on the file next
+/// to it, with the `.lock` extension. It is released when dropped, or when the invocation holding
+/// it exits in any way, so a crashed invocation doesn't leave it behind.
+pub struct TokenStorageLock {
+    // closing the file releases the lock
+    _file: fs::File,
+}
+
+impl TokenStorageLock {
+    /// Lock the token storage at `storage`, trying again while another invocation holds the
+    /// lock, until `timeout` passed.
+    pub fn acquire(storage: &str, timeout: Duration) -> Result<TokenStorageLock, io::Error> {
+        let path = format!("{}.lock", storage);
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        let started = Instant::now();
+        loop {
+            match fs2::FileExt::try_lock_exclusive(&file) {
+                Ok(()) => return Ok(TokenStorageLock { _file: file }),
+                Err(err) if err.kind() != fs2::lock_contended_error().kind() => return Err(err),
+                Err(_) => {}
+            }
+            if started.elapsed() >= timeout {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "The tokens are locked by another invocation, which holds '{}'",
+                        path
+                    ),
+                ));
+            }
+            std::thread::sleep(TOKEN_LOCK_RETRY_DELAY);
+        }
+    }
+}
+
+/// A copy of the persisted tokens which is private to this invocation, for the authenticator to
+/// write to. Writing to the shared storage instead would overwrite the tokens other invocations
+/// obtained in the meantime. `finish()` adds the tokens obtained or refreshed during the
+/// invocation to the shared storage. The copy is removed when dropped.
+pub struct TokenSession {
+    storage: String,
+    private_path: String,
+    copied: Vec<Value>,
+}
+
+impl TokenSession {
+    /// Copy the tokens persisted at `storage`.
+    pub fn start(storage: &str) -> Result<TokenSession, io::Error> {
+        let copied = read_persisted_tokens(storage)?;
+        let private_path = format!("{}.{}", storage, std::process::id());
+        write_persisted_tokens(&private_path, &copied)?;
+        Ok(TokenSession {
+            storage: storage.to_string(),
+            private_path,
+            copied,
+        })
+    }
+
+    /// The path of the private copy of the tokens.
+    pub fn private_path(&self) -> &str {
+        &self.private_path
+    }
+
+    /// Add the tokens which were obtained or refreshed since the start to the shared storage,
+    /// replacing the ones for the same scopes.
+    pub fn finish(&self) -> Result<(), io::Error> {
+        let changed: Vec<Value> = read_persisted_tokens(&self.private_path)?
+            .into_iter()
+            .filter(|token| !self.copied.contains(token))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        update_persisted_tokens(&self.storage, |tokens| {
+            for token in changed {
+                let scopes: Vec<&str> = token["scopes"]
+                    .as_array()
+                    .map(|scopes| scopes.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                tokens.retain(|other| !has_exact_scopes(other, &scopes));
+                tokens.push(token);
+            }
+        })
+    }
+}
+
+impl Drop for TokenSession {
+    fn drop(&mut self) {
+        fs::remove_file(&self.private_path).ok();
+    }
+}
+
+/// Remove and return all tokens obtained for exactly the given set of `scopes`, in any order,
+/// or all tokens if `scopes` is empty.
+pub fn take_tokens_for_scopes(tokens: &mut Vec<Value>, scopes: &[&str]) -> Vec<Value> {
+    let matches = |token: &Value| scopes.is_empty() || has_exact_scopes(token, scopes);
+    let (taken, kept) = tokens.drain(..).partition(matches);
+    *tokens = kept;
+    taken
+}
+
+fn has_exact_scopes(token: &Value, scopes: &[&str]) -> bool {
+    let mut token_scopes: Vec<&str> = match token["scopes"].as_array() {
+        Some(s) => s.iter().filter_map(Value::as_str).collect(),
+        None => Vec::new(),
+    };
+    let mut wanted = scopes.to_vec();
+    token_scopes.sort_unstable();
+    token_scopes.dedup();
+    wanted.sort_unstable();
+    wanted.dedup();
+    token_scopes == wanted
+}
+
+/// Returns the token to revoke for a persisted token, which is its refresh token if there is one.
+/// Revoking it also invalidates all access tokens obtained with it.
+pub fn token_to_revoke(token: &Value) -> Option<&str> {
+    token["token"]["refresh_token"]
+        .as_str()
+        .or_else(|| token["token"]["access_token"].as_str())
+}
+
+/// Describe a persisted token without revealing any of its secrets.
+pub fn token_summary(token: &Value) -> Value {
+    json::json!({
+        "scopes": token["scopes"],
+        "expires_at": token["token"]["expires_at"],
+        "refreshable": token["token"]["refresh_token"].is_string(),
+    })
+}
+
+/// Build the request revoking the given refresh or access `token`.
+pub fn revocation_request(token: &str) -> hyper::Request<hyper::Body> {
+    hyper::Request::post(TOKEN_REVOCATION_URL)
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(hyper::Body::from(format!(
+            "token={}",
+            form_urlencode(token)
+        )))
+        .expect("a valid request")
+}
+
+/// The scopes of the token whose refresh yields an identity token along with the access token.
+pub const IDENTITY_TOKEN_SCOPES: [&str; 2] =
+    ["openid", "https://www.googleapis.com/auth/userinfo.email"];
+
+/// Returns the refresh token persisted for exactly the given set of `scopes`, if any.
+pub fn refresh_token_for_scopes<'a>(tokens: &'a [Value], scopes: &[&str]) -> Option<&'a str> {
+    tokens
+        .iter()
+        .filter(|token| has_exact_scopes(token, scopes))
+        .find_map(|token| token["token"]["refresh_token"].as_str())
+}
+
+/// Build the request refreshing `refresh_token` at the token endpoint of `secret`.
+/// If the token was obtained for the `IDENTITY_TOKEN_SCOPES`, the response carries an identity token.
+pub fn identity_token_request(
+    secret: &ApplicationSecret,
+    refresh_token: &str,
+) -> hyper::Request<hyper::Body> {
+    let body = [
+        ("grant_type", "refresh_token"),
+        ("client_id", secret.client_id.as_str()),
+        ("client_secret", secret.client_secret.as_str()),
+        ("refresh_token", refresh_token),
+    ]
+    .iter()
+    .map(|(k, v)| format!("{}={}", k, form_urlencode(v)))
+    .collect::<Vec<_>>()
+    .join("&");
+    hyper::Request::post(secret.token_uri.as_str())
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(hyper::Body::from(body))
+        .expect("a valid request")
+}
+
+fn form_urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                (b as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Returns the identity token in the `body` of the response to an `identity_token_request()`.
+/// If an `expected_audience` is given, the token must have been issued for it. The audience of
+/// the tokens of installed applications is always their OAuth client id, so this only checks it.
+pub fn identity_token_from_response(
+    body: &[u8],
+    expected_audience: Option<&str>,
+) -> Result<String, String> {
+    let response: Value =
+        json::from_slice(body).map_err(|e| format!("Invalid token response: {}", e))?;
+    let id_token = match response["id_token"].as_str() {
+        Some(token) => token,
+        None => return Err("The token response holds no identity token".to_string()),
+    };
+    if let Some(audience) = expected_audience {
+        let token_audience = id_token_audience(id_token)
+            .ok_or_else(|| "The identity token has no audience".to_string())?;
+        if token_audience != audience {
+            return Err(format!(
+                "The identity token was issued for audience '{}', not '{}'",
+                token_audience, audience
+            ));
+        }
+    }
+    Ok(id_token.to_string())
+}
+
+/// Returns the `aud` claim of the JWT `id_token`, without verifying its signature.
+pub fn id_token_audience(id_token: &str) -> Option<String> {
+    let payload = id_token.split('.').nth(1)?;
+    let claims: Value =
+        json::from_slice(&base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?).ok()?;
+    claims["aud"].as_str().map(str::to_string)
+}
+
+/// Returns the filter selecting the occurrences of vulnerabilities in the resource at `resource_url`.
+pub fn vulnerability_filter(resource_url: &str) -> String {
+    format!(
+        "resourceUrl=\"{}\" AND kind=\"VULNERABILITY\"",
+        resource_url.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Returns the id of the project hosting the image at `resource_url`, which is the first path
+/// segment of registry urls like `https://gcr.io/<project>/<image>` or
+/// `https://<location>-docker.pkg.dev/<project>/<repository>/<image>`.
+pub fn project_from_resource_url(resource_url: &str) -> Option<&str> {
+    let location = resource_url
+        .split_once("://")
+        .map_or(resource_url, |(_, location)| location);
+    let (_, path) = location.split_once('/')?;
+    path.split(['/', '@', ':'])
+        .next()
+        .filter(|project| !project.is_empty())
+}